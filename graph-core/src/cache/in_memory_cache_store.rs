@@ -14,6 +14,21 @@ impl<Value: Clone> InMemoryCacheStore<Value> {
             store: Default::default(),
         }
     }
+
+    /// Copy of every cache id and value in the store.
+    pub fn entries(&self) -> HashMap<String, Value> {
+        let read_lock = self.store.read();
+        let entries = read_lock.clone();
+        drop(read_lock);
+        entries
+    }
+
+    /// Store each of the values. Values with an existing cache id are replaced.
+    pub fn extend<I: IntoIterator<Item = (String, Value)>>(&mut self, entries: I) {
+        let mut write_lock = self.store.write();
+        write_lock.extend(entries);
+        drop(write_lock);
+    }
 }
 
 impl<Value: Clone> CacheStore<Value> for InMemoryCacheStore<Value> {
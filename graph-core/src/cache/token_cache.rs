@@ -1,9 +1,21 @@
 use crate::identity::ForceTokenRefresh;
 use async_trait::async_trait;
-use graph_error::AuthExecutionError;
+use graph_error::{AuthExecutionError, AF};
 
 pub trait AsBearer<RHS = Self> {
     fn as_bearer(&self) -> String;
+
+    /// Same as [AsBearer::as_bearer] but returns an error instead of an empty string
+    /// when there is no access token, for instance when a token response only
+    /// contained a refresh token.
+    fn try_as_bearer(&self) -> Result<String, AuthExecutionError> {
+        let bearer = self.as_bearer();
+        if bearer.is_empty() {
+            Err(AF::msg_err("access_token", "token does not contain an access_token").into())
+        } else {
+            Ok(bearer)
+        }
+    }
 }
 
 impl AsBearer for String {
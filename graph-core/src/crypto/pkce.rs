@@ -113,12 +113,43 @@ impl ProofKeyCodeExchange {
         if code_challenge.len() != 43 {
             return Err(AF::msg_err("code_challenge", "Must be 43-octet sequence"));
         }
+        ProofKeyCodeExchange::validate_code_verifier(code_verifier.as_ref())?;
         Ok(ProofKeyCodeExchange {
             code_verifier: code_verifier.as_ref().to_owned(),
             code_challenge,
             code_challenge_method: code_challenge_method.as_ref().to_owned(),
         })
     }
+
+    /// Validate that the code verifier is 43 to 128 characters long and only uses the
+    /// unreserved characters `A-Z`, `a-z`, `0-9`, `-`, `.`, `_` and `~`.
+    ///
+    /// See [RFC 7636 Section 4.1](https://datatracker.ietf.org/doc/html/rfc7636#section-4.1)
+    pub fn validate_code_verifier(code_verifier: &str) -> IdentityResult<()> {
+        let len = code_verifier.chars().count();
+        if !(43..=128).contains(&len) {
+            return Err(AF::msg_err(
+                "code_verifier",
+                format!("Must be between 43 and 128 characters long but was {len} characters")
+                    .as_str(),
+            ));
+        }
+
+        if let Some(invalid) = code_verifier
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~')))
+        {
+            return Err(AF::msg_err(
+                "code_verifier",
+                format!(
+                    "Invalid character {invalid:?} - only A-Z, a-z, 0-9, '-', '.', '_' and '~' are allowed"
+                )
+                .as_str(),
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -139,4 +170,33 @@ mod test {
         let verifier = URL_SAFE_NO_PAD.encode(context.finish().as_ref());
         assert_eq!(verifier, pkce.code_challenge);
     }
+
+    #[test]
+    fn code_verifier_too_short() {
+        let result = ProofKeyCodeExchange::validate_code_verifier("abc123");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn code_verifier_invalid_character() {
+        let code_verifier = format!("{}+/=", "a".repeat(43));
+        match ProofKeyCodeExchange::validate_code_verifier(code_verifier.as_str()) {
+            Err(AF::RequiredValue { name, message }) => {
+                assert_eq!("code_verifier", name);
+                assert!(message.unwrap().contains("'+'"));
+            }
+            result => panic!("expected invalid code_verifier: {result:#?}"),
+        }
+    }
+
+    #[test]
+    fn code_verifier_valid() {
+        let pkce = ProofKeyCodeExchange::oneshot().unwrap();
+        assert!(ProofKeyCodeExchange::validate_code_verifier(pkce.code_verifier.as_str()).is_ok());
+        assert!(ProofKeyCodeExchange::validate_code_verifier(
+            "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk~._"
+        )
+        .is_ok());
+        assert!(ProofKeyCodeExchange::validate_code_verifier("a".repeat(129).as_str()).is_err());
+    }
 }
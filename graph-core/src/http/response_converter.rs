@@ -1,11 +1,51 @@
 use crate::http::HttpResponseBuilderExt;
 use async_trait::async_trait;
-use graph_error::{AuthExecutionResult, ErrorMessage};
+use graph_error::{AuthExecutionError, AuthExecutionResult, ErrorMessage};
 use http::Response;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
 use serde::de::DeserializeOwned;
 
 pub type JsonHttpResponse = http::Response<Result<serde_json::Value, ErrorMessage>>;
 
+/// Maximum number of characters of a non-json body included in
+/// [AuthExecutionError::UnexpectedResponse].
+const BODY_SNIPPET_LEN: usize = 512;
+
+/// Parse the body as json. Bodies with a content type other than json, such as HTML
+/// error pages returned by proxies, and bodies that are not valid json are returned as
+/// [AuthExecutionError::UnexpectedResponse] with the status and the start of the body.
+fn json_body(
+    status: u16,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> AuthExecutionResult<serde_json::Value> {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_owned());
+    let is_json = content_type
+        .as_deref()
+        .map(|content_type| content_type.to_ascii_lowercase().contains("json"))
+        .unwrap_or(true);
+
+    if is_json {
+        if let Ok(json) = serde_json::from_slice(body) {
+            return Ok(json);
+        }
+    }
+
+    let body = String::from_utf8_lossy(body);
+    let mut snippet: String = body.chars().take(BODY_SNIPPET_LEN).collect();
+    if snippet.len() < body.len() {
+        snippet.push_str("...");
+    }
+    Err(AuthExecutionError::UnexpectedResponse {
+        status,
+        content_type,
+        body: snippet,
+    })
+}
+
 #[async_trait]
 pub trait AsyncResponseConverterExt {
     async fn into_http_response_async<T: DeserializeOwned>(
@@ -23,7 +63,7 @@ impl AsyncResponseConverterExt for reqwest::Response {
         let headers = self.headers().clone();
         let version = self.version();
 
-        let body: serde_json::Value = self.json().await?;
+        let body = json_body(status.as_u16(), &headers, &self.bytes().await?)?;
         let json = body.clone();
 
         let body_result: Result<T, ErrorMessage> = serde_json::from_value(body)
@@ -58,7 +98,7 @@ impl ResponseConverterExt for reqwest::blocking::Response {
         let headers = self.headers().clone();
         let version = self.version();
 
-        let body: serde_json::Value = self.json()?;
+        let body = json_body(status.as_u16(), &headers, &self.bytes()?)?;
         let json = body.clone();
 
         let body_result: Result<T, ErrorMessage> = serde_json::from_value(body)
@@ -77,3 +117,79 @@ impl ResponseConverterExt for reqwest::blocking::Response {
         Ok(builder.body(body_result)?)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn html_error_response() {
+        let html = format!(
+            "<html><head><title>502 Bad Gateway</title></head><body>{}</body></html>",
+            "x".repeat(1024)
+        );
+        let response: reqwest::Response = http::Response::builder()
+            .status(502)
+            .header("content-type", "text/html; charset=utf-8")
+            .body(html)
+            .unwrap()
+            .into();
+
+        match response
+            .into_http_response_async::<serde_json::Value>()
+            .await
+        {
+            Err(AuthExecutionError::UnexpectedResponse {
+                status,
+                content_type,
+                body,
+            }) => {
+                assert_eq!(502, status);
+                assert_eq!(Some("text/html; charset=utf-8"), content_type.as_deref());
+                assert!(body.starts_with("<html><head><title>502 Bad Gateway</title>"));
+                assert_eq!(BODY_SNIPPET_LEN + 3, body.len());
+            }
+            result => panic!("expected unexpected response error: {result:#?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn empty_error_response() {
+        let response: reqwest::Response = http::Response::builder()
+            .status(503)
+            .body("")
+            .unwrap()
+            .into();
+
+        match response
+            .into_http_response_async::<serde_json::Value>()
+            .await
+        {
+            Err(AuthExecutionError::UnexpectedResponse { status, body, .. }) => {
+                assert_eq!(503, status);
+                assert!(body.is_empty());
+            }
+            result => panic!("expected unexpected response error: {result:#?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn json_error_response() {
+        let response: reqwest::Response = http::Response::builder()
+            .status(400)
+            .header("content-type", "application/json; charset=utf-8")
+            .body(r#"{"error": "invalid_grant"}"#)
+            .unwrap()
+            .into();
+
+        let response = response
+            .into_http_response_async::<serde_json::Value>()
+            .await
+            .unwrap();
+        assert_eq!(400, response.status().as_u16());
+        assert_eq!(
+            Some("invalid_grant"),
+            response.body().as_ref().unwrap()["error"].as_str()
+        );
+    }
+}
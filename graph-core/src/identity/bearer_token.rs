@@ -0,0 +1,112 @@
+use std::fmt::{Debug, Display, Formatter};
+
+use zeroize::Zeroize;
+
+use crate::cache::AsBearer;
+
+/// Access token returned by [ClientApplication](crate::identity::ClientApplication).
+///
+/// The token is not included in the [Debug] output so that it does not end up in
+/// logs by accident, and the memory holding the token is zeroed on drop.
+/// [Display] and [BearerToken::as_str] return the raw token.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct BearerToken(String);
+
+impl BearerToken {
+    pub fn new(token: impl Into<String>) -> BearerToken {
+        BearerToken(token.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the raw token. The returned String is not zeroed on drop.
+    pub fn into_string(mut self) -> String {
+        std::mem::take(&mut self.0)
+    }
+}
+
+impl Debug for BearerToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BearerToken(***)")
+    }
+}
+
+impl Display for BearerToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Drop for BearerToken {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl AsBearer for BearerToken {
+    fn as_bearer(&self) -> String {
+        self.0.clone()
+    }
+}
+
+impl AsRef<str> for BearerToken {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl PartialEq<str> for BearerToken {
+    fn eq(&self, other: &str) -> bool {
+        self.0.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for BearerToken {
+    fn eq(&self, other: &&str) -> bool {
+        self.0.as_str() == *other
+    }
+}
+
+impl From<String> for BearerToken {
+    fn from(value: String) -> Self {
+        BearerToken(value)
+    }
+}
+
+impl From<&str> for BearerToken {
+    fn from(value: &str) -> Self {
+        BearerToken(value.to_owned())
+    }
+}
+
+impl From<BearerToken> for String {
+    fn from(value: BearerToken) -> Self {
+        value.into_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn debug_does_not_leak_token() {
+        let bearer = BearerToken::from("eyJ0eXAiOiJKV1QiLCJhbGciOiJSUzI1NiJ9.secret");
+        assert_eq!("BearerToken(***)", format!("{bearer:?}"));
+        assert!(!format!("{bearer:#?}").contains("secret"));
+        assert_eq!(
+            "eyJ0eXAiOiJKV1QiLCJhbGciOiJSUzI1NiJ9.secret",
+            bearer.to_string()
+        );
+        assert_eq!(
+            "eyJ0eXAiOiJKV1QiLCJhbGciOiJSUzI1NiJ9.secret",
+            String::from(bearer)
+        );
+    }
+}
@@ -1,7 +1,12 @@
+use std::future::{Future, IntoFuture};
+use std::pin::Pin;
+
 use async_trait::async_trait;
 use dyn_clone::DynClone;
 use graph_error::AuthExecutionResult;
 
+use crate::identity::BearerToken;
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum ForceTokenRefresh {
     /// Always use the token cache first to when returning tokens.
@@ -21,22 +26,77 @@ dyn_clone::clone_trait_object!(ClientApplication);
 
 #[async_trait]
 pub trait ClientApplication: DynClone + Send + Sync {
-    fn get_token_silent(&mut self) -> AuthExecutionResult<String>;
+    fn get_token_silent(&mut self) -> AuthExecutionResult<BearerToken>;
 
-    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<String>;
+    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<BearerToken>;
 
     fn with_force_token_refresh(&mut self, force_token_refresh: ForceTokenRefresh);
+
+    /// Returns a handle that can be awaited directly to get the bearer token,
+    /// the same as calling [get_token_silent_async](Self::get_token_silent_async).
+    ///
+    /// ```rust,ignore
+    /// let bearer = confidential_client.acquire_token().await?;
+    /// ```
+    fn acquire_token(&mut self) -> AcquireToken<'_, Self>
+    where
+        Self: Sized,
+    {
+        AcquireToken::new(self)
+    }
+}
+
+/// Token acquisition that resolves to the bearer token of a [ClientApplication]
+/// when awaited. Created by [ClientApplication::acquire_token] or [AcquireToken::new]
+/// for trait objects.
+pub struct AcquireToken<'a, C: ClientApplication + ?Sized> {
+    client_application: &'a mut C,
+}
+
+impl<'a, C: ClientApplication + ?Sized> AcquireToken<'a, C> {
+    pub fn new(client_application: &'a mut C) -> AcquireToken<'a, C> {
+        AcquireToken { client_application }
+    }
+}
+
+impl<'a, C: ClientApplication + ?Sized> IntoFuture for AcquireToken<'a, C> {
+    type Output = AuthExecutionResult<BearerToken>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        self.client_application.get_token_silent_async()
+    }
 }
 
 #[async_trait]
 impl ClientApplication for String {
-    fn get_token_silent(&mut self) -> AuthExecutionResult<String> {
-        Ok(self.clone())
+    fn get_token_silent(&mut self) -> AuthExecutionResult<BearerToken> {
+        Ok(BearerToken::from(self.as_str()))
     }
 
-    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<String> {
-        Ok(self.clone())
+    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<BearerToken> {
+        Ok(BearerToken::from(self.as_str()))
     }
 
     fn with_force_token_refresh(&mut self, _force_token_refresh: ForceTokenRefresh) {}
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn await_acquire_token() {
+        let mut client_application = String::from("bearer");
+        assert_eq!(
+            "bearer",
+            client_application.acquire_token().await.unwrap().as_str()
+        );
+
+        let mut client_application: Box<dyn ClientApplication> = Box::new(String::from("bearer"));
+        let bearer = AcquireToken::new(client_application.as_mut())
+            .await
+            .unwrap();
+        assert_eq!("bearer", bearer.as_str());
+    }
+}
@@ -1,7 +1,9 @@
+mod bearer_token;
 mod client_application;
 mod jwk;
 mod jwks;
 
+pub use bearer_token::*;
 pub use client_application::*;
 pub use jwk::*;
 pub use jwks::*;
@@ -25,6 +25,17 @@ pub enum AuthorizationFailure {
 
     #[error("{0:#?}")]
     SerdeJson(#[from] serde_json::Error),
+
+    /// The authorization redirect contained `error=access_denied`, for instance because
+    /// the user declined consent. Applications can show a friendly message and let the
+    /// user sign in again.
+    #[error("access denied, error_description: {error_description:?}")]
+    AccessDenied { error_description: Option<String> },
+
+    /// An encrypted token (JWE) could not be decrypted, for instance because it was
+    /// encrypted for a different key or the ciphertext was modified.
+    #[error("token decryption failed: {0}")]
+    Decryption(String),
 }
 
 impl AuthorizationFailure {
@@ -85,6 +96,10 @@ impl AuthorizationFailure {
     pub fn x509_result<T>(message: impl ToString) -> Result<T, AuthorizationFailure> {
         Err(AuthorizationFailure::Openssl(message.to_string()))
     }
+
+    pub fn decryption(message: impl ToString) -> AuthorizationFailure {
+        AuthorizationFailure::Decryption(message.to_string())
+    }
 }
 
 /// Error either from missing or invalid configuration using one of the
@@ -109,15 +124,86 @@ pub enum AuthExecutionError {
 
     #[error("{0:#?}")]
     JsonWebToken(#[from] jsonwebtoken::errors::Error),
+
+    /// Silent token acquisition failed with `consent_required`. The user or an
+    /// administrator has to consent to the requested scopes, for instance by
+    /// signing in interactively with `prompt=consent`.
+    #[error("consent required, response: {response:#?}")]
+    ConsentRequired {
+        response: http::Response<Result<serde_json::Value, ErrorMessage>>,
+    },
+
+    /// Silent token acquisition failed with `interaction_required`. The user has to
+    /// sign in interactively, for instance to complete multi-factor authentication
+    /// or a conditional access policy.
+    #[error("interaction required, response: {response:#?}")]
+    InteractionRequired {
+        response: http::Response<Result<serde_json::Value, ErrorMessage>>,
+    },
+
+    /// Silent token acquisition failed with `login_required`. There is no usable
+    /// session for the user and a fresh sign-in is required.
+    #[error("login required, response: {response:#?}")]
+    LoginRequired {
+        response: http::Response<Result<serde_json::Value, ErrorMessage>>,
+    },
+
+    /// The token request was cancelled before a response was received.
+    #[error("token request cancelled")]
+    Cancelled,
+
+    /// The client side request budget of the credential is exhausted and the token
+    /// request was not sent.
+    #[error("token request budget exhausted, retry after {retry_after:?}")]
+    Throttled { retry_after: std::time::Duration },
+
+    /// The body of the token response is larger than the maximum response body size
+    /// of the credential and was not parsed.
+    #[error("token response body exceeds the maximum size of {max_size} bytes")]
+    ResponseTooLarge { max_size: usize },
+
+    /// The response is not json, for instance an HTML error page returned by a proxy
+    /// in front of the token endpoint. The body is truncated.
+    #[error("unexpected response, status: {status}, content-type: {content_type:?}, body: {body}")]
+    UnexpectedResponse {
+        status: u16,
+        content_type: Option<String>,
+        body: String,
+    },
 }
 
 impl AuthExecutionError {
+    /// Error for a failed silent token request. Responses with an `error` or `suberror`
+    /// of `consent_required`, `interaction_required` or `login_required` are returned as
+    /// [ConsentRequired](AuthExecutionError::ConsentRequired),
+    /// [InteractionRequired](AuthExecutionError::InteractionRequired) and
+    /// [LoginRequired](AuthExecutionError::LoginRequired) so that callers can choose
+    /// the interactive flow to fall back to. A `suberror` takes precedence over the `error`.
     pub fn silent_token_auth(
         response: http::Response<Result<serde_json::Value, ErrorMessage>>,
     ) -> AuthExecutionError {
-        AuthExecutionError::SilentTokenAuth {
-            message: "silent token auth failed".into(),
-            response,
+        let error_value = |name: &str| -> Option<String> {
+            response
+                .body()
+                .as_ref()
+                .ok()?
+                .get(name)?
+                .as_str()
+                .map(|value| value.to_owned())
+        };
+        let suberror = error_value("suberror");
+        let error = error_value("error");
+
+        match suberror.as_deref().or(error.as_deref()) {
+            Some("consent_required") => AuthExecutionError::ConsentRequired { response },
+            Some("login_required") => AuthExecutionError::LoginRequired { response },
+            _ if error.as_deref() == Some("interaction_required") => {
+                AuthExecutionError::InteractionRequired { response }
+            }
+            _ => AuthExecutionError::SilentTokenAuth {
+                message: "silent token auth failed".into(),
+                response,
+            },
         }
     }
 }
@@ -152,3 +238,61 @@ pub enum AuthTaskExecutionError<R> {
     #[error("{0:#?}")]
     JoinError(#[from] tokio::task::JoinError),
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn error_response(body: serde_json::Value) -> AuthExecutionError {
+        AuthExecutionError::silent_token_auth(
+            http::Response::builder()
+                .status(400)
+                .body(Ok(body))
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn consent_required() {
+        let error = error_response(serde_json::json!({
+            "error": "invalid_grant",
+            "suberror": "consent_required",
+            "error_description": "AADSTS65001: The user or administrator has not consented to use the application."
+        }));
+        assert!(matches!(error, AuthExecutionError::ConsentRequired { .. }));
+
+        let error = error_response(serde_json::json!({ "error": "consent_required" }));
+        assert!(matches!(error, AuthExecutionError::ConsentRequired { .. }));
+    }
+
+    #[test]
+    fn interaction_required() {
+        let error = error_response(serde_json::json!({
+            "error": "interaction_required",
+            "suberror": "basic_action",
+            "error_description": "AADSTS50076: Due to a configuration change made by your administrator, you must use multi-factor authentication."
+        }));
+        assert!(matches!(
+            error,
+            AuthExecutionError::InteractionRequired { .. }
+        ));
+
+        let error = error_response(serde_json::json!({
+            "error": "interaction_required",
+            "suberror": "consent_required"
+        }));
+        assert!(matches!(error, AuthExecutionError::ConsentRequired { .. }));
+    }
+
+    #[test]
+    fn login_required() {
+        let error = error_response(serde_json::json!({ "error": "login_required" }));
+        assert!(matches!(error, AuthExecutionError::LoginRequired { .. }));
+    }
+
+    #[test]
+    fn other_errors() {
+        let error = error_response(serde_json::json!({ "error": "invalid_client" }));
+        assert!(matches!(error, AuthExecutionError::SilentTokenAuth { .. }));
+    }
+}
@@ -158,13 +158,71 @@ impl From<AuthExecutionError> for GraphFailure {
                     message,
                 },
                 AuthorizationFailure::SerdeJson(error) => GraphFailure::SerdeJson(error),
+                AuthorizationFailure::AccessDenied { error_description } => {
+                    GraphFailure::PreFlightError {
+                        url: None,
+                        headers: None,
+                        error: None,
+                        message: format!("access denied, error_description: {error_description:?}"),
+                    }
+                }
+                AuthorizationFailure::Decryption(message) => GraphFailure::PreFlightError {
+                    url: None,
+                    headers: None,
+                    error: None,
+                    message: format!("token decryption failed: {message}"),
+                },
             },
             AuthExecutionError::Request(e) => GraphFailure::ReqwestError(e),
             AuthExecutionError::Http(e) => GraphFailure::HttpError(e),
             AuthExecutionError::SilentTokenAuth { message, response } => {
                 GraphFailure::SilentTokenAuth { message, response }
             }
+            AuthExecutionError::ConsentRequired { response } => GraphFailure::SilentTokenAuth {
+                message: "consent required".into(),
+                response,
+            },
+            AuthExecutionError::InteractionRequired { response } => GraphFailure::SilentTokenAuth {
+                message: "interaction required".into(),
+                response,
+            },
+            AuthExecutionError::LoginRequired { response } => GraphFailure::SilentTokenAuth {
+                message: "login required".into(),
+                response,
+            },
             AuthExecutionError::JsonWebToken(error) => GraphFailure::JsonWebToken(error),
+            AuthExecutionError::Cancelled => GraphFailure::PreFlightError {
+                url: None,
+                headers: None,
+                error: None,
+                message: "token request cancelled".into(),
+            },
+            AuthExecutionError::Throttled { retry_after } => GraphFailure::PreFlightError {
+                url: None,
+                headers: None,
+                error: None,
+                message: format!("token request budget exhausted, retry after {retry_after:?}"),
+            },
+            AuthExecutionError::ResponseTooLarge { max_size } => GraphFailure::PreFlightError {
+                url: None,
+                headers: None,
+                error: None,
+                message: format!(
+                    "token response body exceeds the maximum size of {max_size} bytes"
+                ),
+            },
+            AuthExecutionError::UnexpectedResponse {
+                status,
+                content_type,
+                body,
+            } => GraphFailure::PreFlightError {
+                url: None,
+                headers: None,
+                error: None,
+                message: format!(
+                    "unexpected response, status: {status}, content-type: {content_type:?}, body: {body}"
+                ),
+            },
         }
     }
 }
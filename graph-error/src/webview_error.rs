@@ -30,6 +30,12 @@ pub enum WebViewError {
         error_description: String,
         error_uri: Option<String>,
     },
+    /// The webview could not be started, for instance on headless Linux or in
+    /// containers without a display. Use a flow that does not need a webview
+    /// such as device code or opening the authorization url in the system browser.
+    #[error("webview unavailable: {0}")]
+    WebViewUnavailable(String),
+
     /// Error that happens when building or calling the http request.
     #[error("{0:#?}")]
     AuthExecutionError(#[from] Box<AuthExecutionError>),
@@ -5,6 +5,7 @@ use crate::internal::{
 };
 use async_stream::try_stream;
 use futures::Stream;
+use graph_core::identity::BearerToken;
 use graph_error::{AuthExecutionResult, ErrorMessage, GraphFailure, GraphResult};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
 use reqwest::{Request, Response};
@@ -161,7 +162,7 @@ impl RequestHandler {
 
     pub(crate) async fn default_request_builder_with_token(
         &mut self,
-    ) -> AuthExecutionResult<(String, reqwest::RequestBuilder)> {
+    ) -> AuthExecutionResult<(BearerToken, reqwest::RequestBuilder)> {
         let access_token = self
             .inner
             .client_application
@@ -0,0 +1,110 @@
+use base64::Engine;
+use serde_json::Value;
+
+use graph_error::{IdentityResult, AF};
+
+use crate::identity::{IdToken, Token};
+
+/// The signed-in account of a token, read from the claims of the id token.
+///
+/// The claims are read without verifying the signature of the id token. The id token
+/// is received directly from the token endpoint over TLS, which the Microsoft identity
+/// platform considers sufficient for reading the account of the signed-in user.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AccountInfo {
+    /// The `name` claim, the display name of the user.
+    pub name: Option<String>,
+    /// The `preferred_username` claim, or the `upn` or `email` claim when not present.
+    pub username: Option<String>,
+    /// The `oid` claim, the object id of the user in the tenant.
+    pub oid: Option<String>,
+    /// The `tid` claim, the tenant the user signed in to.
+    pub tid: Option<String>,
+}
+
+impl AccountInfo {
+    fn from_payload(payload: &Value) -> AccountInfo {
+        let claim = |name: &str| payload.get(name).and_then(Value::as_str).map(String::from);
+        AccountInfo {
+            name: claim("name"),
+            username: claim("preferred_username")
+                .or_else(|| claim("upn"))
+                .or_else(|| claim("email")),
+            oid: claim("oid"),
+            tid: claim("tid"),
+        }
+    }
+}
+
+impl TryFrom<&IdToken> for AccountInfo {
+    type Error = AF;
+
+    fn try_from(id_token: &IdToken) -> IdentityResult<AccountInfo> {
+        let payload = id_token
+            .id_token
+            .split('.')
+            .nth(1)
+            .ok_or_else(|| AF::msg_err("id_token", "id token is not a jwt"))?;
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(payload.trim_end_matches('='))
+            .map_err(|err| AF::msg_err("id_token", err.to_string().as_str()))?;
+        let payload: Value = serde_json::from_slice(&payload)?;
+        Ok(AccountInfo::from_payload(&payload))
+    }
+}
+
+impl TryFrom<&Token> for AccountInfo {
+    type Error = AF;
+
+    /// Returns an error if the token does not include an id token. Id tokens are only
+    /// returned when the openid scope is requested.
+    fn try_from(token: &Token) -> IdentityResult<AccountInfo> {
+        let id_token = token.id_token.as_ref().ok_or_else(|| {
+            AF::msg_err(
+                "id_token",
+                "token does not include an id token, request the openid scope",
+            )
+        })?;
+        AccountInfo::try_from(id_token)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn id_token(payload: serde_json::Value) -> String {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        format!(
+            "{}.{}.signature",
+            engine.encode(r#"{"alg":"RS256","typ":"JWT"}"#),
+            engine.encode(payload.to_string())
+        )
+    }
+
+    #[test]
+    fn account_info_from_id_token() {
+        let id_token = IdToken::new(
+            id_token(serde_json::json!({
+                "name": "Adele Vance",
+                "upn": "adelev@contoso.com",
+                "oid": "00000000-0000-0000-66f3-3332eca7ea81",
+                "tid": "9122040d-6c67-4c5b-b112-36a304b66dad"
+            }))
+            .as_str(),
+            None,
+            None,
+            None,
+        );
+
+        let account_info = AccountInfo::try_from(&id_token).unwrap();
+        assert_eq!(Some("Adele Vance".to_owned()), account_info.name);
+        assert_eq!(Some("adelev@contoso.com".to_owned()), account_info.username);
+        assert_eq!(
+            Some("9122040d-6c67-4c5b-b112-36a304b66dad".to_owned()),
+            account_info.tid
+        );
+
+        assert!(AccountInfo::try_from(&Token::default()).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 use url::Url;
 use uuid::Uuid;
 
-use crate::identity::AadAuthorityAudience;
+use crate::identity::{AadAuthorityAudience, CacheKeyStrategy};
 use crate::AzureCloudInstance;
 
 /// Application Options typically stored as JSON file in .net applications.
@@ -34,6 +34,14 @@ pub struct ApplicationOptions {
     pub azure_cloud_instance: Option<AzureCloudInstance>,
     #[serde(alias = "redirectUri", alias = "RedirectUri", alias = "redirect_uri")]
     pub redirect_uri: Option<Url>,
+    /// How the cache id of tokens is computed. Defaults to [CacheKeyStrategy::ClientIdAndTenant].
+    #[serde(
+        default,
+        alias = "cacheKeyStrategy",
+        alias = "CacheKeyStrategy",
+        alias = "cache_key_strategy"
+    )]
+    pub cache_key_strategy: Option<CacheKeyStrategy>,
 }
 
 impl ApplicationOptions {
@@ -46,6 +54,7 @@ impl ApplicationOptions {
             instance: None,
             azure_cloud_instance: None,
             redirect_uri: None,
+            cache_key_strategy: None,
         }
     }
 }
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+use graph_core::crypto::ProofKeyCodeExchange;
+use graph_error::{IdentityResult, AF};
+use time::OffsetDateTime;
+
+use crate::identity::{AuthorizationResponse, Clock, ClockRef};
+
+/// The values of an authorization request that are needed again to redeem the
+/// authorization response, such as the PKCE code verifier and the nonce.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct AuthSession {
+    /// The code verifier of the PKCE code challenge sent in the authorization request.
+    pub code_verifier: Option<String>,
+    /// The nonce sent in the authorization request, compared with the nonce of the id token.
+    pub nonce: Option<String>,
+}
+
+impl AuthSession {
+    pub fn new(code_verifier: Option<String>, nonce: Option<String>) -> AuthSession {
+        AuthSession {
+            code_verifier,
+            nonce,
+        }
+    }
+}
+
+impl From<&ProofKeyCodeExchange> for AuthSession {
+    fn from(value: &ProofKeyCodeExchange) -> Self {
+        AuthSession::new(Some(value.code_verifier.clone()), None)
+    }
+}
+
+impl Debug for AuthSession {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthSession")
+            .field("code_verifier", &"[REDACTED]")
+            .field("nonce", &self.nonce)
+            .finish()
+    }
+}
+
+/// Stores the [AuthSession] of each authorization request by its `state` so that a
+/// server handling many sign-ins at once can match each redirect to the PKCE code
+/// verifier and nonce of its own request.
+///
+/// A session is stored when the authorization url is built with `url_with_session_store`
+/// and taken with [take_for_response](AuthSessionStore::take_for_response) when the
+/// authorization response with the same state is redeemed.
+pub trait AuthSessionStore: Send + Sync {
+    /// Store the session under the state of the authorization request, replacing any
+    /// session stored under the same state.
+    fn store(&self, state: &str, session: AuthSession);
+
+    /// Remove and return the session of the state. Returns None if no session was
+    /// stored for the state or the session expired, in which case the authorization
+    /// response must be rejected. A session can only be taken once.
+    fn take(&self, state: &str) -> Option<AuthSession>;
+
+    /// Remove and return the session of the state of the authorization response.
+    /// Returns an error if the response has no state or no session is stored for its
+    /// state, in which case the authorization response must be rejected.
+    ///
+    /// Pass the session to `with_auth_session` of the credential builder to redeem
+    /// the code with the code verifier of the request, and compare the nonce with the
+    /// nonce of the id token using [ClaimsValidator::require_nonce](crate::identity::ClaimsValidator::require_nonce).
+    fn take_for_response(
+        &self,
+        authorization_response: &AuthorizationResponse,
+    ) -> IdentityResult<AuthSession> {
+        let state = authorization_response
+            .state
+            .as_deref()
+            .ok_or_else(|| AF::required("state"))?;
+        self.take(state).ok_or_else(|| {
+            AF::msg_err(
+                "state",
+                "no authorization session is stored for the state of the response",
+            )
+        })
+    }
+}
+
+/// An [AuthSessionStore] that keeps the sessions in memory until they expire. Clones
+/// share the same sessions.
+///
+/// The default store keeps sessions for ten minutes, the lifetime of authorization
+/// codes issued by the Microsoft identity platform.
+#[derive(Clone)]
+pub struct InMemoryAuthSessionStore {
+    sessions: Arc<Mutex<HashMap<String, (AuthSession, OffsetDateTime)>>>,
+    ttl: time::Duration,
+    clock: ClockRef,
+}
+
+impl InMemoryAuthSessionStore {
+    /// A store that keeps each session for the duration.
+    pub fn new(ttl: time::Duration) -> InMemoryAuthSessionStore {
+        InMemoryAuthSessionStore {
+            sessions: Default::default(),
+            ttl,
+            clock: Default::default(),
+        }
+    }
+
+    /// A store that keeps each session for the duration, using the clock to expire
+    /// sessions.
+    pub fn with_clock(
+        ttl: time::Duration,
+        clock: impl Clock + 'static,
+    ) -> InMemoryAuthSessionStore {
+        InMemoryAuthSessionStore {
+            sessions: Default::default(),
+            ttl,
+            clock: ClockRef::new(clock),
+        }
+    }
+
+    /// The number of sessions stored that have not expired.
+    pub fn len(&self) -> usize {
+        let now = self.clock.now_utc();
+        let sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions
+            .values()
+            .filter(|(_, expires_on)| *expires_on > now)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for InMemoryAuthSessionStore {
+    fn default() -> Self {
+        InMemoryAuthSessionStore::new(time::Duration::minutes(10))
+    }
+}
+
+impl AuthSessionStore for InMemoryAuthSessionStore {
+    fn store(&self, state: &str, session: AuthSession) {
+        let now = self.clock.now_utc();
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|_, (_, expires_on)| *expires_on > now);
+        sessions.insert(state.to_owned(), (session, now + self.ttl));
+    }
+
+    fn take(&self, state: &str) -> Option<AuthSession> {
+        let now = self.clock.now_utc();
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|_, (_, expires_on)| *expires_on > now);
+        sessions.remove(state).map(|(session, _)| session)
+    }
+}
+
+impl Debug for InMemoryAuthSessionStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InMemoryAuthSessionStore")
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::{ConfidentialClientApplication, TestClock};
+    use graph_core::crypto::GenPkce;
+
+    #[test]
+    fn sessions_are_taken_by_state() {
+        let clock = TestClock::new(OffsetDateTime::UNIX_EPOCH);
+        let store =
+            InMemoryAuthSessionStore::with_clock(time::Duration::minutes(10), clock.clone());
+
+        store.store(
+            "state_1",
+            AuthSession::new(Some("code_verifier_1".into()), Some("nonce_1".into())),
+        );
+        store.store(
+            "state_2",
+            AuthSession::new(Some("code_verifier_2".into()), Some("nonce_2".into())),
+        );
+        assert_eq!(2, store.len());
+
+        let session = store.take("state_2").unwrap();
+        assert_eq!(Some("code_verifier_2".to_owned()), session.code_verifier);
+        assert_eq!(Some("nonce_2".to_owned()), session.nonce);
+
+        let session = store.take("state_1").unwrap();
+        assert_eq!(Some("code_verifier_1".to_owned()), session.code_verifier);
+        assert_eq!(Some("nonce_1".to_owned()), session.nonce);
+
+        // Sessions are only taken once.
+        assert_eq!(None, store.take("state_1"));
+        assert!(store.is_empty());
+
+        store.store("state_3", AuthSession::default());
+        clock.advance(time::Duration::minutes(11));
+        assert_eq!(None, store.take("state_3"));
+    }
+
+    #[test]
+    fn sessions_are_stored_with_url_and_taken_for_response() {
+        let store = InMemoryAuthSessionStore::default();
+        let client_id = "bb301aaa-1201-4259-a230-923423ee4f3d";
+        let mut app = ConfidentialClientApplication::builder(client_id);
+        let pkce_1 = ProofKeyCodeExchange::oneshot().unwrap();
+        let pkce_2 = ProofKeyCodeExchange::oneshot().unwrap();
+
+        let mut states = Vec::new();
+        for pkce in [&pkce_1, &pkce_2] {
+            let mut url_builder = app.auth_code_url_builder();
+            url_builder
+                .with_redirect_uri(url::Url::parse("http://localhost:8000").unwrap())
+                .with_scope(["User.Read"])
+                .with_state_generated()
+                .with_pkce(pkce);
+            url_builder.url_with_session_store(&store).unwrap();
+            states.push(url_builder.build().state().cloned().unwrap());
+        }
+        assert_eq!(2, store.len());
+
+        // Redeem the responses in the opposite order of the requests.
+        for (state, pkce) in states.iter().zip([&pkce_1, &pkce_2]).rev() {
+            let response = serde_urlencoded::from_str::<AuthorizationResponse>(&format!(
+                "code=code&state={state}"
+            ))
+            .unwrap();
+            let session = store.take_for_response(&response).unwrap();
+            let credential = app
+                .with_auth_code("code")
+                .with_client_secret("secret")
+                .with_auth_session(&session)
+                .build()
+                .into_inner();
+            assert_eq!(
+                Some(pkce.code_verifier.clone()),
+                credential.code_verifier.clone()
+            );
+        }
+
+        // A response whose state has no session, or that was already redeemed, is rejected.
+        let response = serde_urlencoded::from_str::<AuthorizationResponse>(&format!(
+            "code=code&state={}",
+            states[0]
+        ))
+        .unwrap();
+        assert!(store.take_for_response(&response).is_err());
+        let response = serde_urlencoded::from_str::<AuthorizationResponse>("code=code").unwrap();
+        assert!(store.take_for_response(&response).is_err());
+    }
+
+    #[test]
+    fn url_with_session_store_requires_state() {
+        let store = InMemoryAuthSessionStore::default();
+        let result = ConfidentialClientApplication::builder("bb301aaa-1201-4259-a230-923423ee4f3d")
+            .auth_code_url_builder()
+            .with_redirect_uri(url::Url::parse("http://localhost:8000").unwrap())
+            .with_scope(["User.Read"])
+            .url_with_session_store(&store);
+        assert!(result.is_err());
+        assert!(store.is_empty());
+    }
+}
@@ -1,6 +1,9 @@
+use std::collections::HashMap;
 use std::fmt::Display;
 use url::{ParseError, Url};
 
+use graph_error::{IdentityResult, AF};
+
 lazy_static! {
     pub static ref AZURE_PUBLIC_CLOUD_INSTANCE: Url = {
         Url::parse(AzureCloudInstance::AzurePublic.as_ref())
@@ -78,6 +81,16 @@ impl From<AzureCloudInstance> for Url {
 
 impl AzureCloudInstance {
     pub fn auth_uri(&self, authority: &Authority) -> Result<Url, ParseError> {
+        match authority {
+            Authority::AdfsHost(authority_url) => {
+                return adfs_uri(authority_url, "oauth2/authorize")
+            }
+            Authority::B2C(authority_url) => {
+                return adfs_uri(authority_url, "oauth2/v2.0/authorize")
+            }
+            _ => {}
+        }
+
         Url::parse(&format!(
             "{}/{}/oauth2/v2.0/authorize",
             self.as_ref(),
@@ -86,6 +99,12 @@ impl AzureCloudInstance {
     }
 
     pub fn token_uri(&self, authority: &Authority) -> Result<Url, ParseError> {
+        match authority {
+            Authority::AdfsHost(authority_url) => return adfs_uri(authority_url, "oauth2/token"),
+            Authority::B2C(authority_url) => return adfs_uri(authority_url, "oauth2/v2.0/token"),
+            _ => {}
+        }
+
         Url::parse(&format!(
             "{}/{}/oauth2/v2.0/token",
             self.as_ref(),
@@ -102,6 +121,10 @@ impl AzureCloudInstance {
     }
 
     pub fn device_code_uri(&self, authority: &Authority) -> Result<Url, ParseError> {
+        if let Authority::AdfsHost(authority_url) = authority {
+            return adfs_uri(authority_url, "oauth2/devicecode");
+        }
+
         Url::parse(&format!(
             "{}/{}/oauth2/v2.0/devicecode",
             self.as_ref(),
@@ -110,6 +133,16 @@ impl AzureCloudInstance {
     }
 
     pub fn openid_configuration_uri(&self, authority: &Authority) -> Result<Url, ParseError> {
+        match authority {
+            Authority::AdfsHost(authority_url) => {
+                return adfs_uri(authority_url, ".well-known/openid-configuration")
+            }
+            Authority::B2C(authority_url) => {
+                return adfs_uri(authority_url, "v2.0/.well-known/openid-configuration")
+            }
+            _ => {}
+        }
+
         Url::parse(&format!(
             "{}/{}/v2.0/.well-known/openid-configuration",
             self.as_ref(),
@@ -118,14 +151,37 @@ impl AzureCloudInstance {
     }
 
     pub fn issuer(&self, authority: &Authority) -> Result<Url, ParseError> {
+        if let Authority::AdfsHost(authority_url) = authority {
+            return Ok(authority_url.clone());
+        }
+
         Url::parse(&format!("{}/{}/v2.0", self.as_ref(), authority.as_ref()))
     }
 
-    /*
+    /// The bare host of the login endpoint of the cloud, without the scheme, such as
+    /// `login.microsoftonline.com` for [AzureCloudInstance::AzurePublic]. Use
+    /// [as_ref](AsRef::as_ref) for the full base url.
+    pub fn login_host(&self) -> &'static str {
+        match self {
+            AzureCloudInstance::AzurePublic => "login.microsoftonline.com",
+            AzureCloudInstance::AzureChina => "login.chinacloudapi.cn",
+            AzureCloudInstance::AzureGermany => "login.microsoftonline.de",
+            AzureCloudInstance::AzureUsGovernment => "login.microsoftonline.us",
+        }
+    }
+
+    /// The Microsoft Graph `.default` scope for the national cloud. Used to request
+    /// app-only tokens for Microsoft Graph with the client credentials grant.
     pub fn default_microsoft_graph_scope(&self) -> &'static str {
-        "https://graph.microsoft.com/.default"
+        match self {
+            AzureCloudInstance::AzurePublic => "https://graph.microsoft.com/.default",
+            AzureCloudInstance::AzureChina => "https://microsoftgraph.chinacloudapi.cn/.default",
+            AzureCloudInstance::AzureGermany => "https://graph.microsoft.de/.default",
+            AzureCloudInstance::AzureUsGovernment => "https://graph.microsoft.us/.default",
+        }
     }
 
+    /*
     pub fn default_managed_identity_scope(&self) -> &'static str {
         match self {
             AzureCloudInstance::AzurePublic => "https://management.azure.com//.default",
@@ -139,6 +195,14 @@ impl AzureCloudInstance {
     */
 }
 
+fn adfs_uri(authority_url: &Url, path: &str) -> Result<Url, ParseError> {
+    Url::parse(&format!(
+        "{}/{}",
+        authority_url.as_str().trim_end_matches('/'),
+        path
+    ))
+}
+
 /// Specifies which Microsoft accounts can be used for sign-in with a given application.
 /// See https://aka.ms/msal-net-application-configuration
 ///
@@ -240,9 +304,205 @@ pub enum Authority {
     /// school account or directory guests with a personal Microsoft account) can sign in
     /// to the application.
     TenantId(String),
+    /// An on-premises AD FS server with its own host and path. The url is the full
+    /// authority, for instance https://adfs.contoso.com/adfs, and endpoints are built
+    /// from it such as https://adfs.contoso.com/adfs/oauth2/token.
+    ///
+    /// The cloud instance is not used for endpoints when the authority is [Authority::AdfsHost].
+    AdfsHost(Url),
+    /// An Azure AD B2C authority with its own host. The url is the full authority
+    /// including the tenant and user flow, for instance
+    /// https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_signupsignin, and
+    /// endpoints are built from it such as
+    /// https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_signupsignin/oauth2/v2.0/token.
+    ///
+    /// The cloud instance is not used for endpoints when the authority is [Authority::B2C].
+    B2C(Url),
+}
+
+/// Scopes of OpenID Connect that are not scopes of a resource and are sent as the
+/// `scope` parameter to both endpoint versions.
+const OPENID_SCOPES: [&str; 4] = ["openid", "profile", "email", "offline_access"];
+
+/// `grant_type` of device code token requests to the v1.0 endpoint.
+pub(crate) const DEVICE_CODE_GRANT_TYPE_V1: &str = "device_code";
+
+/// `grant_type` of device code token requests to the v2.0 endpoint, defined by RFC 8628.
+pub(crate) const DEVICE_CODE_GRANT_TYPE_V2: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Version of the token endpoint of an [Authority].
+///
+/// The v2.0 endpoint takes the requested permissions as the `scope` parameter, such as
+/// `https://graph.microsoft.com/.default`. The v1.0 endpoint, used by AD FS, takes the
+/// resource instead, such as `resource=https://graph.microsoft.com`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum EndpointVersion {
+    V1,
+    #[default]
+    V2,
+}
+
+impl EndpointVersion {
+    /// The `grant_type` of device code token requests to the endpoint version,
+    /// [DEVICE_CODE_GRANT_TYPE_V1] or [DEVICE_CODE_GRANT_TYPE_V2].
+    pub(crate) fn device_code_grant_type(&self) -> &'static str {
+        match self {
+            EndpointVersion::V1 => DEVICE_CODE_GRANT_TYPE_V1,
+            EndpointVersion::V2 => DEVICE_CODE_GRANT_TYPE_V2,
+        }
+    }
+
+    /// Convert the `scope` or `resource` parameter of a token request form to the
+    /// parameter of the endpoint version.
+    ///
+    /// For [EndpointVersion::V1] a single `{resource}/.default` scope is sent as the
+    /// `resource` parameter. OpenID Connect scopes such as `openid` stay in the `scope`
+    /// parameter. Scopes that are not a `.default` scope, or `.default` scopes of more
+    /// than one resource, cannot be converted and return an error.
+    ///
+    /// For [EndpointVersion::V2] a `resource` parameter is sent as the `{resource}/.default` scope.
+    pub fn convert_scope_parameter(
+        &self,
+        form: &mut HashMap<String, String>,
+    ) -> IdentityResult<()> {
+        match self {
+            EndpointVersion::V1 => {
+                let scope = match form.remove("scope") {
+                    Some(scope) => scope,
+                    None => return Ok(()),
+                };
+                let (openid_scopes, resource_scopes): (Vec<&str>, Vec<&str>) = scope
+                    .split_whitespace()
+                    .partition(|scope| OPENID_SCOPES.contains(scope));
+
+                let resources = resource_scopes
+                    .iter()
+                    .map(|scope| {
+                        scope.strip_suffix("/.default").ok_or_else(|| {
+                            AF::msg_err(
+                                "scope".to_owned(),
+                                format!("scope {scope} cannot be sent to a v1.0 endpoint, use the {{resource}}/.default scope"),
+                            )
+                        })
+                    })
+                    .collect::<IdentityResult<Vec<&str>>>()?;
+
+                match resources.as_slice() {
+                    [] => {}
+                    [resource] => {
+                        form.insert("resource".into(), resource.to_string());
+                    }
+                    _ => {
+                        return AF::msg_result(
+                            "scope",
+                            format!(
+                                "a v1.0 endpoint accepts a single resource, found: {}",
+                                resources.join(", ")
+                            ),
+                        )
+                    }
+                }
+
+                if !openid_scopes.is_empty() {
+                    form.insert("scope".into(), openid_scopes.join(" "));
+                }
+                Ok(())
+            }
+            EndpointVersion::V2 => {
+                let resource = match form.remove("resource") {
+                    Some(resource) => resource,
+                    None => return Ok(()),
+                };
+                let resource_scope = format!("{}/.default", resource.trim_end_matches('/'));
+                let scope = match form.remove("scope") {
+                    Some(scope) if !scope.trim().is_empty() => format!("{resource_scope} {scope}"),
+                    _ => resource_scope,
+                };
+                form.insert("scope".into(), scope);
+                Ok(())
+            }
+        }
+    }
 }
 
 impl Authority {
+    /// The version of the token endpoint of the authority. [Authority::AdfsHost] uses
+    /// the v1.0 endpoint and all other authorities the v2.0 endpoint.
+    pub fn endpoint_version(&self) -> EndpointVersion {
+        match self {
+            Authority::AdfsHost(_) => EndpointVersion::V1,
+            _ => EndpointVersion::V2,
+        }
+    }
+
+    /// Create an [Authority::AdfsHost] from the full AD FS authority url, for instance
+    /// https://adfs.contoso.com/adfs.
+    pub fn adfs_host(authority_url: impl AsRef<str>) -> Result<Authority, ParseError> {
+        let authority_url = Url::parse(authority_url.as_ref())?;
+        if authority_url.cannot_be_a_base() || authority_url.host_str().is_none() {
+            return Err(ParseError::EmptyHost);
+        }
+        Ok(Authority::AdfsHost(authority_url))
+    }
+
+    /// Parse a full authority url such as https://login.microsoftonline.com/contoso.onmicrosoft.com
+    /// into the cloud instance of the host and the authority of the first path segment.
+    ///
+    /// Urls on a `b2clogin.com` host are parsed as an [Authority::B2C] and use
+    /// [AzureCloudInstance::AzurePublic]. Returns an error if the url is not https, the
+    /// host is not the login host of a cloud instance or B2C, or the url has no tenant.
+    pub fn parse_authority_url(
+        authority_url: &str,
+    ) -> IdentityResult<(AzureCloudInstance, Authority)> {
+        let url = Url::parse(authority_url.trim())
+            .map_err(|err| AF::msg_err("authority_url", err.to_string().as_str()))?;
+        if url.scheme() != "https" {
+            return AF::msg_result("authority_url", "authority url must use https");
+        }
+
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let tenant = url
+            .path_segments()
+            .and_then(|mut segments| segments.next())
+            .filter(|tenant| !tenant.is_empty())
+            .map(String::from);
+        let tenant = match tenant {
+            Some(tenant) => tenant,
+            None => {
+                return AF::msg_result(
+                    "authority_url",
+                    "authority url must include the tenant, such as https://login.microsoftonline.com/common",
+                )
+            }
+        };
+
+        if host.ends_with(".b2clogin.com") {
+            let mut authority_url = url;
+            authority_url.set_query(None);
+            authority_url.set_fragment(None);
+            return Ok((
+                AzureCloudInstance::AzurePublic,
+                Authority::B2C(authority_url),
+            ));
+        }
+
+        let cloud = [
+            AzureCloudInstance::AzurePublic,
+            AzureCloudInstance::AzureChina,
+            AzureCloudInstance::AzureGermany,
+            AzureCloudInstance::AzureUsGovernment,
+        ]
+        .into_iter()
+        .find(|cloud| cloud.login_host() == host);
+        match cloud {
+            Some(cloud) => Ok((cloud, Authority::from(tenant.as_str()))),
+            None => AF::msg_result(
+                "authority_url",
+                format!("{host} is not the login host of a cloud instance"),
+            ),
+        }
+    }
+
     pub fn tenant_id(&self) -> Option<&String> {
         match self {
             Authority::TenantId(tenant_id) => Some(tenant_id),
@@ -253,10 +513,14 @@ impl Authority {
     pub fn as_str(&self) -> &str {
         match self {
             Authority::AzureActiveDirectory | Authority::Common => "common",
-            Authority::AzureDirectoryFederatedServices => "adfs",
+            Authority::AzureDirectoryFederatedServices | Authority::AdfsHost(_) => "adfs",
             Authority::Organizations => "organizations",
             Authority::Consumers => "consumers",
             Authority::TenantId(tenant_id) => tenant_id.as_str(),
+            Authority::B2C(authority_url) => authority_url
+                .path_segments()
+                .and_then(|mut segments| segments.next())
+                .unwrap_or_default(),
         }
     }
 }
@@ -307,3 +571,175 @@ impl From<&str> for Authority {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn login_host_per_cloud() {
+        for (cloud, host) in [
+            (AzureCloudInstance::AzurePublic, "login.microsoftonline.com"),
+            (AzureCloudInstance::AzureChina, "login.chinacloudapi.cn"),
+            (AzureCloudInstance::AzureGermany, "login.microsoftonline.de"),
+            (
+                AzureCloudInstance::AzureUsGovernment,
+                "login.microsoftonline.us",
+            ),
+        ] {
+            assert_eq!(host, cloud.login_host());
+            assert_eq!(Some(host), Url::from(cloud).host_str());
+        }
+    }
+
+    #[test]
+    fn adfs_host_endpoints() {
+        let authority = Authority::adfs_host("https://adfs.contoso.com/adfs/").unwrap();
+        let cloud = AzureCloudInstance::AzurePublic;
+
+        assert_eq!(
+            "https://adfs.contoso.com/adfs/oauth2/token",
+            cloud.token_uri(&authority).unwrap().as_str()
+        );
+        assert_eq!(
+            "https://adfs.contoso.com/adfs/oauth2/authorize",
+            cloud.auth_uri(&authority).unwrap().as_str()
+        );
+        assert_eq!(
+            "https://adfs.contoso.com/adfs/.well-known/openid-configuration",
+            cloud.openid_configuration_uri(&authority).unwrap().as_str()
+        );
+        assert_eq!("adfs", authority.as_str());
+    }
+
+    #[test]
+    fn parse_standard_authority_url() {
+        let (cloud, authority) = Authority::parse_authority_url(
+            "https://login.microsoftonline.com/contoso.onmicrosoft.com",
+        )
+        .unwrap();
+        assert_eq!(AzureCloudInstance::AzurePublic, cloud);
+        assert_eq!(
+            Authority::TenantId("contoso.onmicrosoft.com".to_owned()),
+            authority
+        );
+
+        let (cloud, authority) =
+            Authority::parse_authority_url("https://login.microsoftonline.us/organizations/")
+                .unwrap();
+        assert_eq!(AzureCloudInstance::AzureUsGovernment, cloud);
+        assert_eq!(Authority::Organizations, authority);
+
+        assert!(Authority::parse_authority_url("http://login.microsoftonline.com/common").is_err());
+        assert!(Authority::parse_authority_url("https://login.microsoftonline.com").is_err());
+        assert!(Authority::parse_authority_url("https://login.contoso.com/common").is_err());
+        assert!(Authority::parse_authority_url("login.microsoftonline.com/common").is_err());
+    }
+
+    #[test]
+    fn parse_b2c_authority_url() {
+        let (cloud, authority) = Authority::parse_authority_url(
+            "https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_signupsignin",
+        )
+        .unwrap();
+        assert_eq!(AzureCloudInstance::AzurePublic, cloud);
+        assert_eq!("contoso.onmicrosoft.com", authority.as_str());
+        assert_eq!(EndpointVersion::V2, authority.endpoint_version());
+        assert_eq!(
+            "https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_signupsignin/oauth2/v2.0/token",
+            cloud.token_uri(&authority).unwrap().as_str()
+        );
+        assert_eq!(
+            "https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_signupsignin/oauth2/v2.0/authorize",
+            cloud.auth_uri(&authority).unwrap().as_str()
+        );
+    }
+
+    #[test]
+    fn adfs_host_requires_host() {
+        assert!(Authority::adfs_host("adfs").is_err());
+        assert!(Authority::adfs_host("mailto:admin@contoso.com").is_err());
+    }
+
+    fn form(name: &str, value: &str) -> HashMap<String, String> {
+        HashMap::from([
+            ("grant_type".to_owned(), "client_credentials".to_owned()),
+            (name.to_owned(), value.to_owned()),
+        ])
+    }
+
+    #[test]
+    fn default_scope_for_each_endpoint_version() {
+        let authority = Authority::adfs_host("https://adfs.contoso.com/adfs").unwrap();
+        assert_eq!(EndpointVersion::V1, authority.endpoint_version());
+        assert_eq!(EndpointVersion::V2, Authority::Common.endpoint_version());
+        assert_eq!(
+            EndpointVersion::V2,
+            Authority::AzureDirectoryFederatedServices.endpoint_version()
+        );
+
+        let mut v1_form = form("scope", "https://graph.microsoft.com/.default");
+        EndpointVersion::V1
+            .convert_scope_parameter(&mut v1_form)
+            .unwrap();
+        assert_eq!(
+            Some(&"https://graph.microsoft.com".to_owned()),
+            v1_form.get("resource")
+        );
+        assert!(!v1_form.contains_key("scope"));
+
+        let mut v2_form = form("scope", "https://graph.microsoft.com/.default");
+        EndpointVersion::V2
+            .convert_scope_parameter(&mut v2_form)
+            .unwrap();
+        assert_eq!(
+            Some(&"https://graph.microsoft.com/.default".to_owned()),
+            v2_form.get("scope")
+        );
+        assert!(!v2_form.contains_key("resource"));
+
+        let mut v2_form = form("resource", "https://graph.microsoft.com/");
+        EndpointVersion::V2
+            .convert_scope_parameter(&mut v2_form)
+            .unwrap();
+        assert_eq!(
+            Some(&"https://graph.microsoft.com/.default".to_owned()),
+            v2_form.get("scope")
+        );
+    }
+
+    #[test]
+    fn v1_keeps_openid_scopes() {
+        let mut v1_form = form(
+            "scope",
+            "openid offline_access https://management.azure.com/.default",
+        );
+        EndpointVersion::V1
+            .convert_scope_parameter(&mut v1_form)
+            .unwrap();
+        assert_eq!(
+            Some(&"https://management.azure.com".to_owned()),
+            v1_form.get("resource")
+        );
+        assert_eq!(
+            Some(&"openid offline_access".to_owned()),
+            v1_form.get("scope")
+        );
+    }
+
+    #[test]
+    fn v1_rejects_scopes_without_resource() {
+        let mut v1_form = form("scope", "User.Read");
+        assert!(EndpointVersion::V1
+            .convert_scope_parameter(&mut v1_form)
+            .is_err());
+
+        let mut v1_form = form(
+            "scope",
+            "https://graph.microsoft.com/.default https://management.azure.com/.default",
+        );
+        assert!(EndpointVersion::V1
+            .convert_scope_parameter(&mut v1_form)
+            .is_err());
+    }
+}
@@ -1,9 +1,13 @@
+use graph_error::{AuthorizationFailure, IdentityResult, AF};
 use serde::Deserializer;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
+use std::str::FromStr;
 use url::Url;
 
+use crate::identity::IdToken;
+
 /// The specification defines errors here:
 /// https://datatracker.ietf.org/doc/html/draft-ietf-oauth-v2-31#section-4.2.2.1
 ///
@@ -84,6 +88,18 @@ impl Display for AuthorizationResponseError {
     }
 }
 
+/// Parse the AADSTS error code from the text of an error_description such as
+/// `AADSTS50076: Due to a configuration change made by your administrator...`.
+///
+/// Use when the numeric `error_codes` are not included in the response.
+pub fn parse_aadsts_from_description(error_description: &str) -> Option<u32> {
+    let (_, code) = error_description.split_once("AADSTS")?;
+    let end = code
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(code.len());
+    code[..end].parse().ok()
+}
+
 fn deserialize_expires_in<'de, D>(expires_in: D) -> Result<Option<i64>, D::Error>
 where
     D: Deserializer<'de>,
@@ -154,6 +170,118 @@ impl AuthorizationResponse {
     pub fn is_err(&self) -> bool {
         self.error.is_some()
     }
+
+    /// The AADSTS error code of an error response. Uses the first value of `error_codes`
+    /// if included in the response and otherwise falls back to parsing the code from
+    /// the error_description.
+    pub fn error_code(&self) -> Option<u32> {
+        let error_code = match self.additional_fields.get("error_codes") {
+            Some(Value::Array(error_codes)) => error_codes.first().and_then(|code| match code {
+                Value::Number(code) => code.as_u64().and_then(|code| u32::try_from(code).ok()),
+                Value::String(code) => code.parse().ok(),
+                _ => None,
+            }),
+            Some(Value::String(error_codes)) => error_codes
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .find_map(|code| {
+                    code.trim_matches(|c: char| c == '[' || c == ']')
+                        .parse()
+                        .ok()
+                }),
+            _ => None,
+        };
+
+        error_code.or_else(|| {
+            self.error_description
+                .as_deref()
+                .and_then(parse_aadsts_from_description)
+        })
+    }
+
+    /// Returns the response if it is not an error response. A response with
+    /// `error=access_denied`, for instance because the user declined consent, is returned
+    /// as [AuthorizationFailure::AccessDenied] and other errors as
+    /// [AuthorizationFailure::RequiredValue] with the error and error_description.
+    pub fn into_result(self) -> IdentityResult<AuthorizationResponse> {
+        match self.error.as_ref() {
+            None => Ok(self),
+            Some(AuthorizationResponseError::AccessDenied) => Err(AF::AccessDenied {
+                error_description: self.error_description,
+            }),
+            Some(error) => AF::msg_result(
+                "error",
+                format!(
+                    "{error}, error_description: {}",
+                    self.error_description.as_deref().unwrap_or_default()
+                ),
+            ),
+        }
+    }
+
+    /// Validate the `at_hash` and `c_hash` claims of the id token against the access
+    /// token and code of the response, as required for the hybrid and implicit flows.
+    /// Claims that are not present in the id token are not validated.
+    pub fn validate_id_token_hashes(&self) -> IdentityResult<()> {
+        if let Some(id_token) = self.id_token.as_ref() {
+            let id_token = IdToken::new(id_token, None, None, None);
+            if let Some(access_token) = self.access_token.as_ref() {
+                id_token.validate_at_hash(access_token)?;
+            }
+            if let Some(code) = self.code.as_ref() {
+                id_token.validate_c_hash(code)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn has_response_fields(&self) -> bool {
+        self.code.is_some()
+            || self.id_token.is_some()
+            || self.access_token.is_some()
+            || self.error.is_some()
+    }
+
+    /// Parse the query or fragment of a redirect uri. The query is checked first and
+    /// then the fragment. Returns an error if neither contains a code, id_token,
+    /// access_token, or error.
+    pub fn from_redirect_uri(uri: &Url) -> IdentityResult<AuthorizationResponse> {
+        [uri.query(), uri.fragment()]
+            .into_iter()
+            .flatten()
+            .filter_map(|value| serde_urlencoded::from_str::<AuthorizationResponse>(value).ok())
+            .find(|response| response.has_response_fields())
+            .ok_or_else(|| {
+                AF::msg_err(
+                    "redirect_uri",
+                    "query or fragment does not contain an authorization response",
+                )
+            })
+    }
+}
+
+impl TryFrom<&Url> for AuthorizationResponse {
+    type Error = AuthorizationFailure;
+
+    fn try_from(value: &Url) -> Result<Self, Self::Error> {
+        AuthorizationResponse::from_redirect_uri(value)
+    }
+}
+
+impl TryFrom<Url> for AuthorizationResponse {
+    type Error = AuthorizationFailure;
+
+    fn try_from(value: Url) -> Result<Self, Self::Error> {
+        AuthorizationResponse::from_redirect_uri(&value)
+    }
+}
+
+impl FromStr for AuthorizationResponse {
+    type Err = AuthorizationFailure;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let uri = Url::parse(s)?;
+        AuthorizationResponse::from_redirect_uri(&uri)
+    }
 }
 
 impl Debug for AuthorizationResponse {
@@ -227,4 +355,130 @@ mod test {
         let response: AuthorizationResponse = serde_urlencoded::from_str(query).unwrap();
         assert_eq!(Some(String::from("token")), response.access_token);
     }
+
+    #[test]
+    pub fn try_from_query_url() {
+        let uri = Url::parse("http://localhost:8000/redirect?code=code&state=state").unwrap();
+        let response = AuthorizationResponse::try_from(&uri).unwrap();
+        assert_eq!(Some(String::from("code")), response.code);
+        assert_eq!(Some(String::from("state")), response.state);
+    }
+
+    #[test]
+    pub fn try_from_fragment_url() {
+        let response = AuthorizationResponse::from_str(
+            "http://localhost:8000/redirect#id_token=id_token&session_state=session_state",
+        )
+        .unwrap();
+        assert_eq!(Some(String::from("id_token")), response.id_token);
+        assert_eq!(Some(String::from("session_state")), response.session_state);
+    }
+
+    #[test]
+    pub fn try_from_invalid_url() {
+        let uri = Url::parse("http://localhost:8000/redirect?name=value").unwrap();
+        assert!(AuthorizationResponse::try_from(&uri).is_err());
+        assert!(AuthorizationResponse::from_str("http://localhost:8000/redirect").is_err());
+        assert!(AuthorizationResponse::from_str("not a url").is_err());
+    }
+
+    #[test]
+    pub fn parse_aadsts_code() {
+        assert_eq!(
+            Some(50076),
+            parse_aadsts_from_description(
+                "AADSTS50076: Due to a configuration change made by your administrator, \
+                or because you moved to a new location, you must use multi-factor authentication \
+                to access '00000003-0000-0000-c000-000000000000'. \
+                Trace ID: 0000aaaa-11bb-cccc-dd22-eeeeee333333"
+            )
+        );
+        assert_eq!(
+            Some(65004),
+            parse_aadsts_from_description(
+                "AADSTS65004: User declined to consent to access the app."
+            )
+        );
+        assert_eq!(
+            Some(700016),
+            parse_aadsts_from_description(
+                "invalid_client: AADSTS700016: Application with identifier 'id' was not found"
+            )
+        );
+        assert_eq!(
+            None,
+            parse_aadsts_from_description("The user or administrator has not consented")
+        );
+        assert_eq!(None, parse_aadsts_from_description("AADSTS: missing code"));
+    }
+
+    #[test]
+    pub fn error_code_falls_back_to_description() {
+        let response: AuthorizationResponse = serde_urlencoded::from_str(
+            "error=access_denied&error_description=AADSTS65004%3A+User+declined+to+consent",
+        )
+        .unwrap();
+        assert_eq!(Some(65004), response.error_code());
+
+        let response: AuthorizationResponse = serde_json::from_value(serde_json::json!({
+            "error": "interaction_required",
+            "error_description": "AADSTS50076: Multi-factor authentication required",
+            "error_codes": [50079]
+        }))
+        .unwrap();
+        assert_eq!(Some(50079), response.error_code());
+    }
+
+    #[test]
+    pub fn access_denied_redirect() {
+        let response = AuthorizationResponse::from_str(
+            "http://localhost:8000/redirect?error=access_denied\
+            &error_description=AADSTS65004%3A+User+declined+to+consent+to+access+the+app.\
+            &state=state",
+        )
+        .unwrap();
+
+        match response.into_result() {
+            Err(AF::AccessDenied { error_description }) => assert_eq!(
+                Some("AADSTS65004: User declined to consent to access the app.".to_owned()),
+                error_description
+            ),
+            result => panic!("expected access denied error: {result:#?}"),
+        }
+
+        let response =
+            AuthorizationResponse::from_str("http://localhost:8000/redirect?error=server_error")
+                .unwrap();
+        assert!(matches!(
+            response.into_result(),
+            Err(AF::RequiredValue { .. })
+        ));
+
+        let response =
+            AuthorizationResponse::from_str("http://localhost:8000/redirect?code=code").unwrap();
+        assert_eq!(
+            Some("code".to_owned()),
+            response.into_result().unwrap().code
+        );
+    }
+
+    #[test]
+    pub fn hybrid_flow_id_token_hashes() {
+        use base64::Engine;
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let id_token = format!(
+            "{}.{}.signature",
+            engine.encode(r#"{"alg":"RS256","typ":"JWT"}"#),
+            engine.encode(r#"{"c_hash":"LDktKdoQak3Pk0cnXxCltA"}"#)
+        );
+
+        let mut response = AuthorizationResponse::from_str(&format!(
+            "http://localhost:8000/redirect#code=Qcb0Orv1zh30vL1MPRsbm-diHiMwcLyZvn1arpZv-Jxf_11jnpEX3Tgfvk&id_token={id_token}"
+        ))
+        .unwrap();
+        assert!(response.validate_id_token_hashes().is_ok());
+
+        response.code = Some("code".to_owned());
+        assert!(response.validate_id_token_hashes().is_err());
+    }
 }
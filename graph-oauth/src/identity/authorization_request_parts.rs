@@ -1,7 +1,18 @@
-use http::header::CONTENT_TYPE;
-use http::{HeaderMap, HeaderValue};
+use base64::Engine;
+use http::header::{AUTHORIZATION, CONTENT_TYPE};
+use http::{HeaderMap, HeaderValue, Method};
 use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
 use url::Url;
+use uuid::Uuid;
+
+/// Header used to correlate a token request with the logs of the Microsoft identity
+/// platform (ESTS).
+pub const CLIENT_REQUEST_ID_HEADER: &str = "client-request-id";
+
+/// Header that asks the Microsoft identity platform to echo the `client-request-id`
+/// in the response.
+pub const RETURN_CLIENT_REQUEST_ID_HEADER: &str = "return-client-request-id";
 
 pub struct AuthorizationRequestParts {
     pub(crate) uri: Url,
@@ -29,6 +40,16 @@ impl AuthorizationRequestParts {
         }
     }
 
+    pub fn with_client_request_id(&mut self, client_request_id: Uuid) {
+        if let Ok(header_value) = HeaderValue::from_str(client_request_id.to_string().as_str()) {
+            self.headers.insert(CLIENT_REQUEST_ID_HEADER, header_value);
+        }
+        self.headers.insert(
+            RETURN_CLIENT_REQUEST_ID_HEADER,
+            HeaderValue::from_static("true"),
+        );
+    }
+
     pub fn with_extra_headers(&mut self, extra_headers: &HeaderMap) {
         for (header_name, header_value) in extra_headers.iter() {
             self.headers.insert(header_name, header_value.clone());
@@ -43,3 +64,58 @@ impl AuthorizationRequestParts {
         }
     }
 }
+
+/// A token request that is ready to be sent, returned by
+/// [prepare_request](crate::identity::TokenCredentialExecutor::prepare_request) without
+/// performing the network call. Used for debugging or to send the token request with
+/// another HTTP client.
+///
+/// The request includes the secrets of the credential: the client secret or assertion
+/// in the form, and the client secret in the `Authorization` header when the credential
+/// uses basic auth. The [Debug] output lists the header names and form keys only.
+#[derive(Clone)]
+pub struct PreparedTokenRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    pub form: HashMap<String, String>,
+}
+
+impl PreparedTokenRequest {
+    /// The form url encoded request body.
+    pub fn body(&self) -> String {
+        serde_urlencoded::to_string(&self.form).unwrap_or_default()
+    }
+}
+
+impl From<AuthorizationRequestParts> for PreparedTokenRequest {
+    fn from(value: AuthorizationRequestParts) -> Self {
+        let mut headers = value.headers;
+        if let Some((client_identifier, secret)) = value.basic_auth {
+            let credentials = base64::engine::general_purpose::STANDARD
+                .encode(format!("{client_identifier}:{secret}"));
+            if let Ok(mut header_value) = HeaderValue::from_str(&format!("Basic {credentials}")) {
+                header_value.set_sensitive(true);
+                headers.insert(AUTHORIZATION, header_value);
+            }
+        }
+
+        PreparedTokenRequest {
+            method: Method::POST,
+            url: value.uri,
+            headers,
+            form: value.form_urlencoded,
+        }
+    }
+}
+
+impl Debug for PreparedTokenRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PreparedTokenRequest")
+            .field("method", &self.method)
+            .field("url", &self.url.as_str())
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("form", &self.form.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
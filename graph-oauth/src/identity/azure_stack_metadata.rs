@@ -0,0 +1,246 @@
+use reqwest::tls::Version;
+use reqwest::IntoUrl;
+use url::Url;
+
+use graph_core::http::{AsyncResponseConverterExt, ResponseConverterExt};
+use graph_error::{AuthExecutionError, AuthExecutionResult, IdentityResult, AF};
+
+use crate::identity::{Authority, AzureCloudInstance};
+
+/// Authentication endpoints of an Azure Stack Hub deployment.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AzureStackAuthentication {
+    /// The AD FS or Azure Active Directory login endpoint of the deployment,
+    /// for instance https://adfs.local.azurestack.external/adfs/
+    pub login_endpoint: Url,
+    #[serde(default)]
+    pub audiences: Vec<String>,
+}
+
+/// Endpoints of an Azure Stack Hub deployment as returned by the metadata endpoint
+/// of Azure Resource Manager, for instance
+/// https://management.local.azurestack.external/metadata/endpoints?api-version=2015-01-01
+///
+/// Azure Stack Hub deployments that use AD FS, including disconnected deployments,
+/// have their own login endpoint which is used as an [Authority::AdfsHost]. Deployments
+/// that use Azure Active Directory use the login endpoint of one of the Azure clouds.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AzureStackMetadata {
+    #[serde(default)]
+    pub gallery_endpoint: Option<String>,
+    #[serde(default)]
+    pub graph_endpoint: Option<String>,
+    #[serde(default)]
+    pub portal_endpoint: Option<String>,
+    pub authentication: AzureStackAuthentication,
+}
+
+impl AzureStackMetadata {
+    /// Fetch the metadata from the metadata endpoint. The metadata url must use https.
+    pub fn fetch(metadata_url: impl IntoUrl) -> AuthExecutionResult<AzureStackMetadata> {
+        AzureStackMetadata::get(metadata_url, true)
+    }
+
+    /// Fetch the metadata from the metadata endpoint. The metadata url must use https.
+    pub async fn fetch_async(
+        metadata_url: impl IntoUrl,
+    ) -> AuthExecutionResult<AzureStackMetadata> {
+        AzureStackMetadata::get_async(metadata_url, true).await
+    }
+
+    /// Same as [AzureStackMetadata::fetch] but allows http in order to use mocking frameworks.
+    #[cfg(feature = "test-util")]
+    pub fn fetch_with_https_only(
+        metadata_url: impl IntoUrl,
+        https_only: bool,
+    ) -> AuthExecutionResult<AzureStackMetadata> {
+        AzureStackMetadata::get(metadata_url, https_only)
+    }
+
+    /// Same as [AzureStackMetadata::fetch_async] but allows http in order to use
+    /// mocking frameworks.
+    #[cfg(feature = "test-util")]
+    pub async fn fetch_async_with_https_only(
+        metadata_url: impl IntoUrl,
+        https_only: bool,
+    ) -> AuthExecutionResult<AzureStackMetadata> {
+        AzureStackMetadata::get_async(metadata_url, https_only).await
+    }
+
+    fn get(
+        metadata_url: impl IntoUrl,
+        https_only: bool,
+    ) -> AuthExecutionResult<AzureStackMetadata> {
+        let http_client = reqwest::blocking::ClientBuilder::new()
+            .min_tls_version(Version::TLS_1_2)
+            .https_only(https_only)
+            .build()?;
+        let response = http_client.get(metadata_url.into_url()?).send()?;
+
+        if !response.status().is_success() {
+            return Err(AuthExecutionError::silent_token_auth(
+                response.into_http_response()?,
+            ));
+        }
+
+        Ok(response.json()?)
+    }
+
+    async fn get_async(
+        metadata_url: impl IntoUrl,
+        https_only: bool,
+    ) -> AuthExecutionResult<AzureStackMetadata> {
+        let http_client = reqwest::ClientBuilder::new()
+            .min_tls_version(Version::TLS_1_2)
+            .https_only(https_only)
+            .build()?;
+        let response = http_client.get(metadata_url.into_url()?).send().await?;
+
+        if !response.status().is_success() {
+            return Err(AuthExecutionError::silent_token_auth(
+                response.into_http_response_async().await?,
+            ));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// True if the login endpoint is an AD FS endpoint.
+    pub fn is_adfs(&self) -> bool {
+        self.authentication
+            .login_endpoint
+            .path()
+            .trim_end_matches('/')
+            .to_ascii_lowercase()
+            .ends_with("/adfs")
+    }
+
+    /// The AD FS authority for deployments that use AD FS.
+    pub fn adfs_authority(&self) -> Option<Authority> {
+        if self.is_adfs() {
+            Some(Authority::AdfsHost(
+                self.authentication.login_endpoint.clone(),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// The Azure cloud of the login endpoint for deployments that use Azure Active Directory.
+    /// Returns an error if the login endpoint is not the login endpoint of a known Azure cloud.
+    pub fn azure_cloud_instance(&self) -> IdentityResult<AzureCloudInstance> {
+        let login_host = self.authentication.login_endpoint.host_str();
+        [
+            AzureCloudInstance::AzurePublic,
+            AzureCloudInstance::AzureChina,
+            AzureCloudInstance::AzureGermany,
+            AzureCloudInstance::AzureUsGovernment,
+        ]
+        .into_iter()
+        .find(|instance| Url::from(instance).host_str() == login_host)
+        .ok_or_else(|| {
+            AF::msg_err(
+                "loginEndpoint",
+                format!(
+                    "{} is not an AD FS or Azure login endpoint",
+                    self.authentication.login_endpoint
+                )
+                .as_str(),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn metadata_document(login_endpoint: &str) -> String {
+        serde_json::json!({
+            "galleryEndpoint": "https://providers.local.azurestack.external:30016/",
+            "graphEndpoint": "https://graph.local.azurestack.external/",
+            "portalEndpoint": "https://portal.local.azurestack.external/",
+            "authentication": {
+                "loginEndpoint": login_endpoint,
+                "audiences": ["https://management.adfs.azurestack.local/4de154de-f8a8-4017-af41-df619da68155"]
+            }
+        })
+        .to_string()
+    }
+
+    /// Serves the metadata document at the metadata endpoint of Azure Resource Manager.
+    async fn mock_metadata_endpoint(body: String) -> (MockServer, Url) {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/metadata/endpoints"))
+            .and(query_param("api-version", "2015-01-01"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "application/json"))
+            .mount(&mock_server)
+            .await;
+
+        let url = Url::parse(&format!(
+            "{}/metadata/endpoints?api-version=2015-01-01",
+            mock_server.uri()
+        ))
+        .unwrap();
+        (mock_server, url)
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn fetch_adfs_metadata() {
+        let (_mock_server, url) = mock_metadata_endpoint(metadata_document(
+            "https://adfs.local.azurestack.external/adfs/",
+        ))
+        .await;
+        let metadata = AzureStackMetadata::fetch_async_with_https_only(url, false)
+            .await
+            .unwrap();
+
+        assert!(metadata.is_adfs());
+        let authority = metadata.adfs_authority().unwrap();
+        assert_eq!(
+            "https://adfs.local.azurestack.external/adfs/oauth2/token",
+            AzureCloudInstance::default()
+                .token_uri(&authority)
+                .unwrap()
+                .as_str()
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_requires_https() {
+        let (mock_server, url) = mock_metadata_endpoint(metadata_document(
+            "https://adfs.local.azurestack.external/adfs/",
+        ))
+        .await;
+
+        assert!(AzureStackMetadata::fetch_async(url).await.is_err());
+        assert!(mock_server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn azure_active_directory_metadata() {
+        let metadata: AzureStackMetadata =
+            serde_json::from_str(metadata_document("https://login.microsoftonline.us/").as_str())
+                .unwrap();
+
+        assert!(!metadata.is_adfs());
+        assert_eq!(None, metadata.adfs_authority());
+        assert_eq!(
+            AzureCloudInstance::AzureUsGovernment,
+            metadata.azure_cloud_instance().unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_login_endpoint() {
+        let metadata: AzureStackMetadata =
+            serde_json::from_str(metadata_document("https://login.contoso.com/").as_str()).unwrap();
+        assert!(metadata.azure_cloud_instance().is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use graph_error::{AuthExecutionError, AuthExecutionResult};
+use tokio::sync::Notify;
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// Cancels an in-flight token request such as a silent token refresh. Clones share
+/// the same state so a token can be handed to another task or a network change
+/// handler and cancelled from there.
+///
+/// A cancelled request never writes to the token cache. The previously cached token,
+/// if any, is left as is so that the request can be retried with a new
+/// [CancellationToken].
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    state: Arc<CancellationState>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes once [CancellationToken::cancel] has been called.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.state.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Run the future until it completes or this token is cancelled. The future is
+    /// dropped on cancellation and [AuthExecutionError::Cancelled] is returned.
+    pub(crate) async fn run_until_cancelled<T, F>(&self, future: F) -> AuthExecutionResult<T>
+    where
+        F: Future<Output = AuthExecutionResult<T>>,
+    {
+        tokio::select! {
+            biased;
+            _ = self.cancelled() => Err(AuthExecutionError::Cancelled),
+            result = future => result,
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use std::collections::BTreeSet;
+
+use graph_core::identity::{Claims, DecodedJwt};
+use graph_error::{IdentityResult, AF};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::identity::{AzureCloudInstance, NonceStore};
+
+/// Application id of the Microsoft Graph resource. Used as the `aud` claim in
+/// some access tokens issued for Microsoft Graph.
+pub const MICROSOFT_GRAPH_RESOURCE_ID: &str = "00000003-0000-0000-c000-000000000000";
+
+/// Validates the claims of a decoded access token such as the app roles (`roles`)
+/// and delegated permissions (`scp`) that a resource server requires before
+/// accepting a request.
+///
+/// Decoding and verifying the signature of the token is not done here. The
+/// [ClaimsValidator] only operates on claims that have already been decoded.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClaimsValidator {
+    claims: Claims,
+}
+
+impl ClaimsValidator {
+    pub fn new(claims: Claims) -> ClaimsValidator {
+        ClaimsValidator { claims }
+    }
+
+    pub fn claims(&self) -> &Claims {
+        &self.claims
+    }
+
+    /// The app roles in the `roles` claim. Application permissions granted to
+    /// the client are returned in this claim.
+    pub fn roles(&self) -> BTreeSet<String> {
+        match self.claims.additional_fields.get("roles") {
+            Some(Value::Array(roles)) => roles
+                .iter()
+                .filter_map(|role| role.as_str().map(|s| s.to_owned()))
+                .collect(),
+            Some(Value::String(role)) => BTreeSet::from([role.to_owned()]),
+            _ => BTreeSet::new(),
+        }
+    }
+
+    /// The delegated permissions in the space separated `scp` claim.
+    pub fn scopes(&self) -> BTreeSet<String> {
+        match self.claims.additional_fields.get("scp") {
+            Some(Value::String(scp)) => scp.split_whitespace().map(|s| s.to_owned()).collect(),
+            _ => BTreeSet::new(),
+        }
+    }
+
+    /// Returns an error listing each of the required roles that are missing
+    /// from the `roles` claim.
+    pub fn require_roles(&self, roles: &[&str]) -> IdentityResult<()> {
+        ClaimsValidator::require("roles", self.roles(), roles)
+    }
+
+    /// Returns an error listing each of the required scopes that are missing
+    /// from the `scp` claim.
+    pub fn require_scopes(&self, scopes: &[&str]) -> IdentityResult<()> {
+        ClaimsValidator::require("scp", self.scopes(), scopes)
+    }
+
+    /// Returns an error if the `aud` claim is not the Microsoft Graph application id
+    /// or the Microsoft Graph url for the given cloud, such as https://graph.microsoft.com
+    /// for [AzureCloudInstance::AzurePublic].
+    pub fn require_graph_audience(&self, cloud: &AzureCloudInstance) -> IdentityResult<()> {
+        let graph_url = cloud
+            .default_microsoft_graph_scope()
+            .trim_end_matches("/.default");
+
+        if self.has_audience(&[MICROSOFT_GRAPH_RESOURCE_ID, graph_url]) {
+            Ok(())
+        } else {
+            AF::msg_result(
+                "aud",
+                format!("token audience {} is not Microsoft Graph", self.claims.aud),
+            )
+        }
+    }
+
+    /// Returns an error if the `aud` claim does not match any of the given audiences.
+    ///
+    /// Depending on the token version the `aud` claim is the application id of the
+    /// resource, the resource uri, or the `api://{client_id}` application id uri. An
+    /// application id and `api://` uri with the same id match each other, and trailing
+    /// slashes and letter case are ignored.
+    pub fn require_audience(&self, audiences: &[&str]) -> IdentityResult<()> {
+        if self.has_audience(audiences) {
+            Ok(())
+        } else {
+            AF::msg_result(
+                "aud",
+                format!(
+                    "token audience {} is not one of: {}",
+                    self.claims.aud,
+                    audiences.join(", ")
+                ),
+            )
+        }
+    }
+
+    /// Returns an error if the `tid` claim is not the given tenant id, for instance to
+    /// pin a multi-tenant application to a single tenant. The tenant id must be the
+    /// tenant GUID, tenant domain names are not accepted. Letter case is ignored.
+    pub fn require_tenant(&self, tenant_id: &str) -> IdentityResult<()> {
+        let tenant_id = Uuid::parse_str(tenant_id.trim()).map_err(|_| {
+            AF::msg_err(
+                "tid".to_owned(),
+                format!("{tenant_id} is not a tenant GUID"),
+            )
+        })?;
+
+        match self.claims.tid.as_deref() {
+            Some(tid) if Uuid::parse_str(tid.trim()).ok() == Some(tenant_id) => Ok(()),
+            Some(tid) => AF::msg_result(
+                "tid",
+                format!("token tenant {tid} is not the required tenant {tenant_id}"),
+            ),
+            None => AF::msg_result("tid", "token does not have a tid claim"),
+        }
+    }
+
+    /// Returns an error if the `iss` claim is not the issuer of the tenant in the given
+    /// cloud. Both the v1.0 issuer, such as `https://sts.windows.net/{tid}/`, and the v2.0
+    /// issuer, such as `https://login.microsoftonline.com/{tid}/v2.0`, are accepted since
+    /// the token version depends on the resource and not on the endpoint the token was
+    /// requested from. Trailing slashes and letter case are ignored.
+    pub fn require_issuer(
+        &self,
+        cloud: &AzureCloudInstance,
+        tenant_id: &str,
+    ) -> IdentityResult<()> {
+        let tenant_id = tenant_id.trim();
+        let issuers = [
+            format!(
+                "https://{}/{}",
+                ClaimsValidator::v1_issuer_host(cloud),
+                tenant_id
+            ),
+            format!("{}/{}/v2.0", cloud.as_ref(), tenant_id),
+        ];
+
+        let iss = self.claims.iss.trim().trim_end_matches('/');
+        if issuers
+            .iter()
+            .any(|issuer| issuer.eq_ignore_ascii_case(iss))
+        {
+            Ok(())
+        } else {
+            AF::msg_result(
+                "iss",
+                format!(
+                    "token issuer {} is not one of: {}",
+                    self.claims.iss,
+                    issuers.join(", ")
+                ),
+            )
+        }
+    }
+
+    /// The client id of the application that requested the token, the `azp` claim of
+    /// v2.0 access tokens or the `appid` claim of v1.0 access tokens.
+    pub fn authorized_party(&self) -> Option<&str> {
+        ["azp", "appid"].iter().find_map(|claim| {
+            self.claims
+                .additional_fields
+                .get(*claim)
+                .and_then(Value::as_str)
+        })
+    }
+
+    /// Returns an error if the application that requested the token is not one of the
+    /// given client ids, for instance to restrict a resource server to known clients.
+    /// The `azp` claim is used for v2.0 access tokens and the `appid` claim for v1.0
+    /// access tokens. Letter case is ignored.
+    pub fn require_authorized_party(&self, client_ids: &[&str]) -> IdentityResult<()> {
+        match self.authorized_party() {
+            Some(authorized_party)
+                if client_ids
+                    .iter()
+                    .any(|client_id| client_id.trim().eq_ignore_ascii_case(authorized_party)) =>
+            {
+                Ok(())
+            }
+            Some(authorized_party) => AF::msg_result(
+                "azp",
+                format!("client application {authorized_party} is not an allowed client"),
+            ),
+            None => AF::msg_result("azp", "token does not have an azp or appid claim"),
+        }
+    }
+
+    /// Returns an error if the `nonce` claim does not match the nonce of the request.
+    pub fn require_nonce(&self, nonce: &str) -> IdentityResult<()> {
+        match self.claims.nonce.as_deref() {
+            Some(claim) if claim == nonce => Ok(()),
+            Some(_) => AF::msg_result("nonce", "nonce does not match the nonce of the request"),
+            None => AF::msg_result("nonce", "token does not have a nonce claim"),
+        }
+    }
+
+    /// Returns an error if the `nonce` claim does not match the nonce of the request or
+    /// if the nonce was already accepted. The nonce is recorded in the store when it is
+    /// accepted so that a replayed token with the same nonce is rejected.
+    pub fn require_unused_nonce(&self, nonce: &str, store: &dyn NonceStore) -> IdentityResult<()> {
+        self.require_nonce(nonce)?;
+        if store.record(nonce) {
+            Ok(())
+        } else {
+            AF::msg_result("nonce", "nonce was already used")
+        }
+    }
+
+    fn v1_issuer_host(cloud: &AzureCloudInstance) -> &'static str {
+        match cloud {
+            AzureCloudInstance::AzurePublic | AzureCloudInstance::AzureUsGovernment => {
+                "sts.windows.net"
+            }
+            AzureCloudInstance::AzureChina => "sts.chinacloudapi.cn",
+            AzureCloudInstance::AzureGermany => "sts.microsoftonline.de",
+        }
+    }
+
+    fn has_audience(&self, audiences: &[&str]) -> bool {
+        let aud = ClaimsValidator::normalize_audience(self.claims.aud.as_str());
+        audiences
+            .iter()
+            .any(|audience| ClaimsValidator::normalize_audience(audience) == aud)
+    }
+
+    fn normalize_audience(audience: &str) -> String {
+        let audience = audience.trim().trim_end_matches('/');
+        let application_id = audience.strip_prefix("api://").unwrap_or(audience);
+        match Uuid::parse_str(application_id) {
+            Ok(uuid) => uuid.hyphenated().to_string(),
+            Err(_) => audience.to_ascii_lowercase(),
+        }
+    }
+
+    fn require(claim: &str, granted: BTreeSet<String>, required: &[&str]) -> IdentityResult<()> {
+        let missing: Vec<&str> = required
+            .iter()
+            .filter(|value| !granted.contains(**value))
+            .copied()
+            .collect();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            AF::msg_result(
+                claim,
+                format!("missing required values: {}", missing.join(", ")),
+            )
+        }
+    }
+}
+
+impl From<Claims> for ClaimsValidator {
+    fn from(value: Claims) -> Self {
+        ClaimsValidator::new(value)
+    }
+}
+
+impl From<DecodedJwt> for ClaimsValidator {
+    fn from(value: DecodedJwt) -> Self {
+        ClaimsValidator::new(value.claims)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn claims() -> Claims {
+        serde_json::from_value(serde_json::json!({
+            "aud": "https://graph.microsoft.com",
+            "iss": "https://login.microsoftonline.com/tenant/v2.0",
+            "iat": 1700000000,
+            "nbf": 1700000000,
+            "exp": 1700003600,
+            "roles": ["User.Read.All", "Mail.Read"],
+            "scp": "User.Read Files.Read"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn missing_required_role() {
+        let validator = ClaimsValidator::new(claims());
+        let err = validator
+            .require_roles(&["User.Read.All", "Sites.Read.All", "Group.Read.All"])
+            .unwrap_err();
+
+        match err {
+            AF::RequiredValue { name, message } => {
+                assert_eq!("roles", name);
+                assert_eq!(
+                    Some("missing required values: Sites.Read.All, Group.Read.All".to_owned()),
+                    message
+                );
+            }
+            _ => panic!("unexpected error: {:#?}", err),
+        }
+    }
+
+    #[test]
+    fn required_roles_and_scopes_satisfied() {
+        let validator = ClaimsValidator::new(claims());
+        assert!(validator
+            .require_roles(&["User.Read.All", "Mail.Read"])
+            .is_ok());
+        assert!(validator
+            .require_scopes(&["User.Read", "Files.Read"])
+            .is_ok());
+        assert!(validator.require_scopes(&["Mail.Send"]).is_err());
+    }
+
+    #[test]
+    fn graph_audience() {
+        let validator = ClaimsValidator::new(claims());
+        assert!(validator
+            .require_graph_audience(&AzureCloudInstance::AzurePublic)
+            .is_ok());
+        assert!(validator
+            .require_graph_audience(&AzureCloudInstance::AzureUsGovernment)
+            .is_err());
+
+        let mut claims = claims();
+        claims.aud = MICROSOFT_GRAPH_RESOURCE_ID.to_owned();
+        assert!(ClaimsValidator::new(claims)
+            .require_graph_audience(&AzureCloudInstance::AzureChina)
+            .is_ok());
+    }
+
+    #[test]
+    fn foreign_audience() {
+        let mut claims = claims();
+        claims.aud = "api://5a1b2c3d-0000-0000-0000-000000000000".to_owned();
+        let err = ClaimsValidator::new(claims)
+            .require_graph_audience(&AzureCloudInstance::AzurePublic)
+            .unwrap_err();
+
+        match err {
+            AF::RequiredValue { name, .. } => assert_eq!("aud", name),
+            _ => panic!("unexpected error: {:#?}", err),
+        }
+    }
+
+    #[test]
+    fn audience_representations() {
+        let client_id = "5A1B2C3D-1111-2222-3333-444455556666";
+        let mut claims = claims();
+
+        claims.aud = client_id.to_owned();
+        let validator = ClaimsValidator::new(claims.clone());
+        assert!(validator.require_audience(&[client_id]).is_ok());
+        assert!(validator
+            .require_audience(&["api://5a1b2c3d-1111-2222-3333-444455556666"])
+            .is_ok());
+
+        claims.aud = "api://5a1b2c3d-1111-2222-3333-444455556666".to_owned();
+        let validator = ClaimsValidator::new(claims.clone());
+        assert!(validator
+            .require_audience(&["https://contoso.com/api", client_id])
+            .is_ok());
+
+        claims.aud = "https://contoso.com/api/".to_owned();
+        let validator = ClaimsValidator::new(claims);
+        assert!(validator
+            .require_audience(&["https://Contoso.com/api"])
+            .is_ok());
+        assert!(validator
+            .require_audience(&[client_id, "api://contoso.com/api"])
+            .is_err());
+    }
+
+    #[test]
+    fn matching_tenant() {
+        let mut claims = claims();
+        claims.tid = Some("72f988bf-86f1-41af-91ab-2d7cd011db47".to_owned());
+        let validator = ClaimsValidator::new(claims);
+        assert!(validator
+            .require_tenant("72f988bf-86f1-41af-91ab-2d7cd011db47")
+            .is_ok());
+        assert!(validator
+            .require_tenant("72F988BF-86F1-41AF-91AB-2D7CD011DB47")
+            .is_ok());
+    }
+
+    #[test]
+    fn v1_and_v2_issuers() {
+        let tenant_id = "72f988bf-86f1-41af-91ab-2d7cd011db47";
+        let mut claims = claims();
+
+        claims.iss = format!("https://sts.windows.net/{tenant_id}/");
+        let validator = ClaimsValidator::new(claims.clone());
+        assert!(validator
+            .require_issuer(&AzureCloudInstance::AzurePublic, tenant_id)
+            .is_ok());
+
+        claims.iss = format!("https://login.microsoftonline.com/{tenant_id}/v2.0");
+        let validator = ClaimsValidator::new(claims.clone());
+        assert!(validator
+            .require_issuer(&AzureCloudInstance::AzurePublic, tenant_id)
+            .is_ok());
+        assert!(validator
+            .require_issuer(&AzureCloudInstance::AzureChina, tenant_id)
+            .is_err());
+
+        let err = validator
+            .require_issuer(
+                &AzureCloudInstance::AzurePublic,
+                "f8cdef31-a31e-4b4a-93e4-5f571e91255a",
+            )
+            .unwrap_err();
+        match err {
+            AF::RequiredValue { name, .. } => assert_eq!("iss", name),
+            _ => panic!("unexpected error: {:#?}", err),
+        }
+    }
+
+    #[test]
+    fn nonce_is_single_use() {
+        let mut claims = claims();
+        claims.nonce = Some("nonce-value".to_owned());
+        let validator = ClaimsValidator::new(claims);
+        let store = crate::identity::InMemoryNonceStore::default();
+
+        assert!(validator.require_nonce("other").is_err());
+        assert!(validator.require_unused_nonce("other", &store).is_err());
+        assert!(validator
+            .require_unused_nonce("nonce-value", &store)
+            .is_ok());
+
+        let err = validator
+            .require_unused_nonce("nonce-value", &store)
+            .unwrap_err();
+        match err {
+            AF::RequiredValue { name, message } => {
+                assert_eq!("nonce", name);
+                assert_eq!(Some("nonce was already used".to_owned()), message);
+            }
+            _ => panic!("unexpected error: {:#?}", err),
+        }
+    }
+
+    #[test]
+    fn mismatching_tenant() {
+        let mut claims = claims();
+        claims.tid = Some("f8cdef31-a31e-4b4a-93e4-5f571e91255a".to_owned());
+        let err = ClaimsValidator::new(claims.clone())
+            .require_tenant("72f988bf-86f1-41af-91ab-2d7cd011db47")
+            .unwrap_err();
+
+        match err {
+            AF::RequiredValue { name, .. } => assert_eq!("tid", name),
+            _ => panic!("unexpected error: {:#?}", err),
+        }
+
+        assert!(ClaimsValidator::new(claims.clone())
+            .require_tenant("contoso.onmicrosoft.com")
+            .is_err());
+
+        claims.tid = None;
+        assert!(ClaimsValidator::new(claims)
+            .require_tenant("f8cdef31-a31e-4b4a-93e4-5f571e91255a")
+            .is_err());
+    }
+
+    #[test]
+    fn authorized_party() {
+        let client_id = "5a1b2c3d-1111-2222-3333-444455556666";
+        let mut claims = claims();
+        claims
+            .additional_fields
+            .insert("appid".into(), Value::String(client_id.into()));
+        let validator = ClaimsValidator::new(claims.clone());
+        assert_eq!(Some(client_id), validator.authorized_party());
+        assert!(validator
+            .require_authorized_party(&["5A1B2C3D-1111-2222-3333-444455556666"])
+            .is_ok());
+
+        let err = validator
+            .require_authorized_party(&["00000000-0000-0000-0000-000000000001"])
+            .unwrap_err();
+        match err {
+            AF::RequiredValue { name, .. } => assert_eq!("azp", name),
+            _ => panic!("unexpected error: {:#?}", err),
+        }
+
+        claims.additional_fields.remove("appid");
+        claims.additional_fields.insert(
+            "azp".into(),
+            Value::String("00000000-0000-0000-0000-000000000001".into()),
+        );
+        assert!(ClaimsValidator::new(claims.clone())
+            .require_authorized_party(&[client_id])
+            .is_err());
+
+        claims.additional_fields.remove("azp");
+        assert!(ClaimsValidator::new(claims)
+            .require_authorized_party(&[client_id])
+            .is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+use time::OffsetDateTime;
+
+/// Source of the current time used by credentials to timestamp tokens and to check
+/// whether a cached token is expired.
+///
+/// Credentials use the [SystemClock] by default. Use a [TestClock] to test expiry
+/// logic without depending on the wall clock.
+pub trait Clock: Send + Sync {
+    fn now_utc(&self) -> OffsetDateTime;
+}
+
+/// The system clock. Returns [OffsetDateTime::now_utc].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_utc(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// A clock that only moves when advanced. Clones share the same time so that a
+/// clone passed to a credential can be advanced by the test.
+///
+/// # Example
+/// ```
+/// # use graph_oauth::{Clock, TestClock, Token};
+/// let clock = TestClock::default();
+/// let mut token = Token::default();
+/// token.expires_in = 3600;
+/// token.gen_timestamp_at(clock.now_utc());
+/// assert!(!token.is_expired_at(clock.now_utc()));
+///
+/// clock.advance(time::Duration::hours(2));
+/// assert!(token.is_expired_at(clock.now_utc()));
+/// ```
+#[derive(Clone, Debug)]
+pub struct TestClock {
+    now: Arc<Mutex<OffsetDateTime>>,
+}
+
+impl TestClock {
+    pub fn new(now: OffsetDateTime) -> TestClock {
+        TestClock {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Move the clock forward by the duration.
+    pub fn advance(&self, duration: time::Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now += duration;
+    }
+
+    /// Set the current time of the clock.
+    pub fn set(&self, now: OffsetDateTime) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Default for TestClock {
+    /// A clock set to the current system time.
+    fn default() -> Self {
+        TestClock::new(OffsetDateTime::now_utc())
+    }
+}
+
+impl Clock for TestClock {
+    fn now_utc(&self) -> OffsetDateTime {
+        *self.now.lock().unwrap()
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct ClockRef(Arc<dyn Clock>);
+
+impl ClockRef {
+    pub(crate) fn new(clock: impl Clock + 'static) -> ClockRef {
+        ClockRef(Arc::new(clock))
+    }
+}
+
+impl Default for ClockRef {
+    fn default() -> Self {
+        ClockRef::new(SystemClock)
+    }
+}
+
+impl std::ops::Deref for ClockRef {
+    type Target = dyn Clock;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl Debug for ClockRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Clock").finish()
+    }
+}
+
+impl PartialEq for ClockRef {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::Token;
+
+    #[test]
+    fn advance_test_clock_expires_token() {
+        let clock = TestClock::new(OffsetDateTime::UNIX_EPOCH);
+        let mut token = Token {
+            expires_in: 3600,
+            ..Default::default()
+        };
+        token.gen_timestamp_at(clock.now_utc());
+
+        clock.advance(time::Duration::minutes(50));
+        assert!(!token.is_expired_at(clock.now_utc()));
+        assert!(token.is_expired_sub_at(time::Duration::minutes(15), clock.now_utc()));
+
+        clock.advance(time::Duration::minutes(11));
+        assert!(token.is_expired_at(clock.now_utc()));
+
+        let shared = clock.clone();
+        clock.set(OffsetDateTime::UNIX_EPOCH);
+        assert_eq!(OffsetDateTime::UNIX_EPOCH, shared.now_utc());
+    }
+}
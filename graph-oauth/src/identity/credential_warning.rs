@@ -0,0 +1,136 @@
+use std::fmt::{Display, Formatter};
+
+/// Scopes that may be combined with a `/.default` scope.
+const OPENID_SCOPES: [&str; 4] = ["openid", "profile", "email", "offline_access"];
+
+/// Resources that were retired by Microsoft and the resource that replaces them.
+const DEPRECATED_RESOURCES: [(&str, &str); 2] = [
+    ("https://graph.windows.net", "https://graph.microsoft.com"),
+    (
+        "https://outlook.office.com/api",
+        "https://graph.microsoft.com",
+    ),
+];
+
+/// A non fatal problem with the configuration of a credential, such as a scope that
+/// is deprecated or a combination of scopes that the Microsoft identity platform
+/// handles differently than requested.
+///
+/// Warnings are returned by [TokenCredentialExecutor::warnings](crate::identity::TokenCredentialExecutor::warnings)
+/// and logged at the warn level when a token request is built.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize)]
+pub enum CredentialWarning {
+    /// The offline_access scope was requested in an app-only flow such as client
+    /// credentials. Refresh tokens are not issued for app-only flows and the scope is
+    /// removed from the request.
+    OfflineAccessInAppOnlyFlow,
+    /// A `/.default` scope was combined with other scopes. The `/.default` scope
+    /// requests all statically configured permissions of the resource and cannot be
+    /// combined with individual permissions.
+    DefaultScopeWithOtherScopes { default_scope: String },
+    /// The scope is for a resource that was retired.
+    DeprecatedScope { scope: String, replacement: String },
+}
+
+impl CredentialWarning {
+    /// The warnings for the scopes of a token request.
+    pub fn for_scope<T: AsRef<str>>(scope: &[T]) -> Vec<CredentialWarning> {
+        let mut warnings = vec![];
+
+        let other_scopes = scope
+            .iter()
+            .filter(|s| !s.as_ref().ends_with(".default"))
+            .filter(|s| !OPENID_SCOPES.contains(&s.as_ref()))
+            .count();
+        if other_scopes > 0 {
+            warnings.extend(
+                scope
+                    .iter()
+                    .filter(|s| s.as_ref().ends_with(".default"))
+                    .map(|s| CredentialWarning::DefaultScopeWithOtherScopes {
+                        default_scope: s.as_ref().to_owned(),
+                    }),
+            );
+        }
+
+        for s in scope.iter() {
+            let s = s.as_ref();
+            if let Some((_, replacement)) = DEPRECATED_RESOURCES
+                .iter()
+                .find(|(resource, _)| s.to_ascii_lowercase().starts_with(resource))
+            {
+                warnings.push(CredentialWarning::DeprecatedScope {
+                    scope: s.to_owned(),
+                    replacement: replacement.to_string(),
+                });
+            }
+        }
+
+        warnings
+    }
+
+    /// The warnings for the scopes of a token request of an app-only flow.
+    pub fn for_app_only_scope<T: AsRef<str>>(scope: &[T]) -> Vec<CredentialWarning> {
+        let mut warnings = vec![];
+        if scope.iter().any(|s| s.as_ref() == "offline_access") {
+            warnings.push(CredentialWarning::OfflineAccessInAppOnlyFlow);
+        }
+        warnings.extend(CredentialWarning::for_scope(scope));
+        warnings
+    }
+}
+
+impl Display for CredentialWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialWarning::OfflineAccessInAppOnlyFlow => write!(
+                f,
+                "offline_access is not supported in app-only flows and is removed from the request"
+            ),
+            CredentialWarning::DefaultScopeWithOtherScopes { default_scope } => write!(
+                f,
+                "{default_scope} cannot be combined with other scopes of a resource"
+            ),
+            CredentialWarning::DeprecatedScope { scope, replacement } => write!(
+                f,
+                "{scope} is for a retired resource, use {replacement} instead"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deprecated_scope_warnings() {
+        assert_eq!(
+            vec![CredentialWarning::DeprecatedScope {
+                scope: "https://graph.windows.net/.default".to_owned(),
+                replacement: "https://graph.microsoft.com".to_owned(),
+            }],
+            CredentialWarning::for_scope(&["https://graph.windows.net/.default"])
+        );
+
+        assert_eq!(
+            vec![CredentialWarning::DefaultScopeWithOtherScopes {
+                default_scope: "https://graph.microsoft.com/.default".to_owned(),
+            }],
+            CredentialWarning::for_scope(&["https://graph.microsoft.com/.default", "User.Read"])
+        );
+
+        assert!(
+            CredentialWarning::for_scope(&["https://graph.microsoft.com/.default", "openid"])
+                .is_empty()
+        );
+        assert!(CredentialWarning::for_scope(&["User.Read", "offline_access"]).is_empty());
+        assert_eq!(
+            vec![CredentialWarning::OfflineAccessInAppOnlyFlow],
+            CredentialWarning::for_app_only_scope(&[
+                "https://graph.microsoft.com/.default",
+                "offline_access"
+            ])
+        );
+    }
+}
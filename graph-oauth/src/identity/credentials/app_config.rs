@@ -1,17 +1,25 @@
-use base64::Engine;
 use http::{HeaderName, HeaderValue};
-use std::collections::{BTreeSet, HashMap};
+use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
+use std::net::IpAddr;
 
 use graph_core::identity::ForceTokenRefresh;
-use graph_error::AF;
+use graph_error::{IdentityResult, AF};
 use reqwest::header::HeaderMap;
 use url::Url;
 use uuid::Uuid;
 
-use crate::identity::{Authority, AzureCloudInstance, IdToken};
+use crate::identity::{
+    credentials::shared_token_store::SharedTokenStoreRef, tracing_targets::CREDENTIAL_EXECUTOR,
+    AccountInfo, Authority, AzureCloudInstance, CacheKeyStrategy, ClockRef, DnsResolverRef,
+    IdToken, RequestBudget, TokenAcquiredHook, TokenUrlRewriter,
+};
+use crate::oauth_serializer::ordered_scope;
 use crate::ApplicationOptions;
 
+/// OpenID Connect scopes that are not registered as permissions of the app.
+const OPENID_SCOPES: [&str; 4] = ["openid", "profile", "email", "offline_access"];
+
 #[derive(Clone, Default, PartialEq)]
 pub struct AppConfig {
     /// The directory tenant that you want to request permission from.
@@ -55,7 +63,7 @@ pub struct AppConfig {
     /// [ClientSecretCredential](crate::identity::ClientSecretCredential),
     /// [ClientCertificateCredential](crate::identity::ClientCertificateCredential),
     /// and [ClientAssertionCredential](crate::identity::ClientAssertionCredential).
-    pub(crate) scope: BTreeSet<String>,
+    pub(crate) scope: Vec<String>,
     /// Optional -  Some flows may require the redirect URI
     /// The redirect_uri of your app, where authentication responses can be sent and received
     /// by your app. It must exactly match one of the redirect_uris you registered in the portal,
@@ -66,6 +74,48 @@ pub struct AppConfig {
     pub(crate) force_token_refresh: ForceTokenRefresh,
     pub(crate) id_token: Option<IdToken>,
     pub(crate) log_pii: bool,
+    /// Replaces the grant_type the credential would normally send. Only intended for
+    /// experimenting with preview grant types that the SDK does not support yet.
+    pub(crate) grant_type_override: Option<String>,
+    /// Additional parameters sent in the form body of token requests, such as the
+    /// `challenge_type` and `continuation_token` of CIAM native authentication.
+    pub(crate) extra_form_parameters: HashMap<String, String>,
+    /// Client side limit on how often the token endpoint is called.
+    pub(crate) request_budget: Option<RequestBudget>,
+    /// The grant_type of the last token request, used as the `grant_type` label of the
+    /// token metrics so that the form is not serialized again for every cache hit.
+    pub(crate) grant_type: Option<String>,
+    /// Headers of the last response from the token endpoint.
+    pub(crate) last_response_headers: Option<HeaderMap>,
+    /// The client-request-id sent with token requests. A new id is generated for
+    /// each request when not set.
+    pub(crate) client_request_id: Option<Uuid>,
+    /// Maximum number of bytes of a token response body that are read before parsing.
+    /// Uses [DEFAULT_MAX_RESPONSE_BODY_SIZE](crate::identity::DEFAULT_MAX_RESPONSE_BODY_SIZE)
+    /// when not set.
+    pub(crate) max_response_body_size: Option<usize>,
+    /// Rewrites the token url before the token request is sent.
+    pub(crate) token_url_rewriter: Option<TokenUrlRewriter>,
+    /// Token store shared between processes or application runs that is read before
+    /// the in memory token cache.
+    pub(crate) shared_token_store: Option<SharedTokenStoreRef>,
+    /// Source of the current time for token timestamps and expiry checks.
+    pub(crate) clock: ClockRef,
+    /// How the cache id is computed. When not set the cache id is computed from the
+    /// client id and the tenant known when the config is created.
+    pub(crate) cache_key_strategy: Option<CacheKeyStrategy>,
+    /// Local address that outbound token requests are bound to.
+    pub(crate) local_address: Option<IpAddr>,
+    /// Resolver used instead of the system resolver for token requests.
+    pub(crate) dns_resolver: Option<DnsResolverRef>,
+    /// Called with an audit record of each acquired token before it is cached.
+    pub(crate) token_acquired_hook: Option<TokenAcquiredHook>,
+    /// Permissions configured on the app registration. When set, token requests for
+    /// scopes that are not registered fail before the request is sent.
+    pub(crate) registered_permissions: Option<Vec<String>>,
+    /// Only send token requests over https. Can only be turned off with the
+    /// `test-util` feature in order to send token requests to a mock server.
+    pub(crate) https_only: bool,
 }
 
 impl TryFrom<ApplicationOptions> for AppConfig {
@@ -73,7 +123,12 @@ impl TryFrom<ApplicationOptions> for AppConfig {
 
     fn try_from(value: ApplicationOptions) -> Result<Self, Self::Error> {
         let client_id = Uuid::try_parse(&value.client_id.to_string()).unwrap_or_default();
-        let cache_id = AppConfig::generate_cache_id(client_id, value.tenant_id.as_ref());
+        let cache_id = value.cache_key_strategy.unwrap_or_default().cache_id(
+            client_id,
+            value.tenant_id.as_deref(),
+            &[],
+            None,
+        );
         Ok(AppConfig {
             tenant_id: value.tenant_id,
             client_id: Uuid::try_parse(&value.client_id.to_string())?,
@@ -94,6 +149,22 @@ impl TryFrom<ApplicationOptions> for AppConfig {
             force_token_refresh: Default::default(),
             id_token: Default::default(),
             log_pii: false,
+            grant_type_override: None,
+            extra_form_parameters: Default::default(),
+            request_budget: None,
+            grant_type: None,
+            last_response_headers: None,
+            client_request_id: None,
+            max_response_body_size: None,
+            token_url_rewriter: None,
+            shared_token_store: None,
+            clock: Default::default(),
+            cache_key_strategy: value.cache_key_strategy,
+            local_address: None,
+            dns_resolver: None,
+            token_acquired_hook: None,
+            registered_permissions: None,
+            https_only: true,
         })
     }
 }
@@ -110,6 +181,20 @@ impl Debug for AppConfig {
                 .field("extra_header_parameters", &self.extra_header_parameters)
                 .field("scope", &self.scope)
                 .field("force_token_refresh", &self.force_token_refresh)
+                .field("grant_type_override", &self.grant_type_override)
+                .field("extra_form_parameters", &self.extra_form_parameters)
+                .field("request_budget", &self.request_budget)
+                .field("client_request_id", &self.client_request_id)
+                .field("max_response_body_size", &self.max_response_body_size)
+                .field("token_url_rewriter", &self.token_url_rewriter)
+                .field("shared_token_store", &self.shared_token_store)
+                .field("clock", &self.clock)
+                .field("cache_key_strategy", &self.cache_key_strategy)
+                .field("local_address", &self.local_address)
+                .field("dns_resolver", &self.dns_resolver)
+                .field("token_acquired_hook", &self.token_acquired_hook)
+                .field("registered_permissions", &self.registered_permissions)
+                .field("https_only", &self.https_only)
                 .finish()
         } else {
             f.debug_struct("AppConfig")
@@ -130,21 +215,61 @@ impl Debug for AppConfig {
                 )
                 .field("scope", &self.scope)
                 .field("force_token_refresh", &self.force_token_refresh)
+                .field("grant_type_override", &self.grant_type_override)
+                .field(
+                    "extra_form_parameters",
+                    &"[REDACTED] - call enable_pii_logging(true) to log value",
+                )
+                .field("request_budget", &self.request_budget)
+                .field("client_request_id", &self.client_request_id)
+                .field("max_response_body_size", &self.max_response_body_size)
+                .field("token_url_rewriter", &self.token_url_rewriter)
+                .field("shared_token_store", &self.shared_token_store)
+                .field("clock", &self.clock)
+                .field("cache_key_strategy", &self.cache_key_strategy)
+                .field("local_address", &self.local_address)
+                .field("dns_resolver", &self.dns_resolver)
+                .field("token_acquired_hook", &self.token_acquired_hook)
+                .field("registered_permissions", &self.registered_permissions)
+                .field("https_only", &self.https_only)
                 .finish()
         }
     }
 }
 
 impl AppConfig {
-    fn generate_cache_id(client_id: Uuid, tenant_id: Option<&String>) -> String {
-        if let Some(tenant_id) = tenant_id.as_ref() {
-            base64::engine::general_purpose::URL_SAFE_NO_PAD
-                .encode(format!("{},{}", tenant_id, client_id))
-        } else {
-            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(client_id.to_string())
+    pub(crate) fn generate_cache_id(client_id: Uuid, tenant_id: Option<&String>) -> String {
+        CacheKeyStrategy::ClientIdAndTenant.cache_id(
+            client_id,
+            tenant_id.map(|tenant_id| tenant_id.as_str()),
+            &[],
+            None,
+        )
+    }
+
+    /// Recompute the cache id when a [CacheKeyStrategy] is set. Without a strategy the
+    /// cache id is kept as computed when the config was created.
+    pub(crate) fn update_cache_id(&mut self) {
+        if let Some(cache_key_strategy) = self.cache_key_strategy {
+            let account_id = self
+                .id_token
+                .as_ref()
+                .and_then(|id_token| AccountInfo::try_from(id_token).ok())
+                .and_then(|account_info| account_info.oid);
+            self.cache_id = cache_key_strategy.cache_id(
+                self.client_id,
+                self.tenant_id.as_deref(),
+                &self.scope,
+                account_id.as_deref(),
+            );
         }
     }
 
+    pub(crate) fn with_cache_key_strategy(&mut self, cache_key_strategy: CacheKeyStrategy) {
+        self.cache_key_strategy = Some(cache_key_strategy);
+        self.update_cache_id();
+    }
+
     pub(crate) fn builder(client_id: impl TryInto<Uuid>) -> AppConfigBuilder {
         AppConfigBuilder::new(client_id)
     }
@@ -170,6 +295,22 @@ impl AppConfig {
             force_token_refresh: Default::default(),
             id_token: Default::default(),
             log_pii: Default::default(),
+            grant_type_override: None,
+            extra_form_parameters: Default::default(),
+            request_budget: None,
+            grant_type: None,
+            last_response_headers: None,
+            client_request_id: None,
+            max_response_body_size: None,
+            token_url_rewriter: None,
+            shared_token_store: None,
+            clock: Default::default(),
+            cache_key_strategy: None,
+            local_address: None,
+            dns_resolver: None,
+            token_acquired_hook: None,
+            registered_permissions: None,
+            https_only: true,
         }
     }
 
@@ -177,8 +318,54 @@ impl AppConfig {
         self.log_pii = log_pii;
     }
 
+    /// The requested scopes that are not registered permissions of the app. Empty when
+    /// no registered permissions are set.
+    ///
+    /// Scopes are compared ignoring case and may be qualified with the resource, for
+    /// instance `https://graph.microsoft.com/User.Read` matches the registered permission
+    /// `User.Read`. The `/.default` scope and the OpenID Connect scopes `openid`,
+    /// `profile`, `email` and `offline_access` do not need to be registered.
+    pub fn unregistered_scopes(&self) -> Vec<String> {
+        let registered_permissions = match self.registered_permissions.as_ref() {
+            Some(registered_permissions) => registered_permissions,
+            None => return vec![],
+        };
+
+        self.scope
+            .iter()
+            .filter(|scope| !scope.ends_with(".default"))
+            .filter(|scope| !OPENID_SCOPES.contains(&scope.as_str()))
+            .filter(|scope| {
+                let scope = scope.to_ascii_lowercase();
+                !registered_permissions.iter().any(|permission| {
+                    let permission = permission.to_ascii_lowercase();
+                    scope == permission || scope.ends_with(&format!("/{permission}"))
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Returns an error naming the requested scopes that are not registered permissions
+    /// of the app, see [unregistered_scopes](AppConfig::unregistered_scopes).
+    pub(crate) fn validate_registered_permissions(&self) -> IdentityResult<()> {
+        let unregistered_scopes = self.unregistered_scopes();
+        if unregistered_scopes.is_empty() {
+            return Ok(());
+        }
+
+        AF::msg_result(
+            "scope",
+            format!(
+                "scopes are not registered permissions of the app: {}",
+                unregistered_scopes.join(" ")
+            ),
+        )
+    }
+
     pub(crate) fn with_client_id(&mut self, client_id: impl TryInto<Uuid>) {
         self.client_id = client_id.try_into().unwrap_or_default();
+        self.update_cache_id();
     }
 
     pub(crate) fn with_authority(&mut self, authority: Authority) {
@@ -186,16 +373,30 @@ impl AppConfig {
             self.tenant_id = Some(tenant_id.clone());
         }
         self.authority = authority;
+        self.update_cache_id();
     }
 
     pub(crate) fn with_azure_cloud_instance(&mut self, azure_cloud_instance: AzureCloudInstance) {
         self.azure_cloud_instance = azure_cloud_instance;
     }
 
+    /// Set the cloud instance and authority from a full authority url such as
+    /// https://login.microsoftonline.com/contoso.onmicrosoft.com.
+    pub(crate) fn with_authority_url(&mut self, authority_url: &str) -> IdentityResult<()> {
+        let (azure_cloud_instance, authority) = Authority::parse_authority_url(authority_url)?;
+        if let Authority::B2C(_) = &authority {
+            self.tenant_id = Some(authority.as_str().to_owned());
+        }
+        self.with_azure_cloud_instance(azure_cloud_instance);
+        self.with_authority(authority);
+        Ok(())
+    }
+
     pub(crate) fn with_tenant(&mut self, tenant_id: impl AsRef<str>) {
         let tenant = tenant_id.as_ref().to_string();
         self.tenant_id = Some(tenant.clone());
         self.authority = Authority::TenantId(tenant);
+        self.update_cache_id();
     }
 
     /// Extends the query parameters of both the default query params and user defined params.
@@ -232,11 +433,134 @@ impl AppConfig {
     }
 
     pub(crate) fn with_scope<T: ToString, I: IntoIterator<Item = T>>(&mut self, scope: I) {
-        self.scope = scope.into_iter().map(|s| s.to_string()).collect();
+        self.scope = ordered_scope(scope);
+        self.update_cache_id();
+    }
+
+    /// Add a scope after the scopes that are already set, unless it is already set.
+    pub(crate) fn add_scope(&mut self, scope: impl ToString) {
+        for scope in ordered_scope([scope]) {
+            if !self.scope.contains(&scope) {
+                self.scope.push(scope);
+                self.update_cache_id();
+            }
+        }
+    }
+
+    /// OpenID Connect requests must include the openid scope. It is added as the first
+    /// scope if it is not already set.
+    pub(crate) fn add_openid_scope(&mut self) {
+        if !self.scope.iter().any(|s| s == "openid") {
+            self.scope.insert(0, "openid".to_owned());
+            self.update_cache_id();
+        }
+    }
+
+    /// Set the scope to the previously granted scopes combined with the newly requested
+    /// scopes so that the next token covers both.
+    pub(crate) fn with_incremental_scope<T: ToString, I: IntoIterator<Item = T>>(
+        &mut self,
+        granted_scope: &[String],
+        scope: I,
+    ) {
+        self.scope = ordered_scope(
+            granted_scope
+                .iter()
+                .filter(|s| !s.is_empty())
+                .cloned()
+                .chain(scope.into_iter().map(|s| s.to_string())),
+        );
+        self.update_cache_id();
+    }
+
+    /// Scopes for app-only flows such as client credentials. Refresh tokens are not issued
+    /// for app-only flows so offline_access is removed if it was requested.
+    pub(crate) fn app_only_scope(&self) -> Vec<String> {
+        let mut scope = self.scope.clone();
+        scope.retain(|s| s != "offline_access");
+        if scope.len() != self.scope.len() {
+            tracing::debug!(
+                target: CREDENTIAL_EXECUTOR,
+                "removed offline_access scope; refresh tokens are not issued for app-only flows"
+            );
+        }
+        scope
+    }
+
+    /// Set the scope to the `/.default` scope of the resource. The `/.default` suffix
+    /// is appended if the resource url does not already end with it.
+    pub(crate) fn with_resource_default(
+        &mut self,
+        resource_url: impl AsRef<str>,
+    ) -> IdentityResult<()> {
+        let resource_url = resource_url.as_ref().trim();
+        let resource = resource_url
+            .strip_suffix("/.default")
+            .unwrap_or(resource_url)
+            .trim_end_matches('/');
+
+        let url = Url::parse(resource)
+            .map_err(|err| AF::msg_err("resource_url", err.to_string().as_str()))?;
+        if url.cannot_be_a_base() || !url.has_host() {
+            return AF::msg_result(
+                "resource_url",
+                "resource url must be an absolute url such as https://graph.microsoft.com",
+            );
+        }
+
+        self.scope = vec![format!("{resource}/.default")];
+        self.update_cache_id();
+        Ok(())
     }
 
     pub(crate) fn with_id_token(&mut self, id_token: IdToken) {
         self.id_token = Some(id_token);
+        self.update_cache_id();
+    }
+
+    /// Apply the https only setting, local address and resolver of token requests to
+    /// the http client builder.
+    pub(crate) fn configure_http_client(
+        &self,
+        mut builder: reqwest::ClientBuilder,
+    ) -> reqwest::ClientBuilder {
+        builder = builder.https_only(self.https_only);
+        if let Some(local_address) = self.local_address {
+            builder = builder.local_address(local_address);
+        }
+        if let Some(dns_resolver) = self.dns_resolver.as_ref() {
+            builder = builder.dns_resolver(std::sync::Arc::new(dns_resolver.clone()));
+        }
+        builder
+    }
+
+    /// Apply the https only setting, local address and resolver of token requests to
+    /// the blocking http client builder.
+    pub(crate) fn configure_blocking_http_client(
+        &self,
+        mut builder: reqwest::blocking::ClientBuilder,
+    ) -> reqwest::blocking::ClientBuilder {
+        builder = builder.https_only(self.https_only);
+        if let Some(local_address) = self.local_address {
+            builder = builder.local_address(local_address);
+        }
+        if let Some(dns_resolver) = self.dns_resolver.as_ref() {
+            builder = builder.dns_resolver(std::sync::Arc::new(dns_resolver.clone()));
+        }
+        builder
+    }
+
+    pub(crate) fn with_grant_type_override(&mut self, grant_type: impl AsRef<str>) {
+        self.grant_type_override = Some(grant_type.as_ref().to_owned());
+    }
+
+    pub(crate) fn with_extra_form_parameter(
+        &mut self,
+        key: impl AsRef<str>,
+        value: impl AsRef<str>,
+    ) {
+        self.extra_form_parameters
+            .insert(key.as_ref().to_owned(), value.as_ref().to_owned());
     }
 }
 
@@ -253,9 +577,8 @@ impl AppConfigBuilder {
     }
 
     pub fn tenant(mut self, tenant: impl Into<String>) -> Self {
-        let tenant_id = tenant.into();
-        self.app_config.tenant_id = Some(tenant_id.clone());
-        self.authority(Authority::TenantId(tenant_id))
+        self.app_config.with_tenant(tenant.into());
+        self
     }
 
     pub fn redirect_uri(mut self, redirect_uri: Url) -> Self {
@@ -269,16 +592,17 @@ impl AppConfigBuilder {
     }
 
     pub fn authority(mut self, authority: Authority) -> Self {
-        self.app_config.authority = authority;
+        self.app_config.with_authority(authority);
         self
     }
 
     pub fn scope<T: ToString, I: IntoIterator<Item = T>>(mut self, scope: I) -> Self {
-        self.app_config.scope = scope.into_iter().map(|s| s.to_string()).collect();
+        self.app_config.with_scope(scope);
         self
     }
 
     pub fn build(mut self) -> AppConfig {
+        self.app_config.update_cache_id();
         if self.app_config.redirect_uri.is_none() {
             self.app_config.redirect_uri = Some(
                 Url::parse("http://localhost")
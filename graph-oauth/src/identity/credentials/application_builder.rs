@@ -1,24 +1,28 @@
 use crate::identity::{
     application_options::ApplicationOptions, credentials::app_config::AppConfig,
-    AuthCodeAuthorizationUrlParameterBuilder, Authority,
+    validate_public_client_redirect_uri, AuthCodeAuthorizationUrlParameterBuilder, Authority,
     AuthorizationCodeAssertionCredentialBuilder, AuthorizationCodeCredentialBuilder,
-    AzureCloudInstance, ClientAssertionCredentialBuilder,
+    AzureCloudInstance, AzureStackMetadata, ClientAssertionCredentialBuilder,
     ClientCredentialsAuthorizationUrlParameterBuilder, ClientSecretCredentialBuilder,
     DeviceCodeCredentialBuilder, DeviceCodePollingExecutor, EnvironmentCredential,
     OpenIdAuthorizationUrlParameterBuilder, OpenIdCredentialBuilder, PublicClientApplication,
     ResourceOwnerPasswordCredential, ResourceOwnerPasswordCredentialBuilder,
 };
-use graph_error::{IdentityResult, AF};
+use graph_error::{AuthExecutionResult, IdentityResult, AF};
 use http::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::IntoUrl;
 use std::collections::HashMap;
 use std::env::VarError;
+use url::Url;
 use uuid::Uuid;
 
 #[cfg(feature = "openssl")]
 use crate::identity::{
-    AuthorizationCodeCertificateCredentialBuilder, ClientCertificateCredentialBuilder,
-    X509Certificate,
+    AuthorizationCodeCertificateCredentialBuilder, ClientCertificateCredential,
+    ClientCertificateCredentialBuilder, ConfidentialClientApplication, X509Certificate,
 };
+#[cfg(feature = "openssl")]
+use std::path::Path;
 
 pub struct ConfidentialClientApplicationBuilder {
     pub(crate) app_config: AppConfig,
@@ -37,6 +41,61 @@ impl ConfidentialClientApplicationBuilder {
         ConfidentialClientApplicationBuilder::try_from(application_options)
     }
 
+    /// Create a builder for an Azure Stack Hub deployment. The authentication endpoints
+    /// are fetched from the metadata endpoint of the deployment, for instance
+    /// https://management.local.azurestack.external/metadata/endpoints?api-version=2015-01-01
+    ///
+    /// The tenant id is only used by deployments that use Azure Active Directory. See
+    /// [with_azure_stack_metadata](Self::with_azure_stack_metadata).
+    pub fn new_azure_stack(
+        client_id: impl TryInto<Uuid>,
+        tenant_id: impl AsRef<str>,
+        metadata_url: impl IntoUrl,
+    ) -> AuthExecutionResult<ConfidentialClientApplicationBuilder> {
+        let metadata = AzureStackMetadata::fetch(metadata_url)?;
+        let mut builder = ConfidentialClientApplicationBuilder::new(client_id);
+        builder
+            .with_tenant(tenant_id)
+            .with_azure_stack_metadata(&metadata)?;
+        Ok(builder)
+    }
+
+    /// Create a builder for an Azure Stack Hub deployment. The authentication endpoints
+    /// are fetched from the metadata endpoint of the deployment, for instance
+    /// https://management.local.azurestack.external/metadata/endpoints?api-version=2015-01-01
+    ///
+    /// The tenant id is only used by deployments that use Azure Active Directory. See
+    /// [with_azure_stack_metadata](Self::with_azure_stack_metadata).
+    pub async fn new_azure_stack_async(
+        client_id: impl TryInto<Uuid>,
+        tenant_id: impl AsRef<str>,
+        metadata_url: impl IntoUrl,
+    ) -> AuthExecutionResult<ConfidentialClientApplicationBuilder> {
+        let metadata = AzureStackMetadata::fetch_async(metadata_url).await?;
+        let mut builder = ConfidentialClientApplicationBuilder::new(client_id);
+        builder
+            .with_tenant(tenant_id)
+            .with_azure_stack_metadata(&metadata)?;
+        Ok(builder)
+    }
+
+    /// Configure the authority from the metadata of an Azure Stack Hub deployment.
+    /// Deployments that use AD FS use the login endpoint as the [Authority::AdfsHost]
+    /// and replace any previously set tenant. Deployments that use Azure Active Directory
+    /// set the [AzureCloudInstance] of the login endpoint and keep the current tenant.
+    pub fn with_azure_stack_metadata(
+        &mut self,
+        metadata: &AzureStackMetadata,
+    ) -> IdentityResult<&mut Self> {
+        if let Some(authority) = metadata.adfs_authority() {
+            self.app_config.with_authority(authority);
+        } else {
+            self.app_config
+                .with_azure_cloud_instance(metadata.azure_cloud_instance()?);
+        }
+        Ok(self)
+    }
+
     pub fn with_tenant(&mut self, tenant_id: impl AsRef<str>) -> &mut Self {
         self.app_config.with_tenant(tenant_id);
         self
@@ -99,6 +158,15 @@ impl ConfidentialClientApplicationBuilder {
         self
     }
 
+    /// Override the grant_type sent in the token request of the credentials built from this
+    /// builder. Credentials use a fixed grant_type by default and this should only be used
+    /// to experiment with preview grant types that are not yet supported by the SDK.
+    /// Refresh token requests always send `grant_type=refresh_token`.
+    pub fn with_grant_type_override(&mut self, grant_type: impl AsRef<str>) -> &mut Self {
+        self.app_config.with_grant_type_override(grant_type);
+        self
+    }
+
     /// Auth Code Authorization Url Builder
     pub fn auth_code_url_builder(&mut self) -> AuthCodeAuthorizationUrlParameterBuilder {
         AuthCodeAuthorizationUrlParameterBuilder::new_with_app_config(self.app_config.clone())
@@ -130,6 +198,20 @@ impl ConfidentialClientApplicationBuilder {
         )
     }
 
+    /// Client Credentials Using a certificate file. Loads the certificate and private key
+    /// from a PEM or PKCS #12 (.pfx) file, see [X509Certificate::from_path], and builds
+    /// the client.
+    #[cfg(feature = "openssl")]
+    pub fn with_certificate_path(
+        &mut self,
+        path: impl AsRef<Path>,
+        password: Option<&str>,
+    ) -> IdentityResult<ConfidentialClientApplication<ClientCertificateCredential>> {
+        let certificate =
+            X509Certificate::from_path(self.app_config.client_id.to_string(), path, password)?;
+        Ok(self.with_client_x509_certificate(&certificate)?.build())
+    }
+
     /// Client Credentials Using Client Secret.
     pub fn with_client_secret(
         &mut self,
@@ -320,6 +402,28 @@ impl PublicClientApplicationBuilder {
         self
     }
 
+    /// Override the grant_type sent in the token request of the credentials built from this
+    /// builder, for instance to send a preview grant type or the legacy device code grant
+    /// type to tenants that still require it. Credentials use a fixed grant_type by default.
+    /// Refresh token requests always send `grant_type=refresh_token`.
+    pub fn with_grant_type_override(&mut self, grant_type: impl AsRef<str>) -> &mut Self {
+        self.app_config.with_grant_type_override(grant_type);
+        self
+    }
+
+    /// Set the redirect uri of the installed application. Returns an error if the redirect
+    /// uri is not valid for a public client, see [validate_public_client_redirect_uri].
+    pub fn with_redirect_uri(&mut self, redirect_uri: Url) -> IdentityResult<&mut Self> {
+        validate_public_client_redirect_uri(&redirect_uri)?;
+        self.app_config.redirect_uri = Some(redirect_uri);
+        Ok(self)
+    }
+
+    /// Auth Code Authorization Url Builder
+    pub fn auth_code_url_builder(&mut self) -> AuthCodeAuthorizationUrlParameterBuilder {
+        AuthCodeAuthorizationUrlParameterBuilder::new_with_app_config(self.app_config.clone())
+    }
+
     pub fn with_device_code_executor(&mut self) -> DeviceCodePollingExecutor {
         DeviceCodePollingExecutor::new_with_app_config(self.app_config.clone())
     }
@@ -385,7 +489,9 @@ mod test {
     use url::Url;
     use uuid::Uuid;
 
-    use crate::identity::{AadAuthorityAudience, AzureCloudInstance, TokenCredentialExecutor};
+    use crate::identity::{
+        AadAuthorityAudience, AzureCloudInstance, TokenCredentialExecutor, OUT_OF_BAND_REDIRECT_URI,
+    };
 
     use super::*;
 
@@ -399,6 +505,7 @@ mod test {
             instance: Some(Url::parse("https://login.microsoft.com").unwrap()),
             azure_cloud_instance: Some(AzureCloudInstance::AzurePublic),
             redirect_uri: None,
+            cache_key_strategy: None,
         })
         .unwrap();
     }
@@ -413,6 +520,7 @@ mod test {
             instance: None,
             azure_cloud_instance: None,
             redirect_uri: None,
+            cache_key_strategy: None,
         })
         .unwrap();
     }
@@ -427,6 +535,7 @@ mod test {
             instance: Some(Url::parse("https://login.microsoft.com").unwrap()),
             azure_cloud_instance: Some(AzureCloudInstance::AzurePublic),
             redirect_uri: None,
+            cache_key_strategy: None,
         })
         .unwrap();
     }
@@ -441,6 +550,7 @@ mod test {
             instance: None,
             azure_cloud_instance: None,
             redirect_uri: None,
+            cache_key_strategy: None,
         })
         .unwrap();
     }
@@ -488,4 +598,92 @@ mod test {
             client_id.to_string()
         );
     }
+
+    #[test]
+    fn azure_stack_adfs_metadata() {
+        let metadata: AzureStackMetadata = serde_json::from_value(serde_json::json!({
+            "graphEndpoint": "https://graph.local.azurestack.external/",
+            "authentication": {
+                "loginEndpoint": "https://adfs.local.azurestack.external/adfs/",
+                "audiences": ["https://management.adfs.azurestack.local/4de154de-f8a8-4017-af41-df619da68155"]
+            }
+        }))
+        .unwrap();
+
+        let mut confidential_client = ConfidentialClientApplicationBuilder::new(Uuid::new_v4())
+            .with_tenant("tenant-id")
+            .with_azure_stack_metadata(&metadata)
+            .unwrap()
+            .with_client_secret("client-secret")
+            .build();
+
+        assert_eq!(
+            "https://adfs.local.azurestack.external/adfs/oauth2/token",
+            confidential_client.uri().unwrap().as_str()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn certificate_path() {
+        use openssl::asn1::Asn1Time;
+        use openssl::hash::MessageDigest;
+        use openssl::pkey::PKey;
+        use openssl::rsa::Rsa;
+        use openssl::x509::{X509NameBuilder, X509};
+
+        let private_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "graph-rs-sdk").unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&private_key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.sign(&private_key, MessageDigest::sha256()).unwrap();
+        let certificate = builder.build();
+
+        let mut pem = certificate.to_pem().unwrap();
+        pem.extend(private_key.private_key_to_pem_pkcs8().unwrap());
+        let path = std::env::temp_dir().join(format!("graph-rs-sdk-{}.pem", Uuid::new_v4()));
+        std::fs::write(&path, pem).unwrap();
+
+        let result = ConfidentialClientApplicationBuilder::new(Uuid::new_v4())
+            .with_tenant("tenant-id")
+            .with_certificate_path(&path, None);
+        std::fs::remove_file(&path).unwrap();
+
+        let mut confidential_client = result.unwrap();
+        assert_eq!(
+            "https://login.microsoftonline.com/tenant-id/oauth2/v2.0/token",
+            confidential_client.uri().unwrap().as_str()
+        );
+    }
+
+    #[test]
+    fn public_client_out_of_band_redirect_uri() {
+        let mut builder = PublicClientApplicationBuilder::new(Uuid::new_v4().to_string());
+        assert!(builder
+            .with_redirect_uri(Url::parse("http://contoso.com/redirect").unwrap())
+            .is_err());
+
+        let url = builder
+            .with_redirect_uri(Url::parse(OUT_OF_BAND_REDIRECT_URI).unwrap())
+            .unwrap()
+            .auth_code_url_builder()
+            .with_scope(["User.Read"])
+            .url()
+            .unwrap();
+        assert!(url
+            .query_pairs()
+            .any(|(key, value)| key == "redirect_uri" && value == OUT_OF_BAND_REDIRECT_URI));
+    }
 }
@@ -2,7 +2,6 @@ use std::collections::{BTreeSet, HashMap};
 use std::fmt::{Debug, Formatter};
 
 use http::{HeaderMap, HeaderName, HeaderValue};
-use reqwest::IntoUrl;
 
 use url::Url;
 use uuid::Uuid;
@@ -11,9 +10,10 @@ use graph_core::crypto::{secure_random_32, ProofKeyCodeExchange};
 use graph_error::{IdentityResult, AF};
 
 use crate::identity::{
-    AppConfig, AsQuery, AuthorizationCodeAssertionCredentialBuilder,
-    AuthorizationCodeCredentialBuilder, AuthorizationUrl, AzureCloudInstance, Prompt, ResponseMode,
-    ResponseType,
+    validate_domain_hint, AppConfig, AsQuery, AuthSession, AuthSessionStore, Authority,
+    AuthorizationCodeAssertionCredentialBuilder, AuthorizationCodeCredentialBuilder,
+    AuthorizationUrl, AzureCloudInstance, IntoRedirectUri, Prompt, ResponseMode, ResponseType,
+    NATIVE_CLIENT_REDIRECT_URI, OUT_OF_BAND_REDIRECT_URI,
 };
 use crate::oauth_serializer::{AuthParameter, AuthSerializer};
 
@@ -27,8 +27,8 @@ use {
         AuthorizationResponse, Token,
     },
     crate::interactive::{
-        HostOptions, InteractiveAuthEvent, UserEvents, WebViewAuth, WebViewAuthorizationEvent,
-        WebViewHostValidator, WebViewOptions, WithInteractiveAuth,
+        spawn_webview, HostOptions, InteractiveAuthEvent, UserEvents, WebViewAuth,
+        WebViewAuthorizationEvent, WebViewHostValidator, WebViewOptions, WithInteractiveAuth,
     },
     crate::{Assertion, Secret},
     graph_error::{AuthExecutionError, WebViewError, WebViewResult},
@@ -134,8 +134,18 @@ pub struct AuthCodeAuthorizationUrlParameters {
     /// this parameter during re-authentication, after already extracting the login_hint
     /// optional claim from an earlier sign-in.
     pub(crate) login_hint: Option<String>,
+    /// Optional
+    /// The claims request parameter as raw JSON, for instance to request claims in the
+    /// id token or to indicate client capabilities such as continuous access evaluation.
+    pub(crate) claims: Option<String>,
     pub(crate) code_challenge: Option<String>,
     pub(crate) code_challenge_method: Option<String>,
+    /// The code verifier of the [ProofKeyCodeExchange] set with `with_pkce`, stored in the
+    /// [AuthSession] of the request by
+    /// [url_with_session_store](AuthCodeAuthorizationUrlParameters::url_with_session_store).
+    pub(crate) code_verifier: Option<String>,
+    /// Used when no prompt or response_mode is set. See [AuthorizeUrlDefaults].
+    pub(crate) defaults: AuthorizeUrlDefaults,
 }
 
 impl Debug for AuthCodeAuthorizationUrlParameters {
@@ -145,6 +155,7 @@ impl Debug for AuthCodeAuthorizationUrlParameters {
             .field("response_type", &self.response_type)
             .field("response_mode", &self.response_mode)
             .field("prompt", &self.prompt)
+            .field("defaults", &self.defaults)
             .finish()
     }
 }
@@ -152,15 +163,14 @@ impl Debug for AuthCodeAuthorizationUrlParameters {
 impl AuthCodeAuthorizationUrlParameters {
     pub fn new(
         client_id: impl AsRef<str>,
-        redirect_uri: impl IntoUrl,
+        redirect_uri: impl IntoRedirectUri,
     ) -> IdentityResult<AuthCodeAuthorizationUrlParameters> {
         let mut response_type = BTreeSet::new();
         response_type.insert(ResponseType::Code);
-        let redirect_uri_result = Url::parse(redirect_uri.as_str());
 
         Ok(AuthCodeAuthorizationUrlParameters {
             app_config: AppConfig::builder(client_id.as_ref())
-                .redirect_uri(redirect_uri.into_url().or(redirect_uri_result)?)
+                .redirect_uri(redirect_uri.into_redirect_uri()?)
                 .build(),
             response_type,
             response_mode: None,
@@ -169,8 +179,11 @@ impl AuthCodeAuthorizationUrlParameters {
             prompt: Default::default(),
             domain_hint: None,
             login_hint: None,
+            claims: None,
             code_challenge: None,
             code_challenge_method: None,
+            code_verifier: None,
+            defaults: Default::default(),
         })
     }
 
@@ -186,6 +199,25 @@ impl AuthCodeAuthorizationUrlParameters {
         self.authorization_url_with_host(azure_cloud_instance)
     }
 
+    /// Build the url and store the [AuthSession] of the request, the code verifier set with
+    /// `with_pkce` and the nonce, in the store under the state of the request. Take the
+    /// session with [AuthSessionStore::take_for_response] when redeeming the authorization
+    /// response. Returns an error if no state is set.
+    pub fn url_with_session_store(&self, store: &dyn AuthSessionStore) -> IdentityResult<Url> {
+        let state = self.state.as_ref().ok_or_else(|| {
+            AF::msg_err(
+                "state",
+                "a state is required to store the authorization session, use with_state_generated",
+            )
+        })?;
+        let url = self.url()?;
+        store.store(
+            state,
+            AuthSession::new(self.code_verifier.clone(), self.nonce.clone()),
+        );
+        Ok(url)
+    }
+
     pub fn into_credential(
         self,
         authorization_code: impl AsRef<str>,
@@ -226,6 +258,14 @@ impl AuthCodeAuthorizationUrlParameters {
         self.nonce.as_ref()
     }
 
+    /// Get the state.
+    ///
+    /// Compare this value with the state returned in the authorization response
+    /// to verify that the response is for a request made by this client.
+    pub fn state(&self) -> Option<&String> {
+        self.state.as_ref()
+    }
+
     #[cfg(feature = "interactive-auth")]
     pub(crate) fn interactive_webview_authentication(
         &self,
@@ -234,59 +274,44 @@ impl AuthCodeAuthorizationUrlParameters {
         let uri = self
             .url()
             .map_err(|err| Box::new(AuthExecutionError::from(err)))?;
-        let redirect_uri = self.redirect_uri().cloned().unwrap();
-        let (sender, receiver) = std::sync::mpsc::channel();
-
-        std::thread::spawn(move || {
+        let redirect_uri = self
+            .redirect_uri()
+            .cloned()
+            .ok_or_else(|| WebViewError::InvalidUri("redirect_uri is required".into()))?;
+        let auth_event = spawn_webview(move |sender| {
             AuthCodeAuthorizationUrlParameters::run(uri, vec![redirect_uri], options, sender)
-                .unwrap();
-        });
-        let mut iter = receiver.try_iter();
-        let mut next = iter.next();
+        })?;
 
-        while next.is_none() {
-            next = iter.next();
-        }
-
-        match next {
-            None => unreachable!(),
-            Some(auth_event) => match auth_event {
-                InteractiveAuthEvent::InvalidRedirectUri(reason) => {
-                    Err(WebViewError::InvalidUri(reason))
+        match auth_event {
+            InteractiveAuthEvent::InvalidRedirectUri(reason) => {
+                Err(WebViewError::InvalidUri(reason))
+            }
+            InteractiveAuthEvent::WebViewUnavailable(reason) => {
+                Err(WebViewError::WebViewUnavailable(reason))
+            }
+            InteractiveAuthEvent::ReachedRedirectUri(uri) => {
+                let response_query = AuthorizationResponse::try_from(&uri)
+                    .map_err(|err| WebViewError::InvalidUri(err.to_string()))?;
+
+                if response_query.is_err() {
+                    tracing::debug!(target: INTERACTIVE_AUTH, "error in authorization query or fragment from redirect uri");
+                    return Err(WebViewError::Authorization {
+                        error: response_query
+                            .error
+                            .map(|query_error| query_error.to_string())
+                            .unwrap_or_default(),
+                        error_description: response_query.error_description.unwrap_or_default(),
+                        error_uri: response_query.error_uri.map(|uri| uri.to_string()),
+                    });
                 }
-                InteractiveAuthEvent::ReachedRedirectUri(uri) => {
-                    let query = uri
-                        .query()
-                        .or(uri.fragment())
-                        .ok_or(WebViewError::InvalidUri(format!(
-                            "uri missing query or fragment: {}",
-                            uri
-                        )))?;
-
-                    let response_query: AuthorizationResponse =
-                        serde_urlencoded::from_str(query)
-                            .map_err(|err| WebViewError::InvalidUri(err.to_string()))?;
-
-                    if response_query.is_err() {
-                        tracing::debug!(target: INTERACTIVE_AUTH, "error in authorization query or fragment from redirect uri");
-                        return Err(WebViewError::Authorization {
-                            error: response_query
-                                .error
-                                .map(|query_error| query_error.to_string())
-                                .unwrap_or_default(),
-                            error_description: response_query.error_description.unwrap_or_default(),
-                            error_uri: response_query.error_uri.map(|uri| uri.to_string()),
-                        });
-                    }
 
-                    tracing::debug!(target: INTERACTIVE_AUTH, "parsed authorization query or fragment from redirect uri");
+                tracing::debug!(target: INTERACTIVE_AUTH, "parsed authorization query or fragment from redirect uri");
 
-                    Ok(response_query)
-                }
-                InteractiveAuthEvent::WindowClosed(window_close_reason) => {
-                    Err(WebViewError::WindowClosed(window_close_reason.to_string()))
-                }
-            },
+                Ok(response_query)
+            }
+            InteractiveAuthEvent::WindowClosed(window_close_reason) => {
+                Err(WebViewError::WindowClosed(window_close_reason.to_string()))
+            }
         }
     }
 
@@ -299,45 +324,30 @@ impl AuthCodeAuthorizationUrlParameters {
         let uri = self
             .url()
             .map_err(|err| Box::new(AuthExecutionError::from(err)))?;
-        let redirect_uri = self.redirect_uri().cloned().unwrap();
-        let (sender, receiver) = std::sync::mpsc::channel();
-
-        std::thread::spawn(move || {
+        let redirect_uri = self
+            .redirect_uri()
+            .cloned()
+            .ok_or_else(|| WebViewError::InvalidUri("redirect_uri is required".into()))?;
+        let auth_event = spawn_webview(move |sender| {
             AuthCodeAuthorizationUrlParameters::run(uri, vec![redirect_uri], options, sender)
-                .unwrap();
-        });
-        let mut iter = receiver.try_iter();
-        let mut next = iter.next();
+        })?;
 
-        while next.is_none() {
-            next = iter.next();
-        }
+        match auth_event {
+            InteractiveAuthEvent::InvalidRedirectUri(reason) => {
+                Err(WebViewError::InvalidUri(reason))
+            }
+            InteractiveAuthEvent::WebViewUnavailable(reason) => {
+                Err(WebViewError::WebViewUnavailable(reason))
+            }
+            InteractiveAuthEvent::ReachedRedirectUri(uri) => {
+                let response_query = AuthorizationResponse::try_from(&uri)
+                    .map_err(|err| WebViewError::InvalidUri(err.to_string()))?;
 
-        match next {
-            None => unreachable!(),
-            Some(auth_event) => match auth_event {
-                InteractiveAuthEvent::InvalidRedirectUri(reason) => {
-                    Err(WebViewError::InvalidUri(reason))
-                }
-                InteractiveAuthEvent::ReachedRedirectUri(uri) => {
-                    let query = uri
-                        .query()
-                        .or(uri.fragment())
-                        .ok_or(WebViewError::InvalidUri(format!(
-                            "uri missing query or fragment: {}",
-                            uri
-                        )))?;
-
-                    let response_query: AuthorizationResponse =
-                        serde_urlencoded::from_str(query)
-                            .map_err(|err| WebViewError::InvalidUri(err.to_string()))?;
-
-                    Ok(response_query)
-                }
-                InteractiveAuthEvent::WindowClosed(window_close_reason) => {
-                    Err(WebViewError::WindowClosed(window_close_reason.to_string()))
-                }
-            },
+                Ok(response_query)
+            }
+            InteractiveAuthEvent::WindowClosed(window_close_reason) => {
+                Err(WebViewError::WindowClosed(window_close_reason.to_string()))
+            }
         }
     }
 }
@@ -361,7 +371,7 @@ mod internal {
                 .with_navigation_handler(move |uri| {
                     if let Ok(url) = Url::parse(uri.as_str()) {
                         let is_valid_host = validator.is_valid_uri(&url);
-                        let is_redirect = validator.is_redirect_host(&url);
+                        let is_redirect = validator.is_redirect_uri(&url);
 
                         if is_redirect {
                             proxy.send_event(UserEvents::ReachedRedirectUri(url))
@@ -371,6 +381,11 @@ mod internal {
                             return true;
                         }
 
+                        if validator.is_redirect_host(&url) {
+                            tracing::debug!(target: INTERACTIVE_AUTH, "redirect uri does not match a configured redirect uri: {url}");
+                            return false;
+                        }
+
                         is_valid_host
                     } else {
                         tracing::debug!(target: INTERACTIVE_AUTH, "unable to navigate webview - url is none");
@@ -397,6 +412,15 @@ impl AuthorizationUrl for AuthCodeAuthorizationUrlParameters {
         azure_cloud_instance: &AzureCloudInstance,
     ) -> IdentityResult<Url> {
         let mut serializer = AuthSerializer::new();
+        let response_mode = self
+            .response_mode
+            .as_ref()
+            .or(self.defaults.response_mode.as_ref());
+        let prompt: BTreeSet<Prompt> = if self.prompt.is_empty() {
+            self.defaults.prompt.iter().cloned().collect()
+        } else {
+            self.prompt.clone()
+        };
 
         if let Some(redirect_uri) = self.app_config.redirect_uri.as_ref() {
             if redirect_uri.as_str().trim().is_empty() {
@@ -411,7 +435,16 @@ impl AuthorizationUrl for AuthCodeAuthorizationUrlParameters {
             return AF::result("client_id");
         }
 
-        if self.app_config.scope.is_empty() {
+        // response_type=none only records consent so a scope is not required.
+        let consent_only = self.response_type.contains(&ResponseType::None);
+        if consent_only && self.response_type.len() > 1 {
+            return AF::msg_result(
+                "response_type",
+                "ResponseType::None cannot be combined with other response types",
+            );
+        }
+
+        if self.app_config.scope.is_empty() && !consent_only {
             return AF::result("scope");
         }
 
@@ -424,7 +457,7 @@ impl AuthorizationUrl for AuthCodeAuthorizationUrlParameters {
 
         if response_types.is_empty() {
             serializer.response_type("code");
-            if let Some(response_mode) = self.response_mode.as_ref() {
+            if let Some(response_mode) = response_mode {
                 serializer.response_mode(response_mode.as_ref());
             }
         } else {
@@ -437,15 +470,15 @@ impl AuthorizationUrl for AuthCodeAuthorizationUrlParameters {
 
             // Set response_mode
             if self.response_type.contains(&ResponseType::IdToken) {
-                if self.response_mode.eq(&Some(ResponseMode::Query)) {
+                if response_mode.eq(&Some(&ResponseMode::Query)) {
                     return Err(AF::msg_err(
                         "response_mode",
                         "ResponseType::IdToken requires ResponseMode::Fragment or ResponseMode::FormPost")
                     );
-                } else if let Some(response_mode) = self.response_mode.as_ref() {
+                } else if let Some(response_mode) = response_mode {
                     serializer.response_mode(response_mode.as_ref());
                 }
-            } else if let Some(response_mode) = self.response_mode.as_ref() {
+            } else if let Some(response_mode) = response_mode {
                 serializer.response_mode(response_mode.as_ref());
             }
         }
@@ -454,8 +487,19 @@ impl AuthorizationUrl for AuthCodeAuthorizationUrlParameters {
             serializer.state(state.as_str());
         }
 
-        if !self.prompt.is_empty() {
-            serializer.prompt(&self.prompt.as_query());
+        if !prompt.is_empty() {
+            if prompt.contains(&Prompt::AdminConsent)
+                && !self.response_type.is_empty()
+                && !self.response_type.contains(&ResponseType::Code)
+                && !consent_only
+            {
+                return AF::msg_result(
+                    "prompt",
+                    "Prompt::AdminConsent requires ResponseType::Code",
+                );
+            }
+
+            serializer.prompt(&prompt.as_query());
         }
 
         if let Some(domain_hint) = self.domain_hint.as_ref() {
@@ -466,6 +510,10 @@ impl AuthorizationUrl for AuthCodeAuthorizationUrlParameters {
             serializer.login_hint(login_hint.as_str());
         }
 
+        if let Some(claims) = self.claims.as_ref() {
+            serializer.claims(claims.as_str());
+        }
+
         if let Some(nonce) = self.nonce.as_ref() {
             serializer.nonce(nonce);
         }
@@ -478,24 +526,29 @@ impl AuthorizationUrl for AuthCodeAuthorizationUrlParameters {
             serializer.code_challenge_method(code_challenge_method.as_str());
         }
 
-        let query = serializer.encode_query(
-            vec![
-                AuthParameter::ResponseMode,
-                AuthParameter::State,
-                AuthParameter::Prompt,
-                AuthParameter::LoginHint,
-                AuthParameter::DomainHint,
-                AuthParameter::Nonce,
-                AuthParameter::CodeChallenge,
-                AuthParameter::CodeChallengeMethod,
-            ],
-            vec![
-                AuthParameter::ClientId,
-                AuthParameter::ResponseType,
-                AuthParameter::RedirectUri,
-                AuthParameter::Scope,
-            ],
-        )?;
+        let mut optional_fields = vec![
+            AuthParameter::ResponseMode,
+            AuthParameter::State,
+            AuthParameter::Prompt,
+            AuthParameter::LoginHint,
+            AuthParameter::Claims,
+            AuthParameter::DomainHint,
+            AuthParameter::Nonce,
+            AuthParameter::CodeChallenge,
+            AuthParameter::CodeChallengeMethod,
+        ];
+        let mut required_fields = vec![
+            AuthParameter::ClientId,
+            AuthParameter::ResponseType,
+            AuthParameter::RedirectUri,
+        ];
+        if consent_only {
+            optional_fields.push(AuthParameter::Scope);
+        } else {
+            required_fields.push(AuthParameter::Scope);
+        }
+
+        let query = serializer.encode_query(optional_fields, required_fields)?;
 
         let mut uri = azure_cloud_instance.auth_uri(&self.app_config.authority)?;
         uri.set_query(Some(query.as_str()));
@@ -522,8 +575,11 @@ impl AuthCodeAuthorizationUrlParameterBuilder {
                 prompt: Default::default(),
                 domain_hint: None,
                 login_hint: None,
+                claims: None,
                 code_challenge: None,
                 code_challenge_method: None,
+                code_verifier: None,
+                defaults: Default::default(),
             },
         }
     }
@@ -543,8 +599,11 @@ impl AuthCodeAuthorizationUrlParameterBuilder {
                 prompt: Default::default(),
                 domain_hint: None,
                 login_hint: None,
+                claims: None,
                 code_challenge: None,
                 code_challenge_method: None,
+                code_verifier: None,
+                defaults: Default::default(),
             },
         }
     }
@@ -554,6 +613,20 @@ impl AuthCodeAuthorizationUrlParameterBuilder {
         self
     }
 
+    /// Use the [NATIVE_CLIENT_REDIRECT_URI] for installed applications that read the
+    /// authorization response from the address of an embedded browser.
+    pub fn with_native_client_redirect_uri(&mut self) -> &mut Self {
+        self.credential.app_config.redirect_uri = Url::parse(NATIVE_CLIENT_REDIRECT_URI).ok();
+        self
+    }
+
+    /// Use the [OUT_OF_BAND_REDIRECT_URI] for installed applications where the user
+    /// copies the authorization code from the browser.
+    pub fn with_out_of_band_redirect_uri(&mut self) -> &mut Self {
+        self.credential.app_config.redirect_uri = Url::parse(OUT_OF_BAND_REDIRECT_URI).ok();
+        self
+    }
+
     /// Default is code. Must include code for the authorization code flow.
     /// Can also include id_token or token if using the hybrid flow.
     pub fn with_response_type<I: IntoIterator<Item = ResponseType>>(
@@ -580,6 +653,13 @@ impl AuthCodeAuthorizationUrlParameterBuilder {
         self
     }
 
+    /// Use the prompt and response_mode of the defaults when none are set with
+    /// [with_prompt](Self::with_prompt) or [with_response_mode](Self::with_response_mode).
+    pub fn with_defaults(&mut self, defaults: &AuthorizeUrlDefaults) -> &mut Self {
+        self.credential.defaults = defaults.clone();
+        self
+    }
+
     /// A value included in the request, generated by the app, that is included in the
     /// resulting id_token as a claim. The app can then verify this value to mitigate token
     /// replay attacks. The value is typically a randomized, unique string that can be used
@@ -604,6 +684,19 @@ impl AuthCodeAuthorizationUrlParameterBuilder {
         self
     }
 
+    /// Generate a secure random state. The state is returned by the authorization server
+    /// in the redirect and can be used to mitigate cross-site request forgery attacks.
+    /// Use [state](Self::state) or the state of the built parameters to get the generated
+    /// value for comparing with the state in the authorization response.
+    pub fn with_state_generated(&mut self) -> &mut Self {
+        self.credential.state = Some(secure_random_32());
+        self
+    }
+
+    pub fn state(&self) -> Option<&String> {
+        self.credential.state.as_ref()
+    }
+
     /// Indicates the type of user interaction that is required. Valid values are login, none,
     /// consent, and select_account.
     ///
@@ -624,11 +717,29 @@ impl AuthCodeAuthorizationUrlParameterBuilder {
         self
     }
 
+    /// Set the domain_hint to the verified domain of the tenant, such as `contoso.com`,
+    /// for a streamlined federated sign-in. Returns an error if the domain is not a
+    /// plausible DNS domain, for instance a tenant id or a url.
+    pub fn with_verified_domain_hint<T: AsRef<str>>(
+        &mut self,
+        domain: T,
+    ) -> IdentityResult<&mut Self> {
+        self.credential.domain_hint = Some(validate_domain_hint(domain)?);
+        Ok(self)
+    }
+
     pub fn with_login_hint<T: AsRef<str>>(&mut self, login_hint: T) -> &mut Self {
         self.credential.login_hint = Some(login_hint.as_ref().to_owned());
         self
     }
 
+    /// The claims request parameter as raw JSON. Pass the JSON as is, it is url encoded
+    /// once when the url is built.
+    pub fn with_claims<T: AsRef<str>>(&mut self, claims: T) -> &mut Self {
+        self.credential.claims = Some(claims.as_ref().to_owned());
+        self
+    }
+
     /// Used to secure authorization code grants by using Proof Key for Code Exchange (PKCE).
     /// Required if code_challenge_method is included.
     pub fn with_code_challenge<T: AsRef<str>>(&mut self, code_challenge: T) -> &mut Self {
@@ -655,6 +766,7 @@ impl AuthCodeAuthorizationUrlParameterBuilder {
     pub fn with_pkce(&mut self, proof_key_for_code_exchange: &ProofKeyCodeExchange) -> &mut Self {
         self.with_code_challenge(proof_key_for_code_exchange.code_challenge.as_str());
         self.with_code_challenge_method(proof_key_for_code_exchange.code_challenge_method.as_str());
+        self.credential.code_verifier = Some(proof_key_for_code_exchange.code_verifier.clone());
         self
     }
 
@@ -662,6 +774,18 @@ impl AuthCodeAuthorizationUrlParameterBuilder {
         self.credential.clone()
     }
 
+    /// Build the parameters, returning an error if no scope was set. Scope is required
+    /// by the authorization endpoint and would otherwise only be reported by
+    /// [url](AuthCodeAuthorizationUrlParameters::url).
+    pub fn try_build(&self) -> IdentityResult<AuthCodeAuthorizationUrlParameters> {
+        if self.credential.app_config.scope.is_empty()
+            && !self.credential.response_type.contains(&ResponseType::None)
+        {
+            return AF::msg_result("scope", "at least one scope is required");
+        }
+        Ok(self.credential.clone())
+    }
+
     pub fn url_with_host(&self, azure_cloud_instance: &AzureCloudInstance) -> IdentityResult<Url> {
         self.credential.url_with_host(azure_cloud_instance)
     }
@@ -670,6 +794,11 @@ impl AuthCodeAuthorizationUrlParameterBuilder {
         self.credential.url()
     }
 
+    /// See [AuthCodeAuthorizationUrlParameters::url_with_session_store].
+    pub fn url_with_session_store(&self, store: &dyn AuthSessionStore) -> IdentityResult<Url> {
+        self.credential.url_with_session_store(store)
+    }
+
     pub fn with_auth_code(
         self,
         authorization_code: impl AsRef<str>,
@@ -837,9 +966,113 @@ impl WithInteractiveAuth<&X509Certificate> for AuthCodeAuthorizationUrlParameter
     }
 }
 
+/// Default prompt and response_mode shared by many authorize urls, for instance kept in the
+/// configuration of an application. A prompt or response_mode set on the
+/// [AuthCodeAuthorizationUrlParameterBuilder] overrides the default.
+///
+/// ```rust
+/// # use graph_oauth::{AuthCodeAuthorizationUrlParameters, AuthorizeUrlDefaults, Prompt, ResponseMode};
+/// # use url::Url;
+/// let defaults = AuthorizeUrlDefaults {
+///     prompt: vec![Prompt::SelectAccount],
+///     response_mode: Some(ResponseMode::FormPost),
+/// };
+///
+/// let url = AuthCodeAuthorizationUrlParameters::builder("6731de76-14a6-49ae-97bc-6eba6914391e")
+///     .with_redirect_uri(Url::parse("http://localhost:8000/redirect").unwrap())
+///     .with_scope(["User.Read"])
+///     .with_defaults(&defaults)
+///     .url()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AuthorizeUrlDefaults {
+    pub prompt: Vec<Prompt>,
+    pub response_mode: Option<ResponseMode>,
+}
+
+/// Optional parameters of [build_authorize_url].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AuthorizeUrlOptions {
+    /// Defaults to `code` when empty.
+    pub response_type: Vec<ResponseType>,
+    pub response_mode: Option<ResponseMode>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub prompt: Vec<Prompt>,
+    pub domain_hint: Option<String>,
+    pub login_hint: Option<String>,
+    pub claims: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+/// Build the /authorize url of the authorization code flow without using the builder.
+/// Returns the same url as [AuthCodeAuthorizationUrlParameterBuilder] for the same inputs.
+///
+/// ```rust
+/// # use graph_oauth::{build_authorize_url, AuthorizeUrlOptions, Authority, AzureCloudInstance};
+/// let url = build_authorize_url(
+///     "6731de76-14a6-49ae-97bc-6eba6914391e",
+///     "http://localhost:8000/redirect",
+///     ["User.Read"],
+///     Authority::TenantId("tenant-id".into()),
+///     &AzureCloudInstance::AzurePublic,
+///     &AuthorizeUrlOptions::default(),
+/// )
+/// .unwrap();
+/// ```
+pub fn build_authorize_url<T: ToString, I: IntoIterator<Item = T>>(
+    client_id: impl TryInto<Uuid>,
+    redirect_uri: impl IntoRedirectUri,
+    scopes: I,
+    authority: impl Into<Authority>,
+    azure_cloud_instance: &AzureCloudInstance,
+    options: &AuthorizeUrlOptions,
+) -> IdentityResult<Url> {
+    let mut builder = AuthCodeAuthorizationUrlParameterBuilder::new(client_id);
+    builder
+        .with_redirect_uri(redirect_uri.into_redirect_uri()?)
+        .with_scope(scopes)
+        .with_authority(authority)
+        .with_prompt(options.prompt.clone());
+
+    if !options.response_type.is_empty() {
+        builder.with_response_type(options.response_type.clone());
+    }
+    if let Some(response_mode) = options.response_mode.as_ref() {
+        builder.with_response_mode(response_mode.clone());
+    }
+    if let Some(state) = options.state.as_ref() {
+        builder.with_state(state);
+    }
+    if let Some(nonce) = options.nonce.as_ref() {
+        builder.with_nonce(nonce);
+    }
+    if let Some(domain_hint) = options.domain_hint.as_ref() {
+        builder.with_domain_hint(domain_hint);
+    }
+    if let Some(login_hint) = options.login_hint.as_ref() {
+        builder.with_login_hint(login_hint);
+    }
+    if let Some(claims) = options.claims.as_ref() {
+        builder.with_claims(claims);
+    }
+    if let Some(code_challenge) = options.code_challenge.as_ref() {
+        builder.with_code_challenge(code_challenge);
+    }
+    if let Some(code_challenge_method) = options.code_challenge_method.as_ref() {
+        builder.with_code_challenge_method(code_challenge_method);
+    }
+
+    builder.url_with_host(azure_cloud_instance)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::identity::Token;
+    use graph_core::crypto::GenPkce;
 
     #[test]
     fn serialize_uri() {
@@ -852,6 +1085,19 @@ mod test {
         assert!(url_result.is_ok());
     }
 
+    #[test]
+    fn try_build_requires_scope() {
+        let mut builder = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4());
+        builder.with_redirect_uri(Url::parse("https://localhost:8080").unwrap());
+        assert!(matches!(
+            builder.try_build(),
+            Err(AF::RequiredValue { name, .. }) if name == "scope"
+        ));
+
+        builder.with_scope(["User.Read"]);
+        assert!(builder.try_build().unwrap().url().is_ok());
+    }
+
     #[test]
     fn url_with_host() {
         let url_result = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
@@ -862,6 +1108,138 @@ mod test {
         assert!(url_result.is_ok());
     }
 
+    #[test]
+    fn prompt_admin_consent() {
+        let url = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080").unwrap())
+            .with_scope(["User.Read.All"])
+            .with_prompt([Prompt::Login, Prompt::AdminConsent])
+            .url()
+            .unwrap();
+
+        let query = url.query().unwrap();
+        assert!(query.contains("prompt=login+admin_consent"));
+        assert!(query.contains("response_type=code"));
+    }
+
+    #[test]
+    fn custom_prompt() {
+        let url = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080").unwrap())
+            .with_scope(["User.Read"])
+            .with_prompt([Prompt::Login, Prompt::Other("create".into())])
+            .url()
+            .unwrap();
+
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(Some(&"login create".to_owned()), query.get("prompt"));
+    }
+
+    #[test]
+    fn verified_domain_hint() {
+        let url = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080").unwrap())
+            .with_scope(["User.Read"])
+            .with_verified_domain_hint("Contoso.com")
+            .unwrap()
+            .url()
+            .unwrap();
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(Some(&"contoso.com".to_owned()), query.get("domain_hint"));
+
+        let mut builder = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4());
+        assert!(builder
+            .with_verified_domain_hint("https://contoso.com")
+            .is_err());
+        assert!(builder
+            .with_verified_domain_hint("6731de76-14a6-49ae-97bc-6eba6914391e")
+            .is_err());
+    }
+
+    #[test]
+    fn default_prompt_unless_overridden() {
+        let defaults = AuthorizeUrlDefaults {
+            prompt: vec![Prompt::SelectAccount],
+            response_mode: Some(ResponseMode::FormPost),
+        };
+
+        let url = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080").unwrap())
+            .with_scope(["User.Read"])
+            .with_defaults(&defaults)
+            .url()
+            .unwrap();
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(Some(&"select_account".to_owned()), query.get("prompt"));
+        assert_eq!(Some(&"form_post".to_owned()), query.get("response_mode"));
+
+        let url = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080").unwrap())
+            .with_scope(["User.Read"])
+            .with_prompt([Prompt::Login])
+            .with_defaults(&defaults)
+            .with_response_mode(ResponseMode::Query)
+            .url()
+            .unwrap();
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(Some(&"login".to_owned()), query.get("prompt"));
+        assert_eq!(Some(&"query".to_owned()), query.get("response_mode"));
+    }
+
+    #[test]
+    fn empty_optional_parameters_omitted() {
+        let url = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080").unwrap())
+            .with_scope(["User.Read"])
+            .with_state("")
+            .with_domain_hint("")
+            .with_login_hint(" ")
+            .with_prompt([])
+            .url()
+            .unwrap();
+
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert!(!query.contains_key("state"));
+        assert!(!query.contains_key("domain_hint"));
+        assert!(!query.contains_key("login_hint"));
+        assert!(!query.contains_key("prompt"));
+        assert!(query.contains_key("client_id"));
+    }
+
+    #[test]
+    fn response_type_none() {
+        let url = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080").unwrap())
+            .with_response_type([ResponseType::None])
+            .with_prompt([Prompt::AdminConsent])
+            .url()
+            .unwrap();
+
+        let query = url.query().unwrap();
+        assert!(query.contains("response_type=none"));
+        assert!(!query.contains("scope="));
+
+        let result = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080").unwrap())
+            .with_scope(["User.Read"])
+            .with_response_type([ResponseType::None, ResponseType::Code])
+            .url();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn prompt_admin_consent_requires_code_response_type() {
+        let url_result = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080").unwrap())
+            .with_scope(["User.Read.All"])
+            .with_response_mode(ResponseMode::Fragment)
+            .with_response_type([ResponseType::IdToken])
+            .with_prompt(Prompt::AdminConsent)
+            .url();
+
+        assert!(url_result.is_err());
+    }
+
     #[test]
     #[should_panic]
     fn response_type_id_token_panics_when_response_mode_query() {
@@ -916,4 +1294,136 @@ mod test {
         let query = url.query().unwrap();
         assert!(query.contains("nonce"));
     }
+
+    #[test]
+    fn incremental_scope_merges_granted_scope() {
+        let granted = Token::new(
+            "Bearer",
+            3600,
+            "access_token",
+            vec!["User.Read", "offline_access"],
+        );
+
+        let mut builder = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4());
+        builder
+            .with_redirect_uri(Url::parse("https://localhost:8080").unwrap())
+            .with_incremental_scope(&granted, ["Mail.Read", "User.Read"]);
+
+        assert_eq!(
+            vec![
+                "User.Read".to_owned(),
+                "offline_access".to_owned(),
+                "Mail.Read".to_owned()
+            ],
+            builder.credential.app_config.scope
+        );
+    }
+
+    #[test]
+    fn generate_state() {
+        let mut builder = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4());
+        builder
+            .with_redirect_uri(Url::parse("https://localhost:8080").unwrap())
+            .with_scope(["read", "write"])
+            .with_state_generated();
+
+        let state = builder.state().cloned().unwrap();
+        assert!(!state.is_empty());
+
+        let url = builder.url().unwrap();
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(Some(&state), query.get("state"));
+        assert_eq!(Some(&state), builder.build().state());
+    }
+
+    #[test]
+    fn claims_and_redirect_uri_encoded_once() {
+        let claims = r#"{"access_token":{"xms_cc":{"values":["cp1"]}}}"#;
+        let url = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("http://localhost:8000/redirect?app=graph").unwrap())
+            .with_scope(["User.Read", "offline_access"])
+            .with_claims(claims)
+            .url()
+            .unwrap();
+
+        let raw_query = url.query().unwrap();
+        assert!(!raw_query.contains("%25"));
+
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(Some(&claims.to_owned()), query.get("claims"));
+        assert_eq!(
+            Some(&"User.Read offline_access".to_owned()),
+            query.get("scope")
+        );
+        assert_eq!(
+            Some(&"http://localhost:8000/redirect?app=graph".to_owned()),
+            query.get("redirect_uri")
+        );
+    }
+
+    #[test]
+    fn installed_app_redirect_uris() {
+        let url = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_native_client_redirect_uri()
+            .with_scope(["User.Read"])
+            .url()
+            .unwrap();
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(
+            Some(&NATIVE_CLIENT_REDIRECT_URI.to_owned()),
+            query.get("redirect_uri")
+        );
+
+        let url = AuthCodeAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_out_of_band_redirect_uri()
+            .with_scope(["User.Read"])
+            .url()
+            .unwrap();
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(
+            Some(&OUT_OF_BAND_REDIRECT_URI.to_owned()),
+            query.get("redirect_uri")
+        );
+    }
+
+    #[test]
+    fn build_authorize_url_same_as_builder() {
+        let client_id = Uuid::new_v4();
+        let pkce = ProofKeyCodeExchange::oneshot().unwrap();
+        let options = AuthorizeUrlOptions {
+            response_mode: Some(ResponseMode::Query),
+            state: Some("state".into()),
+            nonce: Some("nonce".into()),
+            prompt: vec![Prompt::SelectAccount],
+            login_hint: Some("user@contoso.com".into()),
+            code_challenge: Some(pkce.code_challenge.clone()),
+            code_challenge_method: Some(pkce.code_challenge_method.clone()),
+            ..Default::default()
+        };
+
+        let url = build_authorize_url(
+            client_id,
+            "http://localhost:8000/redirect",
+            ["User.Read", "offline_access"],
+            Authority::TenantId("tenant-id".into()),
+            &AzureCloudInstance::AzureUsGovernment,
+            &options,
+        )
+        .unwrap();
+
+        let builder_url = AuthCodeAuthorizationUrlParameters::builder(client_id)
+            .with_redirect_uri(Url::parse("http://localhost:8000/redirect").unwrap())
+            .with_scope(["User.Read", "offline_access"])
+            .with_tenant("tenant-id")
+            .with_response_mode(ResponseMode::Query)
+            .with_state("state")
+            .with_nonce("nonce")
+            .with_prompt(Prompt::SelectAccount)
+            .with_login_hint("user@contoso.com")
+            .with_pkce(&pkce)
+            .url_with_host(&AzureCloudInstance::AzureUsGovernment)
+            .unwrap();
+
+        assert_eq!(builder_url, url);
+    }
 }
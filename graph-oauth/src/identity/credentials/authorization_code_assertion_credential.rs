@@ -3,20 +3,22 @@ use std::fmt::{Debug, Formatter};
 
 use async_trait::async_trait;
 use http::{HeaderMap, HeaderName, HeaderValue};
-use reqwest::IntoUrl;
 use url::Url;
 
 use uuid::Uuid;
+use zeroize::Zeroize;
 
-use graph_core::cache::{CacheStore, InMemoryCacheStore, TokenCache};
-use graph_core::http::{AsyncResponseConverterExt, ResponseConverterExt};
+use graph_core::cache::{InMemoryCacheStore, TokenCache};
+use graph_core::crypto::ProofKeyCodeExchange;
+use graph_core::http::ResponseConverterExt;
 use graph_core::identity::ForceTokenRefresh;
 use graph_error::{AuthExecutionError, AuthExecutionResult, IdentityResult, AF};
 
 use crate::identity::credentials::app_config::AppConfig;
 use crate::identity::{
-    AuthCodeAuthorizationUrlParameterBuilder, Authority, AzureCloudInstance,
-    ConfidentialClientApplication, Token, TokenCredentialExecutor, CLIENT_ASSERTION_TYPE,
+    read_json_limited, AuthCodeAuthorizationUrlParameterBuilder, AuthSession, Authority,
+    AzureCloudInstance, ConfidentialClientApplication, IntoRedirectUri, RefreshTokenCredential,
+    Token, TokenCredentialExecutor, CLIENT_ASSERTION_TYPE,
 };
 use crate::oauth_serializer::{AuthParameter, AuthSerializer};
 
@@ -62,20 +64,25 @@ impl Debug for AuthorizationCodeAssertionCredential {
     }
 }
 
+impl Drop for AuthorizationCodeAssertionCredential {
+    fn drop(&mut self) {
+        self.authorization_code.zeroize();
+        self.refresh_token.zeroize();
+        self.code_verifier.zeroize();
+        self.client_assertion.zeroize();
+    }
+}
+
 impl AuthorizationCodeAssertionCredential {
     pub fn new(
         client_id: impl TryInto<Uuid>,
         authorization_code: impl AsRef<str>,
         client_assertion: impl AsRef<str>,
-        redirect_uri: Option<impl IntoUrl>,
+        redirect_uri: Option<impl IntoRedirectUri>,
     ) -> IdentityResult<AuthorizationCodeAssertionCredential> {
-        let redirect_uri = {
-            if let Some(redirect_uri) = redirect_uri {
-                redirect_uri.into_url().ok()
-            } else {
-                None
-            }
-        };
+        let redirect_uri = redirect_uri
+            .map(IntoRedirectUri::into_redirect_uri)
+            .transpose()?;
 
         Ok(AuthorizationCodeAssertionCredential {
             app_config: AppConfig::builder(client_id)
@@ -115,8 +122,8 @@ impl AuthorizationCodeAssertionCredential {
             ));
         }
 
-        let new_token: Token = response.json()?;
-        self.token_cache.store(cache_id, new_token.clone());
+        let new_token: Token = read_json_limited(response, self.max_response_body_size())?;
+        let new_token = self.store_token(cache_id.as_str(), new_token);
 
         if new_token.refresh_token.is_some() {
             self.refresh_token = new_token.refresh_token.clone();
@@ -125,29 +132,30 @@ impl AuthorizationCodeAssertionCredential {
         Ok(new_token)
     }
 
-    async fn execute_cached_token_refresh_async(
-        &mut self,
-        cache_id: String,
-    ) -> AuthExecutionResult<Token> {
-        let response = self.execute_async().await?;
-
-        if !response.status().is_success() {
-            return Err(AuthExecutionError::silent_token_auth(
-                response.into_http_response_async().await?,
-            ));
-        }
-
-        let new_token: Token = response.json().await?;
+    async fn execute_cached_token_refresh_async(&mut self) -> AuthExecutionResult<Token> {
+        let new_token = self.acquire_token_async().await?;
 
         if new_token.refresh_token.is_some() {
             self.refresh_token = new_token.refresh_token.clone();
         }
 
-        self.token_cache.store(cache_id, new_token.clone());
         Ok(new_token)
     }
 }
 
+token_cache_snapshot!(AuthorizationCodeAssertionCredential);
+
+impl RefreshTokenCredential for AuthorizationCodeAssertionCredential {
+    fn refresh_token(&self) -> Option<&str> {
+        self.refresh_token.as_deref()
+    }
+
+    fn set_refresh_token(&mut self, refresh_token: String) {
+        self.authorization_code = None;
+        self.refresh_token = Some(refresh_token);
+    }
+}
+
 #[async_trait]
 impl TokenCache for AuthorizationCodeAssertionCredential {
     type Token = Token;
@@ -165,8 +173,8 @@ impl TokenCache for AuthorizationCodeAssertionCredential {
                     }
                 }
 
-                if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-                    if token.is_expired_sub(time::Duration::minutes(5)) {
+                if let Some(token) = self.cached_token(cache_id.as_str()) {
+                    if self.is_token_expired(&token) {
                         if let Some(refresh_token) = token.refresh_token.as_ref() {
                             self.refresh_token = Some(refresh_token.to_owned());
                         }
@@ -197,30 +205,27 @@ impl TokenCache for AuthorizationCodeAssertionCredential {
                 // Attempt to bypass a read on the token store by using previous
                 // refresh token stored outside of RwLock
                 if self.refresh_token.is_some() {
-                    if let Ok(token) = self
-                        .execute_cached_token_refresh_async(cache_id.clone())
-                        .await
-                    {
+                    if let Ok(token) = self.execute_cached_token_refresh_async().await {
                         return Ok(token);
                     }
                 }
 
-                if let Some(old_token) = self.token_cache.get(cache_id.as_str()) {
-                    if old_token.is_expired_sub(time::Duration::minutes(5)) {
+                if let Some(old_token) = self.cached_token(cache_id.as_str()) {
+                    if self.is_token_expired(&old_token) {
                         if let Some(refresh_token) = old_token.refresh_token.as_ref() {
                             self.refresh_token = Some(refresh_token.to_owned());
                         }
 
-                        self.execute_cached_token_refresh_async(cache_id).await
+                        self.execute_cached_token_refresh_async().await
                     } else {
                         Ok(old_token.clone())
                     }
                 } else {
-                    self.execute_cached_token_refresh_async(cache_id).await
+                    self.execute_cached_token_refresh_async().await
                 }
             }
             ForceTokenRefresh::Once | ForceTokenRefresh::Always => {
-                let token_result = self.execute_cached_token_refresh_async(cache_id).await;
+                let token_result = self.execute_cached_token_refresh_async().await;
                 if self.app_config.force_token_refresh == ForceTokenRefresh::Once {
                     self.app_config.force_token_refresh = ForceTokenRefresh::Never;
                 }
@@ -262,6 +267,7 @@ impl TokenCredentialExecutor for AuthorizationCodeAssertionCredential {
         }
 
         if let Some(code_verifier) = self.code_verifier.as_ref() {
+            ProofKeyCodeExchange::validate_code_verifier(code_verifier)?;
             serializer.code_verifier(code_verifier.as_ref());
         }
 
@@ -337,6 +343,18 @@ impl TokenCredentialExecutor for AuthorizationCodeAssertionCredential {
     fn app_config(&self) -> &AppConfig {
         &self.app_config
     }
+
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        Some(&self.token_cache)
+    }
+
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        Some(&mut self.token_cache)
+    }
+
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        Some(&mut self.app_config)
+    }
 }
 
 #[derive(Clone)]
@@ -433,6 +451,15 @@ impl AuthorizationCodeAssertionCredentialBuilder {
         self
     }
 
+    /// Set the code verifier of the [AuthSession] taken for the authorization response
+    /// with [AuthSessionStore::take_for_response](crate::identity::AuthSessionStore::take_for_response).
+    pub fn with_auth_session(&mut self, auth_session: &AuthSession) -> &mut Self {
+        if let Some(code_verifier) = auth_session.code_verifier.as_ref() {
+            self.with_code_verifier(code_verifier);
+        }
+        self
+    }
+
     pub fn with_client_assertion<T: AsRef<str>>(&mut self, client_assertion: T) -> &mut Self {
         self.credential.client_assertion = client_assertion.as_ref().to_owned();
         self
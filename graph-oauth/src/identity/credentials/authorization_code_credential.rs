@@ -4,23 +4,30 @@ use std::fmt::{Debug, Formatter};
 use async_trait::async_trait;
 use http::{HeaderMap, HeaderName, HeaderValue};
 
+use time::OffsetDateTime;
 use url::Url;
 use uuid::Uuid;
+use zeroize::Zeroize;
 
 use graph_core::cache::{CacheStore, InMemoryCacheStore, TokenCache};
 use graph_core::crypto::ProofKeyCodeExchange;
-use graph_core::http::{AsyncResponseConverterExt, ResponseConverterExt};
+use graph_core::http::ResponseConverterExt;
 use graph_core::identity::ForceTokenRefresh;
 use graph_error::{AuthExecutionError, AuthExecutionResult, IdentityResult, AF};
 
 use crate::identity::credentials::app_config::{AppConfig, AppConfigBuilder};
 use crate::identity::{
-    tracing_targets::CREDENTIAL_EXECUTOR, Authority, AuthorizationResponse, AzureCloudInstance,
-    ConfidentialClientApplication, Token, TokenCredentialExecutor,
+    read_json_limited, tracing_targets::CREDENTIAL_EXECUTOR, AuthSession, Authority,
+    AuthorizationResponse, AzureCloudInstance, ConfidentialClientApplication,
+    RefreshTokenCredential, Token, TokenCredentialExecutor,
 };
 use crate::oauth_serializer::{AuthParameter, AuthSerializer};
 use crate::AuthCodeAuthorizationUrlParameterBuilder;
 
+/// Authorization codes are short lived, about 10 minutes. Redeeming an older code fails
+/// with AADSTS70008.
+pub const AUTHORIZATION_CODE_MAX_AGE: time::Duration = time::Duration::minutes(10);
+
 credential_builder!(
     AuthorizationCodeCredentialBuilder,
     ConfidentialClientApplication<AuthorizationCodeCredential>
@@ -57,6 +64,10 @@ pub struct AuthorizationCodeCredential {
     /// Required if PKCE was used in the authorization code grant request. For more information,
     /// see the PKCE RFC https://datatracker.ietf.org/doc/html/rfc7636.
     pub(crate) code_verifier: Option<String>,
+    /// When the authorization code was set, according to the clock of the credential.
+    pub(crate) code_captured_at: Option<OffsetDateTime>,
+    /// Authorization codes older than this are rejected before redemption.
+    pub(crate) max_code_age: time::Duration,
     token_cache: InMemoryCacheStore<Token>,
 }
 
@@ -68,6 +79,15 @@ impl Debug for AuthorizationCodeCredential {
     }
 }
 
+impl Drop for AuthorizationCodeCredential {
+    fn drop(&mut self) {
+        self.authorization_code.zeroize();
+        self.refresh_token.zeroize();
+        self.client_secret.zeroize();
+        self.code_verifier.zeroize();
+    }
+}
+
 impl AuthorizationCodeCredential {
     pub fn new(
         tenant_id: impl AsRef<str>,
@@ -83,6 +103,8 @@ impl AuthorizationCodeCredential {
             refresh_token: None,
             client_secret: client_secret.as_ref().to_owned(),
             code_verifier: None,
+            code_captured_at: Some(OffsetDateTime::now_utc()),
+            max_code_age: AUTHORIZATION_CODE_MAX_AGE,
             token_cache: Default::default(),
         })
     }
@@ -103,6 +125,8 @@ impl AuthorizationCodeCredential {
             refresh_token: None,
             client_secret: client_secret.as_ref().to_owned(),
             code_verifier: None,
+            code_captured_at: Some(OffsetDateTime::now_utc()),
+            max_code_age: AUTHORIZATION_CODE_MAX_AGE,
             token_cache: Default::default(),
         })
     }
@@ -134,8 +158,8 @@ impl AuthorizationCodeCredential {
             ));
         }
 
-        let new_token: Token = response.json()?;
-        self.token_cache.store(cache_id, new_token.clone());
+        let new_token: Token = read_json_limited(response, self.max_response_body_size())?;
+        let new_token = self.store_token(cache_id.as_str(), new_token);
 
         if new_token.refresh_token.is_some() {
             self.refresh_token = new_token.refresh_token.clone();
@@ -144,28 +168,30 @@ impl AuthorizationCodeCredential {
         Ok(new_token)
     }
 
-    async fn execute_cached_token_refresh_async(
-        &mut self,
-        cache_id: String,
-    ) -> AuthExecutionResult<Token> {
-        let response = self.execute_async().await?;
-
-        if !response.status().is_success() {
-            return Err(AuthExecutionError::silent_token_auth(
-                response.into_http_response_async().await?,
-            ));
-        }
-
-        let new_token: Token = response.json().await?;
-        self.token_cache.store(cache_id, new_token.clone());
+    async fn execute_cached_token_refresh_async(&mut self) -> AuthExecutionResult<Token> {
+        let new_token = self.acquire_token_async().await?;
 
         if new_token.refresh_token.is_some() {
             self.refresh_token = new_token.refresh_token.clone();
         }
+
         Ok(new_token)
     }
 }
 
+token_cache_snapshot!(AuthorizationCodeCredential);
+
+impl RefreshTokenCredential for AuthorizationCodeCredential {
+    fn refresh_token(&self) -> Option<&str> {
+        self.refresh_token.as_deref()
+    }
+
+    fn set_refresh_token(&mut self, refresh_token: String) {
+        self.authorization_code = None;
+        self.refresh_token = Some(refresh_token);
+    }
+}
+
 #[async_trait]
 impl TokenCache for AuthorizationCodeCredential {
     type Token = Token;
@@ -185,8 +211,8 @@ impl TokenCache for AuthorizationCodeCredential {
                     }
                 }
 
-                if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-                    if token.is_expired_sub(time::Duration::minutes(5)) {
+                if let Some(token) = self.cached_token(cache_id.as_str()) {
+                    if self.is_token_expired(&token) {
                         tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=Some");
                         if let Some(refresh_token) = token.refresh_token.as_ref() {
                             self.refresh_token = Some(refresh_token.to_owned());
@@ -223,32 +249,29 @@ impl TokenCache for AuthorizationCodeCredential {
                 // refresh token stored outside of RwLock
                 if self.refresh_token.is_some() {
                     tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=Some");
-                    if let Ok(token) = self
-                        .execute_cached_token_refresh_async(cache_id.clone())
-                        .await
-                    {
+                    if let Ok(token) = self.execute_cached_token_refresh_async().await {
                         return Ok(token);
                     }
                 }
 
-                if let Some(old_token) = self.token_cache.get(cache_id.as_str()) {
-                    if old_token.is_expired_sub(time::Duration::minutes(5)) {
+                if let Some(old_token) = self.cached_token(cache_id.as_str()) {
+                    if self.is_token_expired(&old_token) {
                         if let Some(refresh_token) = old_token.refresh_token.as_ref() {
                             self.refresh_token = Some(refresh_token.to_owned());
                         }
                         tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=Some");
-                        self.execute_cached_token_refresh_async(cache_id).await
+                        self.execute_cached_token_refresh_async().await
                     } else {
                         tracing::debug!(target: CREDENTIAL_EXECUTOR, "using token from cache");
                         Ok(old_token.clone())
                     }
                 } else {
                     tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=None");
-                    self.execute_cached_token_refresh_async(cache_id).await
+                    self.execute_cached_token_refresh_async().await
                 }
             }
             ForceTokenRefresh::Once | ForceTokenRefresh::Always => {
-                let token_result = self.execute_cached_token_refresh_async(cache_id).await;
+                let token_result = self.execute_cached_token_refresh_async().await;
                 if self.app_config.force_token_refresh == ForceTokenRefresh::Once {
                     self.app_config.force_token_refresh = ForceTokenRefresh::Never;
                 }
@@ -280,6 +303,8 @@ impl AuthorizationCodeCredentialBuilder {
                 refresh_token: None,
                 client_secret: client_secret.as_ref().to_owned(),
                 code_verifier: None,
+                code_captured_at: Some(OffsetDateTime::now_utc()),
+                max_code_age: AUTHORIZATION_CODE_MAX_AGE,
                 token_cache: Default::default(),
             },
         }
@@ -300,6 +325,8 @@ impl AuthorizationCodeCredentialBuilder {
                 refresh_token: None,
                 client_secret: String::new(),
                 code_verifier: None,
+                code_captured_at: None,
+                max_code_age: AUTHORIZATION_CODE_MAX_AGE,
                 token_cache,
             },
         }
@@ -316,6 +343,8 @@ impl AuthorizationCodeCredentialBuilder {
                 refresh_token: None,
                 client_secret: String::new(),
                 code_verifier: None,
+                code_captured_at: Some(OffsetDateTime::now_utc()),
+                max_code_age: AUTHORIZATION_CODE_MAX_AGE,
                 token_cache: Default::default(),
             },
         }
@@ -334,6 +363,8 @@ impl AuthorizationCodeCredentialBuilder {
                 refresh_token: None,
                 client_secret: secret,
                 code_verifier: None,
+                code_captured_at: Some(OffsetDateTime::now_utc()),
+                max_code_age: AUTHORIZATION_CODE_MAX_AGE,
                 token_cache: Default::default(),
             },
         }
@@ -341,10 +372,29 @@ impl AuthorizationCodeCredentialBuilder {
 
     pub fn with_authorization_code<T: AsRef<str>>(&mut self, authorization_code: T) -> &mut Self {
         self.credential.authorization_code = Some(authorization_code.as_ref().to_owned());
+        self.credential.code_captured_at = Some(self.credential.app_config.clock.now_utc());
         self.credential.refresh_token = None;
         self
     }
 
+    /// Record when the authorization code was received, if it was received before the
+    /// code was set on the builder. Defaults to the time the code was set.
+    pub fn with_authorization_code_captured_at(
+        &mut self,
+        captured_at: OffsetDateTime,
+    ) -> &mut Self {
+        self.credential.code_captured_at = Some(captured_at);
+        self
+    }
+
+    /// Reject authorization codes older than the maximum age before sending them to the
+    /// token endpoint, which would fail with AADSTS70008.
+    /// Defaults to [AUTHORIZATION_CODE_MAX_AGE].
+    pub fn with_max_authorization_code_age(&mut self, max_code_age: time::Duration) -> &mut Self {
+        self.credential.max_code_age = max_code_age;
+        self
+    }
+
     pub fn with_refresh_token<T: AsRef<str>>(&mut self, refresh_token: T) -> &mut Self {
         self.credential.refresh_token = Some(refresh_token.as_ref().to_owned());
         self
@@ -370,6 +420,15 @@ impl AuthorizationCodeCredentialBuilder {
         self.with_code_verifier(proof_key_for_code_exchange.code_verifier.as_str());
         self
     }
+
+    /// Set the code verifier of the [AuthSession] taken for the authorization response
+    /// with [AuthSessionStore::take_for_response](crate::identity::AuthSessionStore::take_for_response).
+    pub fn with_auth_session(&mut self, auth_session: &AuthSession) -> &mut Self {
+        if let Some(code_verifier) = auth_session.code_verifier.as_ref() {
+            self.with_code_verifier(code_verifier);
+        }
+        self
+    }
 }
 
 impl From<AuthorizationCodeCredential> for AuthorizationCodeCredentialBuilder {
@@ -397,7 +456,7 @@ impl TokenCredentialExecutor for AuthorizationCodeCredential {
             .set_scope(self.app_config.scope.clone());
 
         let cache_id = self.app_config.cache_id.to_string();
-        if let Some(token) = self.token_cache.get(cache_id.as_str()) {
+        if let Some(token) = self.cached_token(cache_id.as_str()) {
             if let Some(refresh_token) = token.refresh_token.as_ref() {
                 serializer
                     .grant_type("refresh_token")
@@ -446,6 +505,20 @@ impl TokenCredentialExecutor for AuthorizationCodeCredential {
                 );
             }
 
+            if let Some(captured_at) = self.code_captured_at {
+                let code_age = self.app_config.clock.now_utc() - captured_at;
+                if code_age > self.max_code_age {
+                    return AF::msg_result(
+                        AuthParameter::AuthorizationCode.alias(),
+                        format!(
+                            "authorization code is stale, it was received {} seconds ago and the maximum age is {} seconds - request a new authorization code",
+                            code_age.whole_seconds(),
+                            self.max_code_age.whole_seconds()
+                        ),
+                    );
+                }
+            }
+
             if let Some(redirect_uri) = self.app_config.redirect_uri.as_ref() {
                 serializer.redirect_uri(redirect_uri.as_str());
             }
@@ -455,6 +528,7 @@ impl TokenCredentialExecutor for AuthorizationCodeCredential {
                 .grant_type("authorization_code");
 
             if let Some(code_verifier) = self.code_verifier.as_ref() {
+                ProofKeyCodeExchange::validate_code_verifier(code_verifier)?;
                 serializer.code_verifier(code_verifier.as_str());
             }
 
@@ -502,6 +576,18 @@ impl TokenCredentialExecutor for AuthorizationCodeCredential {
     fn app_config(&self) -> &AppConfig {
         &self.app_config
     }
+
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        Some(&self.token_cache)
+    }
+
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        Some(&mut self.token_cache)
+    }
+
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        Some(&mut self.app_config)
+    }
 }
 
 impl Debug for AuthorizationCodeCredentialBuilder {
@@ -527,6 +613,70 @@ impl From<(AppConfig, AuthorizationResponse)> for AuthorizationCodeCredentialBui
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::identity::TestClock;
+    use graph_core::crypto::GenPkce;
+
+    #[test]
+    fn stale_authorization_code() {
+        let clock = TestClock::default();
+        let mut confidential_client = AuthorizationCodeCredential::builder(
+            "auth_code",
+            Uuid::new_v4().to_string(),
+            "client_secret",
+        )
+        .with_clock(clock.clone())
+        .with_authorization_code("auth_code")
+        .with_redirect_uri(Url::parse("http://localhost:8000/redirect").unwrap())
+        .build();
+        assert!(confidential_client.form_urlencode().is_ok());
+
+        clock.advance(time::Duration::minutes(11));
+        match confidential_client.form_urlencode() {
+            Err(AF::RequiredValue {
+                message: Some(message),
+                ..
+            }) => assert!(message.contains("authorization code is stale")),
+            result => panic!("expected stale authorization code error: {result:?}"),
+        }
+    }
+
+    #[test]
+    fn refresh_form_has_no_authorization_code() {
+        let pkce = ProofKeyCodeExchange::oneshot().unwrap();
+        let mut builder = AuthorizationCodeCredential::builder(
+            "auth_code",
+            Uuid::new_v4().to_string(),
+            "client_secret",
+        );
+        builder
+            .with_scope(["User.Read"])
+            .with_redirect_uri(Url::parse("http://localhost:8000/redirect").unwrap())
+            .with_pkce(&pkce);
+        let mut credential = builder.credential.clone();
+
+        let form = credential.form_urlencode().unwrap();
+        assert_eq!(
+            Some("authorization_code"),
+            form.get("grant_type").map(|s| s.as_str())
+        );
+        assert_eq!(Some("auth_code"), form.get("code").map(|s| s.as_str()));
+
+        credential.with_refresh_token("refresh_token");
+        for _ in 0..2 {
+            let form = credential.form_urlencode().unwrap();
+            assert_eq!(
+                Some("refresh_token"),
+                form.get("grant_type").map(|s| s.as_str())
+            );
+            assert_eq!(
+                Some("refresh_token"),
+                form.get("refresh_token").map(|s| s.as_str())
+            );
+            assert!(!form.contains_key("code"));
+            assert!(!form.contains_key("code_verifier"));
+            assert!(!form.contains_key("redirect_uri"));
+        }
+    }
 
     #[test]
     fn with_tenant_id_common() {
@@ -597,4 +747,72 @@ mod test {
             .with_tenant("tenant_id")
             .build();
     }
+
+    #[test]
+    fn scope_order_in_url_and_form() {
+        let scope = ["User.Read", "Mail.Read", "offline_access", "User.Read"];
+        let client_id = Uuid::new_v4();
+
+        let url = AuthorizationCodeCredential::authorization_url_builder(client_id)
+            .with_redirect_uri(Url::parse("http://localhost").unwrap())
+            .with_scope(scope)
+            .url()
+            .unwrap();
+        let url_scope = url
+            .query_pairs()
+            .find(|(key, _)| key == "scope")
+            .map(|(_, value)| value.into_owned());
+        assert_eq!(
+            Some("User.Read Mail.Read offline_access".to_owned()),
+            url_scope
+        );
+
+        let mut credential =
+            AuthorizationCodeCredential::builder("auth_code", client_id.to_string(), "secret")
+                .with_redirect_uri(Url::parse("http://localhost").unwrap())
+                .with_scope(scope)
+                .build();
+        let map = credential.form_urlencode().unwrap();
+        assert_eq!(
+            Some(&"User.Read Mail.Read offline_access".to_owned()),
+            map.get("scope")
+        );
+    }
+
+    #[test]
+    fn invalid_code_verifier_is_rejected() {
+        let mut credential_builder =
+            AuthorizationCodeCredential::builder("auth_code", Uuid::new_v4().to_string(), "secret");
+        credential_builder
+            .with_redirect_uri(Url::parse("http://localhost").unwrap())
+            .with_code_verifier("too-short");
+        let mut credential = credential_builder.build();
+        assert!(credential.form_urlencode().is_err());
+
+        let pkce = ProofKeyCodeExchange::oneshot().unwrap();
+        credential_builder.with_pkce(&pkce);
+        let mut credential = credential_builder.build();
+        let map = credential.form_urlencode().unwrap();
+        assert_eq!(Some(&pkce.code_verifier), map.get("code_verifier"));
+    }
+
+    #[test]
+    fn single_scope_same_as_vec() {
+        let client_id = Uuid::new_v4().to_string();
+        let mut single_scope_credential =
+            AuthorizationCodeCredential::builder("auth_code", client_id.as_str(), "secret")
+                .with_redirect_uri(Url::parse("http://localhost").unwrap())
+                .with_single_scope("User.Read")
+                .build();
+        let mut vec_scope_credential =
+            AuthorizationCodeCredential::builder("auth_code", client_id.as_str(), "secret")
+                .with_redirect_uri(Url::parse("http://localhost").unwrap())
+                .with_scope(vec!["User.Read"])
+                .build();
+
+        assert_eq!(
+            vec_scope_credential.form_urlencode().unwrap(),
+            single_scope_credential.form_urlencode().unwrap()
+        );
+    }
 }
@@ -0,0 +1,418 @@
+use std::fmt::{Debug, Formatter};
+use std::io::{ErrorKind, Read};
+use std::process::{Command, Stdio};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use dyn_clone::DynClone;
+use time::macros::format_description;
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+use graph_core::cache::{AsBearer, CacheStore, InMemoryCacheStore, TokenCache};
+use graph_core::identity::{BearerToken, ClientApplication, ForceTokenRefresh};
+use graph_error::{AuthExecutionError, AuthExecutionResult, IdentityResult, AF};
+
+use crate::identity::{tracing_targets::CREDENTIAL_EXECUTOR, Token};
+
+dyn_clone::clone_trait_object!(AzureCliCommandRunner);
+
+/// Runs the Azure CLI. The default implementation, [AzureCliProcessRunner], starts
+/// the `az` process. Provide your own implementation to change how the CLI is invoked.
+pub trait AzureCliCommandRunner: DynClone + Debug + Send + Sync {
+    /// Run `az` with the given arguments and return the output written to stdout.
+    fn run(&self, args: &[String]) -> IdentityResult<String>;
+}
+
+/// Starts the `az` process. The process is killed and an error is returned if it has not
+/// exited after the timeout, 30 seconds by default, so that a hung Azure CLI, for instance
+/// one waiting for input, does not block the caller forever.
+#[derive(Clone, Debug)]
+pub struct AzureCliProcessRunner {
+    timeout: Duration,
+}
+
+impl AzureCliProcessRunner {
+    pub fn with_timeout(timeout: Duration) -> AzureCliProcessRunner {
+        AzureCliProcessRunner { timeout }
+    }
+
+    fn run_program(&self, program: &str, args: &[String]) -> IdentityResult<String> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| {
+                if err.kind() == ErrorKind::NotFound {
+                    AF::msg_err(
+                        "az",
+                        "Azure CLI not found on PATH. Install the Azure CLI and run az login",
+                    )
+                } else {
+                    AF::msg_err("az", err.to_string().as_str())
+                }
+            })?;
+
+        // Read the pipes while waiting so that a full pipe does not block the process.
+        let stdout = read_pipe(child.stdout.take());
+        let stderr = read_pipe(child.stderr.take());
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            match child
+                .try_wait()
+                .map_err(|err| AF::msg_err("az", err.to_string().as_str()))?
+            {
+                Some(status) => break status,
+                None if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return AF::msg_result(
+                        "az",
+                        format!("Azure CLI did not exit within {:?}", self.timeout),
+                    );
+                }
+                None => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+
+        let stdout = stdout.join().unwrap_or_default();
+        if !status.success() {
+            let stderr = stderr.join().unwrap_or_default();
+            return AF::msg_result("az", String::from_utf8_lossy(&stderr).trim());
+        }
+
+        String::from_utf8(stdout).map_err(|err| AF::msg_err("az", err.to_string().as_str()))
+    }
+}
+
+impl Default for AzureCliProcessRunner {
+    fn default() -> Self {
+        AzureCliProcessRunner::with_timeout(Duration::from_secs(30))
+    }
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+impl AzureCliCommandRunner for AzureCliProcessRunner {
+    fn run(&self, args: &[String]) -> IdentityResult<String> {
+        let program = if cfg!(target_os = "windows") {
+            "az.cmd"
+        } else {
+            "az"
+        };
+        self.run_program(program, args)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AzureCliAccessToken {
+    access_token: String,
+    token_type: Option<String>,
+    /// Local time in the format 2023-10-31 11:59:59.000000
+    expires_on: Option<String>,
+    /// Unix timestamp. Only returned by newer versions of the Azure CLI.
+    #[serde(rename = "expires_on")]
+    expires_on_timestamp: Option<i64>,
+}
+
+impl AzureCliAccessToken {
+    /// The expiry of the token. The `expires_on` unix timestamp is used when the Azure CLI
+    /// returns it. Older versions of the Azure CLI only return `expiresOn` in local time
+    /// without an offset, which is converted with the local UTC offset of the process.
+    ///
+    /// The local offset cannot always be determined, most often in multithreaded processes
+    /// on Unix. UTC is assumed in that case, so the expiry of tokens from older versions of
+    /// the Azure CLI can be off by the local UTC offset. Update the Azure CLI to avoid this.
+    fn expires_on(&self) -> IdentityResult<OffsetDateTime> {
+        if let Some(timestamp) = self.expires_on_timestamp {
+            return OffsetDateTime::from_unix_timestamp(timestamp)
+                .map_err(|err| AF::msg_err("expires_on", err.to_string().as_str()));
+        }
+
+        let expires_on = self
+            .expires_on
+            .as_ref()
+            .ok_or_else(|| AF::required("expiresOn"))?;
+        let format =
+            format_description!("[year]-[month]-[day] [hour]:[minute]:[second].[subsecond]");
+        let local_offset = UtcOffset::current_local_offset().unwrap_or_else(|_| {
+            tracing::warn!(
+                target: CREDENTIAL_EXECUTOR,
+                "local UTC offset unknown, assuming UTC for the expiresOn of the Azure CLI token; update the Azure CLI to get the expires_on timestamp"
+            );
+            UtcOffset::UTC
+        });
+        PrimitiveDateTime::parse(expires_on, format)
+            .map(|date_time| date_time.assume_offset(local_offset))
+            .map_err(|err| AF::msg_err("expiresOn", err.to_string().as_str()))
+    }
+}
+
+/// Uses the account that is logged in to the Azure CLI with `az login`. Tokens are
+/// requested with `az account get-access-token --resource <resource> --output json`.
+///
+/// Intended for local development where the Azure CLI is already installed and logged in.
+///
+/// This credential does not implement [TokenCredentialExecutor](crate::identity::TokenCredentialExecutor)
+/// because no request is sent to the token endpoint: there is no form, uri or
+/// [AppConfig](crate::identity::AppConfig) to build one from. Tokens are cached and returned
+/// through [TokenCache] and [ClientApplication] like the other credentials.
+#[derive(Clone)]
+pub struct AzureCliCredential {
+    resource: String,
+    tenant_id: Option<String>,
+    command_runner: Box<dyn AzureCliCommandRunner>,
+    force_token_refresh: ForceTokenRefresh,
+    token_cache: InMemoryCacheStore<Token>,
+}
+
+impl Debug for AzureCliCredential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AzureCliCredential")
+            .field("resource", &self.resource)
+            .field("force_token_refresh", &self.force_token_refresh)
+            .finish()
+    }
+}
+
+impl AzureCliCredential {
+    /// The resource is the application ID URI of the resource, for instance
+    /// https://graph.microsoft.com for Microsoft Graph.
+    pub fn new(resource: impl AsRef<str>) -> AzureCliCredential {
+        AzureCliCredential {
+            resource: resource.as_ref().to_owned(),
+            tenant_id: None,
+            command_runner: Box::new(AzureCliProcessRunner::default()),
+            force_token_refresh: Default::default(),
+            token_cache: Default::default(),
+        }
+    }
+
+    /// Request a token for a tenant other than the default tenant of the logged in account.
+    pub fn with_tenant(&mut self, tenant_id: impl AsRef<str>) -> &mut Self {
+        self.tenant_id = Some(tenant_id.as_ref().to_owned());
+        self
+    }
+
+    pub fn with_command_runner<T: AzureCliCommandRunner + 'static>(
+        &mut self,
+        command_runner: T,
+    ) -> &mut Self {
+        self.command_runner = Box::new(command_runner);
+        self
+    }
+
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![
+            "account".to_owned(),
+            "get-access-token".to_owned(),
+            "--resource".to_owned(),
+            self.resource.clone(),
+            "--output".to_owned(),
+            "json".to_owned(),
+        ];
+
+        if let Some(tenant_id) = self.tenant_id.as_ref() {
+            args.push("--tenant".to_owned());
+            args.push(tenant_id.clone());
+        }
+
+        args
+    }
+
+    fn cache_id(&self) -> String {
+        match self.tenant_id.as_ref() {
+            Some(tenant_id) => format!("{},{}", tenant_id, self.resource),
+            None => self.resource.clone(),
+        }
+    }
+
+    fn parse_token(output: &str) -> IdentityResult<Token> {
+        let cli_token: AzureCliAccessToken = serde_json::from_str(output)?;
+        let expires_on = cli_token.expires_on()?;
+        let expires_in = (expires_on - OffsetDateTime::now_utc()).whole_seconds();
+
+        let mut token = Token::new(
+            cli_token.token_type.as_deref().unwrap_or("Bearer"),
+            expires_in,
+            cli_token.access_token.as_str(),
+            Vec::<String>::new(),
+        );
+        token.expires_on = Some(expires_on);
+        Ok(token)
+    }
+
+    fn execute_cached_token_refresh(&mut self, cache_id: String) -> AuthExecutionResult<Token> {
+        tracing::debug!(target: CREDENTIAL_EXECUTOR, "requesting token from azure cli");
+        let output = self.command_runner.run(&self.args())?;
+        let token = AzureCliCredential::parse_token(output.as_str())?;
+        self.token_cache.store(cache_id, token.clone());
+        Ok(token)
+    }
+
+    async fn execute_cached_token_refresh_async(
+        &mut self,
+        cache_id: String,
+    ) -> AuthExecutionResult<Token> {
+        tracing::debug!(target: CREDENTIAL_EXECUTOR, "requesting token from azure cli");
+        let command_runner = self.command_runner.clone();
+        let args = self.args();
+        let output = tokio::task::spawn_blocking(move || command_runner.run(&args))
+            .await
+            .map_err(|err| AF::msg_err("az", err.to_string().as_str()))??;
+        let token = AzureCliCredential::parse_token(output.as_str())?;
+        self.token_cache.store(cache_id, token.clone());
+        Ok(token)
+    }
+}
+
+token_cache_snapshot!(AzureCliCredential);
+
+#[async_trait]
+impl TokenCache for AzureCliCredential {
+    type Token = Token;
+
+    fn get_token_silent(&mut self) -> Result<Self::Token, AuthExecutionError> {
+        let cache_id = self.cache_id();
+
+        match self.force_token_refresh {
+            ForceTokenRefresh::Never => match self.token_cache.get(cache_id.as_str()) {
+                Some(token) if !token.is_expired_sub(time::Duration::minutes(5)) => Ok(token),
+                _ => self.execute_cached_token_refresh(cache_id),
+            },
+            ForceTokenRefresh::Once | ForceTokenRefresh::Always => {
+                let token_result = self.execute_cached_token_refresh(cache_id);
+                if self.force_token_refresh == ForceTokenRefresh::Once {
+                    self.force_token_refresh = ForceTokenRefresh::Never;
+                }
+                token_result
+            }
+        }
+    }
+
+    async fn get_token_silent_async(&mut self) -> Result<Self::Token, AuthExecutionError> {
+        let cache_id = self.cache_id();
+
+        match self.force_token_refresh {
+            ForceTokenRefresh::Never => match self.token_cache.get(cache_id.as_str()) {
+                Some(token) if !token.is_expired_sub(time::Duration::minutes(5)) => Ok(token),
+                _ => self.execute_cached_token_refresh_async(cache_id).await,
+            },
+            ForceTokenRefresh::Once | ForceTokenRefresh::Always => {
+                let token_result = self.execute_cached_token_refresh_async(cache_id).await;
+                if self.force_token_refresh == ForceTokenRefresh::Once {
+                    self.force_token_refresh = ForceTokenRefresh::Never;
+                }
+                token_result
+            }
+        }
+    }
+
+    fn with_force_token_refresh(&mut self, force_token_refresh: ForceTokenRefresh) {
+        self.force_token_refresh = force_token_refresh;
+    }
+}
+
+#[async_trait]
+impl ClientApplication for AzureCliCredential {
+    fn get_token_silent(&mut self) -> AuthExecutionResult<BearerToken> {
+        let token = TokenCache::get_token_silent(self)?;
+        token.try_as_bearer().map(BearerToken::from)
+    }
+
+    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<BearerToken> {
+        let token = TokenCache::get_token_silent_async(self).await?;
+        token.try_as_bearer().map(BearerToken::from)
+    }
+
+    fn with_force_token_refresh(&mut self, force_token_refresh: ForceTokenRefresh) {
+        TokenCache::with_force_token_refresh(self, force_token_refresh);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Clone, Debug)]
+    struct MockCommandRunner {
+        output: String,
+    }
+
+    impl AzureCliCommandRunner for MockCommandRunner {
+        fn run(&self, args: &[String]) -> IdentityResult<String> {
+            assert_eq!(
+                vec![
+                    "account",
+                    "get-access-token",
+                    "--resource",
+                    "https://graph.microsoft.com",
+                    "--output",
+                    "json"
+                ],
+                args
+            );
+            Ok(self.output.clone())
+        }
+    }
+
+    #[test]
+    fn parse_cli_token() {
+        let expires_on = OffsetDateTime::now_utc().unix_timestamp() + 3600;
+        let output = serde_json::json!({
+            "accessToken": "cli_access_token",
+            "expiresOn": "2023-10-31 11:59:59.000000",
+            "expires_on": expires_on,
+            "subscription": "subscription",
+            "tenant": "tenant",
+            "tokenType": "Bearer"
+        });
+
+        let mut credential = AzureCliCredential::new("https://graph.microsoft.com");
+        credential.with_command_runner(MockCommandRunner {
+            output: output.to_string(),
+        });
+
+        let token = TokenCache::get_token_silent(&mut credential).unwrap();
+        assert_eq!("cli_access_token", token.access_token);
+        assert_eq!("Bearer", token.token_type);
+        assert!(!token.is_expired());
+        assert_eq!(
+            Some(expires_on),
+            token.expires_on.map(|date_time| date_time.unix_timestamp())
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn process_is_killed_after_timeout() {
+        let runner = AzureCliProcessRunner::with_timeout(Duration::from_millis(100));
+        let start = Instant::now();
+        assert!(runner.run_program("sleep", &["5".to_owned()]).is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let output = runner.run_program("echo", &["token".to_owned()]).unwrap();
+        assert_eq!("token", output.trim());
+    }
+
+    #[test]
+    fn parse_cli_token_local_expires_on() {
+        let token = AzureCliCredential::parse_token(
+            r#"{"accessToken": "cli_access_token", "expiresOn": "2023-10-31 11:59:59.000000", "tokenType": "Bearer"}"#,
+        )
+        .unwrap();
+        assert_eq!("cli_access_token", token.access_token);
+        assert!(token.is_expired());
+    }
+}
@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 use graph_core::cache::AsBearer;
-use graph_core::identity::{ClientApplication, ForceTokenRefresh};
+use graph_core::identity::{BearerToken, ClientApplication, ForceTokenRefresh};
 use graph_error::AuthExecutionResult;
 use std::fmt::Display;
 
@@ -49,12 +49,12 @@ impl From<String> for BearerTokenCredential {
 
 #[async_trait]
 impl ClientApplication for BearerTokenCredential {
-    fn get_token_silent(&mut self) -> AuthExecutionResult<String> {
-        Ok(self.0.clone())
+    fn get_token_silent(&mut self) -> AuthExecutionResult<BearerToken> {
+        Ok(BearerToken::from(self.0.as_str()))
     }
 
-    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<String> {
-        Ok(self.0.clone())
+    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<BearerToken> {
+        Ok(BearerToken::from(self.0.as_str()))
     }
 
     fn with_force_token_refresh(&mut self, _force_token_refresh: ForceTokenRefresh) {}
@@ -0,0 +1,137 @@
+use base64::Engine;
+use uuid::Uuid;
+
+/// How the cache id of the tokens of a credential is computed, which decides which
+/// token requests share a cached token.
+///
+/// The default, [CacheKeyStrategy::ClientIdAndTenant], is the cache id used when no
+/// strategy is set.
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize,
+)]
+pub enum CacheKeyStrategy {
+    /// One cached token per client id, regardless of tenant.
+    ClientId,
+    /// One cached token per client id and tenant.
+    #[default]
+    ClientIdAndTenant,
+    /// One cached token per client id, tenant, set of scopes and signed-in account.
+    /// The account is the `oid` claim of the id token of the credential, if any.
+    ClientIdTenantScopeAndAccount,
+}
+
+impl CacheKeyStrategy {
+    /// Compute the cache id for the inputs. Scopes are compared ignoring order and case.
+    pub fn cache_id(
+        &self,
+        client_id: Uuid,
+        tenant_id: Option<&str>,
+        scope: &[String],
+        account_id: Option<&str>,
+    ) -> String {
+        let key = match self {
+            CacheKeyStrategy::ClientId => client_id.to_string(),
+            CacheKeyStrategy::ClientIdAndTenant => match tenant_id {
+                Some(tenant_id) => format!("{},{}", tenant_id, client_id),
+                None => client_id.to_string(),
+            },
+            CacheKeyStrategy::ClientIdTenantScopeAndAccount => {
+                let mut scope: Vec<String> = scope.iter().map(|s| s.to_lowercase()).collect();
+                scope.sort();
+                scope.dedup();
+                format!(
+                    "{},{},{},{}",
+                    tenant_id.unwrap_or_default(),
+                    client_id,
+                    scope.join(" "),
+                    account_id.unwrap_or_default()
+                )
+            }
+        };
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::{AppConfig, Authority};
+
+    #[test]
+    fn cache_id_per_strategy() {
+        let client_id = Uuid::new_v4();
+        let scope = vec!["User.Read".to_owned(), "Mail.Read".to_owned()];
+        let cache_id = |strategy: CacheKeyStrategy| {
+            strategy.cache_id(client_id, Some("tenant"), &scope, Some("oid"))
+        };
+
+        let client = cache_id(CacheKeyStrategy::ClientId);
+        let client_tenant = cache_id(CacheKeyStrategy::ClientIdAndTenant);
+        let client_tenant_scope_account = cache_id(CacheKeyStrategy::ClientIdTenantScopeAndAccount);
+        assert_ne!(client, client_tenant);
+        assert_ne!(client_tenant, client_tenant_scope_account);
+        assert_ne!(client, client_tenant_scope_account);
+
+        assert_eq!(
+            client,
+            CacheKeyStrategy::ClientId.cache_id(client_id, Some("other"), &[], None)
+        );
+        assert_eq!(
+            client_tenant_scope_account,
+            CacheKeyStrategy::ClientIdTenantScopeAndAccount.cache_id(
+                client_id,
+                Some("tenant"),
+                &["mail.read".to_owned(), "user.read".to_owned()],
+                Some("oid")
+            )
+        );
+        assert_ne!(
+            client_tenant_scope_account,
+            CacheKeyStrategy::ClientIdTenantScopeAndAccount.cache_id(
+                client_id,
+                Some("tenant"),
+                &scope,
+                Some("other-oid")
+            )
+        );
+    }
+
+    #[test]
+    fn setters_recompute_cache_id() {
+        let mut app_config = AppConfig::new(Uuid::new_v4());
+        app_config.with_cache_key_strategy(CacheKeyStrategy::ClientIdTenantScopeAndAccount);
+        let mut cache_ids = vec![app_config.cache_id.clone()];
+
+        app_config.with_tenant("tenant");
+        cache_ids.push(app_config.cache_id.clone());
+        app_config.with_scope(["User.Read"]);
+        cache_ids.push(app_config.cache_id.clone());
+        app_config.add_openid_scope();
+        cache_ids.push(app_config.cache_id.clone());
+        app_config.with_incremental_scope(&["User.Read".to_owned()], ["Mail.Read"]);
+        cache_ids.push(app_config.cache_id.clone());
+        app_config
+            .with_resource_default("https://graph.microsoft.com")
+            .unwrap();
+        cache_ids.push(app_config.cache_id.clone());
+
+        let unique: std::collections::HashSet<&String> = cache_ids.iter().collect();
+        assert_eq!(cache_ids.len(), unique.len());
+        assert_eq!(
+            CacheKeyStrategy::ClientIdTenantScopeAndAccount.cache_id(
+                app_config.client_id,
+                Some("tenant"),
+                &["https://graph.microsoft.com/.default".to_owned()],
+                None
+            ),
+            app_config.cache_id
+        );
+
+        let app_config = AppConfig::builder(Uuid::new_v4())
+            .tenant("tenant")
+            .scope(["User.Read"])
+            .build();
+        assert_eq!(Some("tenant"), app_config.tenant_id.as_deref());
+        assert_eq!(Authority::TenantId("tenant".into()), app_config.authority);
+    }
+}
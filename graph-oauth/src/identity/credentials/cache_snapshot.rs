@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Formatter};
+
+use serde_json::Value;
+use time::OffsetDateTime;
+
+use crate::identity::{IdToken, Token};
+
+/// A [Token] as stored in a [CacheSnapshot]. Unlike deserializing a [Token] from
+/// a token response, the timestamp and expiration are kept as is so that a token
+/// that is imported has the same expiration as when it was exported.
+#[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CachedToken {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub ext_expires_in: Option<i64>,
+    pub scope: Vec<String>,
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub refresh_token_expires_in: Option<i64>,
+    pub user_id: Option<String>,
+    pub id_token: Option<String>,
+    pub state: Option<String>,
+    pub session_state: Option<String>,
+    pub nonce: Option<String>,
+    pub correlation_id: Option<String>,
+    pub client_info: Option<String>,
+    /// Unix timestamp of when the token was received.
+    pub timestamp: Option<i64>,
+    /// Unix timestamp of when the access token expires.
+    pub expires_on: Option<i64>,
+    pub additional_fields: HashMap<String, Value>,
+}
+
+impl Debug for CachedToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CachedToken")
+            .field("access_token", &"[REDACTED]")
+            .field("token_type", &self.token_type)
+            .field("expires_in", &self.expires_in)
+            .field("scope", &self.scope)
+            .field("refresh_token", &"[REDACTED]")
+            .field("expires_on", &self.expires_on)
+            .finish()
+    }
+}
+
+impl From<Token> for CachedToken {
+    fn from(value: Token) -> Self {
+        CachedToken {
+            access_token: value.access_token,
+            token_type: value.token_type,
+            expires_in: value.expires_in,
+            ext_expires_in: value.ext_expires_in,
+            scope: value.scope,
+            refresh_token: value.refresh_token,
+            refresh_token_expires_in: value.refresh_token_expires_in,
+            user_id: value.user_id,
+            id_token: value.id_token.map(|id_token| id_token.id_token),
+            state: value.state,
+            session_state: value.session_state,
+            nonce: value.nonce,
+            correlation_id: value.correlation_id,
+            client_info: value.client_info,
+            timestamp: value.timestamp.map(|timestamp| timestamp.unix_timestamp()),
+            expires_on: value
+                .expires_on
+                .map(|expires_on| expires_on.unix_timestamp()),
+            additional_fields: value.additional_fields,
+        }
+    }
+}
+
+impl From<CachedToken> for Token {
+    fn from(value: CachedToken) -> Self {
+        let mut token = Token::new(
+            value.token_type.as_str(),
+            value.expires_in,
+            value.access_token.as_str(),
+            value.scope,
+        );
+        token.ext_expires_in = value.ext_expires_in;
+        token.refresh_token = value.refresh_token;
+        token.refresh_token_expires_in = value.refresh_token_expires_in;
+        token.user_id = value.user_id;
+        token.id_token = value
+            .id_token
+            .map(|id_token| IdToken::new(id_token.as_str(), None, None, None));
+        token.state = value.state;
+        token.session_state = value.session_state;
+        token.nonce = value.nonce;
+        token.correlation_id = value.correlation_id;
+        token.client_info = value.client_info;
+        token.timestamp = value
+            .timestamp
+            .and_then(|timestamp| OffsetDateTime::from_unix_timestamp(timestamp).ok());
+        token.expires_on = value
+            .expires_on
+            .and_then(|expires_on| OffsetDateTime::from_unix_timestamp(expires_on).ok());
+        token.additional_fields = value.additional_fields;
+        token
+    }
+}
+
+/// Serializable copy of every token in a credential's token cache keyed by cache id.
+///
+/// The snapshot holds the raw access tokens, refresh tokens, and id tokens. Anyone with
+/// the snapshot can call APIs as the application or user, so store and transfer it
+/// with the same care as a client secret. The [Debug] output does not include token values.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CacheSnapshot {
+    pub entries: BTreeMap<String, CachedToken>,
+}
+
+impl CacheSnapshot {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+impl FromIterator<(String, Token)> for CacheSnapshot {
+    fn from_iter<T: IntoIterator<Item = (String, Token)>>(iter: T) -> Self {
+        CacheSnapshot {
+            entries: iter
+                .into_iter()
+                .map(|(cache_id, token)| (cache_id, CachedToken::from(token)))
+                .collect(),
+        }
+    }
+}
+
+/// Export and import the tokens of a credential's token cache.
+pub trait TokenCacheSnapshot {
+    /// Copy every token in the token cache. See [CacheSnapshot] for handling
+    /// the raw tokens in the snapshot.
+    fn export_cache(&self) -> CacheSnapshot;
+
+    /// Store every token in the snapshot in the token cache. Tokens with the
+    /// same cache id as a token in the snapshot are replaced.
+    fn import_cache(&mut self, snapshot: CacheSnapshot);
+}
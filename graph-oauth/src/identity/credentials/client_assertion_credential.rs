@@ -5,17 +5,19 @@ use async_trait::async_trait;
 use http::{HeaderMap, HeaderName, HeaderValue};
 
 use uuid::Uuid;
+use zeroize::Zeroize;
 
 use crate::oauth_serializer::{AuthParameter, AuthSerializer};
-use graph_core::cache::{CacheStore, InMemoryCacheStore, TokenCache};
-use graph_core::http::{AsyncResponseConverterExt, ResponseConverterExt};
+use graph_core::cache::{InMemoryCacheStore, TokenCache};
+use graph_core::http::ResponseConverterExt;
 use graph_core::identity::ForceTokenRefresh;
 use graph_error::{AuthExecutionError, AuthExecutionResult, IdentityResult, AF};
 
 use crate::identity::credentials::app_config::AppConfig;
 use crate::identity::{
-    tracing_targets::CREDENTIAL_EXECUTOR, Authority, AzureCloudInstance,
-    ConfidentialClientApplication, Token, TokenCredentialExecutor, CLIENT_ASSERTION_TYPE,
+    read_json_limited, tracing_targets::CREDENTIAL_EXECUTOR, Authority, AzureCloudInstance,
+    ConfidentialClientApplication, CredentialWarning, Token, TokenCredentialExecutor,
+    CLIENT_ASSERTION_TYPE,
 };
 
 credential_builder!(
@@ -23,6 +25,8 @@ credential_builder!(
     ConfidentialClientApplication<ClientAssertionCredential>
 );
 
+client_credentials_builder!(ClientAssertionCredentialBuilder);
+
 /// Client Credentials Using an Assertion.
 ///
 /// The OAuth 2.0 client credentials grant flow permits a web service (confidential client) to use
@@ -61,6 +65,12 @@ impl Debug for ClientAssertionCredential {
     }
 }
 
+impl Drop for ClientAssertionCredential {
+    fn drop(&mut self) {
+        self.client_assertion.zeroize();
+    }
+}
+
 impl ClientAssertionCredential {
     pub fn new(
         tenant_id: impl AsRef<str>,
@@ -87,29 +97,14 @@ impl ClientAssertionCredential {
             ));
         }
 
-        let new_token: Token = response.json()?;
-        self.token_cache.store(cache_id, new_token.clone());
-        Ok(new_token)
-    }
-
-    async fn execute_cached_token_refresh_async(
-        &mut self,
-        cache_id: String,
-    ) -> AuthExecutionResult<Token> {
-        let response = self.execute_async().await?;
-
-        if !response.status().is_success() {
-            return Err(AuthExecutionError::silent_token_auth(
-                response.into_http_response_async().await?,
-            ));
-        }
-
-        let new_token: Token = response.json().await?;
-        self.token_cache.store(cache_id, new_token.clone());
+        let new_token: Token = read_json_limited(response, self.max_response_body_size())?;
+        let new_token = self.store_token(cache_id.as_str(), new_token);
         Ok(new_token)
     }
 }
 
+token_cache_snapshot!(ClientAssertionCredential);
+
 #[async_trait]
 impl TokenCache for ClientAssertionCredential {
     type Token = Token;
@@ -117,8 +112,8 @@ impl TokenCache for ClientAssertionCredential {
     #[tracing::instrument]
     fn get_token_silent(&mut self) -> Result<Self::Token, AuthExecutionError> {
         let cache_id = self.app_config.cache_id.to_string();
-        if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-            if token.is_expired_sub(time::Duration::minutes(5)) {
+        if let Some(token) = self.cached_token(cache_id.as_str()) {
+            if self.is_token_expired(&token) {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=None");
                 self.execute_cached_token_refresh(cache_id)
             } else {
@@ -134,17 +129,17 @@ impl TokenCache for ClientAssertionCredential {
     #[tracing::instrument]
     async fn get_token_silent_async(&mut self) -> Result<Self::Token, AuthExecutionError> {
         let cache_id = self.app_config.cache_id.to_string();
-        if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-            if token.is_expired_sub(time::Duration::minutes(5)) {
+        if let Some(token) = self.cached_token(cache_id.as_str()) {
+            if self.is_token_expired(&token) {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=None");
-                self.execute_cached_token_refresh_async(cache_id).await
+                self.acquire_token_async().await
             } else {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "using token from cache");
                 Ok(token.clone())
             }
         } else {
             tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=None");
-            self.execute_cached_token_refresh_async(cache_id).await
+            self.acquire_token_async().await
         }
     }
 
@@ -174,7 +169,7 @@ impl TokenCredentialExecutor for ClientAssertionCredential {
             .client_id(client_id.as_str())
             .client_assertion(self.client_assertion.as_str())
             .client_assertion_type(self.client_assertion_type.as_str())
-            .set_scope(self.app_config.scope.clone())
+            .set_scope(self.app_config.app_only_scope())
             .grant_type("client_credentials");
 
         serializer.as_credential_map(
@@ -203,6 +198,22 @@ impl TokenCredentialExecutor for ClientAssertionCredential {
     fn app_config(&self) -> &AppConfig {
         &self.app_config
     }
+
+    fn warnings(&self) -> Vec<CredentialWarning> {
+        CredentialWarning::for_app_only_scope(&self.app_config.scope)
+    }
+
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        Some(&self.token_cache)
+    }
+
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        Some(&mut self.token_cache)
+    }
+
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        Some(&mut self.app_config)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -231,9 +242,7 @@ impl ClientAssertionCredentialBuilder {
         signed_assertion: impl AsRef<str>,
         mut app_config: AppConfig,
     ) -> ClientAssertionCredentialBuilder {
-        app_config
-            .scope
-            .insert("https://graph.microsoft.com/.default".to_string());
+        app_config.add_scope("https://graph.microsoft.com/.default");
         ClientAssertionCredentialBuilder {
             credential: ClientAssertionCredential {
                 app_config,
@@ -248,4 +257,41 @@ impl ClientAssertionCredentialBuilder {
         self.credential.client_assertion = client_assertion.as_ref().to_owned();
         self
     }
+
+    /// Set the client_assertion_type sent in the token request. Defaults to
+    /// `urn:ietf:params:oauth:client-assertion-type:jwt-bearer` and should only be
+    /// changed for preview scenarios that use a different assertion type.
+    pub fn with_client_assertion_type<T: AsRef<str>>(
+        &mut self,
+        client_assertion_type: T,
+    ) -> &mut Self {
+        self.credential.client_assertion_type = client_assertion_type.as_ref().to_owned();
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn client_assertion_type_in_form() {
+        let mut builder = ClientAssertionCredentialBuilder::new(
+            Uuid::new_v4().to_string(),
+            "eyJhbGciOiJSUzI1NiJ9.eyJhdWQiOiJhdWQifQ.signature",
+        );
+        let form = builder.credential.form_urlencode().unwrap();
+        assert_eq!(
+            Some(&CLIENT_ASSERTION_TYPE.to_owned()),
+            form.get("client_assertion_type")
+        );
+
+        builder
+            .with_client_assertion_type("urn:ietf:params:oauth:client-assertion-type:saml2-bearer");
+        let form = builder.credential.form_urlencode().unwrap();
+        assert_eq!(
+            Some(&"urn:ietf:params:oauth:client-assertion-type:saml2-bearer".to_owned()),
+            form.get("client_assertion_type")
+        );
+    }
 }
@@ -20,6 +20,22 @@ macro_rules! credential_builder_base {
                 self
             }
 
+            /// Set the cloud instance and tenant from a full authority url, for instance
+            /// https://login.microsoftonline.com/contoso.onmicrosoft.com or the B2C authority
+            /// https://contoso.b2clogin.com/contoso.onmicrosoft.com/B2C_1_signupsignin.
+            ///
+            /// Returns an error if the url is not https or is not the authority url of a
+            /// cloud instance or B2C.
+            pub fn with_authority_url(
+                &mut self,
+                authority_url: &str,
+            ) -> graph_error::IdentityResult<&mut Self> {
+                self.credential
+                    .app_config
+                    .with_authority_url(authority_url)?;
+                Ok(self)
+            }
+
             pub fn with_azure_cloud_instance(
                 &mut self,
                 azure_cloud_instance: crate::identity::AzureCloudInstance,
@@ -83,6 +99,213 @@ macro_rules! credential_builder_base {
                 self.credential.app_config.with_scope(scope);
                 self
             }
+
+            /// Convenience method for a single scope. Same as calling
+            /// [with_scope](Self::with_scope) with a vec containing only `scope`.
+            /// Replaces current scopes if any were added previously.
+            pub fn with_single_scope(&mut self, scope: impl AsRef<str>) -> &mut Self {
+                self.credential.app_config.with_scope([scope.as_ref()]);
+                self
+            }
+
+            /// Incremental consent. Request the scopes in the `scope` of a previously
+            /// received token along with the new scopes so that the resulting token
+            /// includes both. Duplicate scopes are only sent once.
+            /// Replaces current scopes if any were added previously.
+            pub fn with_incremental_scope<T: ToString, I: IntoIterator<Item = T>>(
+                &mut self,
+                granted: &crate::identity::Token,
+                scope: I,
+            ) -> &mut Self {
+                self.credential
+                    .app_config
+                    .with_incremental_scope(&granted.scope, scope);
+                self
+            }
+
+            /// Set the `challenge_type` sent in the token request of CIAM native authentication,
+            /// the challenge types the client supports such as `oob`, `password` and `redirect`.
+            /// The challenge types are sent space separated.
+            pub fn with_challenge_type<T: ToString, I: IntoIterator<Item = T>>(
+                &mut self,
+                challenge_type: I,
+            ) -> &mut Self {
+                let challenge_type = challenge_type
+                    .into_iter()
+                    .map(|challenge_type| challenge_type.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                self.credential
+                    .app_config
+                    .with_extra_form_parameter("challenge_type", challenge_type);
+                self
+            }
+
+            /// Set the `continuation_token` sent in the token request of CIAM native
+            /// authentication. The continuation token is returned by the previous request
+            /// of the native authentication flow.
+            pub fn with_continuation_token(
+                &mut self,
+                continuation_token: impl AsRef<str>,
+            ) -> &mut Self {
+                self.credential
+                    .app_config
+                    .with_extra_form_parameter("continuation_token", continuation_token);
+                self
+            }
+
+            /// Bind outbound token requests to the local address, for instance to pin token
+            /// requests to a specific network interface.
+            pub fn with_local_address(&mut self, local_address: std::net::IpAddr) -> &mut Self {
+                self.credential.app_config.local_address = Some(local_address);
+                self
+            }
+
+            /// Resolve the host of the token endpoint using the resolver instead of the
+            /// system resolver.
+            pub fn with_resolver(
+                &mut self,
+                resolver: impl reqwest::dns::Resolve + 'static,
+            ) -> &mut Self {
+                self.credential.app_config.dns_resolver =
+                    Some(crate::identity::DnsResolverRef::new(resolver));
+                self
+            }
+
+            /// Allow token requests over http in order to use mocking frameworks.
+            /// Token requests are only sent over https by default.
+            #[cfg(feature = "test-util")]
+            pub fn with_https_only(&mut self, https_only: bool) -> &mut Self {
+                self.credential.app_config.https_only = https_only;
+                self
+            }
+
+            /// Send the `Accept-Language` header on token requests so that the
+            /// `error_description` of error responses is localized, for instance `fr-FR`
+            /// or `de-DE, de;q=0.9`. Returns an error if the value is not a valid header value.
+            pub fn with_accept_language(
+                &mut self,
+                language: impl AsRef<str>,
+            ) -> graph_error::IdentityResult<&mut Self> {
+                let header_value = HeaderValue::from_str(language.as_ref()).map_err(|err| {
+                    graph_error::AF::msg_err("accept_language", err.to_string().as_str())
+                })?;
+                self.credential
+                    .app_config
+                    .with_extra_header_param(http::header::ACCEPT_LANGUAGE, header_value);
+                Ok(self)
+            }
+
+            /// Send a fixed `client-request-id` with token requests instead of a new random
+            /// id for each request. The id is used to find the request in the logs of the
+            /// Microsoft identity platform.
+            pub fn with_client_request_id(&mut self, client_request_id: uuid::Uuid) -> &mut Self {
+                self.credential.app_config.client_request_id = Some(client_request_id);
+                self
+            }
+
+            /// Maximum number of bytes of a token response body that are read before
+            /// parsing. Larger responses fail with
+            /// [AuthExecutionError::ResponseTooLarge](graph_error::AuthExecutionError::ResponseTooLarge).
+            /// Default is [DEFAULT_MAX_RESPONSE_BODY_SIZE](crate::identity::DEFAULT_MAX_RESPONSE_BODY_SIZE).
+            pub fn with_max_response_body_size(
+                &mut self,
+                max_response_body_size: usize,
+            ) -> &mut Self {
+                self.credential.app_config.max_response_body_size = Some(max_response_body_size);
+                self
+            }
+
+            /// Rewrite the token url computed by the credential before each token request
+            /// is sent, for instance to route token requests through a gateway that
+            /// rewrites the host.
+            pub fn with_token_url_rewriter<F>(&mut self, rewriter: F) -> &mut Self
+            where
+                F: Fn(url::Url) -> url::Url + Send + Sync + 'static,
+            {
+                self.credential.app_config.token_url_rewriter =
+                    Some(crate::identity::TokenUrlRewriter::new(rewriter));
+                self
+            }
+
+            /// Call the hook with a [TokenAuditRecord](crate::identity::TokenAuditRecord) for
+            /// each token acquired from the token endpoint, before the token is cached. The
+            /// record includes the scopes, audience and expiry of the token but not the
+            /// token values, for audit logging.
+            pub fn on_token_acquired<F>(&mut self, hook: F) -> &mut Self
+            where
+                F: Fn(&crate::identity::TokenAuditRecord) + Send + Sync + 'static,
+            {
+                self.credential.app_config.token_acquired_hook =
+                    Some(crate::identity::TokenAcquiredHook::new(hook));
+                self
+            }
+
+            /// The permissions configured on the app registration, for instance `User.Read`.
+            /// Token requests for scopes that are not registered permissions fail before
+            /// the request is sent, see [AppConfig::unregistered_scopes](crate::identity::AppConfig::unregistered_scopes).
+            pub fn with_registered_permissions<T: ToString, I: IntoIterator<Item = T>>(
+                &mut self,
+                registered_permissions: I,
+            ) -> &mut Self {
+                self.credential.app_config.registered_permissions = Some(
+                    registered_permissions
+                        .into_iter()
+                        .map(|permission| permission.to_string())
+                        .collect(),
+                );
+                self
+            }
+
+            /// Compute the cache id of tokens using the [CacheKeyStrategy](crate::identity::CacheKeyStrategy)
+            /// to cache tokens per client, per tenant or per scopes and account.
+            pub fn with_cache_key_strategy(
+                &mut self,
+                cache_key_strategy: crate::identity::CacheKeyStrategy,
+            ) -> &mut Self {
+                self.credential
+                    .app_config
+                    .with_cache_key_strategy(cache_key_strategy);
+                self
+            }
+
+            /// Use the clock for token timestamps and expiry checks instead of the
+            /// system clock, for instance a [TestClock](crate::identity::TestClock)
+            /// in tests of token expiry.
+            pub fn with_clock(
+                &mut self,
+                clock: impl crate::identity::Clock + 'static,
+            ) -> &mut Self {
+                self.credential.app_config.clock = crate::identity::ClockRef::new(clock);
+                self
+            }
+
+            /// Read tokens from a store shared between processes or application runs
+            /// before the in memory token cache and save new tokens to it. See
+            /// [SharedTokenStore](crate::identity::SharedTokenStore).
+            pub fn with_shared_token_store(
+                &mut self,
+                store: impl crate::identity::SharedTokenStore + 'static,
+            ) -> &mut Self {
+                self.credential.app_config.shared_token_store = Some(
+                    crate::identity::credentials::shared_token_store::SharedTokenStoreRef::new(
+                        store,
+                    ),
+                );
+                self
+            }
+
+            /// Limit how often the credential calls the token endpoint. When the budget
+            /// is exhausted token requests either wait or fail with
+            /// [AuthExecutionError::Throttled](graph_error::AuthExecutionError::Throttled)
+            /// depending on the [BudgetExhaustedBehavior](crate::identity::BudgetExhaustedBehavior).
+            pub fn with_request_budget(
+                &mut self,
+                request_budget: crate::identity::RequestBudget,
+            ) -> &mut Self {
+                self.credential.app_config.request_budget = Some(request_budget);
+                self
+            }
         }
     };
 }
@@ -98,3 +321,45 @@ macro_rules! credential_builder {
         }
     };
 }
+
+macro_rules! token_cache_snapshot {
+    ($name:ident) => {
+        impl crate::identity::TokenCacheSnapshot for $name {
+            fn export_cache(&self) -> crate::identity::CacheSnapshot {
+                self.token_cache.entries().into_iter().collect()
+            }
+
+            fn import_cache(&mut self, snapshot: crate::identity::CacheSnapshot) {
+                self.token_cache.extend(
+                    snapshot
+                        .entries
+                        .into_iter()
+                        .map(|(cache_id, token)| (cache_id, crate::identity::Token::from(token))),
+                );
+            }
+        }
+    };
+}
+
+macro_rules! client_credentials_builder {
+    ($name:ident) => {
+        impl $name {
+            /// Request an app-only token for the resource by setting the scope to the
+            /// `/.default` scope of the resource, for instance https://graph.microsoft.com
+            /// becomes https://graph.microsoft.com/.default. Resource urls that already
+            /// end with `/.default` are used as is.
+            ///
+            /// Replaces any previously set scopes. Returns an error if the resource
+            /// is not a well-formed absolute url.
+            pub fn with_resource_default(
+                &mut self,
+                resource_url: impl AsRef<str>,
+            ) -> graph_error::IdentityResult<&mut Self> {
+                self.credential
+                    .app_config
+                    .with_resource_default(resource_url)?;
+                Ok(self)
+            }
+        }
+    };
+}
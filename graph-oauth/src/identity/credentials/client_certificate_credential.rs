@@ -5,9 +5,10 @@ use async_trait::async_trait;
 use http::{HeaderMap, HeaderName, HeaderValue};
 
 use uuid::Uuid;
+use zeroize::Zeroize;
 
-use graph_core::cache::{CacheStore, InMemoryCacheStore, TokenCache};
-use graph_core::http::{AsyncResponseConverterExt, ResponseConverterExt};
+use graph_core::cache::{InMemoryCacheStore, TokenCache};
+use graph_core::http::ResponseConverterExt;
 use graph_core::identity::ForceTokenRefresh;
 use graph_error::{AuthExecutionError, AuthExecutionResult, AuthorizationFailure, IdentityResult};
 
@@ -15,9 +16,9 @@ use crate::identity::credentials::app_config::AppConfig;
 #[cfg(feature = "openssl")]
 use crate::identity::X509Certificate;
 use crate::identity::{
-    tracing_targets::CREDENTIAL_EXECUTOR, Authority, AzureCloudInstance,
-    ClientCredentialsAuthorizationUrlParameterBuilder, ConfidentialClientApplication, Token,
-    TokenCredentialExecutor,
+    read_json_limited, tracing_targets::CREDENTIAL_EXECUTOR, Authority, AzureCloudInstance,
+    ClientCredentialsAuthorizationUrlParameterBuilder, ConfidentialClientApplication,
+    CredentialWarning, Token, TokenCredentialExecutor,
 };
 use crate::oauth_serializer::{AuthParameter, AuthSerializer};
 
@@ -29,6 +30,8 @@ credential_builder!(
     ConfidentialClientApplication<ClientCertificateCredential>
 );
 
+client_credentials_builder!(ClientCertificateCredentialBuilder);
+
 /// Client Credentials Using A Certificate
 ///
 /// The OAuth 2.0 client credentials grant flow permits a web service (confidential client) to use
@@ -59,6 +62,10 @@ pub struct ClientCertificateCredential {
     /// openssl crate. This is significantly easier than having to format the assertion from
     /// the certificate yourself.
     pub(crate) client_assertion: String,
+    /// Base64 Url encoded SHA-1 thumbprint (x5t) of the certificate used to sign the assertion.
+    pub(crate) thumbprint: Option<String>,
+    /// Base64 Url encoded SHA-256 thumbprint (x5t#S256) of the certificate used to sign the assertion.
+    pub(crate) thumbprint_sha256: Option<String>,
     token_cache: InMemoryCacheStore<Token>,
 }
 
@@ -83,34 +90,31 @@ impl ClientCertificateCredential {
         ClientCredentialsAuthorizationUrlParameterBuilder::new(client_id)
     }
 
-    fn execute_cached_token_refresh(&mut self, cache_id: String) -> AuthExecutionResult<Token> {
-        let response = self.execute()?;
-
-        if !response.status().is_success() {
-            return Err(AuthExecutionError::silent_token_auth(
-                response.into_http_response()?,
-            ));
-        }
+    /// Base64 Url encoded (No Pad) SHA-1 thumbprint (x5t) of the certificate that was
+    /// used to sign the client assertion. This is None if the client assertion was
+    /// not created from a certificate.
+    pub fn thumbprint(&self) -> Option<&str> {
+        self.thumbprint.as_deref()
+    }
 
-        let new_token: Token = response.json()?;
-        self.token_cache.store(cache_id, new_token.clone());
-        Ok(new_token)
+    /// Base64 Url encoded (No Pad) SHA-256 thumbprint (x5t#S256) of the certificate that was
+    /// used to sign the client assertion. This is None if the client assertion was
+    /// not created from a certificate.
+    pub fn thumbprint_sha256(&self) -> Option<&str> {
+        self.thumbprint_sha256.as_deref()
     }
 
-    async fn execute_cached_token_refresh_async(
-        &mut self,
-        cache_id: String,
-    ) -> AuthExecutionResult<Token> {
-        let response = self.execute_async().await?;
+    fn execute_cached_token_refresh(&mut self, cache_id: String) -> AuthExecutionResult<Token> {
+        let response = self.execute()?;
 
         if !response.status().is_success() {
             return Err(AuthExecutionError::silent_token_auth(
-                response.into_http_response_async().await?,
+                response.into_http_response()?,
             ));
         }
 
-        let new_token: Token = response.json().await?;
-        self.token_cache.store(cache_id, new_token.clone());
+        let new_token: Token = read_json_limited(response, self.max_response_body_size())?;
+        let new_token = self.store_token(cache_id.as_str(), new_token);
         Ok(new_token)
     }
 }
@@ -123,6 +127,14 @@ impl Debug for ClientCertificateCredential {
     }
 }
 
+impl Drop for ClientCertificateCredential {
+    fn drop(&mut self) {
+        self.client_assertion.zeroize();
+    }
+}
+
+token_cache_snapshot!(ClientCertificateCredential);
+
 #[async_trait]
 impl TokenCache for ClientCertificateCredential {
     type Token = Token;
@@ -130,8 +142,8 @@ impl TokenCache for ClientCertificateCredential {
     #[tracing::instrument]
     fn get_token_silent(&mut self) -> Result<Self::Token, AuthExecutionError> {
         let cache_id = self.app_config.cache_id.to_string();
-        if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-            if token.is_expired_sub(time::Duration::minutes(5)) {
+        if let Some(token) = self.cached_token(cache_id.as_str()) {
+            if self.is_token_expired(&token) {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=None");
                 self.execute_cached_token_refresh(cache_id)
             } else {
@@ -147,17 +159,17 @@ impl TokenCache for ClientCertificateCredential {
     #[tracing::instrument]
     async fn get_token_silent_async(&mut self) -> Result<Self::Token, AuthExecutionError> {
         let cache_id = self.app_config.cache_id.to_string();
-        if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-            if token.is_expired_sub(time::Duration::minutes(5)) {
+        if let Some(token) = self.cached_token(cache_id.as_str()) {
+            if self.is_token_expired(&token) {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token refresh");
-                self.execute_cached_token_refresh_async(cache_id).await
+                self.acquire_token_async().await
             } else {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "using token from cache");
                 Ok(token.clone())
             }
         } else {
             tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request");
-            self.execute_cached_token_refresh_async(cache_id).await
+            self.acquire_token_async().await
         }
     }
 
@@ -188,7 +200,7 @@ impl TokenCredentialExecutor for ClientCertificateCredential {
             .client_assertion(self.client_assertion.as_str())
             .client_assertion_type(self.client_assertion_type.as_str())
             .grant_type("client_credentials")
-            .set_scope(self.app_config.scope.clone());
+            .set_scope(self.app_config.app_only_scope());
 
         serializer.as_credential_map(
             vec![AuthParameter::Scope],
@@ -216,6 +228,22 @@ impl TokenCredentialExecutor for ClientCertificateCredential {
     fn app_config(&self) -> &AppConfig {
         &self.app_config
     }
+
+    fn warnings(&self) -> Vec<CredentialWarning> {
+        CredentialWarning::for_app_only_scope(&self.app_config.scope)
+    }
+
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        Some(&self.token_cache)
+    }
+
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        Some(&mut self.token_cache)
+    }
+
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        Some(&mut self.app_config)
+    }
 }
 
 #[derive(Clone)]
@@ -232,6 +260,8 @@ impl ClientCertificateCredentialBuilder {
                     .build(),
                 client_assertion_type: CLIENT_ASSERTION_TYPE.to_owned(),
                 client_assertion: Default::default(),
+                thumbprint: None,
+                thumbprint_sha256: None,
                 token_cache: Default::default(),
             },
         }
@@ -242,14 +272,14 @@ impl ClientCertificateCredentialBuilder {
         x509: &X509Certificate,
         mut app_config: AppConfig,
     ) -> IdentityResult<ClientCertificateCredentialBuilder> {
-        app_config
-            .scope
-            .insert("https://graph.microsoft.com/.default".into());
+        app_config.add_scope("https://graph.microsoft.com/.default");
         let mut credential_builder = ClientCertificateCredentialBuilder {
             credential: ClientCertificateCredential {
                 app_config,
                 client_assertion_type: CLIENT_ASSERTION_TYPE.to_owned(),
                 client_assertion: Default::default(),
+                thumbprint: None,
+                thumbprint_sha256: None,
                 token_cache: Default::default(),
             },
         };
@@ -264,6 +294,8 @@ impl ClientCertificateCredentialBuilder {
         } else {
             self.with_client_assertion(certificate.sign_with_tenant(None)?);
         }
+        self.credential.thumbprint = Some(certificate.get_thumbprint()?);
+        self.credential.thumbprint_sha256 = Some(certificate.get_thumbprint_sha256()?);
         Ok(self)
     }
 
@@ -273,6 +305,17 @@ impl ClientCertificateCredentialBuilder {
         self
     }
 
+    /// Set the client_assertion_type sent in the token request. Defaults to
+    /// `urn:ietf:params:oauth:client-assertion-type:jwt-bearer` and should only be
+    /// changed for preview scenarios that use a different assertion type.
+    pub fn with_client_assertion_type<T: AsRef<str>>(
+        &mut self,
+        client_assertion_type: T,
+    ) -> &mut Self {
+        self.credential.client_assertion_type = client_assertion_type.as_ref().to_owned();
+        self
+    }
+
     pub fn credential(self) -> ClientCertificateCredential {
         self.credential
     }
@@ -293,6 +336,8 @@ impl From<ClientCertificateCredentialBuilder> for ClientCertificateCredential {
 #[cfg(test)]
 mod test {
     use super::*;
+    #[cfg(feature = "openssl")]
+    use crate::identity::{PKey, X509};
 
     #[test]
     fn test_uuid_fake() {
@@ -320,4 +365,20 @@ mod test {
             "4ef900be-dfd9-4da6-b224-0011e46c54dd"
         );
     }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn certificate_thumbprint() {
+        let cert = X509::from_pem(include_bytes!("test/cert.pem")).unwrap();
+        let private_key = PKey::private_key_from_pem(include_bytes!("test/key.pem")).unwrap();
+        let client_id = Uuid::new_v4().to_string();
+        let x509 = X509Certificate::new(client_id.as_str(), cert, private_key);
+
+        let credential = ClientCertificateCredential::new(client_id, &x509).unwrap();
+        assert_eq!(Some("IE7rT0hxrPHBPF26UG2usJ8trlo"), credential.thumbprint());
+        assert_eq!(
+            Some("7WmL-WvBempwyNqpdJeX8bscma_1necs_vUJ9wOmNds"),
+            credential.thumbprint_sha256()
+        );
+    }
 }
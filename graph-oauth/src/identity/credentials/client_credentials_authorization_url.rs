@@ -1,11 +1,11 @@
-use reqwest::IntoUrl;
-
 use url::Url;
 use uuid::Uuid;
 
 use graph_error::{AuthorizationFailure, IdentityResult};
 
-use crate::identity::{credentials::app_config::AppConfig, Authority, AzureCloudInstance};
+use crate::identity::{
+    credentials::app_config::AppConfig, Authority, AzureCloudInstance, IntoRedirectUri,
+};
 use crate::oauth_serializer::{AuthParameter, AuthSerializer};
 use crate::{ClientAssertionCredentialBuilder, ClientSecretCredentialBuilder};
 
@@ -28,10 +28,9 @@ pub struct ClientCredentialsAuthorizationUrlParameters {
 impl ClientCredentialsAuthorizationUrlParameters {
     pub fn new(
         client_id: impl AsRef<str>,
-        redirect_uri: impl IntoUrl,
+        redirect_uri: impl IntoRedirectUri,
     ) -> IdentityResult<ClientCredentialsAuthorizationUrlParameters> {
-        let redirect_uri_result = Url::parse(redirect_uri.as_str());
-        let redirect_uri = redirect_uri.into_url().or(redirect_uri_result)?;
+        let redirect_uri = redirect_uri.into_redirect_uri()?;
 
         Ok(ClientCredentialsAuthorizationUrlParameters {
             app_config: AppConfig::builder(client_id.as_ref())
@@ -5,16 +5,17 @@ use async_trait::async_trait;
 use http::{HeaderMap, HeaderName, HeaderValue};
 
 use uuid::Uuid;
+use zeroize::Zeroize;
 
-use graph_core::cache::{CacheStore, InMemoryCacheStore, TokenCache};
-use graph_core::http::{AsyncResponseConverterExt, ResponseConverterExt};
+use graph_core::cache::{InMemoryCacheStore, TokenCache};
+use graph_core::http::ResponseConverterExt;
 use graph_core::identity::ForceTokenRefresh;
 use graph_error::{AuthExecutionError, AuthExecutionResult, AuthorizationFailure, IdentityResult};
 
 use crate::identity::{
-    credentials::app_config::AppConfig, tracing_targets::CREDENTIAL_EXECUTOR, Authority,
-    AzureCloudInstance, ClientCredentialsAuthorizationUrlParameterBuilder,
-    ConfidentialClientApplication, Token, TokenCredentialExecutor,
+    credentials::app_config::AppConfig, read_json_limited, tracing_targets::CREDENTIAL_EXECUTOR,
+    Authority, AzureCloudInstance, ClientCredentialsAuthorizationUrlParameterBuilder,
+    ConfidentialClientApplication, CredentialWarning, Token, TokenCredentialExecutor,
 };
 use crate::oauth_serializer::{AuthParameter, AuthSerializer};
 
@@ -23,6 +24,8 @@ credential_builder!(
     ConfidentialClientApplication<ClientSecretCredential>
 );
 
+client_credentials_builder!(ClientSecretCredentialBuilder);
+
 /// Client Credentials flow using a client secret.
 ///
 /// The OAuth 2.0 client credentials grant flow permits a web service (confidential client)
@@ -57,6 +60,12 @@ impl Debug for ClientSecretCredential {
     }
 }
 
+impl Drop for ClientSecretCredential {
+    fn drop(&mut self) {
+        self.client_secret.zeroize();
+    }
+}
+
 impl ClientSecretCredential {
     pub fn new(
         client_id: impl AsRef<str>,
@@ -101,37 +110,22 @@ impl ClientSecretCredential {
             ));
         }
 
-        let new_token: Token = response.json()?;
-        self.token_cache.store(cache_id, new_token.clone());
-        Ok(new_token)
-    }
-
-    async fn execute_cached_token_refresh_async(
-        &mut self,
-        cache_id: String,
-    ) -> AuthExecutionResult<Token> {
-        let response = self.execute_async().await?;
-
-        if !response.status().is_success() {
-            return Err(AuthExecutionError::silent_token_auth(
-                response.into_http_response_async().await?,
-            ));
-        }
-
-        let new_token: Token = response.json().await?;
-        self.token_cache.store(cache_id, new_token.clone());
+        let new_token: Token = read_json_limited(response, self.max_response_body_size())?;
+        let new_token = self.store_token(cache_id.as_str(), new_token);
         Ok(new_token)
     }
 }
 
+token_cache_snapshot!(ClientSecretCredential);
+
 #[async_trait]
 impl TokenCache for ClientSecretCredential {
     type Token = Token;
 
     fn get_token_silent(&mut self) -> Result<Self::Token, AuthExecutionError> {
         let cache_id = self.app_config.cache_id.to_string();
-        if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-            if token.is_expired_sub(time::Duration::minutes(5)) {
+        if let Some(token) = self.cached_token(cache_id.as_str()) {
+            if self.is_token_expired(&token) {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=None");
                 self.execute_cached_token_refresh(cache_id)
             } else {
@@ -146,17 +140,17 @@ impl TokenCache for ClientSecretCredential {
 
     async fn get_token_silent_async(&mut self) -> Result<Self::Token, AuthExecutionError> {
         let cache_id = self.app_config.cache_id.to_string();
-        if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-            if token.is_expired_sub(time::Duration::minutes(5)) {
+        if let Some(token) = self.cached_token(cache_id.as_str()) {
+            if self.is_token_expired(&token) {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=None");
-                self.execute_cached_token_refresh_async(cache_id).await
+                self.acquire_token_async().await
             } else {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "using token from cache");
                 Ok(token.clone())
             }
         } else {
             tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=None");
-            self.execute_cached_token_refresh_async(cache_id).await
+            self.acquire_token_async().await
         }
     }
 
@@ -182,7 +176,7 @@ impl TokenCredentialExecutor for ClientSecretCredential {
             .client_id(client_id.as_str())
             .client_secret(self.client_secret.as_str())
             .grant_type("client_credentials")
-            .set_scope(self.app_config.scope.clone());
+            .set_scope(self.app_config.app_only_scope());
 
         // Don't include ClientId and Client Secret in the fields for form url encode because
         // Client Id and Client Secret are already included as basic auth.
@@ -211,6 +205,22 @@ impl TokenCredentialExecutor for ClientSecretCredential {
     fn app_config(&self) -> &AppConfig {
         &self.app_config
     }
+
+    fn warnings(&self) -> Vec<CredentialWarning> {
+        CredentialWarning::for_app_only_scope(&self.app_config.scope)
+    }
+
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        Some(&self.token_cache)
+    }
+
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        Some(&mut self.token_cache)
+    }
+
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        Some(&mut self.app_config)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -229,9 +239,7 @@ impl ClientSecretCredentialBuilder {
         client_secret: impl AsRef<str>,
         mut app_config: AppConfig,
     ) -> ClientSecretCredentialBuilder {
-        app_config
-            .scope
-            .insert("https://graph.microsoft.com/.default".into());
+        app_config.add_scope("https://graph.microsoft.com/.default");
         Self {
             credential: ClientSecretCredential {
                 app_config,
@@ -250,3 +258,440 @@ impl ClientSecretCredentialBuilder {
         self.credential.clone()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::{
+        CacheSnapshot, CachedToken, SharedTokenStore, TestClock, TokenCacheSnapshot,
+    };
+    use base64::Engine;
+    use graph_core::cache::CacheStore;
+    use graph_core::identity::ClientApplication;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn cancelled_refresh_leaves_cached_token() {
+        use crate::identity::CancellationToken;
+        use wiremock::matchers::{body_string_contains, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        // The token endpoint answers after the refresh is cancelled.
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/tenant/oauth2/v2.0/token"))
+            .and(body_string_contains("grant_type=client_credentials"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({
+                        "token_type": "Bearer",
+                        "expires_in": 3600,
+                        "access_token": "new_access_token"
+                    }))
+                    .set_delay(Duration::from_secs(30)),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let mock_server_uri = mock_server.uri();
+        let mut credential = ConfidentialClientApplication::builder(Uuid::new_v4())
+            .with_client_secret("ALDSKFJLKERLKJALSDKJF2209LAKJGFL")
+            .with_tenant("tenant")
+            .with_https_only(false)
+            .with_token_url_rewriter(move |url| {
+                url::Url::parse(mock_server_uri.as_str())
+                    .unwrap()
+                    .join(url.path())
+                    .unwrap()
+            })
+            .build()
+            .into_inner();
+        let cache_id = credential.app_config.cache_id.clone();
+        // Expired so that get_token_silent_async attempts a refresh.
+        let token = Token::new(
+            "Bearer",
+            0,
+            "cached_access_token",
+            vec!["https://graph.microsoft.com/.default"],
+        );
+        credential.token_cache.store(cache_id.clone(), token);
+
+        let cancellation_token = CancellationToken::new();
+        let cancel = cancellation_token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            cancel.cancel();
+        });
+
+        let mut confidential_client = ConfidentialClientApplication::from(credential);
+        let result = confidential_client
+            .get_token_silent_async_with_cancellation(&cancellation_token)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(1, mock_server.received_requests().await.unwrap().len());
+
+        let credential = confidential_client.into_inner();
+        let cached_token = credential.token_cache.get(cache_id.as_str()).unwrap();
+        assert_eq!("cached_access_token", cached_token.access_token);
+    }
+
+    #[test]
+    fn offline_access_warning() {
+        let credential = ClientSecretCredentialBuilder::new(
+            Uuid::new_v4().to_string(),
+            "ALDSKFJLKERLKJALSDKJF2209LAKJGFL",
+        )
+        .with_scope(vec![
+            "https://graph.microsoft.com/.default",
+            "offline_access",
+        ])
+        .credential();
+
+        assert_eq!(
+            vec![CredentialWarning::OfflineAccessInAppOnlyFlow],
+            credential.warnings()
+        );
+        assert_eq!(
+            vec![CredentialWarning::OfflineAccessInAppOnlyFlow],
+            ConfidentialClientApplication::from(credential).warnings()
+        );
+    }
+
+    #[test]
+    fn offline_access_removed_from_form() {
+        let mut credential = ClientSecretCredentialBuilder::new(
+            Uuid::new_v4().to_string(),
+            "ALDSKFJLKERLKJALSDKJF2209LAKJGFL",
+        )
+        .with_scope(vec![
+            "https://graph.microsoft.com/.default",
+            "offline_access",
+        ])
+        .credential();
+
+        let form = credential.form_urlencode().unwrap();
+        assert_eq!(
+            Some(&"https://graph.microsoft.com/.default".to_owned()),
+            form.get("scope")
+        );
+    }
+
+    #[test]
+    fn response_type_rejected_for_client_credentials() {
+        let mut credential = ClientSecretCredentialBuilder::new(
+            Uuid::new_v4().to_string(),
+            "ALDSKFJLKERLKJALSDKJF2209LAKJGFL",
+        )
+        .with_scope(vec!["https://graph.microsoft.com/.default"])
+        .with_extra_query_param(("response_type".to_owned(), "code".to_owned()))
+        .credential();
+
+        match credential.request_parts().err() {
+            Some(AuthorizationFailure::RequiredValue { name, .. }) => {
+                assert_eq!("response_type", name)
+            }
+            err => panic!("expected response_type error: {err:?}"),
+        }
+    }
+
+    #[test]
+    fn ciam_parameters_in_form() {
+        let mut credential = ClientSecretCredentialBuilder::new(
+            Uuid::new_v4().to_string(),
+            "ALDSKFJLKERLKJALSDKJF2209LAKJGFL",
+        )
+        .with_scope(vec!["https://graph.microsoft.com/.default"])
+        .with_challenge_type(["oob", "password", "redirect"])
+        .with_continuation_token("uY29tL2F1dGhlbnRpY2F0aW9uL3")
+        .credential();
+
+        let request_parts = credential.request_parts().unwrap();
+        let form = request_parts.form_urlencoded;
+        assert_eq!(
+            Some(&"oob password redirect".to_owned()),
+            form.get("challenge_type")
+        );
+        assert_eq!(
+            Some(&"uY29tL2F1dGhlbnRpY2F0aW9uL3".to_owned()),
+            form.get("continuation_token")
+        );
+        assert_eq!(
+            Some(&"client_credentials".to_owned()),
+            form.get("grant_type")
+        );
+
+        let request = credential.build_request_async().unwrap().build().unwrap();
+        let body = std::str::from_utf8(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert!(body.contains("challenge_type=oob+password+redirect"));
+        assert!(body.contains("continuation_token=uY29tL2F1dGhlbnRpY2F0aW9uL3"));
+    }
+
+    #[test]
+    fn export_import_cache_round_trip() {
+        let mut credential = ClientSecretCredential::new_with_tenant(
+            "tenant",
+            Uuid::new_v4().to_string(),
+            "ALDSKFJLKERLKJALSDKJF2209LAKJGFL",
+        );
+        let mut token = Token::new(
+            "Bearer",
+            3600,
+            "first_access_token",
+            vec!["https://graph.microsoft.com/.default"],
+        );
+        token.refresh_token = Some("refresh_token".to_owned());
+        credential.token_cache.store("first", token);
+        credential.token_cache.store(
+            "second",
+            Token::new(
+                "Bearer",
+                3600,
+                "second_access_token",
+                vec!["https://graph.microsoft.com/.default"],
+            ),
+        );
+
+        let snapshot = credential.export_cache();
+        assert_eq!(2, snapshot.len());
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let snapshot: CacheSnapshot = serde_json::from_str(json.as_str()).unwrap();
+
+        let mut imported = ClientSecretCredential::new_with_tenant(
+            "tenant",
+            Uuid::new_v4().to_string(),
+            "ALDSKFJLKERLKJALSDKJF2209LAKJGFL",
+        );
+        imported.import_cache(snapshot);
+        assert_eq!(credential.export_cache(), imported.export_cache());
+
+        let token = imported.token_cache.get("first").unwrap();
+        assert_eq!(Some("refresh_token".to_owned()), token.refresh_token);
+        assert!(!token.is_expired());
+    }
+
+    #[derive(Clone, Default)]
+    struct MockSharedTokenStore {
+        tokens: Arc<std::sync::Mutex<HashMap<String, CachedToken>>>,
+    }
+
+    impl SharedTokenStore for MockSharedTokenStore {
+        fn load(&self, cache_id: &str) -> Option<CachedToken> {
+            self.tokens.lock().unwrap().get(cache_id).cloned()
+        }
+
+        fn save(&self, cache_id: &str, token: CachedToken) {
+            self.tokens
+                .lock()
+                .unwrap()
+                .insert(cache_id.to_owned(), token);
+        }
+    }
+
+    #[test]
+    fn first_silent_token_from_shared_store() {
+        let store = MockSharedTokenStore::default();
+        let token_requests = Arc::new(AtomicUsize::new(0));
+        let requests = token_requests.clone();
+        let mut confidential_client = ConfidentialClientApplication::builder(Uuid::new_v4())
+            .with_tenant("tenant")
+            .with_client_secret("ALDSKFJLKERLKJALSDKJF2209LAKJGFL")
+            .with_shared_token_store(store.clone())
+            .with_token_url_rewriter(move |uri| {
+                requests.fetch_add(1, Ordering::SeqCst);
+                uri
+            })
+            .build();
+
+        // Token saved to the shared store by another process.
+        let cache_id = confidential_client.app_config().cache_id.clone();
+        store.save(
+            cache_id.as_str(),
+            CachedToken::from(Token::new(
+                "Bearer",
+                3600,
+                "shared_access_token",
+                vec!["https://graph.microsoft.com/.default"],
+            )),
+        );
+
+        let token = confidential_client.get_token_silent().unwrap();
+        assert_eq!("shared_access_token", token.as_str());
+        assert_eq!(0, token_requests.load(Ordering::SeqCst));
+
+        let credential = confidential_client.into_inner();
+        assert_eq!(
+            "shared_access_token",
+            credential
+                .token_cache
+                .get(cache_id.as_str())
+                .unwrap()
+                .access_token
+        );
+    }
+
+    #[test]
+    fn test_clock_expires_cached_token() {
+        let clock = TestClock::default();
+        let mut credential = ConfidentialClientApplication::builder(Uuid::new_v4())
+            .with_tenant("tenant")
+            .with_client_secret("ALDSKFJLKERLKJALSDKJF2209LAKJGFL")
+            .with_clock(clock.clone())
+            .build()
+            .into_inner();
+
+        let cache_id = credential.app_config.cache_id.clone();
+        credential.store_token(
+            cache_id.as_str(),
+            Token::new(
+                "Bearer",
+                3600,
+                "cached_access_token",
+                vec!["https://graph.microsoft.com/.default"],
+            ),
+        );
+
+        let token = credential.get_token_silent().unwrap();
+        assert_eq!("cached_access_token", token.access_token);
+
+        // Tokens are refreshed five minutes before they expire.
+        clock.advance(time::Duration::minutes(54));
+        assert!(!credential.is_token_expired(&token));
+        clock.advance(time::Duration::minutes(2));
+        assert!(credential.is_token_expired(&token));
+    }
+
+    #[test]
+    fn cached_token_ttl_decreases_with_clock() {
+        let clock = TestClock::default();
+        let mut credential = ConfidentialClientApplication::builder(Uuid::new_v4())
+            .with_tenant("tenant")
+            .with_client_secret("ALDSKFJLKERLKJALSDKJF2209LAKJGFL")
+            .with_clock(clock.clone())
+            .build()
+            .into_inner();
+        assert_eq!(None, credential.cached_token_ttl());
+
+        let cache_id = credential.app_config.cache_id.clone();
+        credential.store_token(
+            cache_id.as_str(),
+            Token::new(
+                "Bearer",
+                3600,
+                "cached_access_token",
+                vec!["https://graph.microsoft.com/.default"],
+            ),
+        );
+        assert_eq!(
+            Some(Duration::from_secs(55 * 60)),
+            credential.cached_token_ttl()
+        );
+
+        clock.advance(time::Duration::minutes(30));
+        assert_eq!(
+            Some(Duration::from_secs(25 * 60)),
+            credential.cached_token_ttl()
+        );
+
+        clock.advance(time::Duration::minutes(25));
+        assert_eq!(None, credential.cached_token_ttl());
+    }
+
+    #[test]
+    fn resource_default_scope() {
+        let mut builder = ClientSecretCredentialBuilder::new(
+            Uuid::new_v4().to_string(),
+            "ALDSKFJLKERLKJALSDKJF2209LAKJGFL",
+        );
+
+        builder
+            .with_resource_default("https://management.azure.com")
+            .unwrap();
+        assert_eq!(
+            vec!["https://management.azure.com/.default".to_owned()],
+            builder.credential().app_config.scope
+        );
+
+        builder
+            .with_resource_default("https://graph.microsoft.com/.default")
+            .unwrap();
+        assert_eq!(
+            vec!["https://graph.microsoft.com/.default".to_owned()],
+            builder.credential().app_config.scope
+        );
+    }
+
+    #[test]
+    fn resource_default_invalid_url() {
+        let mut builder = ClientSecretCredentialBuilder::new(
+            Uuid::new_v4().to_string(),
+            "ALDSKFJLKERLKJALSDKJF2209LAKJGFL",
+        );
+        assert!(builder
+            .with_resource_default("graph.microsoft.com")
+            .is_err());
+        assert!(builder.with_resource_default("").is_err());
+    }
+
+    #[test]
+    fn debug_redacts_client_secret() {
+        let credential = ClientSecretCredential::new_with_tenant(
+            "tenant",
+            Uuid::new_v4().to_string(),
+            "ALDSKFJLKERLKJALSDKJF2209LAKJGFL",
+        );
+        assert!(!format!("{credential:?}").contains("ALDSKFJLKERLKJALSDKJF2209LAKJGFL"));
+
+        let confidential_client = ConfidentialClientApplication::builder(Uuid::new_v4())
+            .with_client_secret("ALDSKFJLKERLKJALSDKJF2209LAKJGFL")
+            .with_tenant("tenant")
+            .with_continuation_token("continuation_token_secret")
+            .build();
+        let debug = format!("{confidential_client:?}");
+        assert!(!debug.contains("ALDSKFJLKERLKJALSDKJF2209LAKJGFL"));
+        assert!(!debug.contains("continuation_token_secret"));
+    }
+
+    #[test]
+    fn prepared_request_for_client_secret() {
+        let client_id = Uuid::new_v4();
+        let mut credential =
+            ClientSecretCredential::new_with_tenant("tenant", client_id.to_string(), "secret");
+        let prepared_request = credential.prepare_request().unwrap();
+
+        assert_eq!(http::Method::POST, prepared_request.method);
+        assert_eq!(
+            "https://login.microsoftonline.com/tenant/oauth2/v2.0/token",
+            prepared_request.url.as_str()
+        );
+        assert_eq!(
+            Some(&HeaderValue::from_static(
+                "application/x-www-form-urlencoded"
+            )),
+            prepared_request.headers.get(http::header::CONTENT_TYPE)
+        );
+        let basic_auth =
+            base64::engine::general_purpose::STANDARD.encode(format!("{client_id}:secret"));
+        assert_eq!(
+            Some(format!("Basic {basic_auth}").as_str()),
+            prepared_request
+                .headers
+                .get(http::header::AUTHORIZATION)
+                .and_then(|header_value| header_value.to_str().ok())
+        );
+        assert!(prepared_request.headers.contains_key("client-request-id"));
+        assert_eq!(
+            HashMap::from([
+                ("grant_type".to_owned(), "client_credentials".to_owned()),
+                (
+                    "scope".to_owned(),
+                    "https://graph.microsoft.com/.default".to_owned()
+                ),
+            ]),
+            prepared_request.form
+        );
+        assert!(!format!("{prepared_request:?}").contains("secret"));
+    }
+}
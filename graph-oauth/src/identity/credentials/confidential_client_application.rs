@@ -7,15 +7,18 @@ use reqwest::Response;
 use url::Url;
 use uuid::Uuid;
 
-use graph_core::cache::{AsBearer, TokenCache};
-use graph_core::identity::{ClientApplication, ForceTokenRefresh};
-use graph_error::{AuthExecutionResult, IdentityResult};
+use graph_core::cache::{AsBearer, InMemoryCacheStore, TokenCache};
+use graph_core::http::AsyncResponseConverterExt;
+use graph_core::identity::{BearerToken, ClientApplication, ForceTokenRefresh};
+use graph_error::{AuthExecutionError, AuthExecutionResult, IdentityResult, AF};
 
 use crate::identity::{
-    AppConfig, Authority, AuthorizationCodeAssertionCredential,
-    AuthorizationCodeCertificateCredential, AuthorizationCodeCredential, AzureCloudInstance,
+    read_json_limited_async, AccountInfo, AppConfig, Authority,
+    AuthorizationCodeAssertionCredential, AuthorizationCodeCertificateCredential,
+    AuthorizationCodeCredential, AzureCloudInstance, CacheSnapshot, CancellationToken,
     ClientAssertionCredential, ClientCertificateCredential, ClientSecretCredential,
-    ConfidentialClientApplicationBuilder, OpenIdCredential, TokenCredentialExecutor,
+    ConfidentialClientApplicationBuilder, CredentialWarning, OpenIdCredential,
+    RefreshTokenCredential, Token, TokenCacheSnapshot, TokenCredentialExecutor, UserInfo,
 };
 
 /// Clients capable of maintaining the confidentiality of their credentials
@@ -39,6 +42,76 @@ impl ConfidentialClientApplication<()> {
     pub fn builder(client_id: impl TryInto<Uuid>) -> ConfidentialClientApplicationBuilder {
         ConfidentialClientApplicationBuilder::new(client_id)
     }
+
+    /// Client credentials flow for app-only access to Microsoft Graph. The scope is set
+    /// to only the Microsoft Graph `.default` scope of the given cloud, for instance
+    /// https://graph.microsoft.com/.default for [AzureCloudInstance::AzurePublic].
+    pub fn app_only_graph(
+        client_id: impl TryInto<Uuid>,
+        tenant_id: impl AsRef<str>,
+        client_secret: impl AsRef<str>,
+        azure_cloud_instance: AzureCloudInstance,
+    ) -> ConfidentialClientApplication<ClientSecretCredential> {
+        ConfidentialClientApplicationBuilder::new(client_id)
+            .with_tenant(tenant_id)
+            .with_azure_cloud_instance(azure_cloud_instance)
+            .with_client_secret(client_secret)
+            .with_scope(vec![azure_cloud_instance.default_microsoft_graph_scope()])
+            .build()
+    }
+
+    /// Client credentials flow using a client secret from a connection string of
+    /// semicolon separated key value pairs, for instance
+    /// `ClientId=...;TenantId=...;ClientSecret=...`.
+    ///
+    /// `ClientId` and `ClientSecret` are required and `TenantId` is optional. Keys are
+    /// case insensitive and unknown keys are ignored.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use graph_oauth::ConfidentialClientApplication;
+    /// let confidential_client = ConfidentialClientApplication::from_connection_string(
+    ///     "ClientId=4ef900be-dfd9-4da6-b224-0011e46c54dd;TenantId=contoso.onmicrosoft.com;ClientSecret=secret",
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn from_connection_string(
+        connection_string: impl AsRef<str>,
+    ) -> IdentityResult<ConfidentialClientApplication<ClientSecretCredential>> {
+        let mut client_id = None;
+        let mut tenant_id = None;
+        let mut client_secret = None;
+        for (key, value) in connection_string
+            .as_ref()
+            .split(';')
+            .filter_map(|pair| pair.split_once('='))
+        {
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "clientid" => client_id = Some(value),
+                "tenantid" => tenant_id = Some(value),
+                "clientsecret" => client_secret = Some(value),
+                _ => {}
+            }
+        }
+
+        let client_id = client_id
+            .filter(|client_id| !client_id.is_empty())
+            .ok_or_else(|| AF::msg_err("ClientId", "connection string is missing ClientId"))?;
+        let client_id = Uuid::parse_str(client_id)
+            .map_err(|_| AF::msg_err("ClientId", "ClientId is not a valid uuid"))?;
+        let client_secret = client_secret
+            .filter(|client_secret| !client_secret.is_empty())
+            .ok_or_else(|| {
+                AF::msg_err("ClientSecret", "connection string is missing ClientSecret")
+            })?;
+
+        let mut builder = ConfidentialClientApplicationBuilder::new(client_id);
+        if let Some(tenant_id) = tenant_id.filter(|tenant_id| !tenant_id.is_empty()) {
+            builder.with_tenant(tenant_id);
+        }
+        Ok(builder.with_client_secret(client_secret).build())
+    }
 }
 
 impl<Credential: Clone + Debug + Send + Sync + TokenCredentialExecutor>
@@ -55,20 +128,221 @@ impl<Credential: Clone + Debug + Send + Sync + TokenCredentialExecutor>
     pub fn into_inner(self) -> Credential {
         self.credential
     }
+
+    /// Acquire a token for the scopes and store it in the token cache so that the first
+    /// request after startup does not have to wait for the token endpoint. Intended to be
+    /// called once during initialization, for instance for the app-only token of a service.
+    ///
+    /// The scopes of the credential are not changed. The token is cached under the cache
+    /// id for the scopes and is used by later token requests for the same scopes.
+    pub async fn prewarm_cache_async<T: ToString, I: IntoIterator<Item = T>>(
+        &mut self,
+        scopes: I,
+    ) -> AuthExecutionResult<()> {
+        // The token cache is shared with the copy of the credential.
+        let mut credential = self.credential.clone();
+        let app_config = credential.app_config_mut().ok_or_else(|| {
+            AF::msg_err("token_cache", "credential does not support a token cache")
+        })?;
+        app_config.with_scope(scopes);
+
+        credential.acquire_token_async().await?;
+        Ok(())
+    }
+
+    /// Acquire a token for the scopes from the given tenant, for multi-tenant applications
+    /// that serve many tenants with one app registration.
+    ///
+    /// Tokens are cached under the cache id of the
+    /// [CacheKeyStrategy](crate::identity::CacheKeyStrategy) of the credential for the
+    /// tenant and scopes, by default the same cache id as a credential built
+    /// [with_tenant](crate::identity::ConfidentialClientApplicationBuilder::with_tenant).
+    /// A cached token for the tenant that is not expired is returned without a token
+    /// request. The tenant, authority and scopes of the credential are not changed.
+    pub async fn acquire_token_for_tenant_async<T: ToString, I: IntoIterator<Item = T>>(
+        &mut self,
+        tenant_id: impl AsRef<str>,
+        scopes: I,
+    ) -> AuthExecutionResult<Token> {
+        let tenant_id = tenant_id.as_ref().trim();
+        if tenant_id.is_empty() {
+            return Err(AF::msg_err("tenant_id", "tenant id cannot be empty").into());
+        }
+
+        // The request is built from a copy of the credential so that the credential keeps
+        // its own tenant, scopes and cache id, even when the request is cancelled. The
+        // token cache is shared with the copy.
+        let mut credential = self.credential.clone();
+        let app_config = credential.app_config_mut().ok_or_else(|| {
+            AF::msg_err("token_cache", "credential does not support a token cache")
+        })?;
+        app_config.with_tenant(tenant_id);
+        app_config.with_scope(scopes);
+        app_config.with_cache_key_strategy(app_config.cache_key_strategy.unwrap_or_default());
+        let cache_id = app_config.cache_id.clone();
+
+        match credential.cached_token(cache_id.as_str()) {
+            Some(token) if !credential.is_token_expired(&token) => Ok(token),
+            _ => credential.acquire_token_async().await,
+        }
+    }
+
+    /// Fetch the claims of the signed-in user from the OpenID Connect userinfo endpoint
+    /// with the cached access token. The userinfo endpoint is read from the discovery
+    /// document of the authority.
+    ///
+    /// Returns an error if there is no cached access token that is not expired, for
+    /// instance before a token was acquired with the openid scope.
+    pub async fn userinfo_async(&mut self) -> AuthExecutionResult<UserInfo> {
+        let cache_id = self.credential.app_config().cache_id.clone();
+        let token = self
+            .credential
+            .cached_token(cache_id.as_str())
+            .filter(|token| !self.credential.is_token_expired(token))
+            .ok_or_else(|| {
+                AF::msg_err(
+                    "access_token",
+                    "no cached access token, acquire a token with the openid scope before calling the userinfo endpoint",
+                )
+            })?;
+
+        let app_config = self.credential.app_config();
+        let openid_configuration_url = app_config
+            .azure_cloud_instance
+            .openid_configuration_uri(&app_config.authority)?;
+        UserInfo::fetch_async(openid_configuration_url, token.access_token.as_str()).await
+    }
+}
+
+impl<
+        Credential: Clone + Debug + Send + Sync + TokenCredentialExecutor + RefreshTokenCredential,
+    > ConfidentialClientApplication<Credential>
+{
+    /// Refresh each cached token that expires within the duration using its refresh
+    /// token, for a background maintenance pass over the tokens of a service that keeps
+    /// many tokens. Tokens that are not near expiry and tokens without a refresh token
+    /// are skipped.
+    ///
+    /// Each refresh is a refresh token request for the scopes of the cached token, sent
+    /// to the tenant of its id token, or to the authority of the credential when the token
+    /// has no id token. The request is built from a copy of the credential, the credential
+    /// itself is not changed.
+    ///
+    /// Returns the result of each refresh keyed by cache id. A failed or cancelled refresh
+    /// leaves the cached token unchanged.
+    pub async fn refresh_expiring_tokens_async(
+        &mut self,
+        within: std::time::Duration,
+    ) -> AuthExecutionResult<HashMap<String, AuthExecutionResult<Token>>> {
+        let entries = self
+            .credential
+            .token_cache()
+            .map(|token_cache| token_cache.entries())
+            .ok_or_else(|| {
+                AF::msg_err("token_cache", "credential does not support a token cache")
+            })?;
+        let within = time::Duration::try_from(within).unwrap_or(time::Duration::MAX);
+        let now = self.credential.app_config().clock.now_utc();
+
+        let mut results = HashMap::new();
+        for (cache_id, token) in entries {
+            let refresh_token = match token.refresh_token.as_ref() {
+                Some(refresh_token) if token.is_expired_sub_at(within, now) => {
+                    refresh_token.clone()
+                }
+                _ => continue,
+            };
+
+            let token_result = self
+                .refresh_token_request(cache_id.as_str(), &token, refresh_token)
+                .await
+                .map(|new_token| self.credential.store_token(cache_id.as_str(), new_token));
+            results.insert(cache_id, token_result);
+        }
+        Ok(results)
+    }
+
+    /// Redeem the refresh token of a cached token with a copy of the credential.
+    async fn refresh_token_request(
+        &self,
+        cache_id: &str,
+        token: &Token,
+        refresh_token: String,
+    ) -> AuthExecutionResult<Token> {
+        let mut credential = self.credential.clone();
+        let app_config = credential.app_config_mut().ok_or_else(|| {
+            AF::msg_err("token_cache", "credential does not support a token cache")
+        })?;
+        if let Some(tenant_id) = AccountInfo::try_from(token)
+            .ok()
+            .and_then(|account_info| account_info.tid)
+        {
+            app_config.with_tenant(tenant_id);
+        }
+        app_config.with_scope(token.scope.clone());
+        app_config.force_token_refresh = ForceTokenRefresh::Never;
+        // Credentials that read the refresh token of their cached token look up the
+        // token being refreshed.
+        app_config.cache_id = cache_id.to_owned();
+        credential.set_refresh_token(refresh_token);
+
+        let response = credential.execute_async().await?;
+        if !response.status().is_success() {
+            return Err(AuthExecutionError::silent_token_auth(
+                response.into_http_response_async().await?,
+            ));
+        }
+        read_json_limited_async(response, credential.max_response_body_size()).await
+    }
+}
+
+impl<Credential: Clone + Debug + Send + Sync + TokenCache>
+    ConfidentialClientApplication<Credential>
+{
+    /// Same as [get_token_silent_async](ClientApplication::get_token_silent_async) except that
+    /// the request can be aborted using the [CancellationToken], for instance when the
+    /// network changes and a refresh is stuck. A cancelled request leaves the token cache
+    /// unchanged and returns [AuthExecutionError::Cancelled](graph_error::AuthExecutionError::Cancelled).
+    pub async fn get_token_silent_async_with_cancellation(
+        &mut self,
+        cancellation_token: &CancellationToken,
+    ) -> AuthExecutionResult<BearerToken> {
+        let token = cancellation_token
+            .run_until_cancelled(self.credential.get_token_silent_async())
+            .await?;
+        token.try_as_bearer().map(BearerToken::from)
+    }
+}
+
+impl<Credential: Clone + Debug + Send + Sync + TokenCache<Token = Token>>
+    ConfidentialClientApplication<Credential>
+{
+    /// Get a token from the cache, or from the token endpoint when there is no valid cached
+    /// token, together with the signed-in account read from the claims of its id token.
+    ///
+    /// Returns an error if the token does not include an id token, which requires the
+    /// openid scope.
+    pub async fn acquire_token_with_account_async(
+        &mut self,
+    ) -> AuthExecutionResult<(BearerToken, AccountInfo)> {
+        let token = self.credential.get_token_silent_async().await?;
+        let account_info = AccountInfo::try_from(&token)?;
+        Ok((BearerToken::from(token.try_as_bearer()?), account_info))
+    }
 }
 
 #[async_trait]
 impl<Credential: Clone + Debug + Send + Sync + TokenCache + TokenCredentialExecutor>
     ClientApplication for ConfidentialClientApplication<Credential>
 {
-    fn get_token_silent(&mut self) -> AuthExecutionResult<String> {
+    fn get_token_silent(&mut self) -> AuthExecutionResult<BearerToken> {
         let token = self.credential.get_token_silent()?;
-        Ok(token.as_bearer())
+        token.try_as_bearer().map(BearerToken::from)
     }
 
-    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<String> {
+    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<BearerToken> {
         let token = self.credential.get_token_silent_async().await?;
-        Ok(token.as_bearer())
+        token.try_as_bearer().map(BearerToken::from)
     }
 
     fn with_force_token_refresh(&mut self, force_token_refresh: ForceTokenRefresh) {
@@ -77,6 +351,18 @@ impl<Credential: Clone + Debug + Send + Sync + TokenCache + TokenCredentialExecu
     }
 }
 
+impl<Credential: TokenCacheSnapshot> TokenCacheSnapshot
+    for ConfidentialClientApplication<Credential>
+{
+    fn export_cache(&self) -> CacheSnapshot {
+        self.credential.export_cache()
+    }
+
+    fn import_cache(&mut self, snapshot: CacheSnapshot) {
+        self.credential.import_cache(snapshot)
+    }
+}
+
 #[async_trait]
 impl<Credential: Clone + Debug + Send + Sync + TokenCredentialExecutor> TokenCredentialExecutor
     for ConfidentialClientApplication<Credential>
@@ -109,6 +395,22 @@ impl<Credential: Clone + Debug + Send + Sync + TokenCredentialExecutor> TokenCre
         self.credential.app_config()
     }
 
+    fn warnings(&self) -> Vec<CredentialWarning> {
+        self.credential.warnings()
+    }
+
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        self.credential.token_cache()
+    }
+
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        self.credential.token_cache_mut()
+    }
+
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        self.credential.app_config_mut()
+    }
+
     fn execute(&mut self) -> AuthExecutionResult<reqwest::blocking::Response> {
         self.credential.execute()
     }
@@ -170,7 +472,11 @@ impl From<OpenIdCredential> for ConfidentialClientApplication<OpenIdCredential>
 
 #[cfg(test)]
 mod test {
-    use crate::identity::Authority;
+    use graph_core::cache::CacheStore;
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::identity::{Authority, CacheKeyStrategy, IdToken, MockCredential, MOCK_ID_TOKEN};
 
     use super::*;
 
@@ -235,4 +541,353 @@ mod test {
             credential_uri.as_str()
         );
     }
+
+    #[test]
+    fn grant_type_override_in_form() {
+        let mut confidential_client = ConfidentialClientApplication::builder(Uuid::new_v4())
+            .with_grant_type_override("urn:preview:grant-type")
+            .with_client_secret("ALDSKFJLKERLKJALSDKJF2209LAKJGFL")
+            .with_tenant("tenant")
+            .build();
+
+        let request_parts = confidential_client.credential.request_parts().unwrap();
+
+        assert_eq!(
+            Some(&"urn:preview:grant-type".to_owned()),
+            request_parts.form_urlencoded.get("grant_type")
+        );
+
+        // Refresh token requests are not overridden.
+        let mut credential = ConfidentialClientApplication::builder(Uuid::new_v4())
+            .with_grant_type_override("urn:preview:grant-type")
+            .with_auth_code("auth_code")
+            .with_client_secret("ALDSKFJLKERLKJALSDKJF2209LAKJGFL")
+            .with_scope(["User.Read"])
+            .with_redirect_uri(Url::parse("http://localhost:8000/redirect").unwrap())
+            .build()
+            .into_inner();
+        assert_eq!(
+            Some(&"urn:preview:grant-type".to_owned()),
+            credential
+                .request_parts()
+                .unwrap()
+                .form_urlencoded
+                .get("grant_type")
+        );
+
+        credential.set_refresh_token("refresh_token".to_owned());
+        assert_eq!(
+            Some(&"refresh_token".to_owned()),
+            credential
+                .request_parts()
+                .unwrap()
+                .form_urlencoded
+                .get("grant_type")
+        );
+    }
+
+    #[test]
+    fn accept_language_header_on_token_request() {
+        let mut confidential_client = ConfidentialClientApplication::builder(Uuid::new_v4())
+            .with_client_secret("ALDSKFJLKERLKJALSDKJF2209LAKJGFL")
+            .with_tenant("tenant")
+            .with_accept_language("fr-FR")
+            .unwrap()
+            .build();
+
+        let request = confidential_client
+            .credential
+            .build_request_async()
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            Some("fr-FR"),
+            request
+                .headers()
+                .get(http::header::ACCEPT_LANGUAGE)
+                .and_then(|value| value.to_str().ok())
+        );
+    }
+
+    #[tokio::test]
+    async fn userinfo_requires_cached_token() {
+        let mut confidential_client = ConfidentialClientApplication::new(MockCredential::new());
+
+        let result = confidential_client.userinfo_async().await;
+        assert!(matches!(
+            result,
+            Err(graph_error::AuthExecutionError::Authorization(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn acquire_token_with_account() {
+        let mut confidential_client = ConfidentialClientApplication::new(MockCredential::new());
+
+        let (bearer_token, account_info) = confidential_client
+            .acquire_token_with_account_async()
+            .await
+            .unwrap();
+        assert_eq!("access_token_1", bearer_token.as_str());
+        assert_eq!(
+            AccountInfo {
+                name: Some("Adele Vance".into()),
+                username: Some("adelev@contoso.com".into()),
+                oid: Some("00000000-0000-0000-66f3-3332eca7ea81".into()),
+                tid: Some("9122040d-6c67-4c5b-b112-36a304b66dad".into()),
+            },
+            account_info
+        );
+    }
+
+    #[tokio::test]
+    async fn prewarm_cache_stores_token() {
+        let mut confidential_client = ConfidentialClientApplication::new(MockCredential::new());
+        let cache_id = confidential_client.app_config().cache_id.clone();
+        assert!(confidential_client
+            .credential
+            .token_cache
+            .get(cache_id.as_str())
+            .is_none());
+
+        confidential_client
+            .prewarm_cache_async(["https://graph.microsoft.com/.default"])
+            .await
+            .unwrap();
+
+        let token = confidential_client
+            .credential
+            .token_cache
+            .get(cache_id.as_str())
+            .unwrap();
+        assert_eq!("access_token_1", token.access_token);
+        assert_eq!(
+            vec!["https://graph.microsoft.com/.default".to_owned()],
+            token.scope
+        );
+        assert!(confidential_client.app_config().scope.is_empty());
+    }
+
+    #[tokio::test]
+    async fn acquire_token_for_two_tenants() {
+        let mut app_config = AppConfig::new(Uuid::new_v4());
+        app_config.with_tenant("home");
+        let mut confidential_client =
+            ConfidentialClientApplication::new(MockCredential::with_app_config(app_config));
+
+        let contoso = confidential_client
+            .acquire_token_for_tenant_async("contoso", ["https://graph.microsoft.com/.default"])
+            .await
+            .unwrap();
+        let fabrikam = confidential_client
+            .acquire_token_for_tenant_async("fabrikam", ["https://graph.microsoft.com/.default"])
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(&serde_json::json!(
+                "https://login.microsoftonline.com/contoso/oauth2/v2.0/token"
+            )),
+            contoso.additional_fields.get("token_url")
+        );
+        assert_eq!(
+            Some(&serde_json::json!(
+                "https://login.microsoftonline.com/fabrikam/oauth2/v2.0/token"
+            )),
+            fabrikam.additional_fields.get("token_url")
+        );
+
+        let client_id = confidential_client.app_config().client_id;
+        let entries = confidential_client.credential.token_cache.entries();
+        assert_eq!(2, entries.len());
+        for (tenant, token) in [("contoso", &contoso), ("fabrikam", &fabrikam)] {
+            let cache_id =
+                CacheKeyStrategy::ClientIdAndTenant.cache_id(client_id, Some(tenant), &[], None);
+            assert_eq!(
+                token.additional_fields.get("token_url"),
+                entries[&cache_id].additional_fields.get("token_url")
+            );
+        }
+
+        // The credential keeps its own tenant.
+        assert_eq!(
+            Authority::TenantId("home".into()),
+            confidential_client.app_config().authority
+        );
+    }
+
+    #[tokio::test]
+    async fn acquire_token_for_tenant_uses_cache_key_strategy() {
+        let mut app_config = AppConfig::new(Uuid::new_v4());
+        app_config.with_tenant("home");
+        app_config.with_cache_key_strategy(CacheKeyStrategy::ClientIdTenantScopeAndAccount);
+        let own_cache_id = app_config.cache_id.clone();
+        let mut confidential_client =
+            ConfidentialClientApplication::new(MockCredential::with_app_config(app_config));
+
+        let mail = confidential_client
+            .acquire_token_for_tenant_async("contoso", ["Mail.Read"])
+            .await
+            .unwrap();
+        let user = confidential_client
+            .acquire_token_for_tenant_async("contoso", ["User.Read"])
+            .await
+            .unwrap();
+        assert_eq!(vec!["Mail.Read".to_owned()], mail.scope);
+        assert_eq!(vec!["User.Read".to_owned()], user.scope);
+
+        let client_id = confidential_client.app_config().client_id;
+        let entries = confidential_client.credential.token_cache.entries();
+        assert_eq!(2, entries.len());
+        for scope in ["Mail.Read", "User.Read"] {
+            let cache_id = CacheKeyStrategy::ClientIdTenantScopeAndAccount.cache_id(
+                client_id,
+                Some("contoso"),
+                &[scope.to_owned()],
+                None,
+            );
+            assert_eq!(vec![scope.to_owned()], entries[&cache_id].scope);
+        }
+
+        let app_config = confidential_client.app_config();
+        assert_eq!(own_cache_id, app_config.cache_id);
+        assert!(app_config.scope.is_empty());
+    }
+
+    #[tokio::test]
+    async fn refresh_only_expiring_tokens() {
+        // The tenant of MOCK_ID_TOKEN.
+        let id_token_tenant = "9122040d-6c67-4c5b-b112-36a304b66dad";
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(format!("/{id_token_tenant}/oauth2/v2.0/token")))
+            .and(body_string_contains("grant_type=refresh_token"))
+            .and(body_string_contains("refresh_token=near_refresh_token"))
+            .and(body_string_contains("scope=Mail.Read"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "token_type": "Bearer",
+                "expires_in": 3600,
+                "access_token": "refreshed_access_token",
+                "scope": "Mail.Read",
+                "refresh_token": "new_refresh_token"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mock_server_uri = Url::parse(mock_server.uri().as_str()).unwrap();
+        let mut confidential_client = ConfidentialClientApplication::builder(Uuid::new_v4())
+            .with_tenant("home_tenant")
+            .with_auth_code("authorization_code")
+            .with_client_secret("client_secret")
+            .with_scope(["User.Read"])
+            .with_token_url_rewriter(move |uri| mock_server_uri.join(uri.path()).unwrap())
+            .build();
+        confidential_client
+            .credential
+            .app_config_mut()
+            .unwrap()
+            .https_only = false;
+        let own_cache_id = confidential_client.app_config().cache_id.clone();
+
+        let mut near = Token::new("Bearer", 60, "near_access_token", ["Mail.Read"]);
+        near.refresh_token = Some("near_refresh_token".into());
+        near.id_token = Some(IdToken::new(MOCK_ID_TOKEN, None, None, None));
+        let mut fresh = Token::new("Bearer", 3600, "fresh_access_token", ["User.Read"]);
+        fresh.refresh_token = Some("fresh_refresh_token".into());
+        let token_cache = confidential_client.credential.token_cache_mut().unwrap();
+        token_cache.store("near", near);
+        token_cache.store("fresh", fresh);
+
+        let results = confidential_client
+            .refresh_expiring_tokens_async(std::time::Duration::from_secs(300))
+            .await
+            .unwrap();
+        assert_eq!(1, results.len());
+        let refreshed = results["near"].as_ref().unwrap();
+        assert_eq!("refreshed_access_token", refreshed.access_token);
+        assert_eq!(vec!["Mail.Read".to_owned()], refreshed.scope);
+
+        let requests = mock_server.received_requests().await.unwrap();
+        let body = String::from_utf8_lossy(&requests[0].body);
+        assert!(!body.contains("code=authorization_code"));
+
+        let entries = confidential_client
+            .credential
+            .token_cache()
+            .unwrap()
+            .entries();
+        assert_eq!("refreshed_access_token", entries["near"].access_token);
+        assert_eq!("fresh_access_token", entries["fresh"].access_token);
+
+        // The credential keeps its own grant, tenant and scopes.
+        let credential = &confidential_client.credential;
+        assert_eq!(
+            Some("authorization_code"),
+            credential.authorization_code.as_deref()
+        );
+        assert_eq!(None, credential.refresh_token.as_deref());
+        let app_config = confidential_client.app_config();
+        assert_eq!(own_cache_id, app_config.cache_id);
+        assert_eq!(Some("home_tenant"), app_config.tenant_id.as_deref());
+        assert_eq!(vec!["User.Read".to_owned()], app_config.scope);
+    }
+
+    #[test]
+    fn from_connection_string() {
+        let confidential_client = ConfidentialClientApplication::from_connection_string(
+            "ClientId=4ef900be-dfd9-4da6-b224-0011e46c54dd; TenantId=contoso;\
+            Endpoint=https://contoso.example;ClientSecret=c2VjcmV0==",
+        )
+        .unwrap();
+        assert_eq!(
+            "4ef900be-dfd9-4da6-b224-0011e46c54dd",
+            confidential_client.app_config().client_id.to_string()
+        );
+        assert_eq!(
+            Authority::TenantId("contoso".into()),
+            confidential_client.app_config().authority
+        );
+        assert_eq!("c2VjcmV0==", confidential_client.credential.client_secret);
+
+        let result = ConfidentialClientApplication::from_connection_string(
+            "ClientId=4ef900be-dfd9-4da6-b224-0011e46c54dd;TenantId=contoso",
+        );
+        assert!(matches!(
+            result,
+            Err(AF::RequiredValue { name, .. }) if name == "ClientSecret"
+        ));
+    }
+
+    #[test]
+    fn app_only_graph_default_scope() {
+        let confidential_client = ConfidentialClientApplication::app_only_graph(
+            Uuid::new_v4(),
+            "tenant",
+            "ALDSKFJLKERLKJALSDKJF2209LAKJGFL",
+            AzureCloudInstance::AzureChina,
+        );
+
+        let app_config = confidential_client.app_config();
+        assert_eq!(
+            vec!["https://microsoftgraph.chinacloudapi.cn/.default".to_owned()],
+            app_config.scope
+        );
+        assert_eq!(
+            AzureCloudInstance::AzureChina,
+            app_config.azure_cloud_instance
+        );
+
+        let confidential_client = ConfidentialClientApplication::app_only_graph(
+            Uuid::new_v4(),
+            "tenant",
+            "ALDSKFJLKERLKJALSDKJF2209LAKJGFL",
+            AzureCloudInstance::AzurePublic,
+        );
+        assert_eq!(
+            vec!["https://graph.microsoft.com/.default".to_owned()],
+            confidential_client.app_config().scope
+        );
+    }
 }
@@ -3,39 +3,43 @@ use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::ops::Add;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Duration;
 
-use graph_core::cache::{CacheStore, InMemoryCacheStore, TokenCache};
+use graph_core::cache::{InMemoryCacheStore, TokenCache};
 use graph_core::identity::ForceTokenRefresh;
 use http::{HeaderMap, HeaderName, HeaderValue};
 use tracing::error;
 use url::Url;
 use uuid::Uuid;
+use zeroize::Zeroize;
 
 use crate::identity::{
-    AppConfig, Authority, AzureCloudInstance, DeviceAuthorizationResponse, PollDeviceCodeEvent,
-    PublicClientApplication, Token, TokenCredentialExecutor,
+    read_json_limited, AppConfig, Authority, AzureCloudInstance, DeviceAuthorizationResponse,
+    DeviceCodeStatus, EndpointVersion, PollDeviceCodeEvent, PublicClientApplication,
+    RefreshTokenCredential, Token, TokenCredentialExecutor,
 };
 use crate::oauth_serializer::{AuthParameter, AuthSerializer};
 use graph_core::http::{
-    AsyncResponseConverterExt, HttpResponseExt, JsonHttpResponse, ResponseConverterExt,
+    AsyncResponseConverterExt, HttpResponseBuilderExt, HttpResponseExt, JsonHttpResponse,
+    ResponseConverterExt,
 };
 use graph_error::{
     AuthExecutionError, AuthExecutionResult, AuthTaskExecutionResult, AuthorizationFailure,
-    IdentityResult,
+    ErrorMessage, ErrorStatus, IdentityResult,
 };
 
 #[cfg(feature = "interactive-auth")]
 use {
-    crate::interactive::{HostOptions, UserEvents, WebViewAuth, WebViewOptions},
+    crate::interactive::{
+        is_browser_available, HostOptions, UserEvents, WebViewAuth, WebViewOptions,
+    },
     crate::tracing_targets::INTERACTIVE_AUTH,
     graph_error::WebViewDeviceCodeError,
     tao::{event_loop::EventLoopProxy, window::Window},
     wry::{WebView, WebViewBuilder},
 };
 
-const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
-
 credential_builder!(
     DeviceCodeCredentialBuilder,
     PublicClientApplication<DeviceCodeCredential>
@@ -60,6 +64,9 @@ pub struct DeviceCodeCredential {
     /// A device_code is a long string used to verify the session between the client and the authorization server.
     /// The client uses this parameter to request the access token from the authorization server.
     pub(crate) device_code: Option<String>,
+    /// The endpoint version of the device code grant type sent with the device code. Uses
+    /// the endpoint version of the authority when not set.
+    pub(crate) device_code_grant_type: Option<EndpointVersion>,
     token_cache: InMemoryCacheStore<Token>,
 }
 
@@ -73,6 +80,7 @@ impl DeviceCodeCredential {
             app_config: AppConfig::builder(client_id.as_ref()).scope(scope).build(),
             refresh_token: None,
             device_code: Some(device_code.as_ref().to_owned()),
+            device_code_grant_type: None,
             token_cache: Default::default(),
         }
     }
@@ -100,8 +108,8 @@ impl DeviceCodeCredential {
             ));
         }
 
-        let new_token: Token = response.json()?;
-        self.token_cache.store(cache_id, new_token.clone());
+        let new_token: Token = read_json_limited(response, self.max_response_body_size())?;
+        let new_token = self.store_token(cache_id.as_str(), new_token);
 
         if new_token.refresh_token.is_some() {
             self.refresh_token = new_token.refresh_token.clone();
@@ -110,25 +118,13 @@ impl DeviceCodeCredential {
         Ok(new_token)
     }
 
-    async fn execute_cached_token_refresh_async(
-        &mut self,
-        cache_id: String,
-    ) -> AuthExecutionResult<Token> {
-        let response = self.execute_async().await?;
-
-        if !response.status().is_success() {
-            return Err(AuthExecutionError::silent_token_auth(
-                response.into_http_response_async().await?,
-            ));
-        }
-
-        let new_token: Token = response.json().await?;
+    async fn execute_cached_token_refresh_async(&mut self) -> AuthExecutionResult<Token> {
+        let new_token = self.acquire_token_async().await?;
 
         if new_token.refresh_token.is_some() {
             self.refresh_token = new_token.refresh_token.clone();
         }
 
-        self.token_cache.store(cache_id, new_token.clone());
         Ok(new_token)
     }
 }
@@ -141,6 +137,25 @@ impl Debug for DeviceCodeCredential {
     }
 }
 
+impl Drop for DeviceCodeCredential {
+    fn drop(&mut self) {
+        self.refresh_token.zeroize();
+        self.device_code.zeroize();
+    }
+}
+
+token_cache_snapshot!(DeviceCodeCredential);
+
+impl RefreshTokenCredential for DeviceCodeCredential {
+    fn refresh_token(&self) -> Option<&str> {
+        self.refresh_token.as_deref()
+    }
+
+    fn set_refresh_token(&mut self, refresh_token: String) {
+        self.refresh_token = Some(refresh_token);
+    }
+}
+
 #[async_trait]
 impl TokenCache for DeviceCodeCredential {
     type Token = Token;
@@ -158,8 +173,8 @@ impl TokenCache for DeviceCodeCredential {
                     }
                 }
 
-                if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-                    if token.is_expired_sub(time::Duration::minutes(5)) {
+                if let Some(token) = self.cached_token(cache_id.as_str()) {
+                    if self.is_token_expired(&token) {
                         if let Some(refresh_token) = token.refresh_token.as_ref() {
                             self.refresh_token = Some(refresh_token.to_owned());
                         }
@@ -190,30 +205,27 @@ impl TokenCache for DeviceCodeCredential {
                 // Attempt to bypass a read on the token store by using previous
                 // refresh token stored outside of RwLock
                 if self.refresh_token.is_some() {
-                    if let Ok(token) = self
-                        .execute_cached_token_refresh_async(cache_id.clone())
-                        .await
-                    {
+                    if let Ok(token) = self.execute_cached_token_refresh_async().await {
                         return Ok(token);
                     }
                 }
 
-                if let Some(old_token) = self.token_cache.get(cache_id.as_str()) {
-                    if old_token.is_expired_sub(time::Duration::minutes(5)) {
+                if let Some(old_token) = self.cached_token(cache_id.as_str()) {
+                    if self.is_token_expired(&old_token) {
                         if let Some(refresh_token) = old_token.refresh_token.as_ref() {
                             self.refresh_token = Some(refresh_token.to_owned());
                         }
 
-                        self.execute_cached_token_refresh_async(cache_id).await
+                        self.execute_cached_token_refresh_async().await
                     } else {
                         Ok(old_token.clone())
                     }
                 } else {
-                    self.execute_cached_token_refresh_async(cache_id).await
+                    self.execute_cached_token_refresh_async().await
                 }
             }
             ForceTokenRefresh::Once | ForceTokenRefresh::Always => {
-                let token_result = self.execute_cached_token_refresh_async(cache_id).await;
+                let token_result = self.execute_cached_token_refresh_async().await;
                 if self.app_config.force_token_refresh == ForceTokenRefresh::Once {
                     self.with_force_token_refresh(ForceTokenRefresh::Never);
                 }
@@ -259,7 +271,7 @@ impl TokenCredentialExecutor for DeviceCodeCredential {
 
             serializer
                 .grant_type("refresh_token")
-                .device_code(refresh_token.as_ref());
+                .refresh_token(refresh_token.as_ref());
 
             return serializer.as_credential_map(
                 vec![],
@@ -279,7 +291,11 @@ impl TokenCredentialExecutor for DeviceCodeCredential {
             }
 
             serializer
-                .grant_type(DEVICE_CODE_GRANT_TYPE)
+                .grant_type(
+                    self.device_code_grant_type
+                        .unwrap_or_else(|| self.app_config.authority.endpoint_version())
+                        .device_code_grant_type(),
+                )
                 .device_code(device_code.as_ref());
 
             return serializer.as_credential_map(
@@ -311,6 +327,18 @@ impl TokenCredentialExecutor for DeviceCodeCredential {
     fn app_config(&self) -> &AppConfig {
         &self.app_config
     }
+
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        Some(&self.token_cache)
+    }
+
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        Some(&mut self.token_cache)
+    }
+
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        Some(&mut self.app_config)
+    }
 }
 
 #[derive(Clone)]
@@ -325,6 +353,7 @@ impl DeviceCodeCredentialBuilder {
                 app_config: AppConfig::new(client_id.as_ref()),
                 refresh_token: None,
                 device_code: None,
+                device_code_grant_type: None,
                 token_cache: Default::default(),
             },
         }
@@ -339,6 +368,7 @@ impl DeviceCodeCredentialBuilder {
                 app_config,
                 refresh_token: None,
                 device_code: Some(device_code.as_ref().to_owned()),
+                device_code_grant_type: None,
                 token_cache: Default::default(),
             },
         }
@@ -355,11 +385,245 @@ impl DeviceCodeCredentialBuilder {
         self.credential.refresh_token = Some(refresh_token.as_ref().to_owned());
         self
     }
+
+    /// Send the device code grant type of the endpoint version instead of the one of the
+    /// authority, for instance the `urn:ietf:params:oauth:grant-type:device_code` grant of
+    /// [EndpointVersion::V2] to an AD FS host that supports it. Refresh token requests
+    /// are not affected.
+    pub fn with_device_code_grant_type(&mut self, endpoint_version: EndpointVersion) -> &mut Self {
+        self.credential.device_code_grant_type = Some(endpoint_version);
+        self
+    }
+}
+
+fn clone_response(response: &JsonHttpResponse) -> JsonHttpResponse {
+    let mut builder = http::Response::builder()
+        .status(response.status())
+        .version(response.version());
+    if let Some(url) = response.url() {
+        builder = builder.url(url);
+    }
+    if let Some(json) = response.json() {
+        builder = builder.json(&json);
+    }
+    if let Some(headers) = builder.headers_mut() {
+        headers.extend(response.headers().clone());
+    }
+    builder
+        .body(response.body().clone())
+        .expect("parts of an existing response are valid")
+}
+
+trait PollSender: Clone {
+    /// Send without waiting. Returns false if the receiver was dropped or is full.
+    fn try_send_response(&self, response: JsonHttpResponse) -> bool;
+}
+
+impl PollSender for std::sync::mpsc::Sender<JsonHttpResponse> {
+    fn try_send_response(&self, response: JsonHttpResponse) -> bool {
+        self.send(response).is_ok()
+    }
+}
+
+impl PollSender for tokio::sync::mpsc::Sender<JsonHttpResponse> {
+    fn try_send_response(&self, response: JsonHttpResponse) -> bool {
+        self.try_send(response).is_ok()
+    }
+}
+
+#[derive(Debug)]
+struct PollState<S> {
+    senders: Vec<S>,
+    responses: Vec<JsonHttpResponse>,
+}
+
+/// Shares the polling loop of a [DeviceCodePollingExecutor] between calls to poll so
+/// that only one loop polls the token endpoint at a time. Calling poll while a loop is
+/// running returns a receiver for the running loop instead of starting another one.
+/// The receiver first gets the responses the loop received so far.
+#[derive(Debug)]
+struct SharedPoll<S> {
+    state: Arc<Mutex<Option<PollState<S>>>>,
+}
+
+impl<S> Clone for SharedPoll<S> {
+    fn clone(&self) -> Self {
+        SharedPoll {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<S> Default for SharedPoll<S> {
+    fn default() -> Self {
+        SharedPoll {
+            state: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl<S> SharedPoll<S> {
+    fn lock(&self) -> MutexGuard<'_, Option<PollState<S>>> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn finish(&self) {
+        *self.lock() = None;
+    }
+}
+
+impl<S: PollSender> SharedPoll<S> {
+    /// Subscribe the sender to the running loop and return true, or return false when no
+    /// loop is running in which case the sender is the first subscriber of a new loop that
+    /// the caller has to start. A sender that cannot take the responses received so far
+    /// is not subscribed.
+    fn join(&self, sender: S) -> bool {
+        let mut state = self.lock();
+        if let Some(poll_state) = state.as_mut() {
+            if poll_state
+                .responses
+                .iter()
+                .all(|response| sender.try_send_response(clone_response(response)))
+            {
+                poll_state.senders.push(sender);
+            }
+            true
+        } else {
+            *state = Some(PollState {
+                senders: vec![sender],
+                responses: vec![],
+            });
+            false
+        }
+    }
+
+    /// Forward a response to all subscribers without waiting. Subscribers that are closed
+    /// or whose channel is full are dropped so that a lagging receiver does not stall
+    /// polling for the other subscribers. Returns false if no subscriber received it.
+    fn send(&self, response: JsonHttpResponse) -> bool {
+        let mut state = self.lock();
+        if let Some(poll_state) = state.as_mut() {
+            poll_state
+                .senders
+                .retain(|sender| sender.try_send_response(clone_response(&response)));
+            poll_state.responses.push(response);
+            !poll_state.senders.is_empty()
+        } else {
+            false
+        }
+    }
+}
+
+/// Ends the shared poll when the polling loop returns, including early returns on errors.
+struct FinishPoll<S>(SharedPoll<S>);
+
+impl<S> Drop for FinishPoll<S> {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+/// Sends the [DeviceCodeStatus] of a polling loop to the channel of the caller, if any.
+/// Statuses are dropped when the channel is full so that a slow UI does not delay polling.
+#[derive(Clone, Debug, Default)]
+struct StatusSender(Option<tokio::sync::mpsc::Sender<DeviceCodeStatus>>);
+
+impl StatusSender {
+    fn send(&self, status: DeviceCodeStatus) {
+        if let Some(sender) = self.0.as_ref() {
+            let _ = sender.try_send(status);
+        }
+    }
+}
+
+/// The delay in seconds of the `Retry-After` header of a poll response, if any.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(http::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// The response sent to the subscribers of a polling loop when a poll request fails
+/// without a token endpoint response, for instance when the request budget of the
+/// credential is exhausted. The body holds the error of the request.
+fn poll_request_error_response(error: &AuthExecutionError) -> JsonHttpResponse {
+    let mut builder = http::Response::builder();
+    builder = match error {
+        AuthExecutionError::Throttled { retry_after } => builder
+            .status(http::StatusCode::TOO_MANY_REQUESTS)
+            .header(http::header::RETRY_AFTER, retry_after.as_secs()),
+        _ => builder.status(http::StatusCode::INTERNAL_SERVER_ERROR),
+    };
+    let error_message = ErrorMessage {
+        error: ErrorStatus {
+            message: Some(error.to_string()),
+            ..Default::default()
+        },
+    };
+    builder
+        .body(Err(error_message.clone()))
+        .unwrap_or_else(|_| http::Response::new(Err(error_message)))
+}
+
+/// The interval to wait before the next poll after a `slow_down` response. The interval
+/// is increased by 5 seconds as required by
+/// [RFC 8628 section 3.5](https://datatracker.ietf.org/doc/html/rfc8628#section-3.5),
+/// or to the `Retry-After` of the response if that is longer.
+fn slow_down_interval(interval: Duration, retry_after: Option<Duration>) -> Duration {
+    interval
+        .add(Duration::from_secs(5))
+        .max(retry_after.unwrap_or_default())
+}
+
+/// The status for the error of a failed poll response. The interval is changed on
+/// `slow_down`, see [slow_down_interval].
+fn poll_error_status(
+    error: Option<&str>,
+    retry_after: Option<Duration>,
+    interval: &mut Duration,
+) -> DeviceCodeStatus {
+    let error = match error {
+        Some(error) => error,
+        // Body should have error or we should bail.
+        None => return DeviceCodeStatus::Failed("poll response has no error".to_owned()),
+    };
+
+    match PollDeviceCodeEvent::from_str(error) {
+        Ok(PollDeviceCodeEvent::AuthorizationPending)
+        | Ok(PollDeviceCodeEvent::BadVerificationCode) => DeviceCodeStatus::Polling,
+        Ok(PollDeviceCodeEvent::SlowDown) => {
+            // Should slow down is part of the openid connect spec and means that
+            // that we should wait longer between polling by the amount specified
+            // in the interval field of the device code.
+            *interval = slow_down_interval(*interval, retry_after);
+            DeviceCodeStatus::SlowDown {
+                interval: *interval,
+            }
+        }
+        Ok(PollDeviceCodeEvent::AuthorizationDeclined)
+        | Ok(PollDeviceCodeEvent::ExpiredToken)
+        | Ok(PollDeviceCodeEvent::AccessDenied) => DeviceCodeStatus::Failed(error.to_owned()),
+        Err(_) => {
+            error!(
+                target = "device_code_polling_executor",
+                "invalid PollDeviceCodeEvent"
+            );
+            DeviceCodeStatus::Failed(error.to_owned())
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct DeviceCodePollingExecutor {
     credential: DeviceCodeCredential,
+    active_poll: SharedPoll<std::sync::mpsc::Sender<JsonHttpResponse>>,
+    active_poll_async: SharedPoll<tokio::sync::mpsc::Sender<JsonHttpResponse>>,
+    status_sender: StatusSender,
 }
 
 impl DeviceCodePollingExecutor {
@@ -369,23 +633,54 @@ impl DeviceCodePollingExecutor {
                 app_config,
                 refresh_token: None,
                 device_code: None,
+                device_code_grant_type: None,
                 token_cache: Default::default(),
             },
+            active_poll: Default::default(),
+            active_poll_async: Default::default(),
+            status_sender: Default::default(),
         }
     }
 
     pub fn with_scope<T: ToString, I: IntoIterator<Item = T>>(mut self, scope: I) -> Self {
-        self.credential.app_config.scope = scope.into_iter().map(|s| s.to_string()).collect();
+        self.credential.app_config.with_scope(scope);
         self
     }
 
     pub fn with_tenant(mut self, tenant_id: impl AsRef<str>) -> Self {
-        self.credential.app_config.tenant_id = Some(tenant_id.as_ref().to_owned());
+        self.credential.app_config.with_tenant(tenant_id);
+        self
+    }
+
+    /// See [DeviceCodeCredentialBuilder::with_device_code_grant_type].
+    pub fn with_device_code_grant_type(mut self, endpoint_version: EndpointVersion) -> Self {
+        self.credential.device_code_grant_type = Some(endpoint_version);
         self
     }
 
+    /// Send the [DeviceCodeStatus] of the polling loops started by [poll](Self::poll) and
+    /// [poll_async](Self::poll_async) to the channel, for instance to show the progress of
+    /// the sign in in a terminal UI. Statuses are dropped when the channel is full.
+    pub fn with_status_sender(
+        mut self,
+        status_sender: tokio::sync::mpsc::Sender<DeviceCodeStatus>,
+    ) -> Self {
+        self.status_sender = StatusSender(Some(status_sender));
+        self
+    }
+
+    /// Request a device code and poll the token endpoint until the user signs in or the
+    /// device code expires. The device authorization response and every token response
+    /// are sent to the receiver.
+    ///
+    /// Only one polling loop runs at a time. Calling poll while a loop is running returns
+    /// a receiver for the running loop.
     pub fn poll(&mut self) -> AuthExecutionResult<std::sync::mpsc::Receiver<JsonHttpResponse>> {
         let (sender, receiver) = std::sync::mpsc::channel();
+        if self.active_poll.join(sender) {
+            return Ok(receiver);
+        }
+        let active_poll = FinishPoll(self.active_poll.clone());
 
         let mut credential = self.credential.clone();
         let response = credential.execute()?;
@@ -394,7 +689,10 @@ impl DeviceCodePollingExecutor {
         let json = http_response.json().unwrap();
         let device_code_response: DeviceAuthorizationResponse = serde_json::from_value(json)?;
 
-        sender.send(http_response).unwrap();
+        active_poll.0.send(http_response);
+
+        let status_sender = self.status_sender.clone();
+        status_sender.send(DeviceCodeStatus::Initiated(device_code_response.clone()));
 
         let device_code = device_code_response.device_code;
         let mut interval = Duration::from_secs(device_code_response.interval);
@@ -405,46 +703,41 @@ impl DeviceCodePollingExecutor {
                 // Wait the amount of seconds that interval is.
                 std::thread::sleep(interval);
 
-                let response = credential.execute().unwrap();
-                let http_response = response.into_http_response()?;
+                let http_response = match credential
+                    .execute()
+                    .and_then(|response| response.into_http_response())
+                {
+                    Ok(http_response) => http_response,
+                    Err(error) => {
+                        active_poll.0.send(poll_request_error_response(&error));
+                        status_sender.send(DeviceCodeStatus::Failed(error.to_string()));
+                        break;
+                    }
+                };
                 let status = http_response.status();
 
                 if status.is_success() {
-                    sender.send(http_response)?;
+                    active_poll.0.send(http_response);
+                    status_sender.send(DeviceCodeStatus::Success);
                     break;
                 } else {
-                    let json = http_response.json().unwrap();
-                    let option_error = json["error"].as_str().map(|value| value.to_owned());
-                    sender.send(http_response)?;
-
-                    if let Some(error) = option_error {
-                        match PollDeviceCodeEvent::from_str(error.as_str()) {
-                            Ok(poll_device_code_type) => match poll_device_code_type {
-                                PollDeviceCodeEvent::AuthorizationPending
-                                | PollDeviceCodeEvent::BadVerificationCode => continue,
-                                PollDeviceCodeEvent::AuthorizationDeclined
-                                | PollDeviceCodeEvent::ExpiredToken
-                                | PollDeviceCodeEvent::AccessDenied => break,
-                                PollDeviceCodeEvent::SlowDown => {
-                                    interval = interval.add(Duration::from_secs(5));
-                                    continue;
-                                }
-                            },
-                            Err(_) => {
-                                error!(
-                                    target = "device_code_polling_executor",
-                                    "invalid PollDeviceCodeEvent"
-                                );
-                                break;
-                            }
-                        }
-                    } else {
-                        // Body should have error or we should bail.
+                    let option_error = http_response
+                        .json()
+                        .and_then(|json| json["error"].as_str().map(|value| value.to_owned()));
+                    let retry_after = retry_after(http_response.headers());
+                    if !active_poll.0.send(http_response) {
+                        break;
+                    }
+
+                    let poll_status =
+                        poll_error_status(option_error.as_deref(), retry_after, &mut interval);
+                    let is_finished = poll_status.is_finished();
+                    status_sender.send(poll_status);
+                    if is_finished {
                         break;
                     }
                 }
             }
-            Ok::<(), anyhow::Error>(())
         });
 
         Ok(receiver)
@@ -463,6 +756,11 @@ impl DeviceCodePollingExecutor {
             }
         };
 
+        if self.active_poll_async.join(sender) {
+            return Ok(receiver);
+        }
+        let active_poll = FinishPoll(self.active_poll_async.clone());
+
         let mut credential = self.credential.clone();
         let response = credential.execute_async().await?;
 
@@ -471,9 +769,10 @@ impl DeviceCodePollingExecutor {
         let device_code_response: DeviceAuthorizationResponse =
             serde_json::from_value(json).map_err(AuthExecutionError::from)?;
 
-        sender
-            .send_timeout(http_response, Duration::from_secs(60))
-            .await?;
+        active_poll.0.send(http_response);
+
+        let status_sender = self.status_sender.clone();
+        status_sender.send(DeviceCodeStatus::Initiated(device_code_response.clone()));
 
         let device_code = device_code_response.device_code;
         let mut interval = Duration::from_secs(device_code_response.interval);
@@ -484,47 +783,42 @@ impl DeviceCodePollingExecutor {
                 // Wait the amount of seconds that interval is.
                 tokio::time::sleep(interval).await;
 
-                let response = credential.execute_async().await?;
-                let http_response = response.into_http_response_async().await?;
+                let result = match credential.execute_async().await {
+                    Ok(response) => response.into_http_response_async().await,
+                    Err(error) => Err(error),
+                };
+                let http_response = match result {
+                    Ok(http_response) => http_response,
+                    Err(error) => {
+                        active_poll.0.send(poll_request_error_response(&error));
+                        status_sender.send(DeviceCodeStatus::Failed(error.to_string()));
+                        break;
+                    }
+                };
                 let status = http_response.status();
 
                 if status.is_success() {
-                    sender
-                        .send_timeout(http_response, Duration::from_secs(60))
-                        .await?;
+                    active_poll.0.send(http_response);
+                    status_sender.send(DeviceCodeStatus::Success);
                     break;
                 } else {
-                    let json = http_response.json().unwrap();
-                    let option_error = json["error"].as_str().map(|value| value.to_owned());
-                    sender
-                        .send_timeout(http_response, Duration::from_secs(60))
-                        .await?;
-
-                    if let Some(error) = option_error {
-                        match PollDeviceCodeEvent::from_str(error.as_str()) {
-                            Ok(poll_device_code_type) => match poll_device_code_type {
-                                PollDeviceCodeEvent::AuthorizationPending => continue,
-                                PollDeviceCodeEvent::AuthorizationDeclined => break,
-                                PollDeviceCodeEvent::BadVerificationCode => continue,
-                                PollDeviceCodeEvent::ExpiredToken => break,
-                                PollDeviceCodeEvent::AccessDenied => break,
-                                PollDeviceCodeEvent::SlowDown => {
-                                    // Should slow down is part of the openid connect spec and means that
-                                    // that we should wait longer between polling by the amount specified
-                                    // in the interval field of the device code.
-                                    interval = interval.add(Duration::from_secs(5));
-                                    continue;
-                                }
-                            },
-                            Err(_) => break,
-                        }
-                    } else {
-                        // Body should have error or we should bail.
+                    let option_error = http_response
+                        .json()
+                        .and_then(|json| json["error"].as_str().map(|value| value.to_owned()));
+                    let retry_after = retry_after(http_response.headers());
+                    if !active_poll.0.send(http_response) {
+                        break;
+                    }
+
+                    let poll_status =
+                        poll_error_status(option_error.as_deref(), retry_after, &mut interval);
+                    let is_finished = poll_status.is_finished();
+                    status_sender.send(poll_status);
+                    if is_finished {
                         break;
                     }
                 }
             }
-            Ok::<(), anyhow::Error>(())
         });
 
         Ok(receiver)
@@ -551,6 +845,66 @@ impl DeviceCodePollingExecutor {
             },
         ))
     }
+
+    /// Sign in with the webview when a browser window can be shown, see
+    /// [is_browser_available], and otherwise fall back to the device code flow.
+    ///
+    /// When falling back to the device code flow, `on_device_code` is called with the
+    /// device authorization response before polling starts. Show its message, the
+    /// verification uri and user code, to the user so that they can sign in on another
+    /// device. [DeviceCodeStatus::Initiated] is also sent to the channel of
+    /// [with_status_sender](Self::with_status_sender), if any.
+    #[cfg(feature = "interactive-auth")]
+    pub fn interactive_or_device_code(
+        &mut self,
+        options: WebViewOptions,
+        on_device_code: impl FnOnce(&DeviceAuthorizationResponse),
+    ) -> Result<PublicClientApplication<DeviceCodeCredential>, WebViewDeviceCodeError> {
+        self.interactive_or_device_code_with(options, on_device_code, is_browser_available)
+    }
+
+    #[cfg(feature = "interactive-auth")]
+    pub(crate) fn interactive_or_device_code_with(
+        &mut self,
+        options: WebViewOptions,
+        on_device_code: impl FnOnce(&DeviceAuthorizationResponse),
+        browser_available: impl FnOnce() -> bool,
+    ) -> Result<PublicClientApplication<DeviceCodeCredential>, WebViewDeviceCodeError> {
+        let (device_authorization_response, mut interactive_auth) =
+            self.with_interactive_auth(options).map_err(Box::new)?;
+
+        match DeviceCodeSignIn::select(browser_available) {
+            DeviceCodeSignIn::WebView => interactive_auth.poll(),
+            DeviceCodeSignIn::DeviceCode => {
+                on_device_code(&device_authorization_response);
+                self.status_sender
+                    .send(DeviceCodeStatus::Initiated(device_authorization_response));
+                interactive_auth.poll_device_code()
+            }
+        }
+    }
+}
+
+#[cfg(feature = "interactive-auth")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum DeviceCodeSignIn {
+    WebView,
+    DeviceCode,
+}
+
+#[cfg(feature = "interactive-auth")]
+impl DeviceCodeSignIn {
+    pub(crate) fn select(browser_available: impl FnOnce() -> bool) -> DeviceCodeSignIn {
+        if browser_available() {
+            DeviceCodeSignIn::WebView
+        } else {
+            tracing::debug!(
+                target: INTERACTIVE_AUTH,
+                "no browser available; using device code flow"
+            );
+            DeviceCodeSignIn::DeviceCode
+        }
+    }
 }
 
 #[cfg(feature = "interactive-auth")]
@@ -618,7 +972,9 @@ impl DeviceCodeInteractiveAuth {
 
         let options = self.options.clone();
         std::thread::spawn(move || {
-            DeviceCodeCredential::run(url, vec![], options, sender).unwrap();
+            if let Err(err) = DeviceCodeCredential::run(url, vec![], options, sender) {
+                tracing::debug!(target: INTERACTIVE_AUTH, "webview unavailable: {err}");
+            }
         });
 
         let credential = self.credential.clone();
@@ -626,6 +982,14 @@ impl DeviceCodeInteractiveAuth {
         DeviceCodeInteractiveAuth::poll_internal(interval, credential)
     }
 
+    /// Poll for the token without opening the webview. The user signs in on another
+    /// device using the user code of the device authorization response.
+    pub fn poll_device_code(
+        &mut self,
+    ) -> Result<PublicClientApplication<DeviceCodeCredential>, WebViewDeviceCodeError> {
+        DeviceCodeInteractiveAuth::poll_internal(self.interval, self.credential.clone())
+    }
+
     pub(crate) fn poll_internal(
         mut interval: Duration,
         mut credential: DeviceCodeCredential,
@@ -634,7 +998,7 @@ impl DeviceCodeInteractiveAuth {
             // Wait the amount of seconds that interval is.
             std::thread::sleep(interval);
 
-            let response = credential.execute().unwrap();
+            let response = credential.execute().map_err(Box::new)?;
             let http_response = response.into_http_response().map_err(Box::new)?;
             let status = http_response.status();
 
@@ -643,7 +1007,7 @@ impl DeviceCodeInteractiveAuth {
                     let token: Token = serde_json::from_value(json)
                         .map_err(|err| Box::new(AuthExecutionError::from(err)))?;
                     let cache_id = credential.app_config.cache_id.clone();
-                    credential.token_cache.store(cache_id, token);
+                    credential.store_token(cache_id.as_str(), token);
                     Ok(PublicClientApplication::from(credential))
                 } else {
                     Err(WebViewDeviceCodeError::DeviceCodePollingError(
@@ -660,7 +1024,10 @@ impl DeviceCodeInteractiveAuth {
                             PollDeviceCodeEvent::AuthorizationPending
                             | PollDeviceCodeEvent::BadVerificationCode => continue,
                             PollDeviceCodeEvent::SlowDown => {
-                                interval = interval.add(Duration::from_secs(5));
+                                interval = slow_down_interval(
+                                    interval,
+                                    retry_after(http_response.headers()),
+                                );
                                 continue;
                             }
                             PollDeviceCodeEvent::AuthorizationDeclined
@@ -691,6 +1058,11 @@ impl DeviceCodeInteractiveAuth {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::identity::{
+        BudgetExhaustedBehavior, RequestBudget, TokenUrlRewriter, DEVICE_CODE_GRANT_TYPE_V2,
+    };
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     #[test]
     #[should_panic]
@@ -699,4 +1071,300 @@ mod test {
 
         let _ = credential.form_urlencode().unwrap();
     }
+
+    #[test]
+    fn refresh_token_form() {
+        let mut credential =
+            DeviceCodeCredential::new(Uuid::new_v4().to_string(), "device_code", ["User.Read"]);
+        credential.with_refresh_token("refresh_token");
+
+        let form = credential.form_urlencode().unwrap();
+        assert_eq!(Some(&"refresh_token".to_owned()), form.get("grant_type"));
+        assert_eq!(Some(&"refresh_token".to_owned()), form.get("refresh_token"));
+        assert!(!form.contains_key("device_code"));
+    }
+
+    fn poll_response(status: u16, json: serde_json::Value) -> JsonHttpResponse {
+        http::Response::builder()
+            .status(status)
+            .json(&json)
+            .body(Ok(json))
+            .unwrap()
+    }
+
+    fn device_authorization_json(interval: u64) -> serde_json::Value {
+        serde_json::json!({
+            "device_code": "device_code",
+            "expires_in": 900,
+            "interval": interval,
+            "message": "message",
+            "user_code": "user_code",
+            "verification_uri": "https://microsoft.com/devicelogin"
+        })
+    }
+
+    fn authorization_pending() -> ResponseTemplate {
+        ResponseTemplate::new(400)
+            .set_body_json(serde_json::json!({ "error": "authorization_pending" }))
+    }
+
+    fn token_response() -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "token_type": "Bearer",
+            "expires_in": 3600,
+            "access_token": "access_token"
+        }))
+    }
+
+    /// Serves a single device authorization response at the device code endpoint and the
+    /// token responses, in order, at the token endpoint.
+    async fn mock_device_code_endpoints(
+        interval: u64,
+        token_responses: Vec<ResponseTemplate>,
+    ) -> MockServer {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/common/oauth2/v2.0/devicecode"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(device_authorization_json(interval)),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        for (priority, token_response) in token_responses.into_iter().enumerate() {
+            Mock::given(method("POST"))
+                .and(path("/common/oauth2/v2.0/token"))
+                .respond_with(token_response)
+                .up_to_n_times(1)
+                .with_priority(priority as u8 + 1)
+                .mount(&mock_server)
+                .await;
+        }
+        mock_server
+    }
+
+    /// A polling executor that sends its requests to the mock server.
+    fn mock_polling_executor(mock_server: &MockServer) -> DeviceCodePollingExecutor {
+        let mock_server_uri = Url::parse(mock_server.uri().as_str()).unwrap();
+        let mut app_config = AppConfig::new(Uuid::new_v4());
+        app_config.https_only = false;
+        app_config.token_url_rewriter = Some(TokenUrlRewriter::new(move |uri| {
+            mock_server_uri.join(uri.path()).unwrap()
+        }));
+        DeviceCodePollingExecutor::new_with_app_config(app_config).with_scope(["User.Read"])
+    }
+
+    #[test]
+    fn second_poll_joins_running_loop() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock_server = runtime.block_on(mock_device_code_endpoints(
+            1,
+            vec![authorization_pending(), token_response()],
+        ));
+        let mut executor = mock_polling_executor(&mock_server);
+
+        let first_receiver = executor.poll().unwrap();
+        // Joins the running loop without sending a device authorization request.
+        let second_receiver = executor.poll().unwrap();
+
+        for receiver in [first_receiver, second_receiver] {
+            let responses: Vec<JsonHttpResponse> = receiver.iter().collect();
+            assert_eq!(3, responses.len());
+            assert_eq!(
+                Some("user_code"),
+                responses[0].json().unwrap()["user_code"].as_str()
+            );
+            assert_eq!(400, responses[1].status().as_u16());
+            assert_eq!(200, responses[2].status().as_u16());
+        }
+
+        // A new loop is started after the running loop finished.
+        let (sender, _receiver) = std::sync::mpsc::channel();
+        assert!(!executor.active_poll.join(sender));
+    }
+
+    #[test]
+    fn lagging_subscriber_is_dropped() {
+        let shared_poll = SharedPoll::default();
+        let (lagging_sender, mut lagging_receiver) = tokio::sync::mpsc::channel(1);
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(10);
+        assert!(!shared_poll.join(lagging_sender));
+        assert!(shared_poll.join(sender));
+
+        for status in [400, 400, 200] {
+            let response: JsonHttpResponse = http::Response::builder()
+                .status(status)
+                .body(Ok(serde_json::json!({})))
+                .unwrap();
+            assert!(shared_poll.send(response));
+        }
+
+        assert_eq!(400, lagging_receiver.try_recv().unwrap().status().as_u16());
+        assert!(lagging_receiver.try_recv().is_err());
+        let mut statuses = vec![];
+        while let Ok(response) = receiver.try_recv() {
+            statuses.push(response.status().as_u16());
+        }
+        assert_eq!(vec![400, 400, 200], statuses);
+        assert_eq!(1, shared_poll.lock().as_ref().unwrap().senders.len());
+    }
+
+    #[test]
+    fn throttled_poll_is_sent_to_receiver() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mock_server = runtime.block_on(mock_device_code_endpoints(0, vec![]));
+        let (status_sender, mut status_receiver) = tokio::sync::mpsc::channel(10);
+        let mut executor = mock_polling_executor(&mock_server).with_status_sender(status_sender);
+        // Only the device authorization request fits in the budget.
+        executor.credential.app_config.request_budget = Some(RequestBudget::new(
+            1,
+            Duration::from_secs(3600),
+            BudgetExhaustedBehavior::Reject,
+        ));
+
+        let responses: Vec<JsonHttpResponse> = executor.poll().unwrap().iter().collect();
+        assert_eq!(2, responses.len());
+        assert_eq!(429, responses[1].status().as_u16());
+        assert!(responses[1]
+            .headers()
+            .contains_key(http::header::RETRY_AFTER));
+        assert!(responses[1].body().is_err());
+
+        let mut statuses = vec![];
+        while let Ok(status) = status_receiver.try_recv() {
+            statuses.push(status);
+        }
+        assert!(matches!(statuses.last(), Some(DeviceCodeStatus::Failed(_))));
+    }
+
+    #[tokio::test]
+    async fn status_updates_for_poll_sequence() {
+        let mock_server = mock_device_code_endpoints(
+            0,
+            vec![
+                authorization_pending(),
+                ResponseTemplate::new(400)
+                    .insert_header("retry-after", "1")
+                    .set_body_json(serde_json::json!({ "error": "slow_down" })),
+                authorization_pending(),
+                token_response(),
+            ],
+        )
+        .await;
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(10);
+        let mut executor = mock_polling_executor(&mock_server).with_status_sender(sender);
+
+        let mut responses = executor.poll_async(None).await.unwrap();
+        while responses.recv().await.is_some() {}
+
+        let mut statuses = vec![];
+        while let Ok(status) = receiver.try_recv() {
+            statuses.push(status);
+        }
+        let device_authorization_response: DeviceAuthorizationResponse =
+            serde_json::from_value(device_authorization_json(0)).unwrap();
+        assert_eq!(
+            vec![
+                DeviceCodeStatus::Initiated(device_authorization_response),
+                DeviceCodeStatus::Polling,
+                // The interval grows by 5 seconds even though Retry-After is shorter.
+                DeviceCodeStatus::SlowDown {
+                    interval: Duration::from_secs(5)
+                },
+                DeviceCodeStatus::Polling,
+                DeviceCodeStatus::Success,
+            ],
+            statuses
+        );
+
+        let mut interval = Duration::from_secs(5);
+        assert_eq!(
+            DeviceCodeStatus::Failed("expired_token".to_owned()),
+            poll_error_status(Some("expired_token"), None, &mut interval)
+        );
+    }
+
+    #[test]
+    fn device_code_grant_type_per_endpoint_version() {
+        let mut credential = DeviceCodeCredential::builder(Uuid::new_v4().to_string())
+            .with_device_code("device_code")
+            .with_scope(["User.Read"])
+            .build();
+        let form = credential.form_urlencode().unwrap();
+        assert_eq!(
+            Some(&DEVICE_CODE_GRANT_TYPE_V2.to_owned()),
+            form.get("grant_type")
+        );
+
+        let mut credential = DeviceCodeCredential::builder(Uuid::new_v4().to_string())
+            .with_device_code("device_code")
+            .with_scope(["https://graph.microsoft.com/.default"])
+            .with_authority(Authority::adfs_host("https://adfs.contoso.com/adfs").unwrap())
+            .build();
+        let form = credential.form_urlencode().unwrap();
+        assert_eq!(Some(&"device_code".to_owned()), form.get("grant_type"));
+
+        let mut builder = DeviceCodeCredential::builder(Uuid::new_v4().to_string());
+        builder
+            .with_device_code("device_code")
+            .with_scope(["https://graph.microsoft.com/.default"])
+            .with_authority(Authority::adfs_host("https://adfs.contoso.com/adfs").unwrap())
+            .with_device_code_grant_type(EndpointVersion::V2);
+        let mut credential = builder.credential.clone();
+        let request_parts = credential.request_parts().unwrap();
+        assert_eq!(
+            Some(&DEVICE_CODE_GRANT_TYPE_V2.to_owned()),
+            request_parts.form_urlencoded.get("grant_type")
+        );
+
+        // Refresh token requests keep their grant type.
+        credential.set_refresh_token("refresh_token".to_owned());
+        let request_parts = credential.request_parts().unwrap();
+        assert_eq!(
+            Some(&"refresh_token".to_owned()),
+            request_parts.form_urlencoded.get("grant_type")
+        );
+    }
+
+    #[test]
+    fn slow_down_honors_retry_after() {
+        let response = poll_response(400, serde_json::json!({ "error": "slow_down" }));
+        let mut headers = response.headers().clone();
+        assert_eq!(None, retry_after(&headers));
+
+        headers.insert(http::header::RETRY_AFTER, HeaderValue::from_static("30"));
+        let retry_after = retry_after(&headers);
+        assert_eq!(Some(Duration::from_secs(30)), retry_after);
+
+        let mut interval = Duration::from_secs(5);
+        assert_eq!(
+            DeviceCodeStatus::SlowDown {
+                interval: Duration::from_secs(30)
+            },
+            poll_error_status(Some("slow_down"), retry_after, &mut interval)
+        );
+        // The next poll waits the Retry-After duration.
+        assert_eq!(Duration::from_secs(30), interval);
+
+        // Without Retry-After the interval is increased by 5 seconds.
+        poll_error_status(Some("slow_down"), None, &mut interval);
+        assert_eq!(Duration::from_secs(35), interval);
+
+        // A Retry-After shorter than the increased interval does not shorten it.
+        poll_error_status(
+            Some("slow_down"),
+            Some(Duration::from_secs(0)),
+            &mut interval,
+        );
+        assert_eq!(Duration::from_secs(40), interval);
+    }
+
+    #[test]
+    #[cfg(feature = "interactive-auth")]
+    fn headless_selects_device_code() {
+        let sign_in =
+            DeviceCodeSignIn::select(|| crate::interactive::browser_available("linux", |_| false));
+        assert_eq!(DeviceCodeSignIn::DeviceCode, sign_in);
+        assert_eq!(DeviceCodeSignIn::WebView, DeviceCodeSignIn::select(|| true));
+    }
 }
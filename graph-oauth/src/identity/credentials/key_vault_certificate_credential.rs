@@ -0,0 +1,500 @@
+use std::fmt::{Debug, Formatter};
+
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::{PKey, Private};
+use openssl::x509::X509;
+use reqwest::tls::Version;
+use url::Url;
+use uuid::Uuid;
+
+use graph_core::cache::{AsBearer, TokenCache};
+use graph_core::http::{AsyncResponseConverterExt, ResponseConverterExt};
+use graph_core::identity::{BearerToken, ClientApplication, ForceTokenRefresh};
+use graph_error::{AuthExecutionError, AuthExecutionResult, IdentityResult, AF};
+
+use crate::identity::credentials::app_config::AppConfig;
+use crate::identity::{
+    tracing_targets::CREDENTIAL_EXECUTOR, AzureCloudInstance, ClientCertificateCredential,
+    ClientCertificateCredentialBuilder, Token, X509Certificate,
+};
+
+const KEY_VAULT_API_VERSION: &str = "7.4";
+const PKCS12_CONTENT_TYPE: &str = "application/x-pkcs12";
+const PEM_CONTENT_TYPE: &str = "application/x-pem-file";
+
+/// The cloud of the vault from the DNS suffix of the vault url.
+fn vault_cloud_instance(vault_url: &Url) -> AzureCloudInstance {
+    let host = vault_url.host_str().unwrap_or_default();
+    if host.ends_with(".vault.azure.cn") {
+        AzureCloudInstance::AzureChina
+    } else if host.ends_with(".vault.usgovcloudapi.net") {
+        AzureCloudInstance::AzureUsGovernment
+    } else if host.ends_with(".vault.microsoftazure.de") {
+        AzureCloudInstance::AzureGermany
+    } else {
+        AzureCloudInstance::AzurePublic
+    }
+}
+
+/// The response of the Key Vault Get Secret API. When a certificate is created in
+/// Key Vault with an exportable key, the certificate and private key can be read
+/// as a secret with the same name as the certificate.
+///
+/// See [Get Secret](https://learn.microsoft.com/en-us/rest/api/keyvault/secrets/get-secret/get-secret)
+#[derive(Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyVaultSecret {
+    pub id: Option<String>,
+    pub value: String,
+    pub content_type: Option<String>,
+}
+
+impl Debug for KeyVaultSecret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyVaultSecret")
+            .field("id", &self.id)
+            .field("value", &"[REDACTED]")
+            .field("content_type", &self.content_type)
+            .finish()
+    }
+}
+
+impl KeyVaultSecret {
+    /// Parse the certificate and private key from the secret. Key Vault returns the
+    /// PKCS#12 (PFX) format as base64 without a password. PEM is also supported.
+    pub fn certificate(&self) -> IdentityResult<(X509, PKey<Private>)> {
+        match self.content_type.as_deref() {
+            Some(PEM_CONTENT_TYPE) => {
+                let certificate = X509::from_pem(self.value.as_bytes())
+                    .map_err(|err| AF::x509(err.to_string()))?;
+                let private_key = PKey::private_key_from_pem(self.value.as_bytes())
+                    .map_err(|err| AF::x509(err.to_string()))?;
+                Ok((certificate, private_key))
+            }
+            Some(PKCS12_CONTENT_TYPE) | None => {
+                let der = STANDARD
+                    .decode(self.value.trim())
+                    .map_err(|err| AF::x509(err.to_string()))?;
+                let parsed_pkcs12 = Pkcs12::from_der(&der)
+                    .map_err(|err| AF::x509(err.to_string()))?
+                    .parse2("")
+                    .map_err(|err| AF::x509(err.to_string()))?;
+
+                let certificate = parsed_pkcs12
+                    .cert
+                    .ok_or(AF::x509("No certificate found in Key Vault secret"))?;
+                let private_key = parsed_pkcs12
+                    .pkey
+                    .ok_or(AF::x509("No private key found in Key Vault secret"))?;
+                Ok((certificate, private_key))
+            }
+            Some(content_type) => AF::msg_result(
+                "content_type",
+                format!("unsupported Key Vault secret content type: {content_type}"),
+            ),
+        }
+    }
+}
+
+/// Client credentials flow using a certificate that is stored in Azure Key Vault.
+///
+/// The certificate and private key are read from Key Vault the first time a token
+/// is requested and are then used to sign the client assertion in the same way as
+/// [ClientCertificateCredential]. Requests to Key Vault use the access token of the
+/// `key_vault_credential` which must be for the resource https://vault.azure.net.
+/// The certificate must be created with an exportable private key.
+#[derive(Clone)]
+pub struct KeyVaultCertificateCredential {
+    app_config: AppConfig,
+    secret_url: Url,
+    key_vault_credential: Box<dyn ClientApplication>,
+    credential: Option<ClientCertificateCredential>,
+}
+
+impl Debug for KeyVaultCertificateCredential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyVaultCertificateCredential")
+            .field("app_config", &self.app_config)
+            .field("secret_url", &self.secret_url)
+            .finish()
+    }
+}
+
+impl KeyVaultCertificateCredential {
+    /// The vault url is the DNS name of the vault such as https://my-vault.vault.azure.net
+    ///
+    /// The cloud instance is taken from the DNS suffix of the vault url, for instance
+    /// [AzureCloudInstance::AzureChina] for https://my-vault.vault.azure.cn, and the scope
+    /// defaults to the Microsoft Graph `.default` scope of that cloud.
+    pub fn new(
+        client_id: impl TryInto<Uuid>,
+        tenant_id: impl AsRef<str>,
+        vault_url: impl AsRef<str>,
+        certificate_name: impl AsRef<str>,
+        key_vault_credential: impl ClientApplication + 'static,
+    ) -> IdentityResult<KeyVaultCertificateCredential> {
+        let vault_url = Url::parse(vault_url.as_ref())?;
+        if vault_url.scheme() != "https" {
+            return AF::msg_result("vault_url", "Key Vault url must use https");
+        }
+
+        let azure_cloud_instance = vault_cloud_instance(&vault_url);
+        let mut app_config = AppConfig::new(client_id);
+        app_config.with_tenant(tenant_id);
+        app_config.with_azure_cloud_instance(azure_cloud_instance);
+        app_config.with_scope(vec![azure_cloud_instance.default_microsoft_graph_scope()]);
+
+        let mut secret_url = vault_url
+            .join(format!("secrets/{}", certificate_name.as_ref().trim_matches('/')).as_str())?;
+        secret_url
+            .query_pairs_mut()
+            .append_pair("api-version", KEY_VAULT_API_VERSION);
+
+        Ok(KeyVaultCertificateCredential {
+            app_config,
+            secret_url,
+            key_vault_credential: Box::new(key_vault_credential),
+            credential: None,
+        })
+    }
+
+    /// Use a specific version of the certificate. The latest version is used by default.
+    pub fn with_certificate_version(&mut self, version: impl AsRef<str>) -> &mut Self {
+        if let Ok(mut segments) = self.secret_url.path_segments_mut() {
+            segments.push(version.as_ref());
+        }
+        self.credential = None;
+        self
+    }
+
+    /// Request tokens from the cloud instance. The scope is changed to the Microsoft Graph
+    /// `.default` scope of the cloud instance unless another scope was set.
+    pub fn with_azure_cloud_instance(
+        &mut self,
+        azure_cloud_instance: AzureCloudInstance,
+    ) -> &mut Self {
+        let default_scope = self
+            .app_config
+            .azure_cloud_instance
+            .default_microsoft_graph_scope();
+        if self.app_config.scope == [default_scope] {
+            self.app_config
+                .with_scope(vec![azure_cloud_instance.default_microsoft_graph_scope()]);
+        }
+        self.app_config
+            .with_azure_cloud_instance(azure_cloud_instance);
+        self.credential = None;
+        self
+    }
+
+    pub fn with_scope<T: ToString, I: IntoIterator<Item = T>>(&mut self, scope: I) -> &mut Self {
+        self.app_config.with_scope(scope);
+        self.credential = None;
+        self
+    }
+
+    /// Allow requests to Key Vault and the token endpoint over http in order to use
+    /// mocking frameworks. Requests are only sent over https by default.
+    #[cfg(feature = "test-util")]
+    pub fn with_https_only(&mut self, https_only: bool) -> &mut Self {
+        self.app_config.https_only = https_only;
+        self.credential = None;
+        self
+    }
+
+    fn with_secret(&mut self, secret: KeyVaultSecret) -> IdentityResult<()> {
+        let (certificate, private_key) = secret.certificate()?;
+        let client_id = self.app_config.client_id.to_string();
+        let x509 = match self.app_config.tenant_id.as_ref() {
+            Some(tenant_id) => {
+                X509Certificate::new_with_tenant(client_id, tenant_id, certificate, private_key)
+            }
+            None => X509Certificate::new(client_id, certificate, private_key),
+        };
+
+        let mut builder = ClientCertificateCredentialBuilder::new_with_certificate(
+            &x509,
+            self.app_config.clone(),
+        )?;
+        builder.with_scope(self.app_config.scope.clone());
+        self.credential = Some(builder.credential());
+        tracing::debug!(target: CREDENTIAL_EXECUTOR, "loaded certificate from key vault");
+        Ok(())
+    }
+
+    fn get_secret(&mut self) -> AuthExecutionResult<KeyVaultSecret> {
+        let bearer = self.key_vault_credential.get_token_silent()?;
+        let http_client = self
+            .app_config
+            .configure_blocking_http_client(
+                reqwest::blocking::ClientBuilder::new().min_tls_version(Version::TLS_1_2),
+            )
+            .build()?;
+        let response = http_client
+            .get(self.secret_url.clone())
+            .bearer_auth(bearer)
+            .send()?;
+
+        if !response.status().is_success() {
+            return Err(AuthExecutionError::silent_token_auth(
+                response.into_http_response()?,
+            ));
+        }
+
+        Ok(response.json()?)
+    }
+
+    async fn get_secret_async(&mut self) -> AuthExecutionResult<KeyVaultSecret> {
+        let bearer = self.key_vault_credential.get_token_silent_async().await?;
+        let http_client = self
+            .app_config
+            .configure_http_client(reqwest::ClientBuilder::new().min_tls_version(Version::TLS_1_2))
+            .build()?;
+        let response = http_client
+            .get(self.secret_url.clone())
+            .bearer_auth(bearer)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(AuthExecutionError::silent_token_auth(
+                response.into_http_response_async().await?,
+            ));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    fn certificate_credential(&mut self) -> AuthExecutionResult<&mut ClientCertificateCredential> {
+        self.credential
+            .as_mut()
+            .ok_or_else(|| AuthExecutionError::from(AF::msg_internal_err("credential")))
+    }
+
+    fn load_certificate(&mut self) -> AuthExecutionResult<&mut ClientCertificateCredential> {
+        if self.credential.is_none() {
+            let secret = self.get_secret()?;
+            self.with_secret(secret)?;
+        }
+        self.certificate_credential()
+    }
+
+    async fn load_certificate_async(
+        &mut self,
+    ) -> AuthExecutionResult<&mut ClientCertificateCredential> {
+        if self.credential.is_none() {
+            let secret = self.get_secret_async().await?;
+            self.with_secret(secret)?;
+        }
+        self.certificate_credential()
+    }
+}
+
+#[async_trait]
+impl TokenCache for KeyVaultCertificateCredential {
+    type Token = Token;
+
+    fn get_token_silent(&mut self) -> Result<Self::Token, AuthExecutionError> {
+        TokenCache::get_token_silent(self.load_certificate()?)
+    }
+
+    async fn get_token_silent_async(&mut self) -> Result<Self::Token, AuthExecutionError> {
+        let credential = self.load_certificate_async().await?;
+        TokenCache::get_token_silent_async(credential).await
+    }
+
+    fn with_force_token_refresh(&mut self, force_token_refresh: ForceTokenRefresh) {
+        self.app_config.force_token_refresh = force_token_refresh.clone();
+        if let Some(credential) = self.credential.as_mut() {
+            TokenCache::with_force_token_refresh(credential, force_token_refresh);
+        }
+    }
+}
+
+#[async_trait]
+impl ClientApplication for KeyVaultCertificateCredential {
+    fn get_token_silent(&mut self) -> AuthExecutionResult<BearerToken> {
+        let token = TokenCache::get_token_silent(self)?;
+        token.try_as_bearer().map(BearerToken::from)
+    }
+
+    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<BearerToken> {
+        let token = TokenCache::get_token_silent_async(self).await?;
+        token.try_as_bearer().map(BearerToken::from)
+    }
+
+    fn with_force_token_refresh(&mut self, force_token_refresh: ForceTokenRefresh) {
+        TokenCache::with_force_token_refresh(self, force_token_refresh);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::BearerTokenCredential;
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::rsa::Rsa;
+    use openssl::x509::X509NameBuilder;
+
+    fn pfx_base64() -> String {
+        let private_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "graph-rs-sdk").unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&private_key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        builder.sign(&private_key, MessageDigest::sha256()).unwrap();
+        let certificate = builder.build();
+
+        let pkcs12 = Pkcs12::builder()
+            .name("graph-rs-sdk")
+            .pkey(&private_key)
+            .cert(&certificate)
+            .build2("")
+            .unwrap();
+        STANDARD.encode(pkcs12.to_der().unwrap())
+    }
+
+    #[test]
+    fn parse_pfx_secret() {
+        let secret: KeyVaultSecret = serde_json::from_value(serde_json::json!({
+            "id": "https://my-vault.vault.azure.net/secrets/cert/version",
+            "value": pfx_base64(),
+            "contentType": "application/x-pkcs12"
+        }))
+        .unwrap();
+
+        assert!(secret.certificate().is_ok());
+        assert!(!format!("{secret:?}").contains(secret.value.as_str()));
+    }
+
+    #[test]
+    fn vault_url_requires_https() {
+        assert!(KeyVaultCertificateCredential::new(
+            Uuid::new_v4(),
+            "tenant",
+            "http://my-vault.vault.azure.net",
+            "cert",
+            BearerTokenCredential::new("key_vault_token"),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn scope_of_vault_cloud() {
+        let mut credential = KeyVaultCertificateCredential::new(
+            Uuid::new_v4(),
+            "tenant",
+            "https://my-vault.vault.azure.cn",
+            "cert",
+            BearerTokenCredential::new("key_vault_token"),
+        )
+        .unwrap();
+        assert_eq!(
+            AzureCloudInstance::AzureChina,
+            credential.app_config.azure_cloud_instance
+        );
+        assert_eq!(
+            vec!["https://microsoftgraph.chinacloudapi.cn/.default".to_owned()],
+            credential.app_config.scope
+        );
+
+        credential.with_azure_cloud_instance(AzureCloudInstance::AzureUsGovernment);
+        assert_eq!(
+            vec!["https://graph.microsoft.us/.default".to_owned()],
+            credential.app_config.scope
+        );
+
+        credential
+            .with_scope(["https://contoso.com/.default"])
+            .with_azure_cloud_instance(AzureCloudInstance::AzurePublic);
+        assert_eq!(
+            vec!["https://contoso.com/.default".to_owned()],
+            credential.app_config.scope
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn load_certificate_from_mock_key_vault() {
+        use wiremock::matchers::{bearer_token, method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/secrets/cert/version"))
+            .and(query_param("api-version", "7.4"))
+            .and(bearer_token("key_vault_token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "https://my-vault.vault.azure.net/secrets/cert/version",
+                "value": pfx_base64(),
+                "contentType": "application/x-pkcs12"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mut credential = KeyVaultCertificateCredential::new(
+            Uuid::new_v4(),
+            "tenant",
+            "https://my-vault.vault.azure.net",
+            "cert",
+            BearerTokenCredential::new("key_vault_token"),
+        )
+        .unwrap();
+        credential
+            .with_certificate_version("version")
+            .with_https_only(false);
+        let mut secret_url = Url::parse(mock_server.uri().as_str())
+            .unwrap()
+            .join(credential.secret_url.path())
+            .unwrap();
+        secret_url.set_query(credential.secret_url.query());
+        credential.secret_url = secret_url;
+
+        let certificate_credential = credential.load_certificate_async().await.unwrap();
+        assert!(certificate_credential.thumbprint().is_some());
+        assert!(!certificate_credential.client_assertion.is_empty());
+    }
+
+    #[tokio::test]
+    async fn key_vault_requests_require_https() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let mut credential = KeyVaultCertificateCredential::new(
+            Uuid::new_v4(),
+            "tenant",
+            "https://my-vault.vault.azure.net",
+            "cert",
+            BearerTokenCredential::new("key_vault_token"),
+        )
+        .unwrap();
+        credential.secret_url = Url::parse(mock_server.uri().as_str())
+            .unwrap()
+            .join("secrets/cert")
+            .unwrap();
+
+        assert!(credential.load_certificate_async().await.is_err());
+    }
+}
@@ -1,10 +1,9 @@
 use crate::identity::credentials::app_config::AppConfig;
-use crate::identity::{AzureCloudInstance, Prompt, ResponseMode, ResponseType};
+use crate::identity::{AzureCloudInstance, IntoRedirectUri, Prompt, ResponseMode, ResponseType};
 use crate::oauth_serializer::{AuthParameter, AuthSerializer};
 use graph_core::crypto::secure_random_32;
 use graph_error::{AuthorizationFailure, IdentityResult, AF};
 use http::{HeaderMap, HeaderName, HeaderValue};
-use reqwest::IntoUrl;
 use std::collections::HashMap;
 use url::Url;
 
@@ -220,8 +219,11 @@ impl ImplicitCredentialBuilder {
         }
     }
 
-    pub fn with_redirect_uri<U: IntoUrl>(&mut self, redirect_uri: U) -> anyhow::Result<&mut Self> {
-        self.credential.app_config.redirect_uri = Some(redirect_uri.into_url()?);
+    pub fn with_redirect_uri<U: IntoRedirectUri>(
+        &mut self,
+        redirect_uri: U,
+    ) -> anyhow::Result<&mut Self> {
+        self.credential.app_config.redirect_uri = Some(redirect_uri.into_redirect_uri()?);
         Ok(self)
     }
 
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use graph_core::cache::{InMemoryCacheStore, TokenCache};
+use graph_core::identity::ForceTokenRefresh;
+use graph_error::{AuthExecutionError, AuthExecutionResult, IdentityResult, AF};
+use uuid::Uuid;
+
+use crate::identity::{
+    AppConfig, RefreshTokenCredential, Token, TokenCredentialExecutor, CLIENT_REQUEST_ID_HEADER,
+};
+
+// Payload: {"name":"Adele Vance","preferred_username":"adelev@contoso.com",
+// "oid":"00000000-0000-0000-66f3-3332eca7ea81","tid":"9122040d-6c67-4c5b-b112-36a304b66dad"}
+pub(crate) const MOCK_ID_TOKEN: &str = "eyJhbGciOiJSUzI1NiIsInR5cCI6IkpXVCJ9.eyJuYW1lIjoiQWRlbGUgVmFuY2UiLCJwcmVmZXJyZWRfdXNlcm5hbWUiOiJhZGVsZXZAY29udG9zby5jb20iLCJvaWQiOiIwMDAwMDAwMC0wMDAwLTAwMDAtNjZmMy0zMzMyZWNhN2VhODEiLCJ0aWQiOiI5MTIyMDQwZC02YzY3LTRjNWItYjExMi0zNmEzMDRiNjZkYWQifQ.signature";
+
+/// A credential for tests that answers token requests without sending them. The n-th
+/// token request returns the access token `access_token_n` together with
+/// [MOCK_ID_TOKEN] and the token url of the request. Clones share the token cache and
+/// the recorded requests.
+#[derive(Clone)]
+pub(crate) struct MockCredential {
+    pub(crate) app_config: AppConfig,
+    pub(crate) token_cache: InMemoryCacheStore<Token>,
+    pub(crate) refresh_token: Option<String>,
+    /// The scope of the token responses. The requested scope is returned if None.
+    pub(crate) granted_scope: Option<String>,
+    /// Delays each token response so that other tasks have to wait for the request.
+    pub(crate) delay: Option<Duration>,
+    /// The grant type of every token request. The device code grant stands in for an
+    /// interactive sign-in when no refresh token is set.
+    grant_types: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockCredential {
+    pub(crate) fn new() -> MockCredential {
+        MockCredential::with_app_config(AppConfig::new(Uuid::new_v4()))
+    }
+
+    pub(crate) fn with_app_config(app_config: AppConfig) -> MockCredential {
+        MockCredential {
+            app_config,
+            token_cache: InMemoryCacheStore::new(),
+            refresh_token: None,
+            granted_scope: None,
+            delay: None,
+            grant_types: Default::default(),
+        }
+    }
+
+    /// The grant types of the token requests sent by this credential and its clones.
+    pub(crate) fn grant_types(&self) -> Vec<String> {
+        self.grant_types
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// The number of token requests sent by this credential and its clones.
+    pub(crate) fn requests(&self) -> usize {
+        self.grant_types().len()
+    }
+}
+
+impl Debug for MockCredential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockCredential")
+            .field("app_config", &self.app_config)
+            .finish()
+    }
+}
+
+impl RefreshTokenCredential for MockCredential {
+    fn refresh_token(&self) -> Option<&str> {
+        self.refresh_token.as_deref()
+    }
+
+    fn set_refresh_token(&mut self, refresh_token: String) {
+        self.refresh_token = Some(refresh_token);
+    }
+}
+
+#[async_trait]
+impl TokenCredentialExecutor for MockCredential {
+    fn form_urlencode(&mut self) -> IdentityResult<HashMap<String, String>> {
+        let grant_type = if self.refresh_token.is_some() {
+            "refresh_token"
+        } else {
+            "urn:ietf:params:oauth:grant-type:device_code"
+        };
+        Ok(HashMap::from([(
+            "grant_type".to_owned(),
+            grant_type.to_owned(),
+        )]))
+    }
+
+    fn app_config(&self) -> &AppConfig {
+        &self.app_config
+    }
+
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        Some(&self.token_cache)
+    }
+
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        Some(&mut self.token_cache)
+    }
+
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        Some(&mut self.app_config)
+    }
+
+    async fn execute_async(&mut self) -> AuthExecutionResult<reqwest::Response> {
+        let grant_type = self
+            .form_urlencode()?
+            .remove("grant_type")
+            .unwrap_or_default();
+        let request = {
+            let mut grant_types = self.grant_types.lock().unwrap_or_else(|e| e.into_inner());
+            grant_types.push(grant_type);
+            grant_types.len()
+        };
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+
+        let mut body = serde_json::json!({
+            "token_type": "Bearer",
+            "expires_in": 3600,
+            "access_token": format!("access_token_{request}"),
+            "token_url": self.uri()?.to_string(),
+            "id_token": MOCK_ID_TOKEN,
+        });
+        let scope = self
+            .granted_scope
+            .clone()
+            .unwrap_or_else(|| self.app_config.scope.join(" "));
+        if !scope.is_empty() {
+            body["scope"] = scope.into();
+        }
+        if self.refresh_token.is_some() {
+            body["refresh_token"] = "new_refresh_token".into();
+        }
+
+        let response: reqwest::Response = http::Response::builder()
+            .status(200)
+            .header("x-ms-ests-server", "2.1.17000.5 - WUS2 ProdSlices")
+            .header(
+                CLIENT_REQUEST_ID_HEADER,
+                "8d6b1c57-1c4e-4b6e-9f0a-5d2f3c1b7e42",
+            )
+            .body(body.to_string())
+            .unwrap()
+            .into();
+        self.record_response_headers(response.headers());
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl TokenCache for MockCredential {
+    type Token = Token;
+
+    fn get_token_silent(&mut self) -> AuthExecutionResult<Token> {
+        // The mock only answers async token requests.
+        let cache_id = self.app_config.cache_id.clone();
+        match self.cached_token(cache_id.as_str()) {
+            Some(token) if !self.is_token_expired(&token) => Ok(token),
+            _ => Err(AuthExecutionError::from(AF::msg_err(
+                "token",
+                "no cached token, use get_token_silent_async",
+            ))),
+        }
+    }
+
+    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<Token> {
+        let cache_id = self.app_config.cache_id.clone();
+        match self.cached_token(cache_id.as_str()) {
+            Some(token) if !self.is_token_expired(&token) => Ok(token),
+            _ => self.acquire_token_async().await,
+        }
+    }
+
+    fn with_force_token_refresh(&mut self, force_token_refresh: ForceTokenRefresh) {
+        self.app_config.force_token_refresh = force_token_refresh;
+    }
+}
@@ -5,7 +5,10 @@ pub use auth_code_authorization_url::*;
 pub use authorization_code_assertion_credential::*;
 pub use authorization_code_certificate_credential::*;
 pub use authorization_code_credential::*;
+pub use azure_cli_credential::*;
 pub use bearer_token_credential::*;
+pub use cache_key_strategy::*;
+pub use cache_snapshot::*;
 pub use client_assertion_credential::*;
 
 pub use client_certificate_credential::*;
@@ -14,13 +17,21 @@ pub use client_secret_credential::*;
 pub use confidential_client_application::*;
 pub use device_code_credential::*;
 pub use environment_credential::*;
+#[cfg(feature = "openssl")]
+pub use key_vault_certificate_credential::*;
+#[cfg(test)]
+pub(crate) use mock_credential::*;
 pub use open_id_authorization_url::*;
 pub use open_id_credential::*;
 pub use prompt::*;
 pub use public_client_application::*;
+pub use refresh_token_credential::*;
 pub use resource_owner_password_credential::*;
 pub use response_mode::*;
 pub use response_type::*;
+pub use shared_confidential_client::*;
+pub use shared_token_store::*;
+pub use token_credential_adapter::*;
 pub use token_credential_executor::*;
 #[cfg(feature = "openssl")]
 pub use x509_certificate::*;
@@ -37,7 +48,10 @@ mod auth_code_authorization_url;
 mod authorization_code_assertion_credential;
 mod authorization_code_certificate_credential;
 mod authorization_code_credential;
+mod azure_cli_credential;
 mod bearer_token_credential;
+mod cache_key_strategy;
+mod cache_snapshot;
 mod client_assertion_credential;
 mod client_certificate_credential;
 mod client_credentials_authorization_url;
@@ -45,13 +59,21 @@ mod client_secret_credential;
 mod confidential_client_application;
 mod device_code_credential;
 mod environment_credential;
+#[cfg(feature = "openssl")]
+mod key_vault_certificate_credential;
+#[cfg(test)]
+mod mock_credential;
 mod open_id_authorization_url;
 mod open_id_credential;
 mod prompt;
 mod public_client_application;
+mod refresh_token_credential;
 mod resource_owner_password_credential;
 mod response_mode;
 mod response_type;
+mod shared_confidential_client;
+mod shared_token_store;
+mod token_credential_adapter;
 mod token_credential_executor;
 
 #[cfg(feature = "openssl")]
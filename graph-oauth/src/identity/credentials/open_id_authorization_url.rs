@@ -1,8 +1,6 @@
 use std::collections::BTreeSet;
 use std::fmt::{Debug, Formatter};
 
-use reqwest::IntoUrl;
-
 use url::Url;
 use uuid::Uuid;
 
@@ -11,10 +9,11 @@ use graph_error::{AuthorizationFailure, IdentityResult, AF};
 
 use crate::identity::credentials::app_config::AppConfig;
 use crate::identity::{
-    AsQuery, Authority, AuthorizationUrl, AzureCloudInstance, OpenIdCredentialBuilder, Prompt,
-    ResponseMode, ResponseType,
+    validate_domain_hint, AsQuery, AuthSession, AuthSessionStore, Authority, AuthorizationUrl,
+    AzureCloudInstance, IntoRedirectUri, OpenIdCredentialBuilder, Prompt, ResponseMode,
+    ResponseType,
 };
-use crate::oauth_serializer::{AuthParameter, AuthSerializer};
+use crate::oauth_serializer::{ordered_scope, AuthParameter, AuthSerializer};
 
 use crate::identity::tracing_targets::CREDENTIAL_EXECUTOR;
 
@@ -22,8 +21,8 @@ use crate::identity::tracing_targets::CREDENTIAL_EXECUTOR;
 use {
     crate::identity::AuthorizationResponse,
     crate::interactive::{
-        HostOptions, InteractiveAuthEvent, UserEvents, WebViewAuth, WebViewAuthorizationEvent,
-        WebViewHostValidator, WebViewOptions,
+        spawn_webview, HostOptions, InteractiveAuthEvent, UserEvents, WebViewAuth,
+        WebViewAuthorizationEvent, WebViewHostValidator, WebViewOptions,
     },
     crate::Secret,
     graph_error::{WebViewError, WebViewResult},
@@ -117,6 +116,10 @@ pub struct OpenIdAuthorizationUrlParameters {
     /// this parameter during re-authentication, after already extracting the login_hint
     /// optional claim from an earlier sign-in.
     pub(crate) login_hint: Option<String>,
+    /// Optional
+    /// The claims request parameter as raw JSON, for instance to request claims in the
+    /// id token or to indicate client capabilities such as continuous access evaluation.
+    pub(crate) claims: Option<String>,
 }
 
 impl Debug for OpenIdAuthorizationUrlParameters {
@@ -133,19 +136,17 @@ impl Debug for OpenIdAuthorizationUrlParameters {
 impl OpenIdAuthorizationUrlParameters {
     pub fn new<U: ToString, I: IntoIterator<Item = U>>(
         client_id: impl TryInto<Uuid>,
-        redirect_uri: impl IntoUrl,
+        redirect_uri: impl IntoRedirectUri,
         scope: I,
     ) -> IdentityResult<OpenIdAuthorizationUrlParameters> {
-        let mut scope_set = BTreeSet::new();
-        scope_set.insert("openid".to_owned());
-        scope_set.extend(scope.into_iter().map(|s| s.to_string()));
-
-        let redirect_uri_result = Url::parse(redirect_uri.as_str());
+        let scope_set = ordered_scope(
+            std::iter::once("openid".to_owned()).chain(scope.into_iter().map(|s| s.to_string())),
+        );
 
         Ok(OpenIdAuthorizationUrlParameters {
             app_config: AppConfig::builder(client_id)
                 .scope(scope_set)
-                .redirect_uri(redirect_uri.into_url().or(redirect_uri_result)?)
+                .redirect_uri(redirect_uri.into_redirect_uri()?)
                 .build(),
             response_type: BTreeSet::from([ResponseType::IdToken]),
             response_mode: None,
@@ -154,6 +155,7 @@ impl OpenIdAuthorizationUrlParameters {
             prompt: Default::default(),
             domain_hint: None,
             login_hint: None,
+            claims: None,
         })
     }
 
@@ -167,6 +169,7 @@ impl OpenIdAuthorizationUrlParameters {
             prompt: Default::default(),
             domain_hint: None,
             login_hint: None,
+            claims: None,
         }
     }
 
@@ -186,6 +189,22 @@ impl OpenIdAuthorizationUrlParameters {
         self.authorization_url_with_host(azure_cloud_instance)
     }
 
+    /// Build the url and store the nonce of the request as an [AuthSession] in the store
+    /// under the state of the request. Take the session with
+    /// [AuthSessionStore::take_for_response] when redeeming the authorization response.
+    /// Returns an error if no state is set.
+    pub fn url_with_session_store(&self, store: &dyn AuthSessionStore) -> IdentityResult<Url> {
+        let state = self.state.as_ref().ok_or_else(|| {
+            AF::msg_err(
+                "state",
+                "a state is required to store the authorization session, use with_state_generated",
+            )
+        })?;
+        let url = self.url()?;
+        store.store(state, AuthSession::new(None, Some(self.nonce.clone())));
+        Ok(url)
+    }
+
     /// Get the nonce.
     ///
     /// This value may be generated automatically by the client and may be useful for users
@@ -196,6 +215,14 @@ impl OpenIdAuthorizationUrlParameters {
         &self.nonce
     }
 
+    /// Get the state.
+    ///
+    /// Compare this value with the state returned in the authorization response
+    /// to verify that the response is for a request made by this client.
+    pub fn state(&self) -> Option<&String> {
+        self.state.as_ref()
+    }
+
     #[cfg(feature = "interactive-auth")]
     pub fn interactive_webview_authentication(
         &self,
@@ -209,73 +236,49 @@ impl OpenIdAuthorizationUrlParameters {
             ))?;
         }
         let uri = self.url()?;
-        let redirect_uri = self.redirect_uri().cloned().unwrap();
-        let (sender, receiver) = std::sync::mpsc::channel();
-
-        std::thread::spawn(move || {
-            OpenIdAuthorizationUrlParameters::run(
-                uri,
-                vec![redirect_uri],
-                web_view_options,
-                sender,
-            )
-            .unwrap();
-        });
-        let mut iter = receiver.try_iter();
-        let mut next = iter.next();
-
-        while next.is_none() {
-            next = iter.next();
-        }
+        let redirect_uri = self
+            .redirect_uri()
+            .cloned()
+            .ok_or_else(|| WebViewError::InvalidUri("redirect_uri is required".into()))?;
+        let auth_event = spawn_webview(move |sender| {
+            OpenIdAuthorizationUrlParameters::run(uri, vec![redirect_uri], web_view_options, sender)
+        })?;
+
+        match auth_event {
+            InteractiveAuthEvent::InvalidRedirectUri(reason) => {
+                Err(WebViewError::InvalidUri(reason))
+            }
+            InteractiveAuthEvent::WebViewUnavailable(reason) => {
+                Err(WebViewError::WebViewUnavailable(reason))
+            }
+            InteractiveAuthEvent::ReachedRedirectUri(uri) => {
+                let authorization_response = AuthorizationResponse::try_from(&uri)
+                    .map_err(|err| WebViewError::InvalidUri(err.to_string()))?;
 
-        match next {
-            None => unreachable!(),
-            Some(auth_event) => match auth_event {
-                InteractiveAuthEvent::InvalidRedirectUri(reason) => {
-                    Err(WebViewError::InvalidUri(reason))
+                if authorization_response.is_err() {
+                    tracing::debug!(target: "graph_rs_sdk::interactive_auth", "error in authorization query or fragment from redirect uri");
+                    return Ok(WebViewAuthorizationEvent::Unauthorized(
+                        authorization_response,
+                    ));
                 }
-                InteractiveAuthEvent::ReachedRedirectUri(uri) => {
-                    let query = uri
-                        .query()
-                        .or(uri.fragment())
-                        .ok_or(WebViewError::InvalidUri(format!(
-                            "uri missing query or fragment: {}",
-                            uri
-                        )))?;
-
-                    let authorization_response: AuthorizationResponse =
-                        serde_urlencoded::from_str(query).map_err(|_| {
-                            WebViewError::InvalidUri(format!(
-                                "unable to deserialize query or fragment: {}",
-                                uri
-                            ))
-                        })?;
-
-                    if authorization_response.is_err() {
-                        tracing::debug!(target: "graph_rs_sdk::interactive_auth", "error in authorization query or fragment from redirect uri");
-                        return Ok(WebViewAuthorizationEvent::Unauthorized(
-                            authorization_response,
-                        ));
-                    }
 
-                    tracing::debug!(target: "graph_rs_sdk::interactive_auth", "parsed authorization query or fragment from redirect uri");
+                tracing::debug!(target: "graph_rs_sdk::interactive_auth", "parsed authorization query or fragment from redirect uri");
 
-                    let mut credential_builder = OpenIdCredentialBuilder::from((
-                        self.app_config.clone(),
-                        authorization_response.clone(),
-                    ));
+                let mut credential_builder = OpenIdCredentialBuilder::from((
+                    self.app_config.clone(),
+                    authorization_response.clone(),
+                ));
 
-                    credential_builder.with_client_secret(client_secret);
+                credential_builder.with_client_secret(client_secret);
 
-                    Ok(WebViewAuthorizationEvent::Authorized {
-                        authorization_response,
-                        credential_builder,
-                    })
-                }
-                InteractiveAuthEvent::WindowClosed(window_close_reason) => Ok(
-                    WebViewAuthorizationEvent::WindowClosed(window_close_reason.to_string()),
-                ),
-            },
+                Ok(WebViewAuthorizationEvent::Authorized {
+                    authorization_response,
+                    credential_builder,
+                })
+            }
+            InteractiveAuthEvent::WindowClosed(window_close_reason) => Ok(
+                WebViewAuthorizationEvent::WindowClosed(window_close_reason.to_string()),
+            ),
         }
     }
 }
@@ -300,13 +303,9 @@ impl AuthorizationUrl for OpenIdAuthorizationUrlParameters {
             return AuthorizationFailure::result("client_id");
         }
 
-        if self.app_config.scope.is_empty() || !self.app_config.scope.contains("openid") {
-            let mut scope = self.app_config.scope.clone();
-            scope.insert("openid".into());
-            serializer.set_scope(scope);
-        } else {
-            serializer.set_scope(self.app_config.scope.clone());
-        }
+        let mut app_config = self.app_config.clone();
+        app_config.add_openid_scope();
+        serializer.set_scope(app_config.scope);
 
         serializer
             .client_id(client_id.as_str())
@@ -355,6 +354,16 @@ impl AuthorizationUrl for OpenIdAuthorizationUrlParameters {
         }
 
         if !self.prompt.is_empty() {
+            if self.prompt.contains(&Prompt::AdminConsent)
+                && !self.response_type.is_empty()
+                && !self.response_type.contains(&ResponseType::Code)
+            {
+                return AF::msg_result(
+                    "prompt",
+                    "Prompt::AdminConsent requires ResponseType::Code",
+                );
+            }
+
             serializer.prompt(&self.prompt.as_query());
         }
 
@@ -366,6 +375,10 @@ impl AuthorizationUrl for OpenIdAuthorizationUrlParameters {
             serializer.login_hint(login_hint.as_str());
         }
 
+        if let Some(claims) = self.claims.as_ref() {
+            serializer.claims(claims.as_str());
+        }
+
         let query = serializer.encode_query(
             vec![
                 AuthParameter::ResponseMode,
@@ -373,6 +386,7 @@ impl AuthorizationUrl for OpenIdAuthorizationUrlParameters {
                 AuthParameter::State,
                 AuthParameter::Prompt,
                 AuthParameter::LoginHint,
+                AuthParameter::Claims,
                 AuthParameter::DomainHint,
             ],
             vec![
@@ -405,7 +419,7 @@ impl WebViewAuth for OpenIdAuthorizationUrlParameters {
             .with_navigation_handler(move |uri| {
                 if let Ok(url) = Url::parse(uri.as_str()) {
                     let is_valid_host = validator.is_valid_uri(&url);
-                    let is_redirect = validator.is_redirect_host(&url);
+                    let is_redirect = validator.is_redirect_uri(&url);
 
                     if is_redirect {
                         proxy.send_event(UserEvents::ReachedRedirectUri(url))
@@ -415,6 +429,11 @@ impl WebViewAuth for OpenIdAuthorizationUrlParameters {
                         return true;
                     }
 
+                    if validator.is_redirect_host(&url) {
+                        tracing::debug!(target: "graph_rs_sdk::interactive_auth", "redirect uri does not match a configured redirect uri: {url}");
+                        return false;
+                    }
+
                     is_valid_host
                 } else {
                     tracing::debug!(target: "graph_rs_sdk::interactive_auth", "unable to navigate webview - url is none");
@@ -442,7 +461,7 @@ impl OpenIdAuthorizationUrlParameterBuilder {
     pub(crate) fn new_with_app_config(
         mut app_config: AppConfig,
     ) -> OpenIdAuthorizationUrlParameterBuilder {
-        app_config.scope.insert("openid".into());
+        app_config.add_openid_scope();
         OpenIdAuthorizationUrlParameterBuilder {
             credential: OpenIdAuthorizationUrlParameters::new_with_app_config(app_config),
         }
@@ -518,10 +537,23 @@ impl OpenIdAuthorizationUrlParameterBuilder {
         self
     }
 
+    /// Generate a secure random state. The state is returned by the authorization server
+    /// in the redirect and can be used to mitigate cross-site request forgery attacks.
+    /// Use [state](Self::state) or the state of the built parameters to get the generated
+    /// value for comparing with the state in the authorization response.
+    pub fn with_state_generated(&mut self) -> &mut Self {
+        self.credential.state = Some(secure_random_32());
+        self
+    }
+
+    pub fn state(&self) -> Option<&String> {
+        self.credential.state.as_ref()
+    }
+
     /// Takes an iterator of scopes to use in the request.
     /// Replaces current scopes if any were added previously.
     pub fn with_scope<T: ToString, I: IntoIterator<Item = T>>(&mut self, scope: I) -> &mut Self {
-        self.credential.app_config.scope = scope.into_iter().map(|s| s.to_string()).collect();
+        self.credential.app_config.with_scope(scope);
         self
     }
 
@@ -550,6 +582,17 @@ impl OpenIdAuthorizationUrlParameterBuilder {
         self
     }
 
+    /// Set the domain_hint to the verified domain of the tenant, such as `contoso.com`,
+    /// for a streamlined federated sign-in. Returns an error if the domain is not a
+    /// plausible DNS domain, for instance a tenant id or a url.
+    pub fn with_verified_domain_hint<T: AsRef<str>>(
+        &mut self,
+        domain: T,
+    ) -> IdentityResult<&mut Self> {
+        self.credential.domain_hint = Some(validate_domain_hint(domain)?);
+        Ok(self)
+    }
+
     /// Optional
     /// You can use this parameter to pre-fill the username and email address field of the
     /// sign-in page for the user, if you know the username ahead of time. Often, apps use
@@ -560,6 +603,13 @@ impl OpenIdAuthorizationUrlParameterBuilder {
         self
     }
 
+    /// The claims request parameter as raw JSON. Pass the JSON as is, it is url encoded
+    /// once when the url is built.
+    pub fn with_claims<T: AsRef<str>>(&mut self, claims: T) -> &mut Self {
+        self.credential.claims = Some(claims.as_ref().to_owned());
+        self
+    }
+
     #[cfg(feature = "interactive-auth")]
     pub fn with_interactive_auth(
         &self,
@@ -582,6 +632,11 @@ impl OpenIdAuthorizationUrlParameterBuilder {
         self.credential.url()
     }
 
+    /// See [OpenIdAuthorizationUrlParameters::url_with_session_store].
+    pub fn url_with_session_store(&self, store: &dyn AuthSessionStore) -> IdentityResult<Url> {
+        self.credential.url_with_session_store(store)
+    }
+
     pub fn as_credential(&self, authorization_code: impl AsRef<str>) -> OpenIdCredentialBuilder {
         OpenIdCredentialBuilder::new_with_auth_code(
             self.credential.app_config.clone(),
@@ -644,4 +699,22 @@ mod test {
             client_id.to_string()
         );
     }
+
+    #[test]
+    fn claims_encoded_once() {
+        let claims = r#"{"id_token":{"auth_time":{"essential":true}}}"#;
+        let url = OpenIdAuthorizationUrlParameters::builder(Uuid::new_v4())
+            .with_response_type([ResponseType::Code])
+            .with_scope(["user.read"])
+            .with_claims(claims)
+            .url()
+            .unwrap();
+
+        assert!(!url.query().unwrap().contains("%25"));
+        let claims_value = url
+            .query_pairs()
+            .find(|(key, _)| key == "claims")
+            .map(|(_, value)| value.into_owned());
+        assert_eq!(Some(claims.to_owned()), claims_value);
+    }
 }
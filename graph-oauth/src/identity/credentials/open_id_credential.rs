@@ -5,13 +5,13 @@ use async_trait::async_trait;
 use graph_core::cache::{CacheStore, InMemoryCacheStore, TokenCache};
 use http::{HeaderMap, HeaderName, HeaderValue};
 
-use reqwest::IntoUrl;
 use url::Url;
 use uuid::Uuid;
+use zeroize::Zeroize;
 
 use graph_core::{
     crypto::{GenPkce, ProofKeyCodeExchange},
-    http::{AsyncResponseConverterExt, ResponseConverterExt},
+    http::ResponseConverterExt,
     identity::ForceTokenRefresh,
 };
 
@@ -19,9 +19,10 @@ use graph_error::{AuthExecutionError, AuthExecutionResult, IdentityResult, AF};
 
 use crate::identity::credentials::app_config::{AppConfig, AppConfigBuilder};
 use crate::identity::{
-    Authority, AuthorizationResponse, AzureCloudInstance, ConfidentialClientApplication, IdToken,
-    OpenIdAuthorizationUrlParameterBuilder, OpenIdAuthorizationUrlParameters, Token,
-    TokenCredentialExecutor,
+    read_json_limited, AuthSession, Authority, AuthorizationResponse, AzureCloudInstance,
+    ConfidentialClientApplication, IdToken, IntoRedirectUri,
+    OpenIdAuthorizationUrlParameterBuilder, OpenIdAuthorizationUrlParameters,
+    RefreshTokenCredential, Token, TokenCredentialExecutor,
 };
 use crate::internal::{AuthParameter, AuthSerializer};
 
@@ -75,17 +76,25 @@ impl Debug for OpenIdCredential {
     }
 }
 
+impl Drop for OpenIdCredential {
+    fn drop(&mut self) {
+        self.authorization_code.zeroize();
+        self.refresh_token.zeroize();
+        self.client_secret.zeroize();
+        self.code_verifier.zeroize();
+    }
+}
+
 impl OpenIdCredential {
-    pub fn new<T: AsRef<str>, U: IntoUrl>(
+    pub fn new<T: AsRef<str>, U: IntoRedirectUri>(
         client_id: T,
         client_secret: T,
         authorization_code: T,
         redirect_uri: U,
     ) -> IdentityResult<OpenIdCredential> {
-        let redirect_uri_result = Url::parse(redirect_uri.as_str());
         Ok(OpenIdCredential {
             app_config: AppConfigBuilder::new(client_id.as_ref())
-                .redirect_uri(redirect_uri.into_url().or(redirect_uri_result)?)
+                .redirect_uri(redirect_uri.into_redirect_uri()?)
                 .scope(vec!["openid"])
                 .build(),
             authorization_code: Some(authorization_code.as_ref().to_owned()),
@@ -128,8 +137,8 @@ impl OpenIdCredential {
             ));
         }
 
-        let new_token: Token = response.json()?;
-        self.token_cache.store(cache_id, new_token.clone());
+        let new_token: Token = read_json_limited(response, self.max_response_body_size())?;
+        let new_token = self.store_token(cache_id.as_str(), new_token);
 
         if new_token.refresh_token.is_some() {
             self.refresh_token = new_token.refresh_token.clone();
@@ -138,29 +147,30 @@ impl OpenIdCredential {
         Ok(new_token)
     }
 
-    async fn execute_cached_token_refresh_async(
-        &mut self,
-        cache_id: String,
-    ) -> AuthExecutionResult<Token> {
-        let response = self.execute_async().await?;
-
-        if !response.status().is_success() {
-            return Err(AuthExecutionError::silent_token_auth(
-                response.into_http_response_async().await?,
-            ));
-        }
-
-        let new_token: Token = response.json().await?;
+    async fn execute_cached_token_refresh_async(&mut self) -> AuthExecutionResult<Token> {
+        let new_token = self.acquire_token_async().await?;
 
         if new_token.refresh_token.is_some() {
             self.refresh_token = new_token.refresh_token.clone();
         }
 
-        self.token_cache.store(cache_id, new_token.clone());
         Ok(new_token)
     }
 }
 
+token_cache_snapshot!(OpenIdCredential);
+
+impl RefreshTokenCredential for OpenIdCredential {
+    fn refresh_token(&self) -> Option<&str> {
+        self.refresh_token.as_deref()
+    }
+
+    fn set_refresh_token(&mut self, refresh_token: String) {
+        self.authorization_code = None;
+        self.refresh_token = Some(refresh_token);
+    }
+}
+
 #[async_trait]
 impl TokenCache for OpenIdCredential {
     type Token = Token;
@@ -178,8 +188,8 @@ impl TokenCache for OpenIdCredential {
                     }
                 }
 
-                if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-                    if token.is_expired_sub(time::Duration::minutes(5)) {
+                if let Some(token) = self.cached_token(cache_id.as_str()) {
+                    if self.is_token_expired(&token) {
                         if let Some(refresh_token) = token.refresh_token.as_ref() {
                             self.refresh_token = Some(refresh_token.to_owned());
                         }
@@ -210,30 +220,27 @@ impl TokenCache for OpenIdCredential {
                 // Attempt to bypass a read on the token store by using previous
                 // refresh token stored outside of RwLock
                 if self.refresh_token.is_some() {
-                    if let Ok(token) = self
-                        .execute_cached_token_refresh_async(cache_id.clone())
-                        .await
-                    {
+                    if let Ok(token) = self.execute_cached_token_refresh_async().await {
                         return Ok(token);
                     }
                 }
 
-                if let Some(old_token) = self.token_cache.get(cache_id.as_str()) {
-                    if old_token.is_expired_sub(time::Duration::minutes(5)) {
+                if let Some(old_token) = self.cached_token(cache_id.as_str()) {
+                    if self.is_token_expired(&old_token) {
                         if let Some(refresh_token) = old_token.refresh_token.as_ref() {
                             self.refresh_token = Some(refresh_token.to_owned());
                         }
 
-                        self.execute_cached_token_refresh_async(cache_id).await
+                        self.execute_cached_token_refresh_async().await
                     } else {
                         Ok(old_token.clone())
                     }
                 } else {
-                    self.execute_cached_token_refresh_async(cache_id).await
+                    self.execute_cached_token_refresh_async().await
                 }
             }
             ForceTokenRefresh::Once | ForceTokenRefresh::Always => {
-                let token_result = self.execute_cached_token_refresh_async(cache_id).await;
+                let token_result = self.execute_cached_token_refresh_async().await;
                 if self.app_config.force_token_refresh == ForceTokenRefresh::Once {
                     self.app_config.force_token_refresh = ForceTokenRefresh::Never;
                 }
@@ -290,6 +297,10 @@ impl TokenCredentialExecutor for OpenIdCredential {
                 );
             }
 
+            if let Some(code_verifier) = self.code_verifier.as_ref() {
+                ProofKeyCodeExchange::validate_code_verifier(code_verifier)?;
+            }
+
             if let Some(redirect_uri) = self.app_config.redirect_uri.as_ref() {
                 self.serializer.redirect_uri(redirect_uri.as_str());
             }
@@ -349,6 +360,18 @@ impl TokenCredentialExecutor for OpenIdCredential {
     fn app_config(&self) -> &AppConfig {
         &self.app_config
     }
+
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        Some(&self.token_cache)
+    }
+
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        Some(&mut self.token_cache)
+    }
+
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        Some(&mut self.app_config)
+    }
 }
 
 #[derive(Clone)]
@@ -384,7 +407,7 @@ impl OpenIdCredentialBuilder {
     }
 
     fn new_with_app_config(mut app_config: AppConfig) -> OpenIdCredentialBuilder {
-        app_config.scope.insert("openid".to_string());
+        app_config.add_openid_scope();
         Self {
             credential: OpenIdCredential {
                 app_config,
@@ -403,7 +426,7 @@ impl OpenIdCredentialBuilder {
         mut app_config: AppConfig,
         authorization_code: impl AsRef<str>,
     ) -> OpenIdCredentialBuilder {
-        app_config.scope.insert("openid".to_string());
+        app_config.add_openid_scope();
         OpenIdCredentialBuilder {
             credential: OpenIdCredential {
                 app_config,
@@ -423,7 +446,7 @@ impl OpenIdCredentialBuilder {
         client_secret: impl AsRef<str>,
         mut app_config: AppConfig,
     ) -> OpenIdCredentialBuilder {
-        app_config.scope.insert("openid".to_string());
+        app_config.add_openid_scope();
         OpenIdCredentialBuilder {
             credential: OpenIdCredential {
                 app_config,
@@ -497,6 +520,15 @@ impl OpenIdCredentialBuilder {
         Ok(self)
     }
 
+    /// Set the code verifier, if any, of the [AuthSession] taken for the authorization response
+    /// with [AuthSessionStore::take_for_response](crate::identity::AuthSessionStore::take_for_response).
+    pub fn with_auth_session(&mut self, auth_session: &AuthSession) -> &mut Self {
+        if let Some(code_verifier) = auth_session.code_verifier.as_ref() {
+            self.with_code_verifier(code_verifier);
+        }
+        self
+    }
+
     pub fn credential(&self) -> &OpenIdCredential {
         &self.credential
     }
@@ -12,6 +12,8 @@ use crate::identity::credentials::as_query::AsQuery;
 ///     grant permissions to the app.
 /// - **prompt=select_account** interrupts single sign-on providing account selection experience
 ///     listing all the accounts either in session or any remembered account or an option to choose to use a different account altogether.
+/// - **prompt=admin_consent** shows the admin consent experience after the administrator signs in,
+///     granting consent on behalf of the whole organization. Requires the code response type.
 #[derive(Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum Prompt {
     #[default]
@@ -26,16 +28,25 @@ pub enum Prompt {
     /// Use only for federated users. Provides same functionality as prompt=none
     /// for managed users.
     AttemptNone,
+    /// The administrator will be prompted to sign in and then grant consent on behalf
+    /// of all users in the organization. Can be combined with [Prompt::Login] to force
+    /// the administrator to enter their credentials. Only supported with [ResponseType::Code](crate::identity::ResponseType::Code).
+    AdminConsent,
+    /// A prompt value that is sent verbatim, for values supported by the Microsoft
+    /// identity platform that do not have a variant yet.
+    Other(String),
 }
 
 impl AsRef<str> for Prompt {
-    fn as_ref(&self) -> &'static str {
+    fn as_ref(&self) -> &str {
         match self {
             Prompt::None => "none",
             Prompt::Login => "login",
             Prompt::Consent => "consent",
             Prompt::SelectAccount => "select_account",
             Prompt::AttemptNone => "attempt_none",
+            Prompt::AdminConsent => "admin_consent",
+            Prompt::Other(prompt) => prompt.as_str(),
         }
     }
 }
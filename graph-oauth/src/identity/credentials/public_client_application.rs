@@ -1,13 +1,14 @@
 use crate::identity::credentials::app_config::AppConfig;
 use crate::identity::credentials::application_builder::PublicClientApplicationBuilder;
 use crate::identity::{
-    Authority, AzureCloudInstance, DeviceCodeCredential, ResourceOwnerPasswordCredential,
-    TokenCredentialExecutor,
+    AccountInfo, Authority, AzureCloudInstance, CacheSnapshot, CancellationToken,
+    CredentialWarning, DeviceCodeCredential, RefreshTokenCredential,
+    ResourceOwnerPasswordCredential, Token, TokenCacheSnapshot, TokenCredentialExecutor, UserInfo,
 };
 use async_trait::async_trait;
-use graph_core::cache::{AsBearer, TokenCache};
-use graph_core::identity::{ClientApplication, ForceTokenRefresh};
-use graph_error::{AuthExecutionResult, IdentityResult};
+use graph_core::cache::{AsBearer, InMemoryCacheStore, TokenCache};
+use graph_core::identity::{BearerToken, ClientApplication, ForceTokenRefresh};
+use graph_error::{AuthExecutionResult, IdentityResult, AF};
 use reqwest::Response;
 use std::collections::HashMap;
 use std::fmt::Debug;
@@ -41,20 +42,131 @@ impl<Credential: Clone + Debug + Send + Sync + TokenCredentialExecutor>
     pub(crate) fn credential(credential: Credential) -> PublicClientApplication<Credential> {
         PublicClientApplication { credential }
     }
+
+    /// Fetch the claims of the signed-in user from the OpenID Connect userinfo endpoint
+    /// with the cached access token. The userinfo endpoint is read from the discovery
+    /// document of the authority.
+    ///
+    /// Returns an error if there is no cached access token that is not expired, for
+    /// instance before a token was acquired with the openid scope.
+    pub async fn userinfo_async(&mut self) -> AuthExecutionResult<UserInfo> {
+        let cache_id = self.credential.app_config().cache_id.clone();
+        let token = self
+            .credential
+            .cached_token(cache_id.as_str())
+            .filter(|token| !self.credential.is_token_expired(token))
+            .ok_or_else(|| {
+                AF::msg_err(
+                    "access_token",
+                    "no cached access token, acquire a token with the openid scope before calling the userinfo endpoint",
+                )
+            })?;
+
+        let app_config = self.credential.app_config();
+        let openid_configuration_url = app_config
+            .azure_cloud_instance
+            .openid_configuration_uri(&app_config.authority)?;
+        UserInfo::fetch_async(openid_configuration_url, token.access_token.as_str()).await
+    }
+}
+
+impl<
+        Credential: Clone + Debug + Send + Sync + TokenCredentialExecutor + RefreshTokenCredential,
+    > PublicClientApplication<Credential>
+{
+    /// Get a token without user interaction. Native apps renew tokens silently with the
+    /// refresh token rather than with `prompt=none` in a hidden iframe.
+    ///
+    /// The cached access token is returned unless it is expired or a refresh is forced.
+    /// Otherwise the refresh token of the cached token, or of the credential, is redeemed.
+    /// When there is no refresh token an error is returned, and a rejected refresh token
+    /// is returned as [InteractionRequired](graph_error::AuthExecutionError::InteractionRequired)
+    /// or a similar error. This method never starts an interactive sign-in such as the
+    /// device code flow, the app decides when to prompt the user.
+    pub async fn acquire_token_silent_async(&mut self) -> AuthExecutionResult<BearerToken> {
+        let cache_id = self.credential.app_config().cache_id.clone();
+        let force_token_refresh = self.credential.app_config().force_token_refresh.clone();
+        let cached_token = self.credential.cached_token(cache_id.as_str());
+
+        if force_token_refresh == ForceTokenRefresh::Never {
+            if let Some(token) = cached_token
+                .as_ref()
+                .filter(|token| !self.credential.is_token_expired(token))
+            {
+                return token.try_as_bearer().map(BearerToken::from);
+            }
+        }
+
+        let refresh_token = cached_token
+            .and_then(|token| token.refresh_token)
+            .or_else(|| self.credential.refresh_token().map(|s| s.to_owned()))
+            .ok_or_else(|| {
+                AF::msg_err(
+                    "refresh_token",
+                    "no valid cached access token or refresh token, sign in interactively",
+                )
+            })?;
+        self.credential.set_refresh_token(refresh_token);
+
+        if force_token_refresh == ForceTokenRefresh::Once {
+            if let Some(app_config) = self.credential.app_config_mut() {
+                app_config.force_token_refresh = ForceTokenRefresh::Never;
+            }
+        }
+
+        let token = self.credential.acquire_token_async().await?;
+        if let Some(refresh_token) = token.refresh_token.clone() {
+            self.credential.set_refresh_token(refresh_token);
+        }
+        token.try_as_bearer().map(BearerToken::from)
+    }
+}
+
+impl<Credential: Clone + Debug + Send + Sync + TokenCache> PublicClientApplication<Credential> {
+    /// Same as [get_token_silent_async](ClientApplication::get_token_silent_async) except that
+    /// the request can be aborted using the [CancellationToken], for instance when the
+    /// network changes and a refresh is stuck. A cancelled request leaves the token cache
+    /// unchanged and returns [AuthExecutionError::Cancelled](graph_error::AuthExecutionError::Cancelled).
+    pub async fn get_token_silent_async_with_cancellation(
+        &mut self,
+        cancellation_token: &CancellationToken,
+    ) -> AuthExecutionResult<BearerToken> {
+        let token = cancellation_token
+            .run_until_cancelled(self.credential.get_token_silent_async())
+            .await?;
+        token.try_as_bearer().map(BearerToken::from)
+    }
+}
+
+impl<Credential: Clone + Debug + Send + Sync + TokenCache<Token = Token>>
+    PublicClientApplication<Credential>
+{
+    /// Get a token from the cache, or from the token endpoint when there is no valid cached
+    /// token, together with the signed-in account read from the claims of its id token.
+    ///
+    /// Returns an error if the token does not include an id token, which requires the
+    /// openid scope.
+    pub async fn acquire_token_with_account_async(
+        &mut self,
+    ) -> AuthExecutionResult<(BearerToken, AccountInfo)> {
+        let token = self.credential.get_token_silent_async().await?;
+        let account_info = AccountInfo::try_from(&token)?;
+        Ok((BearerToken::from(token.try_as_bearer()?), account_info))
+    }
 }
 
 #[async_trait]
 impl<Credential: Clone + Debug + Send + Sync + TokenCache> ClientApplication
     for PublicClientApplication<Credential>
 {
-    fn get_token_silent(&mut self) -> AuthExecutionResult<String> {
+    fn get_token_silent(&mut self) -> AuthExecutionResult<BearerToken> {
         let token = self.credential.get_token_silent()?;
-        Ok(token.as_bearer())
+        token.try_as_bearer().map(BearerToken::from)
     }
 
-    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<String> {
+    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<BearerToken> {
         let token = self.credential.get_token_silent_async().await?;
-        Ok(token.as_bearer())
+        token.try_as_bearer().map(BearerToken::from)
     }
 
     fn with_force_token_refresh(&mut self, force_token_refresh: ForceTokenRefresh) {
@@ -63,6 +175,16 @@ impl<Credential: Clone + Debug + Send + Sync + TokenCache> ClientApplication
     }
 }
 
+impl<Credential: TokenCacheSnapshot> TokenCacheSnapshot for PublicClientApplication<Credential> {
+    fn export_cache(&self) -> CacheSnapshot {
+        self.credential.export_cache()
+    }
+
+    fn import_cache(&mut self, snapshot: CacheSnapshot) {
+        self.credential.import_cache(snapshot)
+    }
+}
+
 #[async_trait]
 impl<Credential: Clone + Debug + Send + Sync + TokenCredentialExecutor> TokenCredentialExecutor
     for PublicClientApplication<Credential>
@@ -91,6 +213,22 @@ impl<Credential: Clone + Debug + Send + Sync + TokenCredentialExecutor> TokenCre
         self.credential.app_config()
     }
 
+    fn warnings(&self) -> Vec<CredentialWarning> {
+        self.credential.warnings()
+    }
+
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        self.credential.token_cache()
+    }
+
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        self.credential.token_cache_mut()
+    }
+
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        self.credential.app_config_mut()
+    }
+
     fn execute(&mut self) -> AuthExecutionResult<reqwest::blocking::Response> {
         self.credential.execute()
     }
@@ -113,3 +251,39 @@ impl From<DeviceCodeCredential> for PublicClientApplication<DeviceCodeCredential
         PublicClientApplication::credential(value)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::MockCredential;
+
+    #[tokio::test]
+    async fn silent_token_refreshes_without_interaction() {
+        let mut credential = MockCredential::new();
+        credential.set_refresh_token("refresh_token".to_owned());
+        let mut public_client = PublicClientApplication::new(credential.clone());
+
+        // Cache miss, the refresh token is redeemed.
+        let bearer = public_client.acquire_token_silent_async().await.unwrap();
+        assert_eq!(BearerToken::from("access_token_1"), bearer);
+        assert_eq!(vec!["refresh_token".to_owned()], credential.grant_types());
+        assert_eq!(
+            Some("new_refresh_token"),
+            public_client.credential.refresh_token()
+        );
+
+        // Cache hit, no token request.
+        let bearer = public_client.acquire_token_silent_async().await.unwrap();
+        assert_eq!(BearerToken::from("access_token_1"), bearer);
+        assert_eq!(1, credential.requests());
+    }
+
+    #[tokio::test]
+    async fn silent_token_without_refresh_token_requires_interaction() {
+        let credential = MockCredential::new();
+        let mut public_client = PublicClientApplication::new(credential.clone());
+
+        assert!(public_client.acquire_token_silent_async().await.is_err());
+        assert_eq!(0, credential.requests());
+    }
+}
@@ -0,0 +1,12 @@
+/// A credential that can redeem a refresh token in place of the grant it was built
+/// with. Used by [PublicClientApplication::acquire_token_silent_async](crate::identity::PublicClientApplication::acquire_token_silent_async)
+/// to renew tokens without user interaction, and by
+/// [ConfidentialClientApplication::refresh_expiring_tokens_async](crate::identity::ConfidentialClientApplication::refresh_expiring_tokens_async)
+/// to refresh cached tokens near expiry.
+pub trait RefreshTokenCredential {
+    /// The refresh token redeemed by the next token request, if any.
+    fn refresh_token(&self) -> Option<&str>;
+
+    /// Redeem the refresh token with the next token request.
+    fn set_refresh_token(&mut self, refresh_token: String);
+}
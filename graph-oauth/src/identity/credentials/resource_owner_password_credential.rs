@@ -1,17 +1,18 @@
 use crate::identity::credentials::app_config::AppConfig;
 use crate::identity::{
-    tracing_targets::CREDENTIAL_EXECUTOR, Authority, AzureCloudInstance, Token,
+    read_json_limited, tracing_targets::CREDENTIAL_EXECUTOR, Authority, AzureCloudInstance, Token,
     TokenCredentialExecutor,
 };
 use crate::oauth_serializer::{AuthParameter, AuthSerializer};
 use async_trait::async_trait;
-use graph_core::cache::{CacheStore, InMemoryCacheStore, TokenCache};
-use graph_core::http::{AsyncResponseConverterExt, ResponseConverterExt};
+use graph_core::cache::{InMemoryCacheStore, TokenCache};
+use graph_core::http::ResponseConverterExt;
 use graph_core::identity::ForceTokenRefresh;
 use graph_error::{AuthExecutionError, AuthExecutionResult, IdentityResult, AF};
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use uuid::Uuid;
+use zeroize::Zeroize;
 
 /// Allows an application to sign in the user by directly handling their password.
 /// Not recommended. ROPC can also be done using a client secret or assertion,
@@ -36,12 +37,18 @@ pub struct ResourceOwnerPasswordCredential {
 
 impl Debug for ResourceOwnerPasswordCredential {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("ClientAssertionCredential")
+        f.debug_struct("ResourceOwnerPasswordCredential")
             .field("app_config", &self.app_config)
             .finish()
     }
 }
 
+impl Drop for ResourceOwnerPasswordCredential {
+    fn drop(&mut self) {
+        self.password.zeroize();
+    }
+}
+
 impl ResourceOwnerPasswordCredential {
     pub fn new(
         client_id: impl AsRef<str>,
@@ -87,37 +94,22 @@ impl ResourceOwnerPasswordCredential {
             ));
         }
 
-        let new_token: Token = response.json()?;
-        self.token_cache.store(cache_id, new_token.clone());
-        Ok(new_token)
-    }
-
-    async fn execute_cached_token_refresh_async(
-        &mut self,
-        cache_id: String,
-    ) -> AuthExecutionResult<Token> {
-        let response = self.execute_async().await?;
-
-        if !response.status().is_success() {
-            return Err(AuthExecutionError::silent_token_auth(
-                response.into_http_response_async().await?,
-            ));
-        }
-
-        let new_token: Token = response.json().await?;
-        self.token_cache.store(cache_id, new_token.clone());
+        let new_token: Token = read_json_limited(response, self.max_response_body_size())?;
+        let new_token = self.store_token(cache_id.as_str(), new_token);
         Ok(new_token)
     }
 }
 
+token_cache_snapshot!(ResourceOwnerPasswordCredential);
+
 #[async_trait]
 impl TokenCache for ResourceOwnerPasswordCredential {
     type Token = Token;
 
     fn get_token_silent(&mut self) -> Result<Self::Token, AuthExecutionError> {
         let cache_id = self.app_config.cache_id.to_string();
-        if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-            if token.is_expired_sub(time::Duration::minutes(5)) {
+        if let Some(token) = self.cached_token(cache_id.as_str()) {
+            if self.is_token_expired(&token) {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=None");
                 self.execute_cached_token_refresh(cache_id)
             } else {
@@ -132,17 +124,17 @@ impl TokenCache for ResourceOwnerPasswordCredential {
 
     async fn get_token_silent_async(&mut self) -> Result<Self::Token, AuthExecutionError> {
         let cache_id = self.app_config.cache_id.to_string();
-        if let Some(token) = self.token_cache.get(cache_id.as_str()) {
-            if token.is_expired_sub(time::Duration::minutes(5)) {
+        if let Some(token) = self.cached_token(cache_id.as_str()) {
+            if self.is_token_expired(&token) {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=None");
-                self.execute_cached_token_refresh_async(cache_id).await
+                self.acquire_token_async().await
             } else {
                 tracing::debug!(target: CREDENTIAL_EXECUTOR, "using token from cache");
                 Ok(token.clone())
             }
         } else {
             tracing::debug!(target: CREDENTIAL_EXECUTOR, "executing silent token request; refresh_token=None");
-            self.execute_cached_token_refresh_async(cache_id).await
+            self.acquire_token_async().await
         }
     }
 
@@ -198,6 +190,18 @@ impl TokenCredentialExecutor for ResourceOwnerPasswordCredential {
     fn app_config(&self) -> &AppConfig {
         &self.app_config
     }
+
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        Some(&self.token_cache)
+    }
+
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        Some(&mut self.token_cache)
+    }
+
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        Some(&mut self.app_config)
+    }
 }
 
 #[derive(Clone)]
@@ -257,7 +261,7 @@ impl ResourceOwnerPasswordCredentialBuilder {
     }
 
     pub fn with_scope<T: ToString, I: IntoIterator<Item = T>>(&mut self, scope: I) -> &mut Self {
-        self.credential.app_config.scope = scope.into_iter().map(|s| s.to_string()).collect();
+        self.credential.app_config.with_scope(scope);
         self
     }
 
@@ -9,6 +9,9 @@ pub enum ResponseType {
     Code,
     IdToken,
     Token,
+    /// No code or token is returned, for flows that only record consent.
+    /// Cannot be combined with other response types.
+    None,
     StringSet(BTreeSet<String>),
 }
 
@@ -18,6 +21,7 @@ impl Display for ResponseType {
             ResponseType::Code => "code".to_owned(),
             ResponseType::IdToken => "id_token".to_owned(),
             ResponseType::Token => "token".to_owned(),
+            ResponseType::None => "none".to_owned(),
             ResponseType::StringSet(response_type_vec) => response_type_vec.iter().as_query(),
         };
         write!(f, "{}", str)
@@ -0,0 +1,125 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use graph_core::cache::TokenCache;
+use graph_core::identity::{BearerToken, ClientApplication, ForceTokenRefresh};
+use graph_error::AuthExecutionResult;
+use tokio::sync::MutexGuard;
+
+use crate::identity::{ConfidentialClientApplication, TokenCredentialExecutor};
+
+/// A [ConfidentialClientApplication] that can be cloned cheaply and used from many tasks
+/// at once. Clones share the same client and token cache.
+///
+/// Token requests take `&mut self` on the client, so the client is kept behind an async
+/// mutex and concurrent token requests wait for each other. A task that waits for the
+/// mutex while another task acquires a token gets the token from the cache instead of
+/// sending a second token request.
+///
+/// The blocking [get_token_silent](ClientApplication::get_token_silent) must not be called
+/// from within an async runtime.
+///
+/// ```rust,ignore
+/// let shared_client = SharedConfidentialClient::new(confidential_client);
+/// let client = GraphClient::from_client_app(shared_client.clone());
+/// ```
+#[derive(Clone, Debug)]
+pub struct SharedConfidentialClient<Credential> {
+    client: Arc<tokio::sync::Mutex<ConfidentialClientApplication<Credential>>>,
+    force_token_refresh: Arc<Mutex<Option<ForceTokenRefresh>>>,
+}
+
+impl<Credential: Clone + Debug + Send + Sync + TokenCache + TokenCredentialExecutor>
+    SharedConfidentialClient<Credential>
+{
+    pub fn new(
+        client: ConfidentialClientApplication<Credential>,
+    ) -> SharedConfidentialClient<Credential> {
+        SharedConfidentialClient {
+            client: Arc::new(tokio::sync::Mutex::new(client)),
+            force_token_refresh: Default::default(),
+        }
+    }
+
+    /// Lock the client for calls that are not on [ClientApplication], for instance to
+    /// read the token cache. Token requests of other clones wait until the guard is dropped.
+    pub async fn lock(&self) -> MutexGuard<'_, ConfidentialClientApplication<Credential>> {
+        let mut client = self.client.lock().await;
+        self.apply_force_token_refresh(&mut client);
+        client
+    }
+
+    /// A force token refresh is recorded and applied the next time the client is locked
+    /// so that it can be set without waiting for a token request of another clone.
+    fn apply_force_token_refresh(&self, client: &mut ConfidentialClientApplication<Credential>) {
+        if let Some(force_token_refresh) = self
+            .force_token_refresh
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+        {
+            client.with_force_token_refresh(force_token_refresh);
+        }
+    }
+}
+
+impl<Credential: Clone + Debug + Send + Sync + TokenCache + TokenCredentialExecutor>
+    From<ConfidentialClientApplication<Credential>> for SharedConfidentialClient<Credential>
+{
+    fn from(value: ConfidentialClientApplication<Credential>) -> Self {
+        SharedConfidentialClient::new(value)
+    }
+}
+
+#[async_trait]
+impl<Credential: Clone + Debug + Send + Sync + TokenCache + TokenCredentialExecutor>
+    ClientApplication for SharedConfidentialClient<Credential>
+{
+    fn get_token_silent(&mut self) -> AuthExecutionResult<BearerToken> {
+        let mut client = self.client.blocking_lock();
+        self.apply_force_token_refresh(&mut client);
+        client.get_token_silent()
+    }
+
+    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<BearerToken> {
+        let mut client = self.lock().await;
+        client.get_token_silent_async().await
+    }
+
+    fn with_force_token_refresh(&mut self, force_token_refresh: ForceTokenRefresh) {
+        self.force_token_refresh
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .replace(force_token_refresh);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::MockCredential;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn concurrent_tasks_share_one_token_request() {
+        let mut credential = MockCredential::new();
+        // Slow token endpoint so that the other tasks wait for the lock.
+        credential.delay = Some(Duration::from_millis(50));
+        let shared_client =
+            SharedConfidentialClient::new(ConfidentialClientApplication::new(credential.clone()));
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let mut shared_client = shared_client.clone();
+                tokio::spawn(async move { shared_client.get_token_silent_async().await })
+            })
+            .collect();
+
+        for task in tasks {
+            let bearer = task.await.unwrap().unwrap();
+            assert_eq!(BearerToken::from("access_token_1"), bearer);
+        }
+        assert_eq!(1, credential.requests());
+    }
+}
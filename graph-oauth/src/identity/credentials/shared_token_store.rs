@@ -0,0 +1,50 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use crate::identity::CachedToken;
+
+/// A token store that is shared between processes or application runs, for instance
+/// a file or Redis.
+///
+/// Credentials with a shared token store read the store before their in memory token
+/// cache, so a newly constructed credential uses a valid token from the store without
+/// a token request. Tokens received from the token endpoint are saved to the store.
+///
+/// The stored tokens include refresh tokens. Protect the store the same way as a
+/// client secret.
+pub trait SharedTokenStore: Send + Sync {
+    /// The token stored for the cache id, if any.
+    fn load(&self, cache_id: &str) -> Option<CachedToken>;
+
+    /// Store the token for the cache id, replacing any existing token.
+    fn save(&self, cache_id: &str, token: CachedToken);
+}
+
+#[derive(Clone)]
+pub(crate) struct SharedTokenStoreRef(Arc<dyn SharedTokenStore>);
+
+impl SharedTokenStoreRef {
+    pub(crate) fn new(store: impl SharedTokenStore + 'static) -> SharedTokenStoreRef {
+        SharedTokenStoreRef(Arc::new(store))
+    }
+}
+
+impl std::ops::Deref for SharedTokenStoreRef {
+    type Target = dyn SharedTokenStore;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl Debug for SharedTokenStoreRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SharedTokenStore").finish()
+    }
+}
+
+impl PartialEq for SharedTokenStoreRef {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use graph_core::cache::AsBearer;
+use graph_core::http::ResponseConverterExt;
+use graph_core::identity::{BearerToken, ClientApplication, ForceTokenRefresh};
+use graph_error::{AuthExecutionError, AuthExecutionResult};
+
+use crate::identity::{read_json_limited, Token, TokenCredentialExecutor};
+
+/// Adapts any [TokenCredentialExecutor] to the [ClientApplication] trait that the
+/// Graph client uses to get bearer tokens, so that every credential, including custom
+/// credentials, can be passed to the client the same way.
+///
+/// The cached token of the credential is returned until it expires within five minutes,
+/// after which a new token is requested and stored in the token cache of the credential.
+/// Credentials without a token cache request a new token on every call.
+///
+/// ```rust,ignore
+/// let adapter = TokenCredentialAdapter::new(credential);
+/// let client = GraphClient::from_client_app(adapter);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TokenCredentialAdapter<Credential> {
+    credential: Credential,
+    force_token_refresh: ForceTokenRefresh,
+}
+
+impl<Credential: TokenCredentialExecutor> TokenCredentialAdapter<Credential> {
+    pub fn new(credential: Credential) -> TokenCredentialAdapter<Credential> {
+        TokenCredentialAdapter {
+            credential,
+            force_token_refresh: ForceTokenRefresh::Never,
+        }
+    }
+
+    pub fn credential(&self) -> &Credential {
+        &self.credential
+    }
+
+    pub fn into_credential(self) -> Credential {
+        self.credential
+    }
+
+    /// The cached token, unless it is expired or a refresh is forced.
+    fn valid_cached_token(&mut self) -> Option<Token> {
+        match self.force_token_refresh {
+            ForceTokenRefresh::Never => {
+                let cache_id = self.credential.app_config().cache_id.clone();
+                let token = self.credential.cached_token(cache_id.as_str())?;
+                if self.credential.is_token_expired(&token) {
+                    None
+                } else {
+                    Some(token)
+                }
+            }
+            ForceTokenRefresh::Once => {
+                self.force_token_refresh = ForceTokenRefresh::Never;
+                None
+            }
+            ForceTokenRefresh::Always => None,
+        }
+    }
+}
+
+#[async_trait]
+impl<Credential: Clone + Debug + Send + Sync + TokenCredentialExecutor> ClientApplication
+    for TokenCredentialAdapter<Credential>
+{
+    fn get_token_silent(&mut self) -> AuthExecutionResult<BearerToken> {
+        if let Some(token) = self.valid_cached_token() {
+            return token.try_as_bearer().map(BearerToken::from);
+        }
+
+        let response = self.credential.execute()?;
+        if !response.status().is_success() {
+            return Err(AuthExecutionError::silent_token_auth(
+                response.into_http_response()?,
+            ));
+        }
+
+        let token: Token = read_json_limited(response, self.credential.max_response_body_size())?;
+        let cache_id = self.credential.app_config().cache_id.clone();
+        let token = self.credential.store_token(cache_id.as_str(), token);
+        token.try_as_bearer().map(BearerToken::from)
+    }
+
+    async fn get_token_silent_async(&mut self) -> AuthExecutionResult<BearerToken> {
+        if let Some(token) = self.valid_cached_token() {
+            return token.try_as_bearer().map(BearerToken::from);
+        }
+
+        let token = self.credential.acquire_token_async().await?;
+        token.try_as_bearer().map(BearerToken::from)
+    }
+
+    fn with_force_token_refresh(&mut self, force_token_refresh: ForceTokenRefresh) {
+        self.force_token_refresh = force_token_refresh;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::{AppConfig, ClockRef, MockCredential, TestClock};
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn adapter_refreshes_expired_token() {
+        let clock = TestClock::default();
+        let mut app_config = AppConfig::new(Uuid::new_v4());
+        app_config.clock = ClockRef::new(clock.clone());
+        let credential = MockCredential::with_app_config(app_config);
+        let mut adapter = TokenCredentialAdapter::new(credential.clone());
+
+        let bearer = adapter.get_token_silent_async().await.unwrap();
+        assert_eq!(BearerToken::from("access_token_1"), bearer);
+        let bearer = adapter.get_token_silent_async().await.unwrap();
+        assert_eq!(BearerToken::from("access_token_1"), bearer);
+        assert_eq!(1, credential.requests());
+
+        clock.advance(time::Duration::minutes(56));
+        let bearer = adapter.get_token_silent_async().await.unwrap();
+        assert_eq!(BearerToken::from("access_token_2"), bearer);
+        assert_eq!(2, credential.requests());
+
+        adapter.with_force_token_refresh(ForceTokenRefresh::Once);
+        let bearer = adapter.get_token_silent_async().await.unwrap();
+        assert_eq!(BearerToken::from("access_token_3"), bearer);
+        let bearer = adapter.get_token_silent_async().await.unwrap();
+        assert_eq!(BearerToken::from("access_token_3"), bearer);
+    }
+}
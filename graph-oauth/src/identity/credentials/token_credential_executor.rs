@@ -1,49 +1,164 @@
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::io::Read;
 
 use async_trait::async_trait;
 use dyn_clone::DynClone;
 
 use reqwest::header::HeaderMap;
 use reqwest::tls::Version;
+use serde::de::DeserializeOwned;
 use url::{ParseError, Url};
 use uuid::Uuid;
 
-use graph_error::{AuthExecutionResult, IdentityResult};
+use graph_core::cache::{CacheStore, InMemoryCacheStore};
+use graph_core::http::AsyncResponseConverterExt;
+use graph_error::{AuthExecutionError, AuthExecutionResult, IdentityResult, AF};
 
 use crate::identity::credentials::app_config::AppConfig;
 use crate::identity::{
     tracing_targets::CREDENTIAL_EXECUTOR, Authority, AuthorizationRequestParts, AzureCloudInstance,
+    CachedToken, CredentialWarning, PreparedTokenRequest, Token, TokenAuditRecord,
+    CLIENT_REQUEST_ID_HEADER,
 };
 
+/// Default maximum size of a token response body, 256 KB. Token responses are a few
+/// KB at most so a larger body means a misbehaving or malicious token endpoint.
+pub const DEFAULT_MAX_RESPONSE_BODY_SIZE: usize = 256 * 1024;
+
+/// Read at most `max_size` bytes of the response body and parse it as json. Returns
+/// [AuthExecutionError::ResponseTooLarge] if the body is larger.
+pub(crate) fn read_json_limited<T: DeserializeOwned>(
+    response: reqwest::blocking::Response,
+    max_size: usize,
+) -> AuthExecutionResult<T> {
+    if response
+        .content_length()
+        .is_some_and(|content_length| content_length > max_size as u64)
+    {
+        return Err(AuthExecutionError::ResponseTooLarge { max_size });
+    }
+
+    let mut body = Vec::new();
+    response
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|err| AF::msg_err("response_body".to_owned(), err.to_string()))?;
+    if body.len() > max_size {
+        return Err(AuthExecutionError::ResponseTooLarge { max_size });
+    }
+
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// Same as [read_json_limited] for async responses.
+pub(crate) async fn read_json_limited_async<T: DeserializeOwned>(
+    mut response: reqwest::Response,
+    max_size: usize,
+) -> AuthExecutionResult<T> {
+    if response
+        .content_length()
+        .is_some_and(|content_length| content_length > max_size as u64)
+    {
+        return Err(AuthExecutionError::ResponseTooLarge { max_size });
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_size {
+            return Err(AuthExecutionError::ResponseTooLarge { max_size });
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(serde_json::from_slice(&body)?)
+}
+
 dyn_clone::clone_trait_object!(TokenCredentialExecutor);
 
+/// Tokens that expire within this duration are treated as expired and refreshed.
+const TOKEN_REFRESH_SKEW: time::Duration = time::Duration::minutes(5);
+
 #[async_trait]
 pub trait TokenCredentialExecutor: DynClone + Debug {
     fn uri(&mut self) -> IdentityResult<Url> {
         Ok(self.azure_cloud_instance().token_uri(&self.authority())?)
     }
 
+    /// The form of the token request. Implementations build a new [AuthSerializer] on
+    /// every call so that a credential reused for different grants, for instance a refresh
+    /// token after an authorization code, does not send parameters of the previous grant.
+    ///
+    /// [AuthSerializer]: crate::extensions::AuthSerializer
     fn form_urlencode(&mut self) -> IdentityResult<HashMap<String, String>>;
 
     fn request_parts(&mut self) -> IdentityResult<AuthorizationRequestParts> {
-        let uri = self.uri()?;
-        let form = self.form_urlencode()?;
+        let mut uri = self.uri()?;
+        if let Some(token_url_rewriter) = self.app_config().token_url_rewriter.as_ref() {
+            uri = token_url_rewriter.rewrite(uri);
+        }
+        self.app_config().validate_registered_permissions()?;
+        for warning in self.warnings() {
+            tracing::warn!(target: CREDENTIAL_EXECUTOR, "{warning}");
+        }
+
+        let mut form = self.form_urlencode()?;
+        form.extend(self.app_config().extra_form_parameters.clone());
+        // A response type belongs to the authorize step of a flow. Token requests never
+        // accept one, most often it was copied from the authorization url builder.
+        if form.contains_key("response_type")
+            || self.extra_query_parameters().contains_key("response_type")
+        {
+            return AF::msg_result(
+                "response_type",
+                "token requests do not accept a response type, set the response type on the authorization url builder instead",
+            );
+        }
+        // The override only replaces the primary grant of the credential. Refresh token
+        // requests keep their grant type so that tokens can still be refreshed.
+        if let Some(grant_type) = self.app_config().grant_type_override.as_ref() {
+            if form.get("grant_type").map(String::as_str) != Some("refresh_token") {
+                form.insert("grant_type".into(), grant_type.to_owned());
+            }
+        }
+        if let Some(app_config) = self.app_config_mut() {
+            if app_config.grant_type.as_ref() != form.get("grant_type") {
+                app_config.grant_type = form.get("grant_type").cloned();
+            }
+        }
+        self.authority()
+            .endpoint_version()
+            .convert_scope_parameter(&mut form)?;
         let basic_auth = self.basic_auth();
         let extra_headers = self.extra_header_parameters();
         let extra_query_params = self.extra_query_parameters();
 
+        let client_request_id = self
+            .app_config()
+            .client_request_id
+            .unwrap_or_else(Uuid::new_v4);
+
         let mut auth_request = AuthorizationRequestParts::new(uri, form, basic_auth);
+        auth_request.with_client_request_id(client_request_id);
         auth_request.with_extra_headers(extra_headers);
         auth_request.with_extra_query_parameters(extra_query_params);
 
         Ok(auth_request)
     }
 
+    /// Build the token request without sending it, for debugging or to send the request
+    /// with another HTTP client. The prepared request includes the secrets of the
+    /// credential, see [PreparedTokenRequest].
+    fn prepare_request(&mut self) -> IdentityResult<PreparedTokenRequest> {
+        Ok(PreparedTokenRequest::from(self.request_parts()?))
+    }
+
     fn build_request(&mut self) -> AuthExecutionResult<reqwest::blocking::RequestBuilder> {
-        let http_client = reqwest::blocking::ClientBuilder::new()
-            .min_tls_version(Version::TLS_1_2)
-            .https_only(true)
+        let http_client = self
+            .app_config()
+            .configure_blocking_http_client(
+                reqwest::blocking::ClientBuilder::new().min_tls_version(Version::TLS_1_2),
+            )
             .build()?;
 
         let auth_request = self.request_parts()?;
@@ -76,9 +191,9 @@ pub trait TokenCredentialExecutor: DynClone + Debug {
     }
 
     fn build_request_async(&mut self) -> AuthExecutionResult<reqwest::RequestBuilder> {
-        let http_client = reqwest::ClientBuilder::new()
-            .min_tls_version(Version::TLS_1_2)
-            .https_only(true)
+        let http_client = self
+            .app_config()
+            .configure_http_client(reqwest::ClientBuilder::new().min_tls_version(Version::TLS_1_2))
             .build()?;
 
         let auth_request = self.request_parts()?;
@@ -128,6 +243,154 @@ pub trait TokenCredentialExecutor: DynClone + Debug {
 
     fn app_config(&self) -> &AppConfig;
 
+    /// Non fatal problems with the configuration of the credential, such as deprecated
+    /// scopes. The warnings are logged at the warn level when a token request is built.
+    fn warnings(&self) -> Vec<CredentialWarning> {
+        CredentialWarning::for_scope(&self.app_config().scope)
+    }
+
+    /// The token cache of the credential. Returns None by default.
+    fn token_cache(&self) -> Option<&InMemoryCacheStore<Token>> {
+        None
+    }
+
+    /// The token cache that [acquire_token_async](Self::acquire_token_async) stores tokens in.
+    /// Returns None by default in which case tokens are not cached.
+    fn token_cache_mut(&mut self) -> Option<&mut InMemoryCacheStore<Token>> {
+        None
+    }
+
+    /// The cached token for the cache id. The [SharedTokenStore](crate::identity::SharedTokenStore)
+    /// of the credential, if any, is read before the in memory token cache so that a token
+    /// saved by another process or a previous run is used without a token request.
+    fn cached_token(&mut self, cache_id: &str) -> Option<Token> {
+        let mut token = None;
+        if let Some(shared_token_store) = self.app_config().shared_token_store.clone() {
+            if let Some(cached_token) = shared_token_store.load(cache_id) {
+                let cached_token = Token::from(cached_token);
+                if let Some(token_cache) = self.token_cache_mut() {
+                    token_cache.store(cache_id, cached_token.clone());
+                }
+                token = Some(cached_token);
+            }
+        }
+
+        if token.is_none() {
+            token = self.token_cache_mut()?.get(cache_id);
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(token) = token.as_ref() {
+            if !self.is_token_expired(token) {
+                crate::identity::record_token_cache_hit(crate::identity::grant_type_label(self));
+            }
+        }
+
+        token
+    }
+
+    /// Store the token in the in memory token cache and the
+    /// [SharedTokenStore](crate::identity::SharedTokenStore) of the credential, if any.
+    /// The token acquired hook of the credential, if any, is called before the token
+    /// is stored.
+    /// The timestamp of the token is reset to the current time of the
+    /// [Clock](crate::identity::Clock) of the credential and the stamped token is returned.
+    fn store_token(&mut self, cache_id: &str, mut token: Token) -> Token {
+        token.gen_timestamp_at(self.app_config().clock.now_utc());
+        if let Some(token_acquired_hook) = self.app_config().token_acquired_hook.as_ref() {
+            token_acquired_hook.call(&TokenAuditRecord::new(
+                self.app_config().client_id,
+                cache_id,
+                &token,
+            ));
+        }
+        if let Some(shared_token_store) = self.app_config().shared_token_store.clone() {
+            shared_token_store.save(cache_id, CachedToken::from(token.clone()));
+        }
+        if let Some(token_cache) = self.token_cache_mut() {
+            token_cache.store(cache_id, token.clone());
+        }
+        token
+    }
+
+    /// Whether the token expires within five minutes according to the
+    /// [Clock](crate::identity::Clock) of the credential. Tokens that expire within
+    /// five minutes are refreshed.
+    fn is_token_expired(&self, token: &Token) -> bool {
+        token.is_expired_sub_at(TOKEN_REFRESH_SKEW, self.app_config().clock.now_utc())
+    }
+
+    /// The remaining lifetime of the cached token before it is refreshed, that is the time
+    /// until five minutes before the token expires according to the
+    /// [Clock](crate::identity::Clock) of the credential. Returns None when there is no
+    /// cached token or the token is expired.
+    ///
+    /// Useful for scheduling a refresh ahead of the next request.
+    fn cached_token_ttl(&self) -> Option<std::time::Duration> {
+        let token = self
+            .token_cache()?
+            .get(self.app_config().cache_id.as_str())?;
+        let refresh_on = token.expires_on? - TOKEN_REFRESH_SKEW;
+        let ttl = refresh_on - self.app_config().clock.now_utc();
+        if ttl.is_positive() {
+            std::time::Duration::try_from(ttl).ok()
+        } else {
+            None
+        }
+    }
+
+    /// The scopes granted on the cached token for the cache id, which may differ from the
+    /// scopes that were requested. For incremental consent, a scope that is not included
+    /// requires the user to consent again. Returns None when there is no cached token
+    /// for the cache id.
+    fn cached_scopes_for(&self, cache_id: &str) -> Option<Vec<String>> {
+        self.token_cache()?.get(cache_id).map(|token| token.scope)
+    }
+
+    /// The scopes of the credential that were not granted on the cached token for the
+    /// cache id, see [Token::missing_scopes]. Returns None when there is no cached token
+    /// for the cache id.
+    fn missing_scopes_for(&self, cache_id: &str) -> Option<Vec<String>> {
+        let token = self.token_cache()?.get(cache_id)?;
+        Some(token.missing_scopes(&self.app_config().scope))
+    }
+
+    /// Mutable access to the [AppConfig]. Returns None by default in which case the
+    /// headers of token responses are not recorded.
+    fn app_config_mut(&mut self) -> Option<&mut AppConfig> {
+        None
+    }
+
+    /// The headers of the last response from the token endpoint, such as
+    /// `x-ms-ests-server`, `x-ms-request-id` or `Retry-After`, for diagnosing
+    /// failed or throttled token requests.
+    fn last_response_headers(&self) -> Option<&HeaderMap> {
+        self.app_config().last_response_headers.as_ref()
+    }
+
+    /// The `client-request-id` echoed by the token endpoint in the last response.
+    fn last_client_request_id(&self) -> Option<Uuid> {
+        let client_request_id = self
+            .last_response_headers()?
+            .get(CLIENT_REQUEST_ID_HEADER)?
+            .to_str()
+            .ok()?;
+        Uuid::parse_str(client_request_id).ok()
+    }
+
+    fn record_response_headers(&mut self, headers: &HeaderMap) {
+        if let Some(app_config) = self.app_config_mut() {
+            app_config.last_response_headers = Some(headers.clone());
+        }
+    }
+
+    /// Maximum number of bytes of a token response body that are read before parsing.
+    fn max_response_body_size(&self) -> usize {
+        self.app_config()
+            .max_response_body_size
+            .unwrap_or(DEFAULT_MAX_RESPONSE_BODY_SIZE)
+    }
+
     fn extra_header_parameters(&self) -> &HeaderMap {
         &self.app_config().extra_header_parameters
     }
@@ -141,18 +404,299 @@ pub trait TokenCredentialExecutor: DynClone + Debug {
     }
 
     fn execute(&mut self) -> AuthExecutionResult<reqwest::blocking::Response> {
+        if let Some(request_budget) = self.app_config().request_budget.clone() {
+            request_budget.acquire()?;
+        }
+
         let request_builder = self.build_request()?;
-        let response = request_builder.send()?;
+        #[cfg(feature = "metrics")]
+        let timer =
+            crate::identity::TokenRequestTimer::start(crate::identity::grant_type_label(self));
+        let response = request_builder.send();
+        #[cfg(feature = "metrics")]
+        timer.finish(response.as_ref().ok().map(|response| response.status()));
+        let response = response?;
+        self.record_response_headers(response.headers());
         let status = response.status();
         tracing::debug!(target: CREDENTIAL_EXECUTOR, "authorization response received; status={status:#?}");
         Ok(response)
     }
 
     async fn execute_async(&mut self) -> AuthExecutionResult<reqwest::Response> {
+        if let Some(request_budget) = self.app_config().request_budget.clone() {
+            request_budget.acquire_async().await?;
+        }
+
         let request_builder = self.build_request_async()?;
-        let response = request_builder.send().await?;
+        #[cfg(feature = "metrics")]
+        let timer =
+            crate::identity::TokenRequestTimer::start(crate::identity::grant_type_label(self));
+        let response = request_builder.send().await;
+        #[cfg(feature = "metrics")]
+        timer.finish(response.as_ref().ok().map(|response| response.status()));
+        let response = response?;
+        self.record_response_headers(response.headers());
         let status = response.status();
         tracing::debug!(target: CREDENTIAL_EXECUTOR, "authorization response received; status={status:#?}");
         Ok(response)
     }
+
+    /// Execute the token request, parse the [Token] from a successful response,
+    /// and store it in the token cache under the cache id of the [AppConfig].
+    ///
+    /// Error responses are returned as [AuthExecutionError::silent_token_auth].
+    async fn acquire_token_async(&mut self) -> AuthExecutionResult<Token> {
+        let response = self.execute_async().await?;
+
+        if !response.status().is_success() {
+            return Err(AuthExecutionError::silent_token_auth(
+                response.into_http_response_async().await?,
+            ));
+        }
+
+        let new_token: Token =
+            read_json_limited_async(response, self.max_response_body_size()).await?;
+        let cache_id = self.app_config().cache_id.clone();
+        let new_token = self.store_token(cache_id.as_str(), new_token);
+        Ok(new_token)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::{
+        MockCredential, TokenAcquiredHook, TokenUrlRewriter, RETURN_CLIENT_REQUEST_ID_HEADER,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn acquire_token_async_stores_token() {
+        let mut credential = MockCredential::new();
+        credential.granted_scope = Some("User.Read".into());
+
+        let token = credential.acquire_token_async().await.unwrap();
+        assert_eq!("access_token_1", token.access_token);
+        assert_eq!(vec!["User.Read".to_owned()], token.scope);
+
+        let cache_id = credential.app_config.cache_id.clone();
+        let cached_token = credential.token_cache.get(cache_id.as_str()).unwrap();
+        assert_eq!(token, cached_token);
+    }
+
+    #[tokio::test]
+    async fn cached_scopes_are_granted_scopes() {
+        let mut app_config = AppConfig::new(Uuid::new_v4());
+        app_config.with_scope(["User.Read", "Mail.Read"]);
+        let mut credential = MockCredential::with_app_config(app_config);
+        credential.granted_scope = Some("User.Read".into());
+        let cache_id = credential.app_config.cache_id.clone();
+        assert_eq!(None, credential.cached_scopes_for(cache_id.as_str()));
+
+        credential.acquire_token_async().await.unwrap();
+        assert_eq!(
+            Some(vec!["User.Read".to_owned()]),
+            credential.cached_scopes_for(cache_id.as_str())
+        );
+        assert_eq!(None, credential.cached_scopes_for("other"));
+        assert_eq!(
+            Some(vec!["Mail.Read".to_owned()]),
+            credential.missing_scopes_for(cache_id.as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn token_acquired_hook_receives_redacted_record() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let hook_records = records.clone();
+        let mut credential = MockCredential::new();
+        credential.granted_scope = Some("User.Read".into());
+        credential.app_config.token_acquired_hook =
+            Some(TokenAcquiredHook::new(move |record: &TokenAuditRecord| {
+                hook_records.lock().unwrap().push(record.clone());
+            }));
+
+        let token = credential.acquire_token_async().await.unwrap();
+        let records = records.lock().unwrap();
+        assert_eq!(1, records.len());
+        let record = &records[0];
+        assert_eq!(vec!["User.Read".to_owned()], record.scope);
+        assert_eq!(3600, record.expires_in);
+        assert!(record.expires_on.is_some());
+        assert_eq!(credential.app_config.cache_id, record.cache_id);
+        assert!(!serde_json::to_string(record)
+            .unwrap()
+            .contains(token.access_token.as_str()));
+    }
+
+    #[tokio::test]
+    async fn last_response_headers_after_token_request() {
+        let mut credential = MockCredential::new();
+        assert!(credential.last_response_headers().is_none());
+
+        credential.acquire_token_async().await.unwrap();
+        let headers = credential.last_response_headers().unwrap();
+        assert_eq!(
+            "2.1.17000.5 - WUS2 ProdSlices",
+            headers.get("x-ms-ests-server").unwrap().to_str().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn client_request_id_header() {
+        let mut credential = MockCredential::new();
+
+        let first = credential.request_parts().unwrap();
+        let second = credential.request_parts().unwrap();
+        assert_eq!(
+            "true",
+            first
+                .headers
+                .get(RETURN_CLIENT_REQUEST_ID_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap()
+        );
+        let first_id = first.headers.get(CLIENT_REQUEST_ID_HEADER).unwrap();
+        assert!(Uuid::parse_str(first_id.to_str().unwrap()).is_ok());
+        assert_ne!(
+            first_id,
+            second.headers.get(CLIENT_REQUEST_ID_HEADER).unwrap()
+        );
+
+        let client_request_id = Uuid::new_v4();
+        credential.app_config.client_request_id = Some(client_request_id);
+        let request_parts = credential.request_parts().unwrap();
+        assert_eq!(
+            client_request_id.to_string().as_str(),
+            request_parts
+                .headers
+                .get(CLIENT_REQUEST_ID_HEADER)
+                .unwrap()
+                .to_str()
+                .unwrap()
+        );
+
+        credential.acquire_token_async().await.unwrap();
+        assert_eq!(
+            Some(Uuid::parse_str("8d6b1c57-1c4e-4b6e-9f0a-5d2f3c1b7e42").unwrap()),
+            credential.last_client_request_id()
+        );
+    }
+
+    #[test]
+    fn http_client_local_address() {
+        let mut app_config = AppConfig::new(Uuid::new_v4());
+        let builder = app_config.configure_http_client(reqwest::ClientBuilder::new());
+        assert!(!format!("{builder:?}").contains("local_address"));
+
+        app_config.local_address = Some("10.1.2.3".parse().unwrap());
+        let builder = app_config.configure_http_client(reqwest::ClientBuilder::new());
+        assert!(format!("{builder:?}").contains("local_address: 10.1.2.3"));
+
+        let mut credential = MockCredential::with_app_config(app_config);
+        assert!(credential.build_request_async().is_ok());
+    }
+
+    #[test]
+    fn token_url_rewriter_changes_host() {
+        let mut credential = MockCredential::new();
+        assert_eq!(
+            Some("login.microsoftonline.com"),
+            credential.request_parts().unwrap().uri.host_str()
+        );
+
+        credential.app_config.token_url_rewriter = Some(TokenUrlRewriter::new(|mut uri| {
+            uri.set_host(Some("gateway.contoso.com")).unwrap();
+            uri
+        }));
+        let uri = credential.request_parts().unwrap().uri;
+        assert_eq!(Some("gateway.contoso.com"), uri.host_str());
+        assert_eq!("/common/oauth2/v2.0/token", uri.path());
+
+        let request = credential.build_request_async().unwrap().build().unwrap();
+        assert_eq!(Some("gateway.contoso.com"), request.url().host_str());
+    }
+
+    #[test]
+    fn scopes_must_be_registered_permissions() {
+        let mut app_config = AppConfig::new(Uuid::new_v4());
+        app_config.with_scope([
+            "openid",
+            "https://graph.microsoft.com/User.Read",
+            "Mail.Send",
+        ]);
+        let mut credential = MockCredential::with_app_config(app_config);
+        assert!(credential.app_config.unregistered_scopes().is_empty());
+
+        credential.app_config.registered_permissions =
+            Some(vec!["user.read".to_owned(), "Mail.Read".to_owned()]);
+        assert_eq!(
+            vec!["Mail.Send".to_owned()],
+            credential.app_config.unregistered_scopes()
+        );
+        assert!(credential.request_parts().is_err());
+
+        credential.app_config.registered_permissions =
+            Some(vec!["User.Read".to_owned(), "Mail.Send".to_owned()]);
+        assert!(credential.request_parts().is_ok());
+    }
+
+    #[tokio::test]
+    async fn token_response_larger_than_max_size() {
+        let mut credential = MockCredential::new();
+        credential.app_config.max_response_body_size = Some(64);
+
+        match credential.acquire_token_async().await {
+            Err(AuthExecutionError::ResponseTooLarge { max_size }) => assert_eq!(64, max_size),
+            result => panic!("expected response too large error: {result:#?}"),
+        }
+        let cache_id = credential.app_config.cache_id.clone();
+        assert!(credential.token_cache.get(cache_id.as_str()).is_none());
+    }
+
+    #[tokio::test]
+    async fn oversized_body_without_content_length() {
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let mock_server = MockServer::start().await;
+        let body = format!(
+            "{{\"access_token\":\"{}\"}}",
+            "a".repeat(DEFAULT_MAX_RESPONSE_BODY_SIZE)
+        );
+        // A chunked response does not have a content length so the body size is only
+        // known while reading it.
+        Mock::given(wiremock::matchers::any())
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("transfer-encoding", "chunked")
+                    .set_body_raw(body, "application/json"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let response = reqwest::get(mock_server.uri()).await.unwrap();
+        assert!(response.content_length().is_none());
+        match read_json_limited_async::<Token>(response, DEFAULT_MAX_RESPONSE_BODY_SIZE).await {
+            Err(AuthExecutionError::ResponseTooLarge { max_size }) => {
+                assert_eq!(DEFAULT_MAX_RESPONSE_BODY_SIZE, max_size)
+            }
+            result => panic!("expected response too large error: {result:#?}"),
+        }
+
+        let uri = mock_server.uri();
+        let result = tokio::task::spawn_blocking(move || {
+            let response = reqwest::blocking::get(uri).unwrap();
+            assert!(response.content_length().is_none());
+            read_json_limited::<Token>(response, DEFAULT_MAX_RESPONSE_BODY_SIZE)
+        })
+        .await
+        .unwrap();
+        match result {
+            Err(AuthExecutionError::ResponseTooLarge { max_size }) => {
+                assert_eq!(DEFAULT_MAX_RESPONSE_BODY_SIZE, max_size)
+            }
+            result => panic!("expected response too large error: {result:#?}"),
+        }
+    }
 }
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::path::Path;
 
 use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use base64::Engine;
@@ -27,10 +28,9 @@ fn encode_cert_ref(cert: &X509Ref) -> IdentityResult<String> {
     ))
 }
 
-#[allow(unused)]
-fn thumbprint(cert: &X509) -> IdentityResult<String> {
+fn thumbprint(cert: &X509, digest: MessageDigest) -> IdentityResult<String> {
     let digest_bytes = cert
-        .digest(MessageDigest::sha1())
+        .digest(digest)
         .map_err(|err| AF::x509(err.to_string()))?;
     Ok(URL_SAFE_NO_PAD.encode(digest_bytes))
 }
@@ -160,6 +160,63 @@ impl X509Certificate {
         })
     }
 
+    /// Load the certificate and private key from a file. Files with a `.pfx` or `.p12`
+    /// extension are read as PKCS #12 and files with a `.pem`, `.crt`, `.cer` or `.key`
+    /// extension as PEM containing both the certificate and the private key. For other
+    /// extensions the format is detected from the content of the file.
+    ///
+    /// The password decrypts the PKCS #12 file or an encrypted PEM private key.
+    pub fn from_path(
+        client_id: impl AsRef<str>,
+        path: impl AsRef<Path>,
+        password: Option<&str>,
+    ) -> IdentityResult<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|err| {
+            AF::msg_err(
+                "certificate_path".to_owned(),
+                format!("{}: {err}", path.display()),
+            )
+        })?;
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+        let is_pem = match extension.as_deref() {
+            Some("pfx") | Some("p12") => false,
+            Some("pem") | Some("crt") | Some("cer") | Some("key") => true,
+            _ => bytes
+                .iter()
+                .position(|byte| !byte.is_ascii_whitespace())
+                .is_some_and(|start| bytes[start..].starts_with(b"-----BEGIN")),
+        };
+
+        if is_pem {
+            let certificate = X509::from_pem(&bytes).map_err(|err| AF::x509(err.to_string()))?;
+            let private_key = match password {
+                Some(password) if !password.is_empty() => {
+                    PKey::private_key_from_pem_passphrase(&bytes, password.as_bytes())
+                }
+                _ => PKey::private_key_from_pem(&bytes),
+            }
+            .map_err(|err| AF::x509(err.to_string()))?;
+            Ok(X509Certificate::new(client_id, certificate, private_key))
+        } else {
+            let parsed_pkcs12 = Pkcs12::from_der(&bytes)
+                .map_err(|err| AF::x509(err.to_string()))?
+                .parse2(password.unwrap_or_default())
+                .map_err(|err| AF::x509(err.to_string()))?;
+            let certificate = parsed_pkcs12.cert.ok_or(AF::x509(
+                "No certificate found after parsing Pkcs12 using pass",
+            ))?;
+            let private_key = parsed_pkcs12.pkey.ok_or(AF::x509(
+                "No private key found after parsing Pkcs12 using pass",
+            ))?;
+            Ok(X509Certificate::new(client_id, certificate, private_key))
+        }
+    }
+
     /// Provide your own set of claims in the payload of the JWT.
     ///
     /// Replace the claims that would be generated for the client assertion.
@@ -201,12 +258,15 @@ impl X509Certificate {
     }
 
     /// Base64 Url encoded (No Pad) SHA-1 thumbprint of the X.509 certificate's DER encoding.
+    /// This is the value of the x5t header in the client assertion.
     pub fn get_thumbprint(&self) -> IdentityResult<String> {
-        let digest_bytes = self
-            .certificate
-            .digest(MessageDigest::sha1())
-            .map_err(|err| AF::x509(err.to_string()))?;
-        Ok(URL_SAFE_NO_PAD.encode(digest_bytes))
+        thumbprint(&self.certificate, MessageDigest::sha1())
+    }
+
+    /// Base64 Url encoded (No Pad) SHA-256 thumbprint of the X.509 certificate's DER encoding.
+    /// This is the value used for the x5t#S256 header.
+    pub fn get_thumbprint_sha256(&self) -> IdentityResult<String> {
+        thumbprint(&self.certificate, MessageDigest::sha256())
     }
 
     /// Get the value used for the jti field in the payload. This field is computed
@@ -425,4 +485,23 @@ mod test {
         let certificate = X509Certificate::new("client_id", cert, private_key);
         assert!(certificate.sign_with_tenant(None).is_ok());
     }
+
+    #[test]
+    pub fn thumbprints() {
+        let cert_bytes = include_bytes!("test/cert.pem");
+        let private_key_bytes = include_bytes!("test/key.pem");
+
+        let cert = X509::from_pem(cert_bytes).unwrap();
+        let private_key = PKey::private_key_from_pem(private_key_bytes).unwrap();
+
+        let certificate = X509Certificate::new("client_id", cert, private_key);
+        assert_eq!(
+            "IE7rT0hxrPHBPF26UG2usJ8trlo",
+            certificate.get_thumbprint().unwrap()
+        );
+        assert_eq!(
+            "7WmL-WvBempwyNqpdJeX8bscma_1necs_vUJ9wOmNds",
+            certificate.get_thumbprint_sha256().unwrap()
+        );
+    }
 }
@@ -68,6 +68,13 @@ fn default_interval() -> u64 {
     5
 }
 
+impl DeviceAuthorizationResponse {
+    /// The information needed to show the user where to go and what code to enter.
+    pub fn prompt(&self) -> DeviceCodePrompt {
+        DeviceCodePrompt::from(self)
+    }
+}
+
 impl Display for DeviceAuthorizationResponse {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -85,6 +92,62 @@ impl Display for DeviceAuthorizationResponse {
     }
 }
 
+/// The user facing part of a [DeviceAuthorizationResponse]. The [Display] impl
+/// produces the same instructions that Microsoft returns in the message field:
+///
+/// ```text
+/// To sign in, use a web browser to open the page https://microsoft.com/devicelogin and enter the code FQK5HW3UF to authenticate.
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeviceCodePrompt {
+    /// A short string shown to the user that's used to identify the session on a secondary device.
+    pub user_code: String,
+    /// Verification URL where the user must navigate to authenticate using the device code
+    /// and credentials.
+    pub verification_uri: String,
+    /// Verification URL that includes the user code. Not currently returned by Microsoft.
+    pub verification_uri_complete: Option<String>,
+    /// The number of seconds before the device_code and user_code expire.
+    pub expires_in: u64,
+}
+
+impl From<&DeviceAuthorizationResponse> for DeviceCodePrompt {
+    fn from(value: &DeviceAuthorizationResponse) -> Self {
+        DeviceCodePrompt {
+            user_code: value.user_code.clone(),
+            verification_uri: value.verification_uri.clone(),
+            verification_uri_complete: value.verification_uri_complete.clone(),
+            expires_in: value.expires_in,
+        }
+    }
+}
+
+impl From<DeviceAuthorizationResponse> for DeviceCodePrompt {
+    fn from(value: DeviceAuthorizationResponse) -> Self {
+        DeviceCodePrompt::from(&value)
+    }
+}
+
+impl Display for DeviceCodePrompt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "To sign in, use a web browser to open the page {} and enter the code {} to authenticate.",
+            self.verification_uri, self.user_code
+        )?;
+
+        if let Some(verification_uri_complete) = self.verification_uri_complete.as_ref() {
+            write!(
+                f,
+                " Alternatively, open {} to sign in without entering the code.",
+                verification_uri_complete
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Response types used when polling for a device code
 /// https://datatracker.ietf.org/doc/html/rfc8628#section-3.5
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -157,6 +220,35 @@ impl Display for PollDeviceCodeEvent {
     }
 }
 
+/// Progress of the device code flow, sent to the status channel of a
+/// [DeviceCodePollingExecutor](crate::identity::DeviceCodePollingExecutor) so that a UI
+/// can show the state of the sign in.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DeviceCodeStatus {
+    /// The device code was issued. Show the message of the response to the user.
+    Initiated(DeviceAuthorizationResponse),
+    /// The user has not finished signing in, polling continues.
+    Polling,
+    /// The token endpoint asked to poll less often. Polling continues with the interval.
+    SlowDown { interval: std::time::Duration },
+    /// The user signed in and the token was received.
+    Success,
+    /// Polling stopped without a token. Holds the error of the last poll response, such
+    /// as `expired_token` or `authorization_declined`.
+    Failed(String),
+}
+
+impl DeviceCodeStatus {
+    /// True for [DeviceCodeStatus::Success] and [DeviceCodeStatus::Failed], after which
+    /// no further statuses are sent.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            DeviceCodeStatus::Success | DeviceCodeStatus::Failed(_)
+        )
+    }
+}
+
 #[cfg(feature = "interactive-auth")]
 #[derive(Debug)]
 pub enum InteractiveDeviceCodeEvent {
@@ -174,3 +266,29 @@ pub enum InteractiveDeviceCodeEvent {
         public_application: PublicClientApplication<DeviceCodeCredential>,
     },
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn device_code_prompt_display() {
+        let response: DeviceAuthorizationResponse = serde_json::from_value(serde_json::json!({
+            "device_code": "FABABAAEAAAD--DLA3VO7QrddgJg7WevrgJ7Czy_TDsDClt2ELoEC8ePWFs",
+            "expires_in": 900,
+            "interval": 5,
+            "message": "To sign in, use a web browser to open the page https://microsoft.com/devicelogin and enter the code FQK5HW3UF to authenticate.",
+            "user_code": "FQK5HW3UF",
+            "verification_uri": "https://microsoft.com/devicelogin"
+        }))
+        .unwrap();
+
+        let prompt = response.prompt();
+        assert_eq!(900, prompt.expires_in);
+        assert_eq!(response.message, prompt.to_string());
+
+        let output = prompt.to_string();
+        assert!(output.contains("FQK5HW3UF"));
+        assert!(output.contains("https://microsoft.com/devicelogin"));
+    }
+}
@@ -0,0 +1,33 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use reqwest::dns::{Name, Resolve, Resolving};
+
+/// Shared [Resolve] used by the http client of token requests. Clones share the
+/// same resolver.
+#[derive(Clone)]
+pub(crate) struct DnsResolverRef(Arc<dyn Resolve>);
+
+impl DnsResolverRef {
+    pub(crate) fn new(resolver: impl Resolve + 'static) -> DnsResolverRef {
+        DnsResolverRef(Arc::new(resolver))
+    }
+}
+
+impl Resolve for DnsResolverRef {
+    fn resolve(&self, name: Name) -> Resolving {
+        self.0.resolve(name)
+    }
+}
+
+impl Debug for DnsResolverRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("DnsResolver").finish()
+    }
+}
+
+impl PartialEq for DnsResolverRef {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
@@ -0,0 +1,79 @@
+use uuid::Uuid;
+
+use graph_error::{IdentityResult, AF};
+
+/// Validate that the domain hint is a plausible DNS domain such as `contoso.com`.
+///
+/// Tenant ids, urls, email addresses and single labels such as `contoso` are
+/// rejected. Returns the domain in lowercase without a trailing dot.
+pub fn validate_domain_hint(domain_hint: impl AsRef<str>) -> IdentityResult<String> {
+    let domain_hint = domain_hint.as_ref().trim();
+    let domain = domain_hint.strip_suffix('.').unwrap_or(domain_hint);
+
+    if Uuid::parse_str(domain).is_ok() {
+        return AF::msg_result(
+            "domain_hint",
+            format!("{domain_hint} is a tenant id, use the verified domain of the tenant such as contoso.com"),
+        );
+    }
+
+    let labels: Vec<&str> = domain.split('.').collect();
+    let is_label = |label: &&str| {
+        (1..=63).contains(&label.len())
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+    };
+
+    let is_domain = domain.len() <= 253
+        && labels.len() >= 2
+        && labels.iter().all(is_label)
+        && labels
+            .last()
+            .map(|tld| !tld.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or_default();
+
+    if !is_domain {
+        return AF::msg_result(
+            "domain_hint",
+            format!("{domain_hint} is not a domain, use the verified domain of the tenant such as contoso.com"),
+        );
+    }
+
+    Ok(domain.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn valid_domain_hint() {
+        assert_eq!("contoso.com", validate_domain_hint("contoso.com").unwrap());
+        assert_eq!(
+            "fabrikam.onmicrosoft.com",
+            validate_domain_hint(" Fabrikam.onmicrosoft.com. ").unwrap()
+        );
+        assert_eq!(
+            "my-org.co.uk",
+            validate_domain_hint("my-org.co.uk").unwrap()
+        );
+    }
+
+    #[test]
+    fn rejected_domain_hint() {
+        for domain_hint in [
+            "",
+            "contoso",
+            "https://contoso.com",
+            "contoso.com/tenant",
+            "user@contoso.com",
+            "6731de76-14a6-49ae-97bc-6eba6914391e",
+            "127.0.0.1",
+            "-contoso.com",
+            "contoso..com",
+        ] {
+            assert!(validate_domain_hint(domain_hint).is_err(), "{domain_hint}");
+        }
+    }
+}
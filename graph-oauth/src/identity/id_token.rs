@@ -8,7 +8,7 @@ use std::fmt::{Debug, Display, Formatter};
 use crate::identity::AuthorizationResponse;
 use base64::Engine;
 use graph_core::identity::{Claims, DecodedJwt};
-use graph_error::{AuthorizationFailure, AF};
+use graph_error::{AuthorizationFailure, IdentityResult, AF};
 use jsonwebtoken::errors as JwtErrors;
 use jsonwebtoken::{Algorithm, DecodingKey, Validation};
 use std::str::FromStr;
@@ -116,6 +116,61 @@ impl IdToken {
         Ok(token_data)
     }
 
+    /// Validate the `at_hash` claim of the id token against the access token returned
+    /// with it. Returns Ok if the id token does not include the `at_hash` claim.
+    pub fn validate_at_hash(&self, access_token: &str) -> IdentityResult<()> {
+        self.validate_hash_claim("at_hash", access_token)
+    }
+
+    /// Validate the `c_hash` claim of the id token against the authorization code returned
+    /// with it in the hybrid flow. Returns Ok if the id token does not include the `c_hash` claim.
+    pub fn validate_c_hash(&self, code: &str) -> IdentityResult<()> {
+        self.validate_hash_claim("c_hash", code)
+    }
+
+    fn validate_hash_claim(&self, claim: &str, value: &str) -> IdentityResult<()> {
+        let payload = self
+            .id_token
+            .split('.')
+            .nth(1)
+            .ok_or_else(|| AF::msg_err("id_token", "id token is not a jwt"))?;
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(payload.trim_end_matches('='))
+            .map_err(|err| AF::msg_err("id_token", err.to_string().as_str()))?;
+        let payload: Value = serde_json::from_slice(&payload)?;
+
+        match payload.get(claim).and_then(Value::as_str) {
+            Some(expected) => {
+                let header = self
+                    .decode_header()
+                    .map_err(|err| AF::msg_err("id_token", err.to_string().as_str()))?;
+                if IdToken::hash_claim_value(header.alg, value) == expected {
+                    Ok(())
+                } else {
+                    AF::msg_result(claim, format!("{claim} does not match the id token"))
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// The base64url encoded left-most half of the hash of the value, using the SHA
+    /// hash of the `alg` of the id token, as used for the `at_hash` and `c_hash` claims.
+    pub(crate) fn hash_claim_value(alg: Algorithm, value: &str) -> String {
+        let algorithm = match alg {
+            Algorithm::HS256 | Algorithm::RS256 | Algorithm::ES256 | Algorithm::PS256 => {
+                &ring::digest::SHA256
+            }
+            Algorithm::HS384 | Algorithm::RS384 | Algorithm::ES384 | Algorithm::PS384 => {
+                &ring::digest::SHA384
+            }
+            _ => &ring::digest::SHA512,
+        };
+        let digest = ring::digest::digest(algorithm, value.as_bytes());
+        let digest = digest.as_ref();
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&digest[..digest.len() / 2])
+    }
+
     /// Enable or disable logging of personally identifiable information such
     /// as logging the id_token. This is disabled by default. When log_pii is enabled
     /// passing an [IdToken] to logging or print functions will log id_token field.
@@ -248,3 +303,70 @@ impl FromStr for IdToken {
         deserialize_result
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ACCESS_TOKEN: &str = "jHkWEdUXMU1BwAsC4vtUsZwnNvTIxEl0z9K3vx5KF0Y";
+    const CODE: &str = "Qcb0Orv1zh30vL1MPRsbm-diHiMwcLyZvn1arpZv-Jxf_11jnpEX3Tgfvk";
+
+    fn id_token(alg: &str, payload: serde_json::Value) -> IdToken {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        IdToken::new(
+            format!(
+                "{}.{}.signature",
+                engine.encode(serde_json::json!({"alg": alg, "typ": "JWT"}).to_string()),
+                engine.encode(payload.to_string())
+            )
+            .as_str(),
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn id_token_es384() -> IdToken {
+        id_token(
+            "ES384",
+            serde_json::json!({ "at_hash": "jtAeDp945y1dDqU3nkIVGNZP1HjH_MFs" }),
+        )
+    }
+
+    #[test]
+    fn matching_hashes() {
+        let id_token = id_token(
+            "RS256",
+            serde_json::json!({
+                "at_hash": "77QmUPtjPfzWtF2AnpK9RQ",
+                "c_hash": "LDktKdoQak3Pk0cnXxCltA"
+            }),
+        );
+        assert!(id_token.validate_at_hash(ACCESS_TOKEN).is_ok());
+        assert!(id_token.validate_c_hash(CODE).is_ok());
+
+        let id_token = id_token_es384();
+        assert!(id_token.validate_at_hash(ACCESS_TOKEN).is_ok());
+    }
+
+    #[test]
+    fn mismatching_hashes() {
+        let id_token = id_token(
+            "RS256",
+            serde_json::json!({
+                "at_hash": "77QmUPtjPfzWtF2AnpK9RQ",
+                "c_hash": "LDktKdoQak3Pk0cnXxCltA"
+            }),
+        );
+        assert!(id_token.validate_at_hash("other_access_token").is_err());
+        assert!(id_token.validate_c_hash("other_code").is_err());
+        assert!(id_token_es384().validate_at_hash(CODE).is_err());
+    }
+
+    #[test]
+    fn missing_hash_claims() {
+        let id_token = id_token("RS256", serde_json::json!({ "sub": "subject" }));
+        assert!(id_token.validate_at_hash(ACCESS_TOKEN).is_ok());
+        assert!(id_token.validate_c_hash(CODE).is_ok());
+    }
+}
@@ -0,0 +1,63 @@
+use url::Url;
+
+use graph_error::IdentityResult;
+
+/// Conversion into a redirect uri. A [Url] is used as is and is not parsed again.
+/// Strings are parsed using [Url::parse].
+///
+/// Unlike [IntoUrl](reqwest::IntoUrl) redirect uris without a host, such as the
+/// out-of-band redirect uri or custom schemes, are accepted.
+pub trait IntoRedirectUri {
+    fn into_redirect_uri(self) -> IdentityResult<Url>;
+}
+
+impl IntoRedirectUri for Url {
+    fn into_redirect_uri(self) -> IdentityResult<Url> {
+        Ok(self)
+    }
+}
+
+impl IntoRedirectUri for &Url {
+    fn into_redirect_uri(self) -> IdentityResult<Url> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoRedirectUri for &str {
+    fn into_redirect_uri(self) -> IdentityResult<Url> {
+        Ok(Url::parse(self)?)
+    }
+}
+
+impl IntoRedirectUri for String {
+    fn into_redirect_uri(self) -> IdentityResult<Url> {
+        Ok(Url::parse(self.as_str())?)
+    }
+}
+
+impl IntoRedirectUri for &String {
+    fn into_redirect_uri(self) -> IdentityResult<Url> {
+        Ok(Url::parse(self.as_str())?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::{AuthCodeAuthorizationUrlParameters, AuthorizationUrl};
+
+    #[test]
+    fn redirect_uri_from_url() {
+        let redirect_uri = Url::parse("http://localhost:8000/redirect").unwrap();
+        let parameters = AuthCodeAuthorizationUrlParameters::new(
+            "6731de76-14a6-49ae-97bc-6eba6914391e",
+            redirect_uri.clone(),
+        )
+        .unwrap();
+        assert_eq!(Some(&redirect_uri), parameters.redirect_uri());
+
+        let redirect_uri = Url::parse("urn:ietf:wg:oauth:2.0:oob").unwrap();
+        assert_eq!(redirect_uri, (&redirect_uri).into_redirect_uri().unwrap());
+        assert!("not a url".into_redirect_uri().is_err());
+    }
+}
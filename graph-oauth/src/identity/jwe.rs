@@ -0,0 +1,278 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use openssl::encrypt::Decrypter;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::rsa::Padding;
+use openssl::symm::Cipher;
+
+use graph_core::identity::{Claims, DecodedJwt};
+use graph_error::{IdentityResult, AF};
+
+/// Protected header of a [JsonWebEncryption].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct JweHeader {
+    /// Algorithm used to encrypt the content encryption key, `RSA-OAEP` or `RSA-OAEP-256`.
+    pub alg: String,
+    /// Algorithm used to encrypt the content, `A128GCM` or `A256GCM`.
+    pub enc: String,
+    /// Content type. `JWT` when the encrypted content is a signed JWT.
+    pub cty: Option<String>,
+    pub kid: Option<String>,
+}
+
+/// An encrypted JWT (JWE) in compact serialization, which is five base64url encoded
+/// parts: protected header, encrypted key, initialization vector, ciphertext and
+/// authentication tag.
+///
+/// Tokens may be encrypted for the application, in which case the encrypted content is
+/// a nested signed JWT (JWS) that has to be validated after decryption.
+///
+/// See [RFC 7516](https://datatracker.ietf.org/doc/html/rfc7516)
+#[derive(Clone, Eq, PartialEq)]
+pub struct JsonWebEncryption {
+    protected_header: String,
+    header: JweHeader,
+    encrypted_key: Vec<u8>,
+    iv: Vec<u8>,
+    ciphertext: Vec<u8>,
+    tag: Vec<u8>,
+}
+
+impl JsonWebEncryption {
+    /// True if the token has the five parts of a JWE in compact serialization.
+    /// A signed JWT (JWS) has three.
+    pub fn is_jwe(token: &str) -> bool {
+        token.split('.').count() == 5
+    }
+
+    pub fn parse(token: &str) -> IdentityResult<JsonWebEncryption> {
+        let parts: Vec<&str> = token.trim().split('.').collect();
+        if parts.len() != 5 {
+            return Err(AF::msg_err(
+                "jwe",
+                "JWE compact serialization must have 5 parts",
+            ));
+        }
+
+        let decode = |name: &str, part: &str| {
+            URL_SAFE_NO_PAD
+                .decode(part)
+                .map_err(|err| AF::msg_err(name.to_owned(), format!("invalid base64url: {err}")))
+        };
+
+        let header: JweHeader = serde_json::from_slice(&decode("header", parts[0])?)?;
+        Ok(JsonWebEncryption {
+            protected_header: parts[0].to_owned(),
+            header,
+            encrypted_key: decode("encrypted_key", parts[1])?,
+            iv: decode("iv", parts[2])?,
+            ciphertext: decode("ciphertext", parts[3])?,
+            tag: decode("tag", parts[4])?,
+        })
+    }
+
+    pub fn header(&self) -> &JweHeader {
+        &self.header
+    }
+
+    /// Decrypt the content using the private key of the application. For tokens with a
+    /// `cty` of `JWT` the content is the nested signed JWT.
+    pub fn decrypt(&self, private_key: &PKey<Private>) -> IdentityResult<String> {
+        let oaep_digest = match self.header.alg.as_str() {
+            "RSA-OAEP" => MessageDigest::sha1(),
+            "RSA-OAEP-256" => MessageDigest::sha256(),
+            alg => {
+                return Err(AF::msg_err(
+                    "alg".to_owned(),
+                    format!("unsupported key encryption algorithm {alg}"),
+                ))
+            }
+        };
+        let cipher = match self.header.enc.as_str() {
+            "A128GCM" => Cipher::aes_128_gcm(),
+            "A256GCM" => Cipher::aes_256_gcm(),
+            enc => {
+                return Err(AF::msg_err(
+                    "enc".to_owned(),
+                    format!("unsupported content encryption algorithm {enc}"),
+                ))
+            }
+        };
+
+        let content_encryption_key = self
+            .decrypt_key(private_key, oaep_digest)
+            .map_err(AF::decryption)?;
+        if content_encryption_key.len() != cipher.key_len() {
+            return Err(AF::msg_err(
+                "encrypted_key",
+                "content encryption key does not match the enc algorithm",
+            ));
+        }
+
+        let content = openssl::symm::decrypt_aead(
+            cipher,
+            &content_encryption_key,
+            Some(&self.iv),
+            self.protected_header.as_bytes(),
+            &self.ciphertext,
+            &self.tag,
+        )
+        .map_err(AF::decryption)?;
+
+        String::from_utf8(content)
+            .map_err(|err| AF::msg_err("ciphertext".to_owned(), err.to_string()))
+    }
+
+    fn decrypt_key(
+        &self,
+        private_key: &PKey<Private>,
+        oaep_digest: MessageDigest,
+    ) -> Result<Vec<u8>, openssl::error::ErrorStack> {
+        let mut decrypter = Decrypter::new(private_key)?;
+        decrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
+        decrypter.set_rsa_oaep_md(oaep_digest)?;
+        decrypter.set_rsa_mgf1_md(oaep_digest)?;
+
+        let mut content_encryption_key = vec![0; decrypter.decrypt_len(&self.encrypted_key)?];
+        let len = decrypter.decrypt(&self.encrypted_key, &mut content_encryption_key)?;
+        content_encryption_key.truncate(len);
+        Ok(content_encryption_key)
+    }
+
+    /// Decrypt a nested JWT with the private key of the application and validate the
+    /// signature, audience and issuer of the inner signed JWT.
+    ///
+    /// The inner JWT is validated using the following parameters:
+    /// modulus (n) and exponent (e) of the signing key, the client id of the application
+    /// as the audience, and the issuer for the tenant.
+    pub fn decrypt_and_validate(
+        token: &str,
+        private_key: &PKey<Private>,
+        n: &str,
+        e: &str,
+        client_id: &str,
+        issuer: &str,
+    ) -> IdentityResult<DecodedJwt> {
+        let jws = JsonWebEncryption::parse(token)?.decrypt(private_key)?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[client_id]);
+        validation.set_issuer(&[issuer]);
+
+        let decoding_key = DecodingKey::from_rsa_components(n, e)
+            .map_err(|err| AF::msg_err("decoding_key".to_owned(), err.to_string()))?;
+        jsonwebtoken::decode::<Claims>(jws.as_str(), &decoding_key, &validation)
+            .map_err(|err| AF::msg_err("jws".to_owned(), err.to_string()))
+    }
+}
+
+impl std::fmt::Debug for JsonWebEncryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonWebEncryption")
+            .field("header", &self.header)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use jsonwebtoken::{EncodingKey, Header};
+    use openssl::encrypt::Encrypter;
+    use openssl::rand::rand_bytes;
+    use openssl::rsa::Rsa;
+
+    fn encrypt(jws: &str, encryption_key: &PKey<Private>) -> String {
+        let header = serde_json::json!({
+            "alg": "RSA-OAEP-256",
+            "enc": "A256GCM",
+            "cty": "JWT"
+        });
+        let protected_header = URL_SAFE_NO_PAD.encode(header.to_string());
+
+        let mut content_encryption_key = [0; 32];
+        rand_bytes(&mut content_encryption_key).unwrap();
+        let mut iv = [0; 12];
+        rand_bytes(&mut iv).unwrap();
+
+        let mut encrypter = Encrypter::new(encryption_key).unwrap();
+        encrypter.set_rsa_padding(Padding::PKCS1_OAEP).unwrap();
+        encrypter.set_rsa_oaep_md(MessageDigest::sha256()).unwrap();
+        encrypter.set_rsa_mgf1_md(MessageDigest::sha256()).unwrap();
+        let mut encrypted_key = vec![0; encrypter.encrypt_len(&content_encryption_key).unwrap()];
+        let len = encrypter
+            .encrypt(&content_encryption_key, &mut encrypted_key)
+            .unwrap();
+        encrypted_key.truncate(len);
+
+        let mut tag = [0; 16];
+        let ciphertext = openssl::symm::encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &content_encryption_key,
+            Some(&iv),
+            protected_header.as_bytes(),
+            jws.as_bytes(),
+            &mut tag,
+        )
+        .unwrap();
+
+        [
+            protected_header,
+            URL_SAFE_NO_PAD.encode(encrypted_key),
+            URL_SAFE_NO_PAD.encode(iv),
+            URL_SAFE_NO_PAD.encode(ciphertext),
+            URL_SAFE_NO_PAD.encode(tag),
+        ]
+        .join(".")
+    }
+
+    #[test]
+    fn round_trip_jwe_id_token() {
+        let client_id = "5a1b2c3d-1111-2222-3333-444455556666";
+        let issuer = "https://login.microsoftonline.com/tenant/v2.0";
+
+        let signing_key = Rsa::generate(2048).unwrap();
+        let n = URL_SAFE_NO_PAD.encode(signing_key.n().to_vec());
+        let e = URL_SAFE_NO_PAD.encode(signing_key.e().to_vec());
+        let claims = serde_json::json!({
+            "aud": client_id,
+            "iss": issuer,
+            "iat": 1700000000,
+            "nbf": 1700000000,
+            "exp": 4102444800u64,
+            "name": "Test User"
+        });
+        let jws = jsonwebtoken::encode(
+            &Header::new(Algorithm::RS256),
+            &claims,
+            &EncodingKey::from_rsa_der(&signing_key.private_key_to_der().unwrap()),
+        )
+        .unwrap();
+
+        let encryption_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let jwe = encrypt(jws.as_str(), &encryption_key);
+        assert!(JsonWebEncryption::is_jwe(jwe.as_str()));
+        assert!(!JsonWebEncryption::is_jwe(jws.as_str()));
+
+        let decoded = JsonWebEncryption::decrypt_and_validate(
+            jwe.as_str(),
+            &encryption_key,
+            n.as_str(),
+            e.as_str(),
+            client_id,
+            issuer,
+        )
+        .unwrap();
+        assert_eq!(Some("Test User".to_owned()), decoded.claims.name);
+
+        let wrong_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        assert!(matches!(
+            JsonWebEncryption::parse(jwe.as_str())
+                .unwrap()
+                .decrypt(&wrong_key),
+            Err(AF::Decryption(_))
+        ));
+    }
+}
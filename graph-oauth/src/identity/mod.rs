@@ -1,14 +1,35 @@
+mod account_info;
 mod allowed_host_validator;
 mod application_options;
+mod auth_session_store;
 mod authority;
 mod authorization_query_response;
 mod authorization_request_parts;
 mod authorization_url;
+mod azure_stack_metadata;
+mod cancellation_token;
+mod claims_validator;
+mod clock;
+mod credential_warning;
 mod credentials;
 mod device_authorization_response;
+mod dns_resolver;
+mod domain_hint;
 mod id_token;
 mod into_credential_builder;
+mod into_redirect_uri;
+#[cfg(feature = "openssl")]
+mod jwe;
+mod native_redirect_uri;
+mod nonce_store;
+mod request_budget;
+mod routing_headers;
 mod token;
+mod token_audit;
+#[cfg(feature = "metrics")]
+mod token_metrics;
+mod token_url_rewriter;
+mod user_info;
 
 #[cfg(feature = "openssl")]
 pub use openssl::{
@@ -16,14 +37,35 @@ pub use openssl::{
     x509::X509,
 };
 
+pub use account_info::*;
 pub use allowed_host_validator::*;
 pub use application_options::*;
+pub use auth_session_store::*;
 pub use authority::*;
 pub use authorization_query_response::*;
 pub use authorization_request_parts::*;
 pub use authorization_url::*;
+pub use azure_stack_metadata::*;
+pub use cancellation_token::*;
+pub use claims_validator::*;
+pub use clock::*;
+pub use credential_warning::*;
 pub use credentials::*;
 pub use device_authorization_response::*;
+pub(crate) use dns_resolver::*;
+pub use domain_hint::*;
 pub use id_token::*;
 pub use into_credential_builder::*;
+pub use into_redirect_uri::*;
+#[cfg(feature = "openssl")]
+pub use jwe::*;
+pub use native_redirect_uri::*;
+pub use nonce_store::*;
+pub use request_budget::*;
+pub use routing_headers::*;
 pub use token::*;
+pub use token_audit::*;
+#[cfg(feature = "metrics")]
+pub use token_metrics::*;
+pub use token_url_rewriter::*;
+pub use user_info::*;
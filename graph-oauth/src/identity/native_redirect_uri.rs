@@ -0,0 +1,98 @@
+use url::{Host, Url};
+
+use graph_error::{IdentityResult, AF};
+
+/// Redirect uri for installed applications that do not host a redirect endpoint.
+/// The authorization response is returned to the nativeclient page and read from the
+/// address of the embedded browser.
+pub const NATIVE_CLIENT_REDIRECT_URI: &str =
+    "https://login.microsoftonline.com/common/oauth2/nativeclient";
+
+/// Out-of-band redirect uri for installed applications. The authorization code is
+/// shown to the user in the browser instead of being sent to a redirect endpoint.
+pub const OUT_OF_BAND_REDIRECT_URI: &str = "urn:ietf:wg:oauth:2.0:oob";
+
+pub fn is_native_client_redirect_uri(redirect_uri: &Url) -> bool {
+    redirect_uri.as_str() == NATIVE_CLIENT_REDIRECT_URI
+}
+
+pub fn is_out_of_band_redirect_uri(redirect_uri: &Url) -> bool {
+    redirect_uri.as_str() == OUT_OF_BAND_REDIRECT_URI
+}
+
+fn is_loopback(redirect_uri: &Url) -> bool {
+    match redirect_uri.host() {
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+/// Validate a redirect uri of a public client (installed application).
+///
+/// Valid redirect uris are the [NATIVE_CLIENT_REDIRECT_URI], the [OUT_OF_BAND_REDIRECT_URI],
+/// https uris, http uris of the loopback interface such as `http://localhost:8000` and
+/// custom schemes such as `msal{client_id}://auth`. Redirect uris cannot have a fragment.
+pub fn validate_public_client_redirect_uri(redirect_uri: &Url) -> IdentityResult<()> {
+    if is_native_client_redirect_uri(redirect_uri) || is_out_of_band_redirect_uri(redirect_uri) {
+        return Ok(());
+    }
+
+    if redirect_uri.fragment().is_some() {
+        return Err(AF::msg_err(
+            "redirect_uri",
+            "redirect uri cannot contain a fragment",
+        ));
+    }
+
+    match redirect_uri.scheme() {
+        "https" => Ok(()),
+        "http" if is_loopback(redirect_uri) => Ok(()),
+        "http" => Err(AF::msg_err(
+            "redirect_uri",
+            "http redirect uris must use the loopback interface, use https or a custom scheme instead",
+        )),
+        _ if redirect_uri.cannot_be_a_base() => Err(AF::msg_err(
+            "redirect_uri",
+            "custom scheme redirect uris must have an authority, for instance msal{client_id}://auth",
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn public_client_redirect_uris() {
+        for redirect_uri in [
+            NATIVE_CLIENT_REDIRECT_URI,
+            OUT_OF_BAND_REDIRECT_URI,
+            "http://localhost:8000/redirect",
+            "http://127.0.0.1",
+            "http://[::1]:8000",
+            "https://contoso.com/redirect",
+            "msal00000000-0000-0000-0000-000000000000://auth",
+        ] {
+            let redirect_uri = Url::parse(redirect_uri).unwrap();
+            assert!(
+                validate_public_client_redirect_uri(&redirect_uri).is_ok(),
+                "{redirect_uri}"
+            );
+        }
+
+        for redirect_uri in [
+            "http://contoso.com/redirect",
+            "https://localhost:8000/redirect#fragment",
+            "urn:ietf:wg:oauth:2.0:other",
+        ] {
+            let redirect_uri = Url::parse(redirect_uri).unwrap();
+            assert!(
+                validate_public_client_redirect_uri(&redirect_uri).is_err(),
+                "{redirect_uri}"
+            );
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+use time::OffsetDateTime;
+
+use crate::identity::{Clock, ClockRef};
+
+/// Records the nonces of accepted id tokens so that a nonce is only accepted once.
+///
+/// Matching the nonce of an id token to the nonce of the request does not prevent an
+/// id token from being replayed while the request is still outstanding. A nonce store
+/// rejects an id token whose nonce was already accepted.
+pub trait NonceStore: Send + Sync {
+    /// Record the nonce as used. Returns false if the nonce was already recorded and
+    /// the record has not expired.
+    fn record(&self, nonce: &str) -> bool;
+}
+
+/// A [NonceStore] that keeps the nonces in memory until they expire. Clones share the
+/// same nonces.
+///
+/// The default store keeps nonces for one hour, the lifetime of id tokens issued by
+/// the Microsoft identity platform.
+#[derive(Clone)]
+pub struct InMemoryNonceStore {
+    seen: Arc<Mutex<HashMap<String, OffsetDateTime>>>,
+    ttl: time::Duration,
+    clock: ClockRef,
+}
+
+impl InMemoryNonceStore {
+    /// A store that keeps each nonce for the duration.
+    pub fn new(ttl: time::Duration) -> InMemoryNonceStore {
+        InMemoryNonceStore {
+            seen: Default::default(),
+            ttl,
+            clock: Default::default(),
+        }
+    }
+
+    /// A store that keeps each nonce for the duration, using the clock to expire nonces.
+    pub fn with_clock(ttl: time::Duration, clock: impl Clock + 'static) -> InMemoryNonceStore {
+        InMemoryNonceStore {
+            seen: Default::default(),
+            ttl,
+            clock: ClockRef::new(clock),
+        }
+    }
+
+    /// The number of nonces recorded that have not expired.
+    pub fn len(&self) -> usize {
+        let now = self.clock.now_utc();
+        let seen = self.seen.lock().unwrap();
+        seen.values()
+            .filter(|expires_on| **expires_on > now)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for InMemoryNonceStore {
+    fn default() -> Self {
+        InMemoryNonceStore::new(time::Duration::hours(1))
+    }
+}
+
+impl NonceStore for InMemoryNonceStore {
+    fn record(&self, nonce: &str) -> bool {
+        let now = self.clock.now_utc();
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, expires_on| *expires_on > now);
+        if seen.contains_key(nonce) {
+            return false;
+        }
+        seen.insert(nonce.to_owned(), now + self.ttl);
+        true
+    }
+}
+
+impl Debug for InMemoryNonceStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InMemoryNonceStore")
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+#[cfg(feature = "web")]
+#[derive(Clone)]
+pub(crate) struct NonceStoreRef(Arc<dyn NonceStore>);
+
+#[cfg(feature = "web")]
+impl NonceStoreRef {
+    pub(crate) fn new(store: impl NonceStore + 'static) -> NonceStoreRef {
+        NonceStoreRef(Arc::new(store))
+    }
+}
+
+#[cfg(feature = "web")]
+impl std::ops::Deref for NonceStoreRef {
+    type Target = dyn NonceStore;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+#[cfg(feature = "web")]
+impl Debug for NonceStoreRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("NonceStore").finish()
+    }
+}
+
+#[cfg(feature = "web")]
+impl PartialEq for NonceStoreRef {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::TestClock;
+
+    #[test]
+    fn nonce_expires() {
+        let clock = TestClock::new(OffsetDateTime::UNIX_EPOCH);
+        let store = InMemoryNonceStore::with_clock(time::Duration::minutes(10), clock.clone());
+
+        assert!(store.record("nonce"));
+        assert!(!store.record("nonce"));
+        assert!(store.record("other"));
+        assert_eq!(2, store.len());
+
+        clock.advance(time::Duration::minutes(11));
+        assert!(store.is_empty());
+        assert!(store.record("nonce"));
+    }
+}
@@ -0,0 +1,158 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use graph_error::{AuthExecutionError, AuthExecutionResult};
+
+use crate::identity::tracing_targets::CREDENTIAL_EXECUTOR;
+
+/// What a credential does when its [RequestBudget] is exhausted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BudgetExhaustedBehavior {
+    /// Wait until a request is available again and then send the token request.
+    #[default]
+    Wait,
+    /// Return [AuthExecutionError::Throttled] without sending the token request.
+    Reject,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    available: u32,
+    last_refill: Instant,
+}
+
+/// Client side limit on how often a credential calls the token endpoint so that a
+/// misbehaving loop does not get the application throttled by the Microsoft identity
+/// platform (ESTS).
+///
+/// The budget is a token bucket that starts with `capacity` requests. A request is
+/// added back every `refill_interval` up to `capacity`. Clones share the same budget.
+#[derive(Clone, Debug)]
+pub struct RequestBudget {
+    capacity: u32,
+    refill_interval: Duration,
+    behavior: BudgetExhaustedBehavior,
+    state: Arc<Mutex<BucketState>>,
+}
+
+impl RequestBudget {
+    pub fn new(
+        capacity: u32,
+        refill_interval: Duration,
+        behavior: BudgetExhaustedBehavior,
+    ) -> RequestBudget {
+        let capacity = capacity.max(1);
+        RequestBudget {
+            capacity,
+            refill_interval,
+            behavior,
+            state: Arc::new(Mutex::new(BucketState {
+                available: capacity,
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+
+    /// Take a request from the budget or return how long until the next request is available.
+    fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        let now = Instant::now();
+
+        let elapsed = now.saturating_duration_since(state.last_refill);
+        let refills = (elapsed.as_nanos() / self.refill_interval.as_nanos().max(1))
+            .min(self.capacity as u128) as u32;
+        if refills > 0 {
+            state.available = state.available.saturating_add(refills).min(self.capacity);
+            state.last_refill = if state.available == self.capacity {
+                now
+            } else {
+                state.last_refill + self.refill_interval * refills
+            };
+        }
+
+        if state.available > 0 {
+            state.available -= 1;
+            Ok(())
+        } else {
+            Err(self
+                .refill_interval
+                .saturating_sub(now.saturating_duration_since(state.last_refill)))
+        }
+    }
+
+    pub(crate) fn acquire(&self) -> AuthExecutionResult<()> {
+        loop {
+            match self.try_acquire() {
+                Ok(()) => return Ok(()),
+                Err(retry_after) => {
+                    self.on_exhausted(retry_after)?;
+                    std::thread::sleep(retry_after);
+                }
+            }
+        }
+    }
+
+    pub(crate) async fn acquire_async(&self) -> AuthExecutionResult<()> {
+        loop {
+            match self.try_acquire() {
+                Ok(()) => return Ok(()),
+                Err(retry_after) => {
+                    self.on_exhausted(retry_after)?;
+                    tokio::time::sleep(retry_after).await;
+                }
+            }
+        }
+    }
+
+    fn on_exhausted(&self, retry_after: Duration) -> AuthExecutionResult<()> {
+        match self.behavior {
+            BudgetExhaustedBehavior::Wait => {
+                tracing::debug!(
+                    target: CREDENTIAL_EXECUTOR,
+                    "request budget exhausted; waiting {retry_after:?}"
+                );
+                Ok(())
+            }
+            BudgetExhaustedBehavior::Reject => Err(AuthExecutionError::Throttled { retry_after }),
+        }
+    }
+}
+
+impl PartialEq for RequestBudget {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reject_when_exhausted() {
+        let budget =
+            RequestBudget::new(2, Duration::from_secs(60), BudgetExhaustedBehavior::Reject);
+        assert!(budget.acquire().is_ok());
+        assert!(budget.clone().acquire().is_ok());
+
+        match budget.acquire() {
+            Err(AuthExecutionError::Throttled { retry_after }) => {
+                assert!(retry_after <= Duration::from_secs(60));
+                assert!(retry_after > Duration::from_secs(50));
+            }
+            result => panic!("expected throttled error: {result:#?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn wait_when_exhausted() {
+        let budget =
+            RequestBudget::new(1, Duration::from_millis(100), BudgetExhaustedBehavior::Wait);
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            budget.acquire_async().await.unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+}
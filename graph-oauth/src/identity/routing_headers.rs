@@ -0,0 +1,109 @@
+use http::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::Value;
+
+use graph_core::identity::Claims;
+
+/// Header used by Exchange Online and Microsoft Graph to route a request to the
+/// mailbox of the user that the token was issued for.
+pub const ANCHOR_MAILBOX_HEADER: &str = "x-anchormailbox";
+
+/// The anchor mailbox of the signed-in user in the form `Oid:{oid}@{tid}`, or
+/// `UPN:{upn}` when the object id or tenant id is not in the claims.
+///
+/// Returns [None] for app-only tokens, which are not issued for a user. A token
+/// is considered app-only when the `idtyp` claim is `app` or when it has no `scp` claim.
+pub fn anchor_mailbox(claims: &Claims) -> Option<String> {
+    let idtyp = claims
+        .additional_fields
+        .get("idtyp")
+        .and_then(Value::as_str);
+    if idtyp == Some("app") || !claims.additional_fields.contains_key("scp") {
+        return None;
+    }
+
+    if let (Some(oid), Some(tid)) = (claims.oid.as_ref(), claims.tid.as_ref()) {
+        return Some(format!("Oid:{oid}@{tid}"));
+    }
+
+    claims
+        .additional_fields
+        .get("upn")
+        .and_then(Value::as_str)
+        .or(claims.preferred_username.as_deref())
+        .map(|upn| format!("UPN:{upn}"))
+}
+
+/// The routing headers recommended for requests to Microsoft Graph made with
+/// a token that has the given claims. Empty for app-only tokens.
+pub fn routing_headers(claims: &Claims) -> HeaderMap {
+    let mut header_map = HeaderMap::new();
+    if let Some(header_value) =
+        anchor_mailbox(claims).and_then(|value| HeaderValue::from_str(value.as_str()).ok())
+    {
+        header_map.insert(HeaderName::from_static(ANCHOR_MAILBOX_HEADER), header_value);
+    }
+    header_map
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn claims(value: serde_json::Value) -> Claims {
+        let mut claims = serde_json::json!({
+            "aud": "https://graph.microsoft.com",
+            "iss": "https://sts.windows.net/tenant/",
+            "iat": 1700000000,
+            "nbf": 1700000000,
+            "exp": 1700003600
+        });
+        claims
+            .as_object_mut()
+            .unwrap()
+            .extend(value.as_object().unwrap().clone());
+        serde_json::from_value(claims).unwrap()
+    }
+
+    #[test]
+    fn anchor_mailbox_from_oid_and_tid() {
+        let claims = claims(serde_json::json!({
+            "oid": "3d8bd89c-2ba0-4a3e-a1f4-b0e8ef0a9a1c",
+            "tid": "72f988bf-86f1-41af-91ab-2d7cd011db47",
+            "upn": "user@contoso.com",
+            "scp": "Mail.Read"
+        }));
+
+        let header_map = routing_headers(&claims);
+        assert_eq!(
+            Some(&HeaderValue::from_static(
+                "Oid:3d8bd89c-2ba0-4a3e-a1f4-b0e8ef0a9a1c@72f988bf-86f1-41af-91ab-2d7cd011db47"
+            )),
+            header_map.get(ANCHOR_MAILBOX_HEADER)
+        );
+    }
+
+    #[test]
+    fn anchor_mailbox_from_upn() {
+        let claims = claims(serde_json::json!({
+            "upn": "user@contoso.com",
+            "scp": "Mail.Read"
+        }));
+
+        assert_eq!(
+            Some("UPN:user@contoso.com".to_owned()),
+            anchor_mailbox(&claims)
+        );
+    }
+
+    #[test]
+    fn app_only_token_has_no_routing_headers() {
+        let claims = claims(serde_json::json!({
+            "oid": "3d8bd89c-2ba0-4a3e-a1f4-b0e8ef0a9a1c",
+            "tid": "72f988bf-86f1-41af-91ab-2d7cd011db47",
+            "idtyp": "app",
+            "roles": ["Mail.Read"]
+        }));
+
+        assert!(routing_headers(&claims).is_empty());
+    }
+}
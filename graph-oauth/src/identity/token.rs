@@ -1,7 +1,8 @@
-use graph_error::{AuthorizationFailure, GraphFailure, AF};
+use graph_error::{AuthorizationFailure, GraphFailure, IdentityResult, AF};
 use serde::{Deserialize, Deserializer};
 use serde_aux::prelude::*;
 use serde_json::Value;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::Display;
@@ -12,6 +13,23 @@ use graph_core::{cache::AsBearer, identity::Claims};
 use jsonwebtoken::{Algorithm, DecodingKey, TokenData, Validation};
 use time::OffsetDateTime;
 
+/// Scopes of OpenID Connect that are not permissions of a resource.
+const OPENID_SCOPES: [&str; 4] = ["openid", "profile", "email", "offline_access"];
+
+/// The resource of scopes that are requested without a resource, such as `User.Read`.
+const MICROSOFT_GRAPH_RESOURCE: &str = "https://graph.microsoft.com/";
+
+/// The scope qualified with its resource, `https://graph.microsoft.com/User.Read` for
+/// `User.Read`. Scopes that already include a resource and OpenID Connect scopes are
+/// returned unchanged.
+fn qualified_scope(scope: &str) -> Cow<'_, str> {
+    if scope.contains('/') || OPENID_SCOPES.contains(&scope) {
+        Cow::Borrowed(scope)
+    } else {
+        Cow::Owned(format!("{MICROSOFT_GRAPH_RESOURCE}{scope}"))
+    }
+}
+
 fn deserialize_scope<'de, D>(scope: D) -> Result<Vec<String>, D::Error>
 where
     D: Deserializer<'de>,
@@ -28,8 +46,12 @@ where
 // which can only be done after deserialization.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct PhantomToken {
+    // Responses in some error recovery flows only contain a refresh token.
+    #[serde(default)]
     access_token: String,
+    #[serde(default)]
     token_type: String,
+    #[serde(default)]
     #[serde(deserialize_with = "deserialize_number_from_string")]
     expires_in: i64,
     /// Legacy version of expires_in
@@ -38,6 +60,9 @@ struct PhantomToken {
     #[serde(deserialize_with = "deserialize_scope")]
     scope: Vec<String>,
     refresh_token: Option<String>,
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_option_number_from_string")]
+    refresh_token_expires_in: Option<i64>,
     user_id: Option<String>,
     id_token: Option<String>,
     state: Option<String>,
@@ -107,6 +132,10 @@ pub struct Token {
     /// For more information, see
     /// [Refresh tokens in the Microsoft identity platform.](https://learn.microsoft.com/en-us/azure/active-directory/develop/refresh-tokens)
     pub refresh_token: Option<String>,
+    /// Lifetime in seconds of the refresh token, if returned by the token endpoint.
+    /// Returned for refresh tokens with a fixed lifetime such as those issued to
+    /// single-page applications.
+    pub refresh_token_expires_in: Option<i64>,
     pub user_id: Option<String>,
     pub id_token: Option<IdToken>,
     pub state: Option<String>,
@@ -140,6 +169,7 @@ impl Token {
             scope: scope.into_iter().map(|s| s.to_string()).collect(),
             access_token: access_token.into(),
             refresh_token: None,
+            refresh_token_expires_in: None,
             user_id: None,
             id_token: None,
             state: None,
@@ -199,6 +229,44 @@ impl Token {
         self
     }
 
+    /// The requested scopes that were not granted, for instance when consent to some of
+    /// the scopes of a request was denied. The app can decide to continue with the
+    /// granted scopes or to ask the user to consent again.
+    ///
+    /// Scopes are compared ignoring case together with their resource. Scopes without a
+    /// resource are scopes of Microsoft Graph, so `https://graph.microsoft.com/User.Read`
+    /// and `User.Read` match while `api://contoso/User.Read` does not. `/.default`
+    /// scopes are never reported because they are granted as the individual permissions
+    /// of the resource, and `offline_access` is granted when a refresh token is returned.
+    /// When the token response has no scope, the requested scopes were granted.
+    ///
+    /// # Example
+    /// ```
+    /// # use graph_oauth::Token;
+    ///
+    /// let mut access_token = Token::default();
+    /// access_token.with_scope(vec!["User.Read", "Mail.Read"]);
+    /// assert_eq!(vec!["Files.Read".to_owned()], access_token.missing_scopes(&["User.Read", "Files.Read"]));
+    /// ```
+    pub fn missing_scopes<T: AsRef<str>>(&self, requested_scope: &[T]) -> Vec<String> {
+        if self.scope.iter().all(|scope| scope.trim().is_empty()) {
+            return vec![];
+        }
+
+        requested_scope
+            .iter()
+            .map(|scope| scope.as_ref())
+            .filter(|scope| !scope.ends_with(".default"))
+            .filter(|scope| !(*scope == "offline_access" && self.refresh_token.is_some()))
+            .filter(|scope| {
+                !self.scope.iter().any(|granted| {
+                    qualified_scope(granted).eq_ignore_ascii_case(&qualified_scope(scope))
+                })
+            })
+            .map(|scope| scope.to_owned())
+            .collect()
+    }
+
     /// Set the access token.
     ///
     /// # Example
@@ -320,7 +388,12 @@ impl Token {
     /// println!("{:#?}", access_token.timestamp);
     /// ```
     pub fn gen_timestamp(&mut self) {
-        let timestamp = time::OffsetDateTime::now_utc();
+        self.gen_timestamp_at(time::OffsetDateTime::now_utc());
+    }
+
+    /// Reset the timestamp to the given time and the expires_on timestamp to the
+    /// given time plus expires_in. See [Token::gen_timestamp].
+    pub fn gen_timestamp_at(&mut self, timestamp: OffsetDateTime) {
         let expires_on = timestamp.add(time::Duration::seconds(self.expires_in));
         self.timestamp = Some(timestamp);
         self.expires_on = Some(expires_on);
@@ -337,8 +410,14 @@ impl Token {
     /// println!("{:#?}", access_token.is_expired());
     /// ```
     pub fn is_expired(&self) -> bool {
+        self.is_expired_at(OffsetDateTime::now_utc())
+    }
+
+    /// Check whether the access token is expired at the given time, for instance the
+    /// time of a [Clock](crate::identity::Clock).
+    pub fn is_expired_at(&self, now: OffsetDateTime) -> bool {
         if let Some(expires_on) = self.expires_on.as_ref() {
-            expires_on.lt(&OffsetDateTime::now_utc())
+            expires_on.lt(&now)
         } else {
             false
         }
@@ -356,8 +435,14 @@ impl Token {
     /// println!("{:#?}", access_token.is_expired_sub(time::Duration::minutes(5)));
     /// ```
     pub fn is_expired_sub(&self, duration: time::Duration) -> bool {
+        self.is_expired_sub_at(duration, OffsetDateTime::now_utc())
+    }
+
+    /// Check whether the access token is expired sub duration at the given time.
+    /// See [Token::is_expired_sub].
+    pub fn is_expired_sub_at(&self, duration: time::Duration, now: OffsetDateTime) -> bool {
         if let Some(expires_on) = self.expires_on.as_ref() {
-            expires_on.sub(duration).lt(&OffsetDateTime::now_utc())
+            expires_on.sub(duration).lt(&now)
         } else {
             false
         }
@@ -379,6 +464,70 @@ impl Token {
         Some(self.expires_on? - self.timestamp?)
     }
 
+    /// The time the refresh token expires, computed from the timestamp of the token and
+    /// `refresh_token_expires_in`. None if the token endpoint did not return
+    /// `refresh_token_expires_in`.
+    pub fn refresh_token_expires_on(&self) -> Option<OffsetDateTime> {
+        Some(self.timestamp? + time::Duration::seconds(self.refresh_token_expires_in?))
+    }
+
+    /// Time left until the refresh token expires, after which a new interactive sign-in
+    /// is needed. Returns zero once the refresh token is expired and None if the token
+    /// endpoint did not return `refresh_token_expires_in`.
+    ///
+    /// # Example
+    /// ```
+    /// # use graph_oauth::Token;
+    /// let mut token = Token::new("Bearer", 3600, "ASODFIUJ34KJ;LADSK", vec!["User.Read"]);
+    /// token.refresh_token_expires_in = Some(86400);
+    /// assert!(token.refresh_token_ttl().unwrap() > time::Duration::hours(23));
+    /// ```
+    pub fn refresh_token_ttl(&self) -> Option<time::Duration> {
+        self.refresh_token_ttl_at(OffsetDateTime::now_utc())
+    }
+
+    /// Time left at the given time until the refresh token expires. See [Token::refresh_token_ttl].
+    pub fn refresh_token_ttl_at(&self, now: OffsetDateTime) -> Option<time::Duration> {
+        let ttl = self.refresh_token_expires_on()? - now;
+        Some(ttl.max(time::Duration::ZERO))
+    }
+
+    /// The scheme of the Authorization header for the token_type of the token.
+    /// The token_type is compared case-insensitively so that a token_type of `bearer`
+    /// returns `Bearer`. An empty token_type is treated as a bearer token.
+    ///
+    /// # Example
+    /// ```
+    /// # use graph_oauth::Token;
+    ///
+    /// let access_token = Token::new("bearer", 3600, "ASODFIUJ34KJ;LADSK", vec!["User.Read"]);
+    /// assert_eq!("Bearer", access_token.authorization_scheme());
+    /// ```
+    pub fn authorization_scheme(&self) -> &str {
+        let token_type = self.token_type.trim();
+        if token_type.is_empty() || token_type.eq_ignore_ascii_case("bearer") {
+            "Bearer"
+        } else if token_type.eq_ignore_ascii_case("pop") {
+            "PoP"
+        } else {
+            token_type
+        }
+    }
+
+    /// The value of the Authorization header: the [authorization_scheme](Token::authorization_scheme)
+    /// followed by the access token.
+    ///
+    /// # Example
+    /// ```
+    /// # use graph_oauth::Token;
+    ///
+    /// let access_token = Token::new("bearer", 3600, "ASODFIUJ34KJ;LADSK", vec!["User.Read"]);
+    /// assert_eq!("Bearer ASODFIUJ34KJ;LADSK", access_token.authorization_header_value());
+    /// ```
+    pub fn authorization_header_value(&self) -> String {
+        format!("{} {}", self.authorization_scheme(), self.access_token)
+    }
+
     pub fn decode_header(&self) -> jsonwebtoken::errors::Result<jsonwebtoken::Header> {
         let id_token = self
             .id_token
@@ -389,6 +538,19 @@ impl Token {
         jsonwebtoken::decode_header(id_token.as_ref())
     }
 
+    /// Validate the `at_hash` claim of the id token against the access token, and the
+    /// `c_hash` claim against the code of the id token if any. Claims that are not
+    /// present in the id token are not validated. Returns Ok if there is no id token.
+    pub fn validate_id_token_hashes(&self) -> IdentityResult<()> {
+        if let Some(id_token) = self.id_token.as_ref() {
+            id_token.validate_at_hash(self.access_token.as_str())?;
+            if let Some(code) = id_token.code.as_ref() {
+                id_token.validate_c_hash(code)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Decode and validate the id token.
     pub fn decode(
         &self,
@@ -424,6 +586,7 @@ impl Default for Token {
             scope: vec![],
             access_token: String::new(),
             refresh_token: None,
+            refresh_token_expires_in: None,
             user_id: None,
             id_token: None,
             state: None,
@@ -456,6 +619,7 @@ impl TryFrom<AuthorizationResponse> for Token {
             ext_expires_in: None,
             scope: vec![],
             refresh_token: None,
+            refresh_token_expires_in: None,
             user_id: None,
             id_token,
             state: value.state,
@@ -525,6 +689,7 @@ impl fmt::Debug for Token {
             f.debug_struct("MsalAccessToken")
                 .field("bearer_token", &self.access_token)
                 .field("refresh_token", &self.refresh_token)
+                .field("refresh_token_expires_in", &self.refresh_token_expires_in)
                 .field("token_type", &self.token_type)
                 .field("expires_in", &self.expires_in)
                 .field("scope", &self.scope)
@@ -545,6 +710,7 @@ impl fmt::Debug for Token {
                     "refresh_token",
                     &"[REDACTED] - call enable_pii_logging(true) to log value",
                 )
+                .field("refresh_token_expires_in", &self.refresh_token_expires_in)
                 .field("token_type", &self.token_type)
                 .field("expires_in", &self.expires_in)
                 .field("scope", &self.scope)
@@ -587,6 +753,7 @@ impl<'de> Deserialize<'de> for Token {
             ext_expires_in: phantom_access_token.ext_expires_in,
             scope: phantom_access_token.scope,
             refresh_token: phantom_access_token.refresh_token,
+            refresh_token_expires_in: phantom_access_token.refresh_token_expires_in,
             user_id: phantom_access_token.user_id,
             id_token,
             state: phantom_access_token.state,
@@ -609,6 +776,7 @@ impl<'de> Deserialize<'de> for Token {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::identity::{Clock, TestClock};
 
     #[test]
     fn is_expired_test() {
@@ -623,6 +791,40 @@ mod test {
         assert!(!access_token.is_expired());
     }
 
+    #[test]
+    fn missing_scopes_of_partial_consent() {
+        let mut access_token = Token::default();
+        access_token.with_scope(["A", "C"]);
+        assert_eq!(
+            vec!["B".to_owned()],
+            access_token.missing_scopes(&["A", "B", "C"])
+        );
+
+        access_token.with_scope(["https://graph.microsoft.com/user.read"]);
+        assert!(access_token
+            .missing_scopes(&["User.Read", "https://graph.microsoft.com/.default"])
+            .is_empty());
+        assert_eq!(
+            vec!["offline_access".to_owned()],
+            access_token.missing_scopes(&["User.Read", "offline_access"])
+        );
+        access_token.with_refresh_token("refresh_token");
+        assert!(access_token
+            .missing_scopes(&["User.Read", "offline_access"])
+            .is_empty());
+
+        // Scopes of another resource are not granted by a Microsoft Graph scope.
+        assert_eq!(
+            vec!["api://contoso/User.Read".to_owned()],
+            access_token.missing_scopes(&["api://contoso/User.Read"])
+        );
+        access_token.with_scope(["api://contoso/User.Read"]);
+        assert_eq!(
+            vec!["User.Read".to_owned()],
+            access_token.missing_scopes(&["User.Read", "api://contoso/user.read"])
+        );
+    }
+
     pub const ACCESS_TOKEN_INT: &str = r#"{
         "access_token": "fasdfasdfasfdasdfasfsdf",
         "token_type": "Bearer",
@@ -685,4 +887,82 @@ mod test {
         assert_eq!(token.session_state, Some("session_state".to_string()));
         assert_eq!(token.expires_in, 3600);
     }
+
+    #[test]
+    pub fn deserialize_refresh_token_expires_in() {
+        let token: Token = serde_json::from_str(
+            r#"{
+                "access_token": "fasdfasdfasfdasdfasfsdf",
+                "token_type": "Bearer",
+                "expires_in": 3599,
+                "refresh_token": "refresh_token",
+                "refresh_token_expires_in": "86399"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(Some(86399), token.refresh_token_expires_in);
+        assert!(!token
+            .additional_fields
+            .contains_key("refresh_token_expires_in"));
+
+        let clock = TestClock::new(OffsetDateTime::UNIX_EPOCH);
+        let mut token = token;
+        token.gen_timestamp_at(clock.now_utc());
+        let refresh_token_expires_in = time::Duration::seconds(86399);
+        assert_eq!(
+            Some(refresh_token_expires_in),
+            token.refresh_token_ttl_at(clock.now_utc())
+        );
+
+        let elapsed = time::Duration::hours(12);
+        clock.advance(elapsed);
+        assert_eq!(
+            Some(refresh_token_expires_in - elapsed),
+            token.refresh_token_ttl_at(clock.now_utc())
+        );
+
+        clock.advance(refresh_token_expires_in);
+        assert_eq!(
+            Some(time::Duration::ZERO),
+            token.refresh_token_ttl_at(clock.now_utc())
+        );
+
+        let token: Token = serde_json::from_str(ACCESS_TOKEN_INT).unwrap();
+        assert_eq!(None, token.refresh_token_ttl());
+    }
+
+    #[test]
+    pub fn deserialize_refresh_token_only() {
+        let token: Token = serde_json::from_str(
+            r#"{"refresh_token": "refresh_token", "scope": "User.Read offline_access"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(Some("refresh_token".to_owned()), token.refresh_token);
+        assert!(token.access_token.is_empty());
+        assert!(token.try_as_bearer().is_err());
+        assert_eq!(
+            "access_token",
+            Token::new("Bearer", 3600, "access_token", vec!["User.Read"])
+                .try_as_bearer()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    pub fn lowercase_token_type_authorization_header() {
+        let token: Token = serde_json::from_str(
+            r#"{"access_token": "access_token", "token_type": "bearer", "expires_in": 3600}"#,
+        )
+        .unwrap();
+        assert_eq!("bearer", token.token_type);
+        assert_eq!("Bearer", token.authorization_scheme());
+        assert_eq!("Bearer access_token", token.authorization_header_value());
+
+        let token = Token::new("BEARER", 3600, "access_token", vec!["User.Read"]);
+        assert_eq!("Bearer access_token", token.authorization_header_value());
+
+        let token = Token::new("pop", 3600, "access_token", vec!["User.Read"]);
+        assert_eq!("PoP access_token", token.authorization_header_value());
+    }
 }
@@ -0,0 +1,115 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use base64::Engine;
+use serde_json::Value;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::identity::Token;
+
+/// Record of an acquired token for audit logging. The record does not include the
+/// access token, refresh token, or id token.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct TokenAuditRecord {
+    pub client_id: Uuid,
+    /// Cache id the token is stored under.
+    pub cache_id: String,
+    pub token_type: String,
+    /// The scopes granted on the token.
+    pub scope: Vec<String>,
+    /// The `aud` claim of the access token. None when the access token is not a JWT,
+    /// for instance tokens for Microsoft personal accounts.
+    pub audience: Option<String>,
+    pub expires_in: i64,
+    pub expires_on: Option<OffsetDateTime>,
+    pub has_refresh_token: bool,
+    pub has_id_token: bool,
+    pub correlation_id: Option<String>,
+}
+
+impl TokenAuditRecord {
+    pub(crate) fn new(client_id: Uuid, cache_id: &str, token: &Token) -> TokenAuditRecord {
+        TokenAuditRecord {
+            client_id,
+            cache_id: cache_id.to_owned(),
+            token_type: token.token_type.clone(),
+            scope: token.scope.clone(),
+            audience: TokenAuditRecord::audience(token.access_token.as_str()),
+            expires_in: token.expires_in,
+            expires_on: token.expires_on,
+            has_refresh_token: token.refresh_token.is_some(),
+            has_id_token: token.id_token.is_some(),
+            correlation_id: token.correlation_id.clone(),
+        }
+    }
+
+    fn audience(access_token: &str) -> Option<String> {
+        let payload = access_token.split('.').nth(1)?;
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(payload.trim_end_matches('='))
+            .ok()?;
+        let payload: Value = serde_json::from_slice(&payload).ok()?;
+        match payload.get("aud")? {
+            Value::String(aud) => Some(aud.to_owned()),
+            Value::Array(aud) => aud.first()?.as_str().map(String::from),
+            _ => None,
+        }
+    }
+}
+
+/// Called with a [TokenAuditRecord] for each token acquired from the token endpoint,
+/// before the token is cached. Clones share the same function.
+#[derive(Clone)]
+pub(crate) struct TokenAcquiredHook(Arc<dyn Fn(&TokenAuditRecord) + Send + Sync>);
+
+impl TokenAcquiredHook {
+    pub(crate) fn new<F>(hook: F) -> TokenAcquiredHook
+    where
+        F: Fn(&TokenAuditRecord) + Send + Sync + 'static,
+    {
+        TokenAcquiredHook(Arc::new(hook))
+    }
+
+    pub(crate) fn call(&self, record: &TokenAuditRecord) {
+        (self.0)(record)
+    }
+}
+
+impl Debug for TokenAcquiredHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TokenAcquiredHook").finish()
+    }
+}
+
+impl PartialEq for TokenAcquiredHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn audience_of_jwt_access_token() {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        let access_token = format!(
+            "{}.{}.signature",
+            engine.encode(r#"{"alg":"RS256","typ":"JWT"}"#),
+            engine.encode(r#"{"aud":"https://graph.microsoft.com"}"#)
+        );
+        let token = Token::new("Bearer", 3600, access_token.as_str(), ["User.Read"]);
+
+        let record = TokenAuditRecord::new(Uuid::new_v4(), "cache_id", &token);
+        assert_eq!(
+            Some("https://graph.microsoft.com"),
+            record.audience.as_deref()
+        );
+
+        let token = Token::new("Bearer", 3600, "EwAoA8l6BAAU", ["User.Read"]);
+        let record = TokenAuditRecord::new(Uuid::new_v4(), "cache_id", &token);
+        assert_eq!(None, record.audience);
+    }
+}
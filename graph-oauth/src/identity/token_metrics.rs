@@ -0,0 +1,224 @@
+use std::time::Instant;
+
+use reqwest::StatusCode;
+
+use crate::identity::TokenCredentialExecutor;
+
+/// Counter of requests sent to the token endpoint, labeled with `grant_type` and
+/// `status`. The status is the HTTP status code of the response, or `error` when no
+/// response was received.
+pub const TOKEN_REQUESTS_TOTAL: &str = "token_requests_total";
+
+/// Histogram of the duration of requests to the token endpoint in seconds, labeled
+/// with `grant_type` and `status`.
+pub const TOKEN_REQUEST_DURATION_SECONDS: &str = "token_request_duration_seconds";
+
+/// Counter of tokens returned from the token cache without a token request, labeled
+/// with `grant_type`.
+pub const TOKEN_CACHE_HITS_TOTAL: &str = "token_cache_hits_total";
+
+/// The grant type of the token request of the credential, used as the `grant_type` label.
+/// The grant type is recorded on the [AppConfig](crate::identity::AppConfig) when a token
+/// request is built. Before the first request it is read from the form once and recorded.
+pub(crate) fn grant_type_label<T: TokenCredentialExecutor + ?Sized>(credential: &mut T) -> String {
+    if let Some(grant_type) = credential.app_config().grant_type.clone() {
+        return grant_type;
+    }
+
+    let grant_type = credential
+        .form_urlencode()
+        .ok()
+        .and_then(|mut form| form.remove("grant_type"))
+        .map(
+            |grant_type| match credential.app_config().grant_type_override.clone() {
+                Some(grant_type_override) if grant_type != "refresh_token" => grant_type_override,
+                _ => grant_type,
+            },
+        );
+    match grant_type {
+        Some(grant_type) => {
+            if let Some(app_config) = credential.app_config_mut() {
+                app_config.grant_type = Some(grant_type.clone());
+            }
+            grant_type
+        }
+        None => "unknown".to_owned(),
+    }
+}
+
+pub(crate) fn record_token_cache_hit(grant_type: String) {
+    metrics::counter!(TOKEN_CACHE_HITS_TOTAL, "grant_type" => grant_type).increment(1);
+}
+
+/// Measures a single request to the token endpoint.
+pub(crate) struct TokenRequestTimer {
+    grant_type: String,
+    start: Instant,
+}
+
+impl TokenRequestTimer {
+    pub(crate) fn start(grant_type: String) -> TokenRequestTimer {
+        TokenRequestTimer {
+            grant_type,
+            start: Instant::now(),
+        }
+    }
+
+    /// Record the request with the status of the response, None if no response was received.
+    pub(crate) fn finish(self, status: Option<StatusCode>) {
+        let status = status
+            .map(|status| status.as_u16().to_string())
+            .unwrap_or_else(|| "error".to_owned());
+        metrics::counter!(
+            TOKEN_REQUESTS_TOTAL,
+            "grant_type" => self.grant_type.clone(),
+            "status" => status.clone()
+        )
+        .increment(1);
+        metrics::histogram!(
+            TOKEN_REQUEST_DURATION_SECONDS,
+            "grant_type" => self.grant_type,
+            "status" => status
+        )
+        .record(self.start.elapsed().as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::identity::{ConfidentialClientApplication, Token};
+    use graph_core::cache::TokenCache;
+    use metrics::{
+        Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+        SharedString, Unit,
+    };
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use uuid::Uuid;
+
+    #[derive(Default)]
+    struct TestMetric(AtomicU64);
+
+    impl CounterFn for TestMetric {
+        fn increment(&self, value: u64) {
+            self.0.fetch_add(value, Ordering::SeqCst);
+        }
+
+        fn absolute(&self, value: u64) {
+            self.0.store(value, Ordering::SeqCst);
+        }
+    }
+
+    impl HistogramFn for TestMetric {
+        fn record(&self, _value: f64) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Counts counter increments and histogram records by name and labels.
+    #[derive(Default)]
+    struct TestRecorder {
+        metrics: Mutex<HashMap<String, Arc<TestMetric>>>,
+    }
+
+    impl TestRecorder {
+        fn metric(&self, key: &Key) -> Arc<TestMetric> {
+            let mut labels: Vec<String> = key
+                .labels()
+                .map(|label| format!("{}={}", label.key(), label.value()))
+                .collect();
+            labels.sort();
+            let name = format!("{}{{{}}}", key.name(), labels.join(","));
+            self.metrics
+                .lock()
+                .unwrap()
+                .entry(name)
+                .or_default()
+                .clone()
+        }
+
+        fn value(&self, name: &str) -> u64 {
+            self.metrics
+                .lock()
+                .unwrap()
+                .get(name)
+                .map(|metric| metric.0.load(Ordering::SeqCst))
+                .unwrap_or_default()
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {
+        }
+
+        fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+        fn describe_histogram(
+            &self,
+            _key: KeyName,
+            _unit: Option<Unit>,
+            _description: SharedString,
+        ) {
+        }
+
+        fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.metric(key))
+        }
+
+        fn register_gauge(&self, _key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+            Histogram::from_arc(self.metric(key))
+        }
+    }
+
+    #[tokio::test]
+    async fn token_request_and_cache_hit_metrics() {
+        let recorder = TestRecorder::default();
+        let _guard = metrics::set_default_local_recorder(&recorder);
+
+        // Nothing listens on port 1 so the token request fails without a response.
+        let mut credential = ConfidentialClientApplication::builder(Uuid::new_v4())
+            .with_client_secret("secret")
+            .with_tenant("tenant")
+            .with_token_url_rewriter(|_| url::Url::parse("https://127.0.0.1:1/token").unwrap())
+            .build()
+            .into_inner();
+        assert!(credential.acquire_token_async().await.is_err());
+        assert_eq!(
+            Some("client_credentials"),
+            credential.app_config().grant_type.as_deref()
+        );
+
+        assert_eq!(
+            1,
+            recorder.value("token_requests_total{grant_type=client_credentials,status=error}")
+        );
+        assert_eq!(
+            1,
+            recorder.value(
+                "token_request_duration_seconds{grant_type=client_credentials,status=error}"
+            )
+        );
+
+        let cache_id = credential.app_config().cache_id.clone();
+        credential.store_token(
+            cache_id.as_str(),
+            Token::new(
+                "Bearer",
+                3600,
+                "cached_access_token",
+                vec!["https://graph.microsoft.com/.default"],
+            ),
+        );
+        credential.get_token_silent_async().await.unwrap();
+        assert_eq!(
+            1,
+            recorder.value("token_cache_hits_total{grant_type=client_credentials}")
+        );
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use url::Url;
+
+/// Rewrites the token url computed by a credential before the token request is sent,
+/// for instance to route token requests through an internal gateway.
+///
+/// The rewriter is called with the url returned by
+/// [TokenCredentialExecutor::uri](crate::identity::TokenCredentialExecutor::uri) on every
+/// token request. Clones share the same function.
+#[derive(Clone)]
+pub struct TokenUrlRewriter(Arc<dyn Fn(Url) -> Url + Send + Sync>);
+
+impl TokenUrlRewriter {
+    pub fn new<F>(rewriter: F) -> TokenUrlRewriter
+    where
+        F: Fn(Url) -> Url + Send + Sync + 'static,
+    {
+        TokenUrlRewriter(Arc::new(rewriter))
+    }
+
+    pub fn rewrite(&self, uri: Url) -> Url {
+        (self.0)(uri)
+    }
+}
+
+impl Debug for TokenUrlRewriter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TokenUrlRewriter").finish()
+    }
+}
+
+impl PartialEq for TokenUrlRewriter {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
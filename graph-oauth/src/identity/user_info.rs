@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use reqwest::tls::Version;
+use reqwest::IntoUrl;
+use serde_json::Value;
+use url::Url;
+
+use graph_core::http::AsyncResponseConverterExt;
+use graph_error::{AuthExecutionError, AuthExecutionResult, AF};
+
+/// The endpoints of an OpenID Connect provider as returned by its discovery document,
+/// for instance https://login.microsoftonline.com/common/v2.0/.well-known/openid-configuration
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: Url,
+    pub token_endpoint: Url,
+    #[serde(default)]
+    pub userinfo_endpoint: Option<Url>,
+    #[serde(default)]
+    pub jwks_uri: Option<Url>,
+    #[serde(default)]
+    pub end_session_endpoint: Option<Url>,
+}
+
+impl OpenIdConfiguration {
+    /// Fetch the discovery document. The discovery document url must use https.
+    pub async fn fetch_async(
+        openid_configuration_url: impl IntoUrl,
+    ) -> AuthExecutionResult<OpenIdConfiguration> {
+        OpenIdConfiguration::get_async(openid_configuration_url, true).await
+    }
+
+    /// Same as [OpenIdConfiguration::fetch_async] but allows http in order to use
+    /// mocking frameworks.
+    #[cfg(feature = "test-util")]
+    pub async fn fetch_async_with_https_only(
+        openid_configuration_url: impl IntoUrl,
+        https_only: bool,
+    ) -> AuthExecutionResult<OpenIdConfiguration> {
+        OpenIdConfiguration::get_async(openid_configuration_url, https_only).await
+    }
+
+    async fn get_async(
+        openid_configuration_url: impl IntoUrl,
+        https_only: bool,
+    ) -> AuthExecutionResult<OpenIdConfiguration> {
+        let http_client = http_client(https_only)?;
+        let response = http_client
+            .get(openid_configuration_url.into_url()?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(AuthExecutionError::silent_token_auth(
+                response.into_http_response_async().await?,
+            ));
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+/// The claims about the signed-in user returned by the OpenID Connect userinfo endpoint.
+///
+/// The Microsoft identity platform returns the `sub` claim and, depending on the
+/// requested scopes, the `name`, `family_name`, `given_name`, `email` and `picture`
+/// claims. Other claims are kept in `additional_claims`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct UserInfo {
+    pub sub: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub family_name: Option<String>,
+    #[serde(default)]
+    pub given_name: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub picture: Option<String>,
+    #[serde(flatten)]
+    pub additional_claims: HashMap<String, Value>,
+}
+
+impl UserInfo {
+    /// Fetch the userinfo endpoint from the OpenID Connect discovery document and call
+    /// it with the bearer token. The bearer token must be an access token for the
+    /// userinfo endpoint, which is returned when the openid scope is requested.
+    /// The discovery document and userinfo endpoint must use https.
+    pub async fn fetch_async(
+        openid_configuration_url: impl IntoUrl,
+        bearer_token: &str,
+    ) -> AuthExecutionResult<UserInfo> {
+        UserInfo::get_async(openid_configuration_url, bearer_token, true).await
+    }
+
+    /// Same as [UserInfo::fetch_async] but allows http in order to use mocking frameworks.
+    #[cfg(feature = "test-util")]
+    pub async fn fetch_async_with_https_only(
+        openid_configuration_url: impl IntoUrl,
+        bearer_token: &str,
+        https_only: bool,
+    ) -> AuthExecutionResult<UserInfo> {
+        UserInfo::get_async(openid_configuration_url, bearer_token, https_only).await
+    }
+
+    async fn get_async(
+        openid_configuration_url: impl IntoUrl,
+        bearer_token: &str,
+        https_only: bool,
+    ) -> AuthExecutionResult<UserInfo> {
+        let openid_configuration =
+            OpenIdConfiguration::get_async(openid_configuration_url, https_only).await?;
+        let userinfo_endpoint = openid_configuration.userinfo_endpoint.ok_or_else(|| {
+            AF::msg_err(
+                "userinfo_endpoint",
+                "openid configuration does not include a userinfo endpoint",
+            )
+        })?;
+
+        let http_client = http_client(https_only)?;
+        let response = http_client
+            .get(userinfo_endpoint)
+            .bearer_auth(bearer_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(AuthExecutionError::silent_token_auth(
+                response.into_http_response_async().await?,
+            ));
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+fn http_client(https_only: bool) -> AuthExecutionResult<reqwest::Client> {
+    Ok(reqwest::ClientBuilder::new()
+        .min_tls_version(Version::TLS_1_2)
+        .https_only(https_only)
+        .build()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use wiremock::matchers::{bearer_token, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Serves the discovery document and the userinfo response. The userinfo response
+    /// is only returned for requests with the bearer token.
+    async fn mock_openid_provider(bearer: &str) -> (MockServer, Url) {
+        let mock_server = MockServer::start().await;
+        let base_url = mock_server.uri();
+        Mock::given(method("GET"))
+            .and(path("/tenant/v2.0/.well-known/openid-configuration"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "issuer": format!("{base_url}/tenant/v2.0"),
+                "authorization_endpoint": format!("{base_url}/tenant/oauth2/v2.0/authorize"),
+                "token_endpoint": format!("{base_url}/tenant/oauth2/v2.0/token"),
+                "userinfo_endpoint": format!("{base_url}/oidc/userinfo"),
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/oidc/userinfo"))
+            .and(bearer_token(bearer))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "sub": "OLu859SGc2Sr9ZsqbkG-QbeLgJlb41KcdiPoLYNpSFA",
+                "name": "Mikah Ollenburg",
+                "family_name": "Ollenburg",
+                "given_name": "Mikah",
+                "email": "mikoll@contoso.com",
+                "picture": "https://graph.microsoft.com/v1.0/me/photo/$value",
+                "locale": "en-US"
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/oidc/userinfo"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(serde_json::json!({})))
+            .mount(&mock_server)
+            .await;
+
+        let url = Url::parse(&format!(
+            "{base_url}/tenant/v2.0/.well-known/openid-configuration"
+        ))
+        .unwrap();
+        (mock_server, url)
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn fetch_userinfo() {
+        let (_mock_server, url) = mock_openid_provider("access_token").await;
+        let user_info = UserInfo::fetch_async_with_https_only(url, "access_token", false)
+            .await
+            .unwrap();
+
+        assert_eq!("OLu859SGc2Sr9ZsqbkG-QbeLgJlb41KcdiPoLYNpSFA", user_info.sub);
+        assert_eq!(Some("Mikah Ollenburg".to_owned()), user_info.name);
+        assert_eq!(Some("mikoll@contoso.com".to_owned()), user_info.email);
+        assert_eq!(
+            Some(&Value::String("en-US".into())),
+            user_info.additional_claims.get("locale")
+        );
+    }
+
+    #[cfg(feature = "test-util")]
+    #[tokio::test]
+    async fn fetch_userinfo_with_invalid_bearer() {
+        let (_mock_server, url) = mock_openid_provider("access_token").await;
+        assert!(
+            UserInfo::fetch_async_with_https_only(url, "other_access_token", false)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_userinfo_requires_https() {
+        let (mock_server, url) = mock_openid_provider("access_token").await;
+
+        assert!(UserInfo::fetch_async(url, "access_token").await.is_err());
+        assert!(mock_server.received_requests().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,33 @@
+/// Returns false when a webview window cannot be shown, such as on a headless Linux
+/// server without a `DISPLAY` or `WAYLAND_DISPLAY`, or in an SSH session on Windows
+/// and macOS.
+pub fn is_browser_available() -> bool {
+    browser_available(std::env::consts::OS, |name| {
+        std::env::var_os(name).is_some_and(|value| !value.is_empty())
+    })
+}
+
+pub(crate) fn browser_available(os: &str, has_env: impl Fn(&str) -> bool) -> bool {
+    match os {
+        "windows" | "macos" => !(has_env("SSH_CONNECTION") || has_env("SSH_TTY")),
+        _ => has_env("DISPLAY") || has_env("WAYLAND_DISPLAY"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn headless_linux() {
+        assert!(!browser_available("linux", |_| false));
+        assert!(browser_available("linux", |name| name == "WAYLAND_DISPLAY"));
+        assert!(browser_available("freebsd", |name| name == "DISPLAY"));
+    }
+
+    #[test]
+    fn ssh_session() {
+        assert!(browser_available("windows", |_| false));
+        assert!(!browser_available("macos", |name| name == "SSH_CONNECTION"));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::identity::tracing_targets::INTERACTIVE_AUTH;
 use crate::interactive::{HostOptions, WebViewOptions};
+use graph_error::{WebViewError, WebViewResult};
 use std::fmt::{Debug, Display, Formatter};
 use std::sync::mpsc::Sender;
 use std::time::{Duration, Instant};
@@ -41,6 +42,8 @@ pub enum InteractiveAuthEvent {
     InvalidRedirectUri(String),
     ReachedRedirectUri(Url),
     WindowClosed(WindowCloseReason),
+    /// The event loop, window, or webview could not be created.
+    WebViewUnavailable(String),
 }
 
 #[derive(Debug, Clone)]
@@ -68,7 +71,9 @@ where
     ) -> anyhow::Result<()> {
         let mut event_loop: EventLoop<UserEvents> = Self::event_loop();
         let proxy = event_loop.create_proxy();
-        let window = Self::window_builder(&options).build(&event_loop).unwrap();
+        let window = Self::window_builder(&options).build(&event_loop)?;
+        let mut redirect_uris = redirect_uris;
+        redirect_uris.extend(options.redirect_uris.iter().cloned());
         let host_options = HostOptions::new(start_url, redirect_uris, options.ports.clone());
         let webview = Self::webview(host_options, &window, proxy)?;
 
@@ -198,3 +203,54 @@ where
             .build()
     }
 }
+
+/// Run the webview on a separate thread and wait for the first [InteractiveAuthEvent].
+///
+/// On headless systems creating the event loop, window, or webview fails. The failure
+/// is sent as [InteractiveAuthEvent::WebViewUnavailable] instead of panicking the thread
+/// so that callers can fall back to another flow such as device code. A thread that
+/// exits without sending an event, for instance because of a panic, is treated the same.
+pub(crate) fn spawn_webview<F>(run: F) -> WebViewResult<InteractiveAuthEvent>
+where
+    F: FnOnce(Sender<InteractiveAuthEvent>) -> anyhow::Result<()> + Send + 'static,
+{
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let init_sender = sender.clone();
+
+    std::thread::spawn(move || {
+        if let Err(err) = run(sender) {
+            tracing::debug!(target: INTERACTIVE_AUTH, "webview unavailable: {err}");
+            init_sender
+                .send(InteractiveAuthEvent::WebViewUnavailable(err.to_string()))
+                .unwrap_or_default();
+        }
+    });
+
+    receiver.recv().map_err(|_| {
+        WebViewError::WebViewUnavailable("webview exited before sending an event".into())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn webview_init_failure_is_unavailable_event() {
+        let event =
+            spawn_webview(|_sender| Err(anyhow::anyhow!("failed to initialize gtk"))).unwrap();
+
+        match event {
+            InteractiveAuthEvent::WebViewUnavailable(reason) => {
+                assert_eq!("failed to initialize gtk", reason)
+            }
+            _ => panic!("unexpected event: {event:#?}"),
+        }
+    }
+
+    #[test]
+    fn webview_thread_panic_is_unavailable_error() {
+        let result = spawn_webview(|_sender| panic!("failed to initialize gtk"));
+        assert!(matches!(result, Err(WebViewError::WebViewUnavailable(_))));
+    }
+}
@@ -1,3 +1,4 @@
+mod browser_detection;
 mod interactive_auth;
 mod webview_authorization_event;
 mod webview_host_validator;
@@ -7,6 +8,7 @@ mod with_interactive_auth;
 #[allow(unused_imports)]
 pub use webview_host_validator::*;
 
+pub use browser_detection::*;
 pub use interactive_auth::*;
 pub use webview_authorization_event::*;
 pub use webview_options::*;
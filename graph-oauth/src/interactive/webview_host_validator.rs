@@ -69,6 +69,25 @@ impl WebViewHostValidator {
         }
     }
 
+    /// Returns true if the scheme, host, port and path of the url match one of the
+    /// redirect uris. The query and fragment hold the authorization response and are
+    /// not compared. A redirect uri without a port matches any of the configured ports.
+    pub fn is_redirect_uri(&self, url: &Url) -> bool {
+        self.redirect_uris.iter().any(|redirect_uri| {
+            let is_port_match = redirect_uri.port_or_known_default() == url.port_or_known_default()
+                || (redirect_uri.port().is_none()
+                    && url
+                        .port()
+                        .map(|port| self.ports.contains(&(port as usize)))
+                        .unwrap_or_default());
+
+            redirect_uri.scheme() == url.scheme()
+                && redirect_uri.host() == url.host()
+                && is_port_match
+                && redirect_uri.path().trim_end_matches('/') == url.path().trim_end_matches('/')
+        })
+    }
+
     pub fn is_redirect_host(&self, url: &Url) -> bool {
         if let Some(host) = url.host() {
             self.redirect_uris
@@ -87,3 +106,60 @@ impl TryFrom<HostOptions> for WebViewHostValidator {
         WebViewHostValidator::new(value.start_uri, value.redirect_uris, value.ports)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn callback_matches_configured_redirect_uris() {
+        let validator = WebViewHostValidator::new(
+            Url::parse("https://login.microsoftonline.com/common/oauth2/v2.0/authorize").unwrap(),
+            vec![
+                Url::parse("http://localhost:8000/redirect").unwrap(),
+                Url::parse("http://127.0.0.1:8400/auth/callback").unwrap(),
+            ],
+            HashSet::new(),
+        )
+        .unwrap();
+
+        for url in [
+            "http://localhost:8000/redirect?code=code&state=state",
+            "http://localhost:8000/redirect/?code=code",
+            "http://127.0.0.1:8400/auth/callback#code=code",
+        ] {
+            assert!(
+                validator.is_redirect_uri(&Url::parse(url).unwrap()),
+                "{url}"
+            );
+        }
+
+        for url in [
+            "http://localhost:8000/other?code=code",
+            "http://localhost:8001/redirect?code=code",
+            "https://localhost:8000/redirect?code=code",
+            "http://127.0.0.1:8400/redirect?code=code",
+            "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+        ] {
+            assert!(
+                !validator.is_redirect_uri(&Url::parse(url).unwrap()),
+                "{url}"
+            );
+        }
+    }
+
+    #[test]
+    fn redirect_uri_without_port_matches_configured_ports() {
+        let validator = WebViewHostValidator::new(
+            Url::parse("https://login.microsoftonline.com/common/oauth2/v2.0/authorize").unwrap(),
+            vec![Url::parse("http://localhost").unwrap()],
+            HashSet::from([8000, 8001]),
+        )
+        .unwrap();
+
+        assert!(validator.is_redirect_uri(&Url::parse("http://localhost:8001/?code=code").unwrap()));
+        assert!(
+            !validator.is_redirect_uri(&Url::parse("http://localhost:9000/?code=code").unwrap())
+        );
+    }
+}
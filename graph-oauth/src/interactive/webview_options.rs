@@ -44,6 +44,11 @@ pub struct WebViewOptions {
     /// This assumes that you have http://localhost or http://localhost:port
     /// for each port registered in your ADF application registration.
     pub ports: HashSet<usize>,
+    /// Redirect uris accepted in addition to the redirect uri of the request, for
+    /// instance when several loopback redirect uris are registered for the application.
+    /// The webview only accepts a redirect whose scheme, host, port and path match one
+    /// of the redirect uris.
+    pub redirect_uris: Vec<Url>,
     /// Add a timeout that will close the window and return an error
     /// when that timeout is reached. For instance, if your app is waiting on the
     /// user to log in and the user has not logged in after 20 minutes you may
@@ -83,6 +88,12 @@ impl WebViewOptions {
         self
     }
 
+    /// Redirect uris accepted in addition to the redirect uri of the request.
+    pub fn redirect_uris<I: IntoIterator<Item = Url>>(mut self, redirect_uris: I) -> Self {
+        self.redirect_uris = redirect_uris.into_iter().collect();
+        self
+    }
+
     /// Add a timeout that will close the window and return an error
     /// when that timeout is reached. For instance, if your app is waiting on the
     /// user to log in and the user has not logged in after 20 minutes you may
@@ -108,6 +119,7 @@ impl Default for WebViewOptions {
             window_title: "Sign In".to_string(),
             theme: None,
             ports: Default::default(),
+            redirect_uris: vec![],
             timeout: None,
             clear_browsing_data: Default::default(),
         }
@@ -120,6 +132,7 @@ impl Default for WebViewOptions {
         WebViewOptions {
             window_title: "Sign In".to_string(),
             ports: Default::default(),
+            redirect_uris: vec![],
             timeout: None,
             clear_browsing_data: Default::default(),
         }
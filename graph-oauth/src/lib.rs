@@ -53,6 +53,9 @@ pub(crate) mod identity;
 #[cfg(feature = "interactive-auth")]
 pub mod interactive;
 
+#[cfg(feature = "web")]
+pub mod web;
+
 pub(crate) mod internal {
     pub use crate::oauth_serializer::*;
 }
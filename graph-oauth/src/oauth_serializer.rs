@@ -43,6 +43,7 @@ pub enum AuthParameter {
     Username,
     Password,
     DeviceCode,
+    Claims,
 }
 
 impl AuthParameter {
@@ -75,6 +76,7 @@ impl AuthParameter {
             AuthParameter::Username => "username",
             AuthParameter::Password => "password",
             AuthParameter::DeviceCode => "device_code",
+            AuthParameter::Claims => "claims",
         }
     }
 
@@ -105,6 +107,24 @@ impl AsRef<str> for AuthParameter {
     }
 }
 
+/// Collect the scopes in the order given, dropping duplicates. Some servers depend on
+/// the scope order so the order is kept as is instead of being sorted.
+///
+/// A value with several space separated scopes, such as `User.Read Mail.Read`, is split
+/// into separate scopes so that the scopes are always sent space separated with a
+/// single space, which is encoded as `+` in both the query and the form body.
+pub(crate) fn ordered_scope<T: ToString, I: IntoIterator<Item = T>>(scope: I) -> Vec<String> {
+    let mut ordered: Vec<String> = Vec::new();
+    for scope in scope.into_iter().map(|s| s.to_string()) {
+        for scope in scope.split_whitespace() {
+            if !ordered.iter().any(|s| s == scope) {
+                ordered.push(scope.to_owned());
+            }
+        }
+    }
+    ordered
+}
+
 /// Serializer for query/x-www-form-urlencoded OAuth requests.
 ///
 /// OAuth Serializer for query/form serialization that supports the OAuth 2.0 and OpenID
@@ -118,6 +138,9 @@ impl AsRef<str> for AuthParameter {
 #[derive(Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct AuthSerializer {
     scopes: BTreeSet<String>,
+    /// The scopes in the order they were added, which is the order they are sent in.
+    #[serde(default)]
+    scope_order: Vec<String>,
     parameters: BTreeMap<String, String>,
     log_pii: bool,
 }
@@ -134,6 +157,7 @@ impl AuthSerializer {
     pub fn new() -> AuthSerializer {
         AuthSerializer {
             scopes: BTreeSet::new(),
+            scope_order: Vec::new(),
             parameters: BTreeMap::new(),
             log_pii: false,
         }
@@ -458,6 +482,20 @@ impl AuthSerializer {
         self.insert(AuthParameter::LoginHint, value)
     }
 
+    /// Set the claims request parameter. The value is the raw JSON claims request
+    /// and must not be url encoded. Encoding happens once when the query or form
+    /// is serialized.
+    ///
+    /// # Example
+    /// ```
+    /// # use graph_oauth::extensions::AuthSerializer;
+    /// # let mut oauth = AuthSerializer::new();
+    /// oauth.claims(r#"{"access_token":{"xms_cc":{"values":["cp1"]}}}"#);
+    /// ```
+    pub fn claims(&mut self, value: &str) -> &mut AuthSerializer {
+        self.insert(AuthParameter::Claims, value)
+    }
+
     /// Set the client assertion.
     ///
     /// # Example
@@ -525,7 +563,8 @@ impl AuthSerializer {
         self.insert(AuthParameter::DeviceCode, value)
     }
 
-    /// Add a scope' for the OAuth URL.
+    /// Add a scope' for the OAuth URL. Scopes are serialized in the order they are
+    /// added and a scope that was already added is ignored.
     ///
     /// # Example
     /// ```
@@ -538,7 +577,11 @@ impl AuthSerializer {
     /// assert_eq!(oauth.join_scopes(" "), "Sites.Read Sites.ReadWrite Sites.ReadWrite.All");
     /// ```
     pub fn add_scope<T: ToString>(&mut self, scope: T) -> &mut AuthSerializer {
-        self.scopes.insert(scope.to_string());
+        for scope in ordered_scope([scope]) {
+            if self.scopes.insert(scope.clone()) {
+                self.scope_order.push(scope);
+            }
+        }
         self
     }
 
@@ -559,6 +602,21 @@ impl AuthSerializer {
         &self.scopes
     }
 
+    /// Get the scopes in the order they were added, which is the order they are sent in.
+    ///
+    /// # Example
+    /// ```
+    /// # use graph_oauth::extensions::AuthSerializer;
+    /// let mut oauth = AuthSerializer::new();
+    /// oauth.add_scope("Files.ReadWrite");
+    /// oauth.add_scope("Files.Read");
+    ///
+    /// assert_eq!(oauth.get_ordered_scopes(), ["Files.ReadWrite", "Files.Read"]);
+    /// ```
+    pub fn get_ordered_scopes(&self) -> &[String] {
+        &self.scope_order
+    }
+
     /// Join scopes.
     ///
     /// # Example
@@ -571,7 +629,16 @@ impl AuthSerializer {
     /// println!("{:#?}", s);
     /// ```
     pub fn join_scopes(&self, sep: &str) -> String {
-        self.scopes
+        // A serializer deserialized without the scope order sends the scopes sorted.
+        if self.scope_order.len() != self.scopes.len() {
+            return self
+                .scopes
+                .iter()
+                .map(|s| &**s)
+                .collect::<Vec<&str>>()
+                .join(sep);
+        }
+        self.scope_order
             .iter()
             .map(|s| &**s)
             .collect::<Vec<&str>>()
@@ -592,7 +659,8 @@ impl AuthSerializer {
     /// assert_eq!(oauth.join_scopes(" "), "Files.Read Files.ReadWrite");
     /// ```
     pub fn set_scope<T: ToString, I: IntoIterator<Item = T>>(&mut self, iter: I) -> &mut Self {
-        self.scopes = iter.into_iter().map(|s| s.to_string()).collect();
+        self.scope_order = ordered_scope(iter);
+        self.scopes = self.scope_order.iter().cloned().collect();
         self
     }
 
@@ -610,7 +678,9 @@ impl AuthSerializer {
     /// assert_eq!(oauth.join_scopes(" "), "Files.Read Files.ReadWrite");
     /// ```
     pub fn extend_scopes<T: ToString, I: IntoIterator<Item = T>>(&mut self, iter: I) -> &mut Self {
-        self.scopes.extend(iter.into_iter().map(|s| s.to_string()));
+        for scope in iter {
+            self.add_scope(scope);
+        }
         self
     }
 
@@ -648,6 +718,12 @@ impl AuthSerializer {
         }
     }
 
+    /// Serialize the parameters as an application/x-www-form-urlencoded query. Values
+    /// are stored unencoded and are encoded once here, so values such as the redirect
+    /// uri or the claims JSON must not be encoded before they are set. Use the result
+    /// with [Url::set_query](url::Url::set_query), which does not encode the query again.
+    ///
+    /// Optional fields that are empty or only whitespace are omitted from the query.
     pub fn encode_query(
         &mut self,
         optional_fields: Vec<AuthParameter>,
@@ -674,7 +750,9 @@ impl AuthSerializer {
             if parameter.alias().eq("scope") && !self.scopes.is_empty() {
                 serializer.append_pair("scope", self.join_scopes(" ").as_str());
             } else if let Some(val) = self.get(parameter) {
-                serializer.append_pair(parameter.alias(), val.as_str());
+                if !val.trim().is_empty() {
+                    serializer.append_pair(parameter.alias(), val.as_str());
+                }
             }
         }
 
@@ -745,3 +823,106 @@ impl fmt::Debug for AuthSerializer {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use url::Url;
+
+    const CLAIMS: &str = r#"{"access_token":{"xms_cc":{"values":["cp1"]}}}"#;
+
+    #[test]
+    fn encode_query_encodes_once() {
+        let mut serializer = AuthSerializer::new();
+        serializer
+            .client_id("client_id")
+            .redirect_uri("http://localhost:8000/redirect?app=graph")
+            .claims(CLAIMS)
+            .extend_scopes(["User.Read", "offline_access"]);
+
+        let query = serializer
+            .encode_query(
+                vec![AuthParameter::Claims],
+                vec![
+                    AuthParameter::ClientId,
+                    AuthParameter::RedirectUri,
+                    AuthParameter::Scope,
+                ],
+            )
+            .unwrap();
+
+        assert!(!query.contains("%25"));
+        assert!(query.contains("claims=%7B%22access_token%22"));
+        assert!(query.contains("scope=User.Read+offline_access"));
+        assert!(
+            query.contains("redirect_uri=http%3A%2F%2Flocalhost%3A8000%2Fredirect%3Fapp%3Dgraph")
+        );
+
+        let mut url =
+            Url::parse("https://login.microsoftonline.com/common/oauth2/v2.0/authorize").unwrap();
+        url.set_query(Some(query.as_str()));
+        assert_eq!(Some(query.as_str()), url.query());
+
+        let pairs: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        assert_eq!(Some(&CLAIMS.to_owned()), pairs.get("claims"));
+        assert_eq!(
+            Some(&"User.Read offline_access".to_owned()),
+            pairs.get("scope")
+        );
+        assert_eq!(
+            Some(&"http://localhost:8000/redirect?app=graph".to_owned()),
+            pairs.get("redirect_uri")
+        );
+    }
+
+    #[test]
+    fn credential_map_is_not_encoded() {
+        let mut serializer = AuthSerializer::new();
+        serializer
+            .client_id("client_id")
+            .claims(CLAIMS)
+            .add_scope("https://graph.microsoft.com/.default");
+
+        let map = serializer
+            .as_credential_map(
+                vec![AuthParameter::Claims],
+                vec![AuthParameter::ClientId, AuthParameter::Scope],
+            )
+            .unwrap();
+
+        assert_eq!(Some(&CLAIMS.to_owned()), map.get("claims"));
+        assert_eq!(
+            Some(&"https://graph.microsoft.com/.default".to_owned()),
+            map.get("scope")
+        );
+    }
+
+    #[test]
+    fn scope_round_trips_through_query_and_form() {
+        let mut serializer = AuthSerializer::new();
+        serializer
+            .client_id("client_id")
+            .set_scope(["User.Read", " Mail.Read  offline_access ", "User.Read"])
+            .add_scope("api://contoso/.default");
+        let expected = "User.Read Mail.Read offline_access api://contoso/.default";
+
+        let query = serializer
+            .encode_query(vec![], vec![AuthParameter::ClientId, AuthParameter::Scope])
+            .unwrap();
+        assert!(query
+            .contains("scope=User.Read+Mail.Read+offline_access+api%3A%2F%2Fcontoso%2F.default"));
+
+        let map = serializer
+            .as_credential_map(vec![], vec![AuthParameter::ClientId, AuthParameter::Scope])
+            .unwrap();
+        assert_eq!(Some(&expected.to_owned()), map.get("scope"));
+        let form = serde_urlencoded::to_string(&map).unwrap();
+
+        for encoded in [query.clone(), form, query.replace('+', "%20")] {
+            let pairs: HashMap<String, String> = url::form_urlencoded::parse(encoded.as_bytes())
+                .into_owned()
+                .collect();
+            assert_eq!(Some(&expected.to_owned()), pairs.get("scope"));
+        }
+    }
+}
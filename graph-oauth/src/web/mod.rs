@@ -0,0 +1,3 @@
+mod silent_renew;
+
+pub use silent_renew::*;
@@ -0,0 +1,302 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use url::Url;
+use uuid::Uuid;
+
+use graph_core::crypto::secure_random_32;
+use graph_error::{IdentityResult, AF};
+
+use crate::identity::{
+    Authority, AuthorizationResponse, AzureCloudInstance, NonceStore, NonceStoreRef,
+    OpenIdAuthorizationUrlParameterBuilder, OpenIdAuthorizationUrlParameters, Prompt, ResponseMode,
+    ResponseType,
+};
+
+/// Silent token renewal for browser hosted (WASM) applications using a hidden iframe.
+///
+/// The authorize url is built with `prompt=none`, `response_type=id_token token` and
+/// `response_mode=fragment` so that the Microsoft identity platform either redirects
+/// the iframe to the redirect uri with the tokens in the fragment or, if the user has
+/// to interact, with an error such as `login_required` or `interaction_required`.
+///
+/// Integration with the iframe is left to the application:
+///
+/// 1. Create a hidden iframe and set its `src` to [url](SilentRenewParameters::url).
+///    The redirect uri must be on the same origin as the application so that the
+///    location of the iframe can be read after the redirect.
+/// 2. Wait for the iframe to load the redirect uri, for instance by polling
+///    `iframe.contentWindow.location.href` or handling the `load` event, and use a
+///    timeout in case the redirect never happens (third party cookies blocked).
+/// 3. Pass the location of the iframe to [parse_response](SilentRenewParameters::parse_response)
+///    and remove the iframe.
+/// 4. Fall back to an interactive sign-in when the response is an error.
+///
+/// ```rust,ignore
+/// let silent_renew = SilentRenewParameters::builder(client_id)
+///     .with_redirect_uri(Url::parse("https://localhost:8080/silent-renew.html")?)
+///     .with_scope(["User.Read"])
+///     .with_login_hint("user@contoso.com")
+///     .build();
+///
+/// iframe.set_src(silent_renew.url()?.as_str());
+/// // After the iframe reached the redirect uri.
+/// let response = silent_renew.parse_response(&Url::parse(&iframe_location)?)?;
+/// if response.is_err() {
+///     // login_required, interaction_required, ...
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SilentRenewParameters {
+    parameters: OpenIdAuthorizationUrlParameters,
+    nonce_store: Option<NonceStoreRef>,
+}
+
+impl SilentRenewParameters {
+    pub fn builder(client_id: impl TryInto<Uuid>) -> SilentRenewParameterBuilder {
+        SilentRenewParameterBuilder::new(client_id)
+    }
+
+    pub fn url(&self) -> IdentityResult<Url> {
+        self.parameters.url()
+    }
+
+    pub fn url_with_host(&self, azure_cloud_instance: &AzureCloudInstance) -> IdentityResult<Url> {
+        self.parameters.url_with_host(azure_cloud_instance)
+    }
+
+    /// The nonce sent in the request. The nonce claim of the returned id_token must match.
+    pub fn nonce(&self) -> &String {
+        self.parameters.nonce()
+    }
+
+    /// The state sent in the request. The state of the response must match.
+    pub fn state(&self) -> Option<&String> {
+        self.parameters.state()
+    }
+
+    /// Parse the fragment of the uri the iframe was redirected to.
+    ///
+    /// Error responses from the identity platform, for instance `login_required`, are
+    /// returned as an [AuthorizationResponse] where [is_err](AuthorizationResponse::is_err)
+    /// is true. An error is returned if the fragment is missing, the state does not match
+    /// the state of the request, or the nonce claim of the id_token does not match the
+    /// nonce of the request. When a [NonceStore] is set, an error is also returned if the
+    /// nonce was already accepted. The signature of the id_token is not validated.
+    pub fn parse_response(&self, redirect_uri: &Url) -> IdentityResult<AuthorizationResponse> {
+        let fragment = redirect_uri
+            .fragment()
+            .ok_or_else(|| AF::msg_err("redirect_uri", "redirect uri does not have a fragment"))?;
+        let response: AuthorizationResponse = serde_urlencoded::from_str(fragment)
+            .map_err(|err| AF::msg_err("fragment".to_owned(), err.to_string()))?;
+
+        if response.state.as_ref() != self.state() {
+            return Err(AF::msg_err(
+                "state",
+                "state of the response does not match the state of the request",
+            ));
+        }
+
+        if response.is_err() {
+            return Ok(response);
+        }
+
+        let id_token = response
+            .id_token
+            .as_deref()
+            .ok_or_else(|| AF::msg_err("id_token", "response does not contain an id_token"))?;
+        if id_token_nonce(id_token).as_ref() != Some(self.nonce()) {
+            return Err(AF::msg_err(
+                "nonce",
+                "nonce of the id_token does not match the nonce of the request",
+            ));
+        }
+
+        if let Some(nonce_store) = self.nonce_store.as_ref() {
+            if !nonce_store.record(self.nonce()) {
+                return Err(AF::msg_err("nonce", "nonce was already used"));
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+fn id_token_nonce(id_token: &str) -> Option<String> {
+    let payload = URL_SAFE_NO_PAD.decode(id_token.split('.').nth(1)?).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&payload).ok()?;
+    claims.get("nonce")?.as_str().map(|nonce| nonce.to_owned())
+}
+
+pub struct SilentRenewParameterBuilder {
+    builder: OpenIdAuthorizationUrlParameterBuilder,
+    nonce_store: Option<NonceStoreRef>,
+}
+
+impl SilentRenewParameterBuilder {
+    fn new(client_id: impl TryInto<Uuid>) -> SilentRenewParameterBuilder {
+        let mut builder = OpenIdAuthorizationUrlParameters::builder(client_id);
+        builder
+            .with_response_type([ResponseType::IdToken, ResponseType::Token])
+            .with_response_mode(ResponseMode::Fragment)
+            .with_prompt(Prompt::None)
+            .with_state(secure_random_32());
+        SilentRenewParameterBuilder {
+            builder,
+            nonce_store: None,
+        }
+    }
+
+    /// The redirect uri must be on the same origin as the application.
+    pub fn with_redirect_uri(&mut self, redirect_uri: Url) -> &mut Self {
+        self.builder.with_redirect_uri(redirect_uri);
+        self
+    }
+
+    pub fn with_tenant<T: AsRef<str>>(&mut self, tenant: T) -> &mut Self {
+        self.builder.with_tenant(tenant);
+        self
+    }
+
+    pub fn with_authority<T: Into<Authority>>(&mut self, authority: T) -> &mut Self {
+        self.builder.with_authority(authority);
+        self
+    }
+
+    pub fn with_scope<T: ToString, I: IntoIterator<Item = T>>(&mut self, scope: I) -> &mut Self {
+        self.builder.with_scope(scope);
+        self
+    }
+
+    /// Overrides the generated nonce.
+    pub fn with_nonce<T: AsRef<str>>(&mut self, nonce: T) -> &mut Self {
+        self.builder.with_nonce(nonce);
+        self
+    }
+
+    /// Overrides the generated state.
+    pub fn with_state<T: AsRef<str>>(&mut self, state: T) -> &mut Self {
+        self.builder.with_state(state);
+        self
+    }
+
+    /// The account to renew the token for. Without a hint the request fails with
+    /// `login_required` when more than one account is signed in.
+    pub fn with_login_hint<T: AsRef<str>>(&mut self, login_hint: T) -> &mut Self {
+        self.builder.with_login_hint(login_hint);
+        self
+    }
+
+    pub fn with_domain_hint<T: AsRef<str>>(&mut self, domain_hint: T) -> &mut Self {
+        self.builder.with_domain_hint(domain_hint);
+        self
+    }
+
+    /// Reject responses whose nonce was already accepted, for instance an
+    /// [InMemoryNonceStore](crate::identity::InMemoryNonceStore) shared by all silent
+    /// renew requests of the application.
+    pub fn with_nonce_store(&mut self, nonce_store: impl NonceStore + 'static) -> &mut Self {
+        self.nonce_store = Some(NonceStoreRef::new(nonce_store));
+        self
+    }
+
+    pub fn build(&self) -> SilentRenewParameters {
+        SilentRenewParameters {
+            parameters: self.builder.build(),
+            nonce_store: self.nonce_store.clone(),
+        }
+    }
+
+    pub fn url(&self) -> IdentityResult<Url> {
+        self.builder.url()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn id_token(nonce: &str) -> String {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#);
+        let claims = URL_SAFE_NO_PAD.encode(serde_json::json!({ "nonce": nonce }).to_string());
+        format!("{header}.{claims}.signature")
+    }
+
+    #[test]
+    fn silent_renew_url() {
+        let silent_renew = SilentRenewParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080/silent-renew.html").unwrap())
+            .with_scope(["User.Read"])
+            .with_nonce("nonce-value")
+            .with_login_hint("user@contoso.com")
+            .build();
+
+        let url = silent_renew.url().unwrap();
+        let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        let contains = |key: &str, value: &str| {
+            query
+                .iter()
+                .any(|(k, v)| k.as_str() == key && v.as_str() == value)
+        };
+
+        assert!(contains("prompt", "none"));
+        assert!(contains("nonce", "nonce-value"));
+        assert!(contains("response_type", "id_token token"));
+        assert!(contains("response_mode", "fragment"));
+        assert!(contains("login_hint", "user@contoso.com"));
+        assert!(contains("state", silent_renew.state().unwrap().as_str()));
+    }
+
+    #[test]
+    fn parse_fragment_response() {
+        let silent_renew = SilentRenewParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080/silent-renew.html").unwrap())
+            .with_nonce("nonce-value")
+            .with_state("state-value")
+            .build();
+
+        let uri = Url::parse(&format!(
+            "https://localhost:8080/silent-renew.html#access_token=token&id_token={}&state=state-value&expires_in=3600",
+            id_token("nonce-value")
+        ))
+        .unwrap();
+        let response = silent_renew.parse_response(&uri).unwrap();
+        assert_eq!(Some("token".to_owned()), response.access_token);
+        assert_eq!(Some(3600), response.expires_in);
+
+        let uri = Url::parse(&format!(
+            "https://localhost:8080/silent-renew.html#access_token=token&id_token={}&state=state-value",
+            id_token("replayed")
+        ))
+        .unwrap();
+        assert!(silent_renew.parse_response(&uri).is_err());
+
+        let uri = Url::parse(
+            "https://localhost:8080/silent-renew.html#error=login_required&state=state-value",
+        )
+        .unwrap();
+        assert!(silent_renew.parse_response(&uri).unwrap().is_err());
+
+        let uri =
+            Url::parse("https://localhost:8080/silent-renew.html#error=login_required&state=other")
+                .unwrap();
+        assert!(silent_renew.parse_response(&uri).is_err());
+    }
+
+    #[test]
+    fn replayed_nonce_rejected() {
+        let silent_renew = SilentRenewParameters::builder(Uuid::new_v4())
+            .with_redirect_uri(Url::parse("https://localhost:8080/silent-renew.html").unwrap())
+            .with_nonce("nonce-value")
+            .with_state("state-value")
+            .with_nonce_store(crate::identity::InMemoryNonceStore::default())
+            .build();
+
+        let uri = Url::parse(&format!(
+            "https://localhost:8080/silent-renew.html#access_token=token&id_token={}&state=state-value",
+            id_token("nonce-value")
+        ))
+        .unwrap();
+        assert!(silent_renew.parse_response(&uri).is_ok());
+        assert!(silent_renew.parse_response(&uri).is_err());
+    }
+}
@@ -21,7 +21,10 @@
 //! and [tao](https://github.com/tauri-apps/tao) crates for webview support. Supports Linux and Windows platforms. Currently, does not support MacOS - work for this is in progress.
 //! * `openssl`: Enables support for using certificates in Client Credentials and Authorization Code auth flows. Additionally, enables related types such as X509Certificate
 //!   for building/running certificate based auth flows.
-//! * `test-util`: Enables test only features. Currently, this just enables the ability to turn off https only in the http client in order to use mocking frameworks with the crate.
+//! * `web`: Enables helpers for browser hosted applications such as silent token renewal in a hidden iframe using `prompt=none`.
+//! * `metrics`: Records `token_requests_total`, `token_request_duration_seconds` and `token_cache_hits_total` with the [metrics](https://crates.io/crates/metrics)
+//!   crate facade, labeled with the grant type and the response status. Install a recorder such as a Prometheus exporter to collect them.
+//! * `test-util`: Enables test only features. Currently, this just enables the ability to turn off https only in the http client and for token requests in order to use mocking frameworks with the crate.
 //! Other test related features may be added in the future.
 //! * `native-tls`: Enables feature native-tls in the reqwest http-client. See the [reqwest crate](https://crates.io/crates/reqwest) for more details.
 //! * `rustls-tls`: Enables feature rustls-tls in the reqwest http-client. See the [reqwest crate](https://crates.io/crates/reqwest) for more details.
@@ -255,7 +258,7 @@ pub use graph_http::api_impl::{GraphClientConfiguration, ODataQuery};
 
 /// Reexport of graph-oauth crate.
 pub mod identity {
-    pub use graph_core::identity::ClientApplication;
+    pub use graph_core::identity::{BearerToken, ClientApplication};
     pub use graph_oauth::*;
 }
 